@@ -0,0 +1,210 @@
+//! Typed access to a running mcmeta server, for launchers consuming its metadata.
+//!
+//! The `/raw` endpoints wrap their documents in an envelope carrying the document or an error
+//! message, the `/v1` endpoints serve the generated files as they are stored. Both are unwrapped
+//! here, a document the server does not have is returned as `None`.
+
+use reqwest::{StatusCode, Url};
+use serde::{de::DeserializeOwned, Deserialize};
+use thiserror::Error;
+
+use crate::models::forge::ForgeVersionMeta;
+use crate::models::mojang::{MinecraftVersion, MojangVersion, MojangVersionManifest};
+use crate::models::{MetaPackageIndex, MetaVersion, MetaVersionIndex};
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("Invalid mcmeta server url '{url}'")]
+    InvalidUrl { url: String },
+    #[error("Failure requesting {url}")]
+    Request {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("mcmeta server answered {url} with {status}: {message}")]
+    Server {
+        url: String,
+        status: StatusCode,
+        message: String,
+    },
+}
+
+/// The envelope every `/raw` endpoint answers with
+#[derive(Deserialize, Debug)]
+struct APIResponse<T> {
+    data: Option<T>,
+    error: Option<String>,
+}
+
+/// A client of the endpoints of an mcmeta server.
+#[derive(Clone, Debug)]
+pub struct MetaClient {
+    http: reqwest::Client,
+    base_url: Url,
+}
+
+impl MetaClient {
+    /// Creates a client of the server at `base_url`, e.g. `https://meta.example.com/`.
+    pub fn new(base_url: &str) -> Result<Self, ClientError> {
+        Self::with_http_client(reqwest::Client::new(), base_url)
+    }
+
+    /// Creates a client sending its requests through `http`, e.g. to set a user agent or timeouts.
+    pub fn with_http_client(http: reqwest::Client, base_url: &str) -> Result<Self, ClientError> {
+        let base_url = Url::parse(base_url)
+            .ok()
+            .filter(|url| !url.cannot_be_a_base())
+            .ok_or_else(|| ClientError::InvalidUrl {
+                url: base_url.to_string(),
+            })?;
+        Ok(Self { http, base_url })
+    }
+
+    /// Builds the url of an endpoint from its path segments, escaping each of them.
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .expect("base url was checked to be a base")
+            .pop_if_empty()
+            .extend(segments);
+        url
+    }
+
+    /// Fetches a document, `None` if the server answers 404.
+    async fn fetch<T: DeserializeOwned>(
+        &self,
+        url: Url,
+    ) -> Result<Option<(StatusCode, T)>, ClientError> {
+        let request_error = |source| ClientError::Request {
+            url: url.to_string(),
+            source,
+        };
+        let response = self
+            .http
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(request_error)?;
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let body = response.json::<T>().await.map_err(request_error)?;
+        Ok(Some((status, body)))
+    }
+
+    /// Fetches the document of a `/raw` endpoint out of its envelope.
+    async fn fetch_raw<T: DeserializeOwned>(
+        &self,
+        segments: &[&str],
+    ) -> Result<Option<T>, ClientError> {
+        let url = self.url(segments);
+        let Some((status, response)) = self.fetch::<APIResponse<T>>(url.clone()).await? else {
+            return Ok(None);
+        };
+        match response {
+            APIResponse {
+                data: Some(data), ..
+            } if status.is_success() => Ok(Some(data)),
+            APIResponse { error, .. } => Err(ClientError::Server {
+                url: url.to_string(),
+                status,
+                message: error.unwrap_or_else(|| "no data in the response".to_string()),
+            }),
+        }
+    }
+
+    /// Fetches a generated file of a `/v1` endpoint.
+    async fn fetch_generated<T: DeserializeOwned>(
+        &self,
+        segments: &[&str],
+    ) -> Result<Option<T>, ClientError> {
+        let url = self.url(segments);
+        match self.fetch::<serde_json::Value>(url.clone()).await? {
+            None => Ok(None),
+            Some((status, body)) if status.is_success() => serde_json::from_value(body)
+                .map(Some)
+                .map_err(|err| ClientError::Server {
+                    url: url.to_string(),
+                    status,
+                    message: format!("invalid document: {}", err),
+                }),
+            Some((status, body)) => Err(ClientError::Server {
+                url: url.to_string(),
+                status,
+                message: body["error"].as_str().unwrap_or_default().to_string(),
+            }),
+        }
+    }
+
+    /// Mojang's version manifest, as served by `/raw/mojang`.
+    pub async fn get_mojang_manifest(&self) -> Result<Option<MojangVersionManifest>, ClientError> {
+        self.fetch_raw(&["raw", "mojang"]).await
+    }
+
+    /// A Minecraft version json, as served by `/raw/mojang/:version`.
+    pub async fn get_mojang_version(
+        &self,
+        version: &str,
+    ) -> Result<Option<MinecraftVersion>, ClientError> {
+        self.fetch_raw(&["raw", "mojang", version]).await
+    }
+
+    /// The version json of a Forge installer by its long version, as served by `/raw/forge/:version`.
+    pub async fn get_forge_version(
+        &self,
+        long_version: &str,
+    ) -> Result<Option<MojangVersion>, ClientError> {
+        self.fetch_raw(&["raw", "forge", long_version]).await
+    }
+
+    /// The files manifest of a Forge build, as served by `/raw/forge/:version/meta`.
+    pub async fn get_forge_version_meta(
+        &self,
+        long_version: &str,
+    ) -> Result<Option<ForgeVersionMeta>, ClientError> {
+        self.fetch_raw(&["raw", "forge", long_version, "meta"])
+            .await
+    }
+
+    /// The index of every generated package, as served by `/v1/index.json`.
+    pub async fn get_package_index(&self) -> Result<Option<MetaPackageIndex>, ClientError> {
+        self.fetch_generated(&["v1", "index.json"]).await
+    }
+
+    /// The index of the versions of a component, as served by `/v1/:uid/index.json`.
+    pub async fn get_version_index(
+        &self,
+        uid: &str,
+    ) -> Result<Option<MetaVersionIndex>, ClientError> {
+        self.fetch_generated(&["v1", uid, "index.json"]).await
+    }
+
+    /// A version of a component, e.g. `net.minecraft` `1.20.1`, as served by `/v1/:uid/:version.json`.
+    pub async fn get_component(
+        &self,
+        uid: &str,
+        version: &str,
+    ) -> Result<Option<MetaVersion>, ClientError> {
+        self.fetch_generated(&["v1", uid, &format!("{}.json", version)])
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_endpoint_urls() {
+        let client = super::MetaClient::new("https://meta.example.com/mcmeta/").unwrap();
+        assert_eq!(
+            client.url(&["v1", "net.minecraft", "1.20.1.json"]).as_str(),
+            "https://meta.example.com/mcmeta/v1/net.minecraft/1.20.1.json"
+        );
+        assert_eq!(
+            client.url(&["raw", "mojang", "a/b c"]).as_str(),
+            "https://meta.example.com/mcmeta/raw/mojang/a%2Fb%20c"
+        );
+        assert!(super::MetaClient::new("not a url").is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use crate::models::{Dependency, GradleSpecifier, Library, MetaVersion, META_FORMAT_VERSION};
+use serde::{Deserialize, Serialize};
+use serde_valid::Validate;
+use serde_with::skip_serializing_none;
+
+/// An entry of the `/v2/versions/loader` list of a fabric-meta compatible server.
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct FabricLoaderVersion {
+    pub separator: String,
+    pub build: i32,
+    pub maven: GradleSpecifier,
+    pub version: String,
+    pub stable: bool,
+}
+
+/// An entry of the `/v2/versions/intermediary` list of a fabric-meta compatible server.
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct FabricIntermediaryVersion {
+    pub maven: GradleSpecifier,
+    pub version: String,
+    pub stable: bool,
+}
+
+/// A library listed by the launcher json published next to a loader jar.
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct FabricInstallerLibrary {
+    pub name: GradleSpecifier,
+    pub url: Option<String>,
+}
+
+impl From<&FabricInstallerLibrary> for Library {
+    fn from(item: &FabricInstallerLibrary) -> Self {
+        Self {
+            name: Some(item.name.clone()),
+            url: item.url.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Validate, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FabricInstallerLibraries {
+    #[serde(default)]
+    pub client: Vec<FabricInstallerLibrary>,
+    #[serde(default)]
+    pub common: Vec<FabricInstallerLibrary>,
+    #[serde(default)]
+    pub server: Vec<FabricInstallerLibrary>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct FabricMainClasses {
+    pub client: String,
+    pub server: String,
+}
+
+/// Older loaders name a single main class, newer ones one per side.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum FabricMainClass {
+    Single(String),
+    Split(FabricMainClasses),
+}
+
+impl FabricMainClass {
+    pub fn client(&self) -> &str {
+        match self {
+            FabricMainClass::Single(main_class) => main_class,
+            FabricMainClass::Split(main_classes) => &main_classes.client,
+        }
+    }
+}
+
+/// The launcher json (`<loader>.json` on the maven) of a loader version.
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct FabricInstallerDataV1 {
+    pub version: i32,
+    pub libraries: FabricInstallerLibraries,
+    pub main_class: Option<FabricMainClass>,
+}
+
+/// What we know about a jar on the maven after downloading it.
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct FabricJarInfo {
+    #[serde(with = "time::serde::iso8601::option", default)]
+    pub release_time: Option<time::OffsetDateTime>,
+    pub sha1: Option<String>,
+    pub sha256: Option<String>,
+    pub size: Option<u64>,
+}
+
+/// The components generated from one fabric-meta compatible server and its maven.
+#[derive(Clone, Debug)]
+pub struct FabricComponents {
+    pub loader_uid: String,
+    pub loader_name: String,
+    pub intermediary_uid: String,
+    pub intermediary_name: String,
+    pub maven_url: String,
+    /// maven the loader is published to, which differs from `maven_url` for servers mapping their
+    /// own intermediaries onto the stock Fabric loader
+    pub loader_maven_url: String,
+}
+
+impl FabricComponents {
+    /// Builds the component of a loader version, which depends on the matching intermediary mappings.
+    pub fn loader_meta_version(
+        &self,
+        loader: &FabricLoaderVersion,
+        installer: &FabricInstallerDataV1,
+        jar: &FabricJarInfo,
+    ) -> MetaVersion {
+        let mut libraries: Vec<Library> = installer
+            .libraries
+            .common
+            .iter()
+            .chain(installer.libraries.client.iter())
+            .map(Library::from)
+            .collect();
+        libraries.push(Library {
+            name: Some(loader.maven.clone()),
+            url: Some(self.loader_maven_url.clone()),
+            ..Default::default()
+        });
+
+        MetaVersion {
+            format_version: META_FORMAT_VERSION,
+            name: self.loader_name.clone(),
+            version: loader.version.clone(),
+            uid: self.loader_uid.clone(),
+            version_type: Some("release".to_string()),
+            order: Some(10),
+            requires: Some(vec![Dependency {
+                uid: self.intermediary_uid.clone(),
+                ..Default::default()
+            }]),
+            libraries: Some(libraries),
+            main_class: installer
+                .main_class
+                .as_ref()
+                .map(|main_class| main_class.client().to_string()),
+            release_time: jar.release_time,
+            ..Default::default()
+        }
+    }
+
+    /// Builds the component of the intermediary mappings of a Minecraft version.
+    pub fn intermediary_meta_version(
+        &self,
+        intermediary: &FabricIntermediaryVersion,
+        jar: &FabricJarInfo,
+    ) -> MetaVersion {
+        MetaVersion {
+            format_version: META_FORMAT_VERSION,
+            name: self.intermediary_name.clone(),
+            version: intermediary.version.clone(),
+            uid: self.intermediary_uid.clone(),
+            version_type: Some("release".to_string()),
+            order: Some(11),
+            volatile: Some(true),
+            requires: Some(vec![Dependency {
+                uid: "net.minecraft".to_string(),
+                equals: Some(intermediary.version.clone()),
+                suggests: None,
+            }]),
+            libraries: Some(vec![Library {
+                name: Some(intermediary.maven.clone()),
+                url: Some(self.maven_url.clone()),
+                ..Default::default()
+            }]),
+            release_time: jar.release_time,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        FabricComponents, FabricInstallerDataV1, FabricIntermediaryVersion, FabricJarInfo,
+        FabricLoaderVersion,
+    };
+
+    fn babric() -> FabricComponents {
+        FabricComponents {
+            loader_uid: "babric.fabric-loader".to_string(),
+            loader_name: "Babric Loader".to_string(),
+            intermediary_uid: "babric.intermediary".to_string(),
+            intermediary_name: "Babric Intermediary Mappings".to_string(),
+            maven_url: "https://maven.glass-launcher.net/babric/".to_string(),
+            loader_maven_url: "https://maven.glass-launcher.net/babric/".to_string(),
+        }
+    }
+
+    fn jar_info() -> FabricJarInfo {
+        FabricJarInfo {
+            release_time: None,
+            sha1: None,
+            sha256: None,
+            size: None,
+        }
+    }
+
+    #[test]
+    fn test_loader_version() {
+        let loader: FabricLoaderVersion = serde_json::from_str(
+            r#"{
+                "separator": "+",
+                "build": 1,
+                "maven": "babric:fabric-loader:0.14.24-babric.1",
+                "version": "0.14.24-babric.1",
+                "stable": true
+            }"#,
+        )
+        .unwrap();
+        let installer: FabricInstallerDataV1 = serde_json::from_str(
+            r#"{
+                "version": 1,
+                "min_java_version": 8,
+                "libraries": {
+                    "client": [],
+                    "common": [
+                        {
+                            "name": "org.ow2.asm:asm:9.6",
+                            "url": "https://maven.fabricmc.net/",
+                            "sha1": "aa205cf0a06dbd8e04ece91c0b37c3f5d567546a"
+                        }
+                    ],
+                    "server": []
+                },
+                "mainClass": {
+                    "client": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+                    "server": "net.fabricmc.loader.impl.launch.knot.KnotServer"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let meta_version = babric().loader_meta_version(&loader, &installer, &jar_info());
+        assert_eq!(meta_version.uid, "babric.fabric-loader");
+        assert_eq!(
+            meta_version.main_class.as_deref(),
+            Some("net.fabricmc.loader.impl.launch.knot.KnotClient")
+        );
+        assert_eq!(meta_version.requires.unwrap()[0].uid, "babric.intermediary");
+        let libraries = meta_version.libraries.unwrap();
+        assert_eq!(libraries.len(), 2);
+        assert_eq!(
+            libraries[1].name.as_ref().unwrap().to_string(),
+            "babric:fabric-loader:0.14.24-babric.1"
+        );
+        assert_eq!(
+            libraries[1].url.as_deref(),
+            Some("https://maven.glass-launcher.net/babric/")
+        );
+    }
+
+    #[test]
+    fn test_intermediary_version() {
+        let intermediary: FabricIntermediaryVersion = serde_json::from_str(
+            r#"{
+                "maven": "babric:intermediary:b1.7.3",
+                "version": "b1.7.3",
+                "stable": true
+            }"#,
+        )
+        .unwrap();
+
+        let meta_version = babric().intermediary_meta_version(&intermediary, &jar_info());
+        assert_eq!(meta_version.uid, "babric.intermediary");
+        assert_eq!(meta_version.volatile, Some(true));
+        let requires = meta_version.requires.unwrap();
+        assert_eq!(requires[0].uid, "net.minecraft");
+        assert_eq!(requires[0].equals.as_deref(), Some("b1.7.3"));
+
+        let json =
+            serde_json::to_value(babric().intermediary_meta_version(&intermediary, &jar_info()))
+                .unwrap();
+        assert!(json.get("mainClass").is_none());
+        assert!(json["requires"][0].get("suggests").is_none());
+    }
+}
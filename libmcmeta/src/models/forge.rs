@@ -1,10 +1,14 @@
 use crate::models::merge::{self, Merge};
 
-use crate::models::{GradleSpecifier, MojangLibrary};
+use crate::models::mojang::{MojangArgument, MojangVersion};
+use crate::models::{
+    Dependency, GradleSpecifier, Library, MetaVersion, MojangArtifact, MojangLibrary,
+    MojangLibraryDownloads, MojangLibraryExtractRules, MojangRules, META_FORMAT_VERSION,
+};
 use serde::{Deserialize, Serialize};
 use serde_valid::Validate;
 use serde_with::skip_serializing_none;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 #[derive(Deserialize, Serialize, Clone, Debug, Validate)]
 pub struct ForgeMavenMetadata {
@@ -497,6 +501,9 @@ pub enum ForgeInstallerManifestVersion {
     Modern(Box<ForgeInstallerManifest>),
 }
 
+/// The maven Forge publishes its builds to.
+pub const DEFAULT_FORGE_MAVEN_URL: &str = "https://maven.minecraftforge.net";
+
 #[derive(Deserialize, Serialize, Clone, Debug, Validate, Merge)]
 #[serde(deny_unknown_fields)]
 pub struct ForgeFile {
@@ -516,9 +523,11 @@ impl ForgeFile {
         )
     }
 
-    pub fn url(&self, long_version: &str) -> String {
+    /// Returns the url of the file on the maven at `maven_url`, e.g. [`DEFAULT_FORGE_MAVEN_URL`].
+    pub fn url(&self, maven_url: &str, long_version: &str) -> String {
         format!(
-            "https://maven.minecraftforge.net/net/minecraftforge/forge/{}/{}",
+            "{}/net/minecraftforge/forge/{}/{}",
+            maven_url.trim_end_matches('/'),
             long_version,
             self.filename(long_version),
         )
@@ -626,6 +635,14 @@ pub struct ForgeInstallerProfileInstallSection {
 #[derive(Deserialize, Serialize, Clone, Debug, Validate, Merge, Default)]
 #[serde(deny_unknown_fields)]
 pub struct ForgeLibrary {
+    #[merge(strategy = merge::option::overwrite_some)]
+    pub name: Option<GradleSpecifier>,
+    #[merge(strategy = merge::option::recurse)]
+    pub extract: Option<MojangLibraryExtractRules>,
+    #[merge(strategy = merge::option_hashmap::overwrite_key_some)]
+    pub natives: Option<HashMap<String, String>>,
+    #[merge(strategy = merge::option::recurse)]
+    pub rules: Option<MojangRules>,
     #[merge(strategy = merge::overwrite)]
     pub url: Option<String>,
     #[serde(rename = "serverreq")]
@@ -644,6 +661,27 @@ pub struct ForgeLibrary {
 #[derive(Deserialize, Serialize, Clone, Debug, Validate, Merge, Default)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct ForgeVersionFile {
+    #[merge(strategy = merge::option::overwrite_some)]
+    pub id: Option<String>,
+    #[merge(strategy = merge::option::overwrite_some)]
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub time: Option<time::OffsetDateTime>,
+    #[merge(strategy = merge::option::overwrite_some)]
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub release_time: Option<time::OffsetDateTime>,
+    #[serde(rename = "type")]
+    #[merge(strategy = merge::option::overwrite_some)]
+    pub version_type: Option<String>,
+    #[merge(strategy = merge::option::overwrite_some)]
+    pub minecraft_arguments: Option<String>,
+    #[merge(strategy = merge::option::overwrite_some)]
+    pub main_class: Option<String>,
+    #[merge(strategy = merge::option::overwrite_some)]
+    pub minimum_launcher_version: Option<i32>,
+    #[merge(strategy = merge::option::overwrite_some)]
+    pub assets: Option<String>,
+    #[merge(strategy = merge::option::overwrite_some)]
+    pub process_arguments: Option<String>,
     #[merge(strategy = merge::option_vec::append_some)]
     pub libraries: Option<Vec<ForgeLibrary>>, // overrides Mojang libraries
     #[merge(strategy = merge::option::overwrite_some)]
@@ -770,7 +808,7 @@ pub struct ForgeInstallerProfileV2 {
     #[merge(strategy = merge::option_vec::append_some)]
     processors: Option<Vec<ProcessorSpec>>,
     #[merge(strategy = merge::option_vec::append_some)]
-    libraries: Option<Vec<MojangLibrary>>,
+    pub libraries: Option<Vec<MojangLibrary>>,
     #[serde(rename = "mirrorList")]
     #[merge(strategy = merge::option::overwrite_some)]
     mirror_list: Option<String>,
@@ -786,6 +824,60 @@ pub enum ForgeInstallerProfile {
     V2(Box<ForgeInstallerProfileV2>),
 }
 
+impl ForgeInstallerProfile {
+    /// The MCP version the client of a V2 installer is deobfuscated with, from its `MCP_VERSION`
+    /// data entry without the quotes marking it as a literal.
+    pub fn mcp_version(&self) -> Option<String> {
+        let ForgeInstallerProfile::V2(profile) = self else {
+            return None;
+        };
+        let mcp_version = profile.data.as_ref()?.get("MCP_VERSION")?.client.as_ref()?;
+        let mcp_version = mcp_version.trim().trim_matches('\'');
+        (!mcp_version.is_empty()).then(|| mcp_version.to_string())
+    }
+
+    /// The Minecraft version a V2 installer installs Forge for.
+    pub fn minecraft_version(&self) -> Option<&str> {
+        match self {
+            ForgeInstallerProfile::V1(_) => None,
+            ForgeInstallerProfile::V2(profile) => profile.minecraft.as_deref(),
+        }
+    }
+}
+
+/// The Minecraft versions and Forge builds of every MCP version named in V2 installers, so
+/// toolchains can map Forge builds to MCP snapshots and back.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, Validate)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ForgeMcpIndex {
+    pub mcp_versions: BTreeMap<String, ForgeMcpEntry>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq, Validate)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ForgeMcpEntry {
+    /// a single version unless an MCP version was reused across Minecraft versions
+    pub minecraft_versions: BTreeSet<String>,
+    /// long versions of the Forge builds using the MCP version
+    pub forge_versions: BTreeSet<String>,
+}
+
+impl ForgeMcpIndex {
+    /// Records the MCP version of the installer of a Forge build, if it names one.
+    pub fn add(&mut self, long_version: &str, profile: &ForgeInstallerProfile) {
+        let (Some(mcp_version), Some(minecraft_version)) =
+            (profile.mcp_version(), profile.minecraft_version())
+        else {
+            return;
+        };
+        let entry = self.mcp_versions.entry(mcp_version).or_default();
+        entry
+            .minecraft_versions
+            .insert(minecraft_version.to_string());
+        entry.forge_versions.insert(long_version.to_string());
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, Validate, Merge, Default)]
 pub struct InstallerInfo {
     pub sha1hash: Option<String>,
@@ -793,6 +885,142 @@ pub struct InstallerInfo {
     pub size: Option<u64>,
 }
 
+/// The launcher side installer Prism Launcher runs the install processors of modern Forge with.
+pub const FORGEWRAPPER_MAIN_CLASS: &str = "io.github.zekerzhayard.forgewrapper.installer.Main";
+pub const FORGEWRAPPER_LIBRARY: &str = "io.github.zekerzhayard:ForgeWrapper:prism-2024-02-29";
+pub const FORGEWRAPPER_MAVEN_URL: &str = "https://files.prismlauncher.org/maven/";
+/// The arguments of the Mojang version json, which ForgeWrapper builds replace
+const FORGEWRAPPER_MINECRAFT_ARGUMENTS: &str = "--username ${auth_player_name} --version ${version_name} --gameDir ${game_directory} --assetsDir ${assets_root} --assetIndex ${assets_index_name} --uuid ${auth_uuid} --accessToken ${auth_access_token} --userType ${user_type} --versionType ${version_type}";
+
+/// The ForgeWrapper build injected as the main launch library of modern Forge components.
+#[derive(Clone, Debug)]
+pub struct ForgeWrapper {
+    pub name: GradleSpecifier,
+    pub maven_url: String,
+    /// Pins the download along with `size`, without it the launcher resolves the jar from the maven.
+    pub sha1: Option<String>,
+    pub size: Option<i32>,
+    pub mmc_hint: Option<String>,
+}
+
+impl Default for ForgeWrapper {
+    fn default() -> Self {
+        Self {
+            name: FORGEWRAPPER_LIBRARY
+                .parse()
+                .expect("ForgeWrapper specifier must parse"),
+            maven_url: FORGEWRAPPER_MAVEN_URL.to_string(),
+            sha1: None,
+            size: None,
+            mmc_hint: None,
+        }
+    }
+}
+
+impl ForgeWrapper {
+    pub fn library(&self) -> Library {
+        let mut library = Library {
+            name: Some(self.name.clone()),
+            mmc_hint: self.mmc_hint.clone(),
+            ..Default::default()
+        };
+        match &self.sha1 {
+            Some(sha1) => {
+                library.downloads = Some(MojangLibraryDownloads {
+                    artifact: Some(MojangArtifact {
+                        sha1: Some(sha1.clone()),
+                        size: self.size,
+                        url: format!(
+                            "{}/{}",
+                            self.maven_url.trim_end_matches('/'),
+                            self.name.path()
+                        ),
+                        path: None,
+                    }),
+                    classifiers: None,
+                });
+            }
+            None => library.url = Some(self.maven_url.clone()),
+        }
+        library
+    }
+}
+
+/// Converts a library of an installer, pointing the Forge artifacts it leaves without url at the maven.
+fn forge_library_with_url(library: &MojangLibrary, maven_url: &str) -> Library {
+    let mut library = Library::from(library);
+    if let (Some(name), Some(artifact)) = (
+        &library.name,
+        library
+            .downloads
+            .as_mut()
+            .and_then(|downloads| downloads.artifact.as_mut()),
+    ) {
+        if artifact.url.is_empty() && name.group == "net.minecraftforge" {
+            artifact.url = format!("{}/{}", maven_url.trim_end_matches('/'), name.path());
+        }
+    }
+    library
+}
+
+/// Collects the classes passed with `--tweakClass` in the arguments of a version json.
+fn tweakers(minecraft_arguments: &str) -> Vec<String> {
+    let mut tweakers = vec![];
+    let mut arguments = minecraft_arguments.split_whitespace();
+    while let Some(argument) = arguments.next() {
+        if argument == "--tweakClass" {
+            tweakers.extend(arguments.next().map(str::to_string));
+        }
+    }
+    tweakers
+}
+
+/// A part of a version compared the way loose versions are, numbers by value and the rest as text.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum LooseVersionPart<'a> {
+    Number(u64),
+    Text(&'a str),
+}
+
+fn loose_version_parts(version: &str) -> Vec<LooseVersionPart<'_>> {
+    let mut parts = vec![];
+    let mut rest = version;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphanumeric()) {
+        rest = &rest[start..];
+        let numeric = rest.starts_with(|c: char| c.is_ascii_digit());
+        let end = rest
+            .find(|c: char| {
+                if numeric {
+                    !c.is_ascii_digit()
+                } else {
+                    !c.is_ascii_alphabetic()
+                }
+            })
+            .unwrap_or(rest.len());
+        let (part, remaining) = rest.split_at(end);
+        parts.push(match part.parse() {
+            Ok(number) if numeric => LooseVersionPart::Number(number),
+            _ => LooseVersionPart::Text(part),
+        });
+        rest = remaining;
+    }
+    parts
+}
+
+/// Returns `true` if Minecraft ships the artifact of `library` at the same or a newer version.
+fn minecraft_provides(minecraft_libraries: &[GradleSpecifier], library: &GradleSpecifier) -> bool {
+    minecraft_libraries
+        .iter()
+        .find(|known| {
+            known.group == library.group
+                && known.artifact == library.artifact
+                && known.classifier == library.classifier
+        })
+        .is_some_and(|known| {
+            loose_version_parts(&known.version) >= loose_version_parts(&library.version)
+        })
+}
+
 pub struct ForgeProcessedVersion {
     pub build: i32,
     pub raw_version: String,
@@ -803,12 +1031,15 @@ pub struct ForgeProcessedVersion {
     pub installer_url: Option<String>,
     pub universal_filename: Option<String>,
     pub universal_url: Option<String>,
+    pub client_filename: Option<String>,
+    pub client_url: Option<String>,
     pub changelog_url: Option<String>,
     pub long_version: String,
 }
 
 impl ForgeProcessedVersion {
-    pub fn new(entry: &ForgeEntry) -> Self {
+    /// Processes an entry of the derived index, pointing its file urls at the maven at `maven_url`.
+    pub fn new(entry: &ForgeEntry, maven_url: &str) -> Self {
         let mut ver = Self {
             build: entry.build,
             raw_version: entry.version.clone(),
@@ -819,6 +1050,8 @@ impl ForgeProcessedVersion {
             installer_url: None,
             universal_filename: None,
             universal_url: None,
+            client_filename: None,
+            client_url: None,
             changelog_url: None,
             long_version: format!("{}-{}", entry.mc_version, entry.version),
         };
@@ -831,16 +1064,18 @@ impl ForgeProcessedVersion {
             for (classifier, file) in files {
                 let extension = &file.extension;
                 let filename = file.filename(&ver.long_version);
-                let url = file.url(&ver.long_version);
+                let url = file.url(maven_url, &ver.long_version);
 
                 if (classifier == "installer") && (extension == "jar") {
                     ver.installer_filename = Some(filename);
                     ver.installer_url = Some(url);
-                } else if (classifier == "universal" || classifier == "client")
-                    && (extension == "jar" || extension == "zip")
+                } else if (classifier == "universal") && (extension == "jar" || extension == "zip")
                 {
                     ver.universal_filename = Some(filename);
                     ver.universal_url = Some(url);
+                } else if (classifier == "client") && (extension == "jar" || extension == "zip") {
+                    ver.client_filename = Some(filename);
+                    ver.client_url = Some(url);
                 } else if (classifier == "changelog") && (extension == "txt") {
                     ver.changelog_url = Some(url);
                 }
@@ -850,6 +1085,27 @@ impl ForgeProcessedVersion {
         ver
     }
 
+    /// Returns `true` for the builds for Minecraft 1.1 to 1.2.5, which have no installer
+    /// and only ship client and server zips.
+    pub fn is_ancient(&self) -> bool {
+        let mut parts = self.mc_version_sane.split(['.', '-']);
+        match (parts.next(), parts.next()) {
+            (Some("1"), Some(minor)) => minor.parse::<i32>().is_ok_and(|minor| minor < 3),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` for builds that only ship a client zip or jar.
+    pub fn is_client_only(&self) -> bool {
+        self.client_url.is_some() && self.universal_url.is_none() && self.installer_url.is_none()
+    }
+
+    /// Returns `true` if the jar mod of a build without installer is its client file.
+    /// The ancient builds are jar mods of the client, so their client file always wins.
+    fn uses_client_file(&self) -> bool {
+        self.client_url.is_some() && (self.universal_url.is_none() || self.is_ancient())
+    }
+
     pub fn name(&self) -> String {
         format!("Forge {}", self.build)
     }
@@ -861,6 +1117,8 @@ impl ForgeProcessedVersion {
     pub fn filename(&self) -> Option<String> {
         if self.uses_installer() {
             self.installer_filename.clone()
+        } else if self.uses_client_file() {
+            self.client_filename.clone()
         } else {
             self.universal_filename.clone()
         }
@@ -869,11 +1127,240 @@ impl ForgeProcessedVersion {
     pub fn url(&self) -> Option<String> {
         if self.uses_installer() {
             self.installer_url.clone()
+        } else if self.uses_client_file() {
+            self.client_url.clone()
         } else {
             self.universal_url.clone()
         }
     }
 
+    /// Returns the maven coordinates of the jar mod of a build without installer.
+    pub fn jar_mod_specifier(&self) -> Option<GradleSpecifier> {
+        if self.uses_installer() {
+            return None;
+        }
+        let filename = self.filename()?;
+        let classifier = if self.uses_client_file() {
+            "client"
+        } else {
+            "universal"
+        };
+        let extension = filename.rsplit_once('.').map(|(_, extension)| extension)?;
+
+        Some(GradleSpecifier {
+            group: "net.minecraftforge".to_string(),
+            artifact: "forge".to_string(),
+            version: self.long_version.clone(),
+            extension: Some(extension.to_string()),
+            classifier: Some(classifier.to_string()),
+        })
+    }
+
+    /// Builds the component of a build without installer, which is applied as a jar mod.
+    pub fn to_legacy_meta_version(&self, info: &ForgeLegacyInfo) -> Option<MetaVersion> {
+        let jar_mod = Library {
+            name: Some(self.jar_mod_specifier()?),
+            downloads: Some(MojangLibraryDownloads {
+                artifact: Some(MojangArtifact {
+                    sha1: info.sha1.clone(),
+                    size: info.size.map(|size| size as i32),
+                    url: self.url()?,
+                    path: None,
+                }),
+                classifiers: None,
+            }),
+            ..Default::default()
+        };
+
+        Some(MetaVersion {
+            format_version: META_FORMAT_VERSION,
+            name: "Forge".to_string(),
+            version: self.raw_version.clone(),
+            uid: "net.minecraftforge".to_string(),
+            order: Some(5),
+            requires: Some(vec![Dependency {
+                uid: "net.minecraft".to_string(),
+                equals: Some(self.mc_version_sane.clone()),
+                suggests: None,
+            }]),
+            jar_mods: Some(vec![jar_mod]),
+            release_time: info.release_time,
+            ..Default::default()
+        })
+    }
+
+    /// Builds the component of a build with an installer from the version json it contains.
+    ///
+    /// Builds for 1.13 and later run their install processors on launch through `forge_wrapper`,
+    /// which gets the installer and the libraries of the install profile as maven files.
+    /// Earlier builds with a version json only need its libraries and tweakers.
+    pub fn to_installer_meta_version(
+        &self,
+        profile: &ForgeInstallerProfileV2,
+        version: &MojangVersion,
+        info: &InstallerInfo,
+        maven_url: &str,
+        forge_wrapper: &ForgeWrapper,
+    ) -> Option<MetaVersion> {
+        let mut meta_version = MetaVersion {
+            format_version: META_FORMAT_VERSION,
+            name: "Forge".to_string(),
+            version: self.raw_version.clone(),
+            uid: "net.minecraftforge".to_string(),
+            order: Some(5),
+            requires: Some(vec![Dependency {
+                uid: "net.minecraft".to_string(),
+                equals: Some(self.mc_version_sane.clone()),
+                suggests: None,
+            }]),
+            release_time: version.release_time,
+            ..Default::default()
+        };
+        let libraries = |libraries: &[MojangLibrary]| {
+            libraries
+                .iter()
+                .filter(|library| !library.name.as_ref().is_some_and(|name| name.is_log4j()))
+                .map(|library| forge_library_with_url(library, maven_url))
+                .collect::<Vec<_>>()
+        };
+
+        if let Some(arguments) = &version.arguments {
+            let installer = Library {
+                name: Some(GradleSpecifier {
+                    group: "net.minecraftforge".to_string(),
+                    artifact: "forge".to_string(),
+                    version: self.long_version.clone(),
+                    extension: Some("jar".to_string()),
+                    classifier: Some("installer".to_string()),
+                }),
+                downloads: Some(MojangLibraryDownloads {
+                    artifact: Some(MojangArtifact {
+                        sha1: info.sha1hash.clone(),
+                        size: info.size.map(|size| size as i32),
+                        url: self.installer_url.clone()?,
+                        path: None,
+                    }),
+                    classifiers: None,
+                }),
+                ..Default::default()
+            };
+            let mut maven_files = vec![installer];
+            maven_files.extend(libraries(profile.libraries.as_deref().unwrap_or_default()));
+
+            let game_arguments = arguments
+                .game
+                .iter()
+                .flatten()
+                .filter_map(|argument| match argument {
+                    MojangArgument::String(argument) => Some(argument.as_str()),
+                    MojangArgument::Object(_) => None,
+                })
+                .collect::<Vec<_>>();
+            let mut minecraft_arguments = FORGEWRAPPER_MINECRAFT_ARGUMENTS.to_string();
+            for argument in &game_arguments {
+                minecraft_arguments += &format!(" {}", argument);
+            }
+            for (argument, value) in [
+                ("--fml.forgeGroup", "net.minecraftforge"),
+                ("--fml.forgeVersion", &self.raw_version),
+                ("--fml.mcVersion", &self.mc_version),
+            ] {
+                if !game_arguments.contains(&argument) {
+                    minecraft_arguments += &format!(" {} {}", argument, value);
+                }
+            }
+
+            let mut version_libraries = vec![forge_wrapper.library()];
+            version_libraries.extend(libraries(version.libraries.as_deref().unwrap_or_default()));
+
+            meta_version.main_class = Some(FORGEWRAPPER_MAIN_CLASS.to_string());
+            meta_version.minecraft_arguments = Some(minecraft_arguments);
+            meta_version.libraries = Some(version_libraries);
+            meta_version.maven_files = Some(maven_files);
+        } else {
+            let tweakers = tweakers(version.minecraft_arguments.as_ref()?);
+
+            meta_version.main_class = version.main_class.clone();
+            meta_version.libraries =
+                Some(libraries(version.libraries.as_deref().unwrap_or_default()));
+            if !tweakers.is_empty() {
+                meta_version.additional_tweakers = Some(tweakers);
+            }
+        }
+
+        Some(meta_version)
+    }
+
+    /// Builds the component of a build whose installer only has the legacy install profile, which
+    /// describes the version itself. Libraries Minecraft already ships at the same or a newer
+    /// version are left out, as are LWJGL and log4j, which the launcher provides.
+    pub fn to_profile_meta_version(
+        &self,
+        profile: &ForgeInstallerProfileV1,
+        minecraft_libraries: &[GradleSpecifier],
+    ) -> MetaVersion {
+        let version_info = &profile.version_info;
+        let libraries = version_info
+            .libraries
+            .iter()
+            .flatten()
+            .filter_map(|library| {
+                let mut name = library.name.clone()?;
+                if name.is_lwjgl()
+                    || name.is_log4j()
+                    || minecraft_provides(minecraft_libraries, &name)
+                {
+                    return None;
+                }
+                // the universal jar is what the launcher puts on the classpath
+                if name.group == "net.minecraftforge" {
+                    if name.artifact == "minecraftforge" {
+                        name.artifact = "forge".to_string();
+                        name.version = format!("{}-{}", profile.install.minecraft, name.version);
+                        name.classifier = Some("universal".to_string());
+                    } else if name.artifact == "forge" {
+                        name.classifier = Some("universal".to_string());
+                    }
+                }
+                let url = library.url.as_ref().map(|url| {
+                    if url == "http://maven.minecraftforge.net/" {
+                        "https://maven.minecraftforge.net/".to_string()
+                    } else {
+                        url.clone()
+                    }
+                });
+                Some(Library {
+                    name: Some(name),
+                    url,
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let tweakers = version_info
+            .minecraft_arguments
+            .as_deref()
+            .map(tweakers)
+            .unwrap_or_default();
+
+        MetaVersion {
+            format_version: META_FORMAT_VERSION,
+            name: "Forge".to_string(),
+            version: self.raw_version.clone(),
+            uid: "net.minecraftforge".to_string(),
+            order: Some(5),
+            requires: Some(vec![Dependency {
+                uid: "net.minecraft".to_string(),
+                equals: Some(self.mc_version_sane.clone()),
+                suggests: None,
+            }]),
+            main_class: version_info.main_class.clone(),
+            release_time: version_info.time,
+            libraries: Some(libraries),
+            additional_tweakers: (!tweakers.is_empty()).then_some(tweakers),
+            ..Default::default()
+        }
+    }
+
     pub fn is_supported(&self) -> bool {
         if self.url().is_none() {
             return false;
@@ -902,6 +1389,313 @@ impl ForgeProcessedVersion {
 
 #[cfg(test)]
 mod tests {
+    fn forge_entry(long_version: &str, files: &[(&str, &str)]) -> super::ForgeEntry {
+        let (mc_version, version) = long_version.split_once('-').unwrap();
+        super::ForgeEntry {
+            long_version: long_version.to_string(),
+            mc_version: mc_version.to_string(),
+            version: version.to_string(),
+            build: version.rsplit('.').next().unwrap().parse().unwrap(),
+            files: Some(
+                files
+                    .iter()
+                    .map(|(classifier, extension)| {
+                        (
+                            classifier.to_string(),
+                            super::ForgeFile {
+                                classifier: classifier.to_string(),
+                                hash: "0".repeat(32),
+                                extension: extension.to_string(),
+                            },
+                        )
+                    })
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ancient_version() {
+        let entry = forge_entry(
+            "1.2.5-3.4.9.171",
+            &[("client", "zip"), ("server", "zip"), ("universal", "zip")],
+        );
+        let version = super::ForgeProcessedVersion::new(&entry, super::DEFAULT_FORGE_MAVEN_URL);
+        assert!(version.is_ancient());
+        assert!(!version.uses_installer());
+        assert!(version.is_supported());
+        assert_eq!(
+            version.filename().unwrap(),
+            "forge-1.2.5-3.4.9.171-client.zip"
+        );
+        assert_eq!(
+            version.url().unwrap(),
+            "https://maven.minecraftforge.net/net/minecraftforge/forge/1.2.5-3.4.9.171/forge-1.2.5-3.4.9.171-client.zip"
+        );
+
+        let entry = forge_entry("1.1-1.3.4.29", &[("client", "zip"), ("server", "zip")]);
+        let version = super::ForgeProcessedVersion::new(&entry, super::DEFAULT_FORGE_MAVEN_URL);
+        assert!(version.is_ancient());
+        assert_eq!(version.filename().unwrap(), "forge-1.1-1.3.4.29-client.zip");
+    }
+
+    #[test]
+    fn test_custom_maven_url() {
+        let entry = forge_entry("1.3.2-4.3.5.318", &[("client", "zip")]);
+        let version = super::ForgeProcessedVersion::new(&entry, "https://cache.example/forge/");
+        assert_eq!(
+            version.url().unwrap(),
+            "https://cache.example/forge/net/minecraftforge/forge/1.3.2-4.3.5.318/forge-1.3.2-4.3.5.318-client.zip"
+        );
+    }
+
+    #[test]
+    fn test_client_only_version() {
+        let entry = forge_entry("1.3.2-4.3.5.318", &[("client", "zip"), ("src", "zip")]);
+        let version = super::ForgeProcessedVersion::new(&entry, super::DEFAULT_FORGE_MAVEN_URL);
+        assert!(!version.is_ancient());
+        assert!(version.is_client_only());
+        assert!(!version.uses_installer());
+        assert_eq!(
+            version.jar_mod_specifier().unwrap().to_string(),
+            "net.minecraftforge:forge:1.3.2-4.3.5.318:client@zip"
+        );
+
+        let info = super::ForgeLegacyInfo {
+            release_time: None,
+            size: Some(1234),
+            sha256: None,
+            sha1: Some("da39a3ee5e6b4b0d3255bfef95601890afd80709".to_string()),
+        };
+        let meta_version = version.to_legacy_meta_version(&info).unwrap();
+        assert_eq!(meta_version.version, "4.3.5.318");
+        assert_eq!(
+            meta_version.requires.unwrap()[0].equals.as_deref(),
+            Some("1.3.2")
+        );
+        let jar_mod = &meta_version.jar_mods.unwrap()[0];
+        let artifact = jar_mod
+            .downloads
+            .as_ref()
+            .unwrap()
+            .artifact
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            artifact.url,
+            "https://maven.minecraftforge.net/net/minecraftforge/forge/1.3.2-4.3.5.318/forge-1.3.2-4.3.5.318-client.zip"
+        );
+        assert_eq!(
+            jar_mod.name.as_ref().unwrap().path(),
+            "net/minecraftforge/forge/1.3.2-4.3.5.318/forge-1.3.2-4.3.5.318-client.zip"
+        );
+        assert_eq!(artifact.size, Some(1234));
+    }
+
+    #[test]
+    fn test_modern_version() {
+        let entry = forge_entry(
+            "1.12.2-14.23.5.2859",
+            &[("installer", "jar"), ("universal", "jar")],
+        );
+        let version = super::ForgeProcessedVersion::new(&entry, super::DEFAULT_FORGE_MAVEN_URL);
+        assert!(!version.is_ancient());
+        assert!(version.uses_installer());
+        assert_eq!(
+            version.filename().unwrap(),
+            "forge-1.12.2-14.23.5.2859-installer.jar"
+        );
+    }
+
+    #[test]
+    fn test_profile_meta_version() {
+        let entry = forge_entry("1.7.10-10.13.4.1614-1.7.10", &[("installer", "jar")]);
+        let version = super::ForgeProcessedVersion::new(&entry, super::DEFAULT_FORGE_MAVEN_URL);
+        let profile = serde_json::from_str::<super::ForgeInstallerProfile>(
+            r#"{
+                "install": {
+                    "profileName": "Forge",
+                    "target": "1.7.10-Forge10.13.4.1614-1.7.10",
+                    "path": "net.minecraftforge:forge:1.7.10-10.13.4.1614-1.7.10",
+                    "version": "forge 1.7.10-10.13.4.1614-1.7.10",
+                    "filePath": "forge-1.7.10-10.13.4.1614-1.7.10-universal.jar",
+                    "welcome": "Welcome to the simple Forge installer.",
+                    "minecraft": "1.7.10",
+                    "mirrorList": "http://files.minecraftforge.net/mirror-brand.list",
+                    "logo": "/big_logo.png"
+                },
+                "versionInfo": {
+                    "id": "1.7.10-Forge10.13.4.1614-1.7.10",
+                    "time": "2015-06-25T01:06:38-0400",
+                    "releaseTime": "1960-01-01T00:00:00-0700",
+                    "type": "release",
+                    "minecraftArguments": "--username ${auth_player_name} --tweakClass cpw.mods.fml.common.launcher.FMLTweaker",
+                    "mainClass": "net.minecraft.launchwrapper.Launch",
+                    "inheritsFrom": "1.7.10",
+                    "jar": "1.7.10",
+                    "libraries": [
+                        { "name": "net.minecraftforge:forge:1.7.10-10.13.4.1614-1.7.10", "url": "http://files.minecraftforge.net/maven/" },
+                        { "name": "net.minecraft:launchwrapper:1.12", "serverreq": true },
+                        { "name": "com.google.guava:guava:16.0", "serverreq": true, "clientreq": true },
+                        { "name": "org.lwjgl.lwjgl:lwjgl:2.9.1", "clientreq": true },
+                        { "name": "org.scala-lang:scala-library:2.11.1", "url": "http://files.minecraftforge.net/maven/", "checksums": ["0"], "serverreq": true, "clientreq": true }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let super::ForgeInstallerProfile::V1(profile) = profile else {
+            panic!("legacy install profile read as a modern one");
+        };
+        let minecraft_libraries = ["com.google.guava:guava:17.0", "com.mojang:authlib:1.5.21"]
+            .map(|name| name.parse().unwrap());
+
+        let meta_version = version.to_profile_meta_version(&profile, &minecraft_libraries);
+        assert_eq!(
+            meta_version.main_class.as_deref(),
+            Some("net.minecraft.launchwrapper.Launch")
+        );
+        assert_eq!(
+            meta_version.additional_tweakers.unwrap(),
+            vec!["cpw.mods.fml.common.launcher.FMLTweaker"]
+        );
+        assert_eq!(meta_version.release_time.unwrap().year(), 2015);
+        let libraries = meta_version.libraries.unwrap();
+        let names = libraries
+            .iter()
+            .map(|library| library.name.as_ref().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "net.minecraftforge:forge:1.7.10-10.13.4.1614-1.7.10:universal",
+                "net.minecraft:launchwrapper:1.12",
+                "org.scala-lang:scala-library:2.11.1",
+            ]
+        );
+        assert_eq!(
+            libraries[0].url.as_deref(),
+            Some("http://files.minecraftforge.net/maven/")
+        );
+    }
+
+    #[test]
+    fn test_installer_meta_version() {
+        let entry = forge_entry("1.20.1-47.1.0", &[("installer", "jar")]);
+        let version = super::ForgeProcessedVersion::new(&entry, super::DEFAULT_FORGE_MAVEN_URL);
+        let profile: super::ForgeInstallerProfileV2 = serde_json::from_str(
+            r#"{
+                "spec": 1,
+                "libraries": [
+                    {
+                        "name": "net.minecraftforge:forge:1.20.1-47.1.0:universal",
+                        "downloads": { "artifact": { "path": "", "url": "", "sha1": "abc", "size": 1 } }
+                    },
+                    {
+                        "name": "org.apache.logging.log4j:log4j-api:2.19.0",
+                        "downloads": { "artifact": { "url": "https://example.com/log4j.jar" } }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let mojang_version: crate::models::mojang::MojangVersion = serde_json::from_str(
+            r#"{
+                "id": "1.20.1-forge-47.1.0",
+                "mainClass": "cpw.mods.bootstraplauncher.BootstrapLauncher",
+                "releaseTime": "2023-07-08T14:52:32+00:00",
+                "time": "2023-07-08T14:52:32+00:00",
+                "arguments": { "game": ["--launchTarget", "forgeclient", "--fml.forgeVersion", "47.1.0"] },
+                "libraries": []
+            }"#,
+        )
+        .unwrap();
+        let info = super::InstallerInfo {
+            sha1hash: Some("def".to_string()),
+            sha256hash: None,
+            size: Some(42),
+        };
+
+        let meta_version = version
+            .to_installer_meta_version(
+                &profile,
+                &mojang_version,
+                &info,
+                super::DEFAULT_FORGE_MAVEN_URL,
+                &super::ForgeWrapper {
+                    sha1: Some("b059aa8c4d2508055c6ed2a2561923a5e670a5eb".to_string()),
+                    size: Some(34860),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            meta_version.main_class.as_deref(),
+            Some(super::FORGEWRAPPER_MAIN_CLASS)
+        );
+        let forge_wrapper = &meta_version.libraries.unwrap()[0];
+        assert!(forge_wrapper.url.is_none());
+        assert_eq!(
+            forge_wrapper.downloads.as_ref().unwrap().artifact.as_ref().unwrap().url,
+            "https://files.prismlauncher.org/maven/io/github/zekerzhayard/ForgeWrapper/prism-2024-02-29/ForgeWrapper-prism-2024-02-29.jar"
+        );
+        let maven_files = meta_version.maven_files.unwrap();
+        assert_eq!(maven_files.len(), 2);
+        let universal = maven_files[1]
+            .downloads
+            .as_ref()
+            .unwrap()
+            .artifact
+            .as_ref()
+            .unwrap();
+        assert!(universal
+            .url
+            .ends_with("net/minecraftforge/forge/1.20.1-47.1.0/forge-1.20.1-47.1.0-universal.jar"));
+        let arguments = meta_version.minecraft_arguments.unwrap();
+        assert!(arguments.contains("--launchTarget forgeclient"));
+        assert_eq!(arguments.matches("--fml.forgeVersion").count(), 1);
+        assert!(arguments.contains("--fml.mcVersion 1.20.1"));
+    }
+
+    #[test]
+    fn test_mcp_index() {
+        let profile = |minecraft: &str, mcp_version: &str| -> super::ForgeInstallerProfile {
+            serde_json::from_str(&format!(
+                r#"{{
+                    "spec": 0,
+                    "minecraft": "{}",
+                    "data": {{
+                        "MCP_VERSION": {{ "client": "'{}'", "server": "'{}'" }}
+                    }}
+                }}"#,
+                minecraft, mcp_version, mcp_version
+            ))
+            .unwrap()
+        };
+
+        let mut index = super::ForgeMcpIndex::default();
+        index.add("1.16.5-36.2.39", &profile("1.16.5", "20210115.111550"));
+        index.add("1.16.5-36.2.34", &profile("1.16.5", "20210115.111550"));
+        index.add("1.17.1-37.1.1", &profile("1.17.1", "20210706.113038"));
+        // installers without the data entry are left out
+        index.add(
+            "1.13.2-25.0.219",
+            &serde_json::from_str(r#"{"spec": 0, "minecraft": "1.13.2"}"#).unwrap(),
+        );
+
+        assert_eq!(index.mcp_versions.len(), 2);
+        let entry = &index.mcp_versions["20210115.111550"];
+        assert_eq!(
+            entry.minecraft_versions.iter().collect::<Vec<_>>(),
+            vec!["1.16.5"]
+        );
+        assert_eq!(entry.forge_versions.len(), 2);
+        assert!(index.mcp_versions["20210706.113038"]
+            .forge_versions
+            .contains("1.17.1-37.1.1"));
+    }
+
     #[test]
     fn test_deserialization() {
         // meta dir is ./meta
@@ -0,0 +1,410 @@
+use serde::{Deserialize, Serialize};
+use serde_valid::Validate;
+use serde_with::skip_serializing_none;
+use std::collections::BTreeMap;
+
+pub const JAVA_UID: &str = "net.minecraft.java";
+
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct MojangJavaRuntimeAvailability {
+    pub group: i32,
+    pub progress: i32,
+}
+
+/// The file manifest listing every file of a runtime.
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct MojangJavaRuntimeManifest {
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct MojangJavaRuntimeVersion {
+    pub name: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub released: time::OffsetDateTime,
+}
+
+/// A Java runtime Mojang publishes for one platform.
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct MojangJavaRuntime {
+    pub availability: MojangJavaRuntimeAvailability,
+    pub manifest: MojangJavaRuntimeManifest,
+    pub version: MojangJavaRuntimeVersion,
+}
+
+/// Mojang's Java runtime index, keyed by platform and then by runtime component, e.g. `java-runtime-gamma`.
+pub type MojangJavaRuntimeIndex = BTreeMap<String, BTreeMap<String, Vec<MojangJavaRuntime>>>;
+
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct JavaChecksum {
+    #[serde(rename = "type")]
+    pub checksum_type: String,
+    pub hash: String,
+}
+
+/// The version of a Java runtime, split up from its name.
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Validate, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct JavaVersionMeta {
+    pub major: i32,
+    pub minor: i32,
+    pub security: i32,
+    pub build: Option<i32>,
+    pub name: String,
+}
+
+impl JavaVersionMeta {
+    /// Parse both the legacy (`8u51`, `1.8.0_51`) and the modern (`17.0.8`) version names.
+    pub fn parse(name: &str) -> Option<Self> {
+        let numbers = |s: &str| {
+            s.split('.')
+                .map(|n| n.parse::<i32>().ok())
+                .collect::<Option<Vec<_>>>()
+        };
+
+        let (major, minor, security, build) = if let Some((major, update)) = name.split_once('u') {
+            (major.parse().ok()?, 0, update.parse().ok()?, None)
+        } else if let Some((version, update)) = name.split_once('_') {
+            let version = numbers(version)?;
+            if version.first() != Some(&1) {
+                return None;
+            }
+            (*version.get(1)?, 0, update.parse().ok()?, None)
+        } else {
+            let version = numbers(name)?;
+            (
+                *version.first()?,
+                version.get(1).copied().unwrap_or(0),
+                version.get(2).copied().unwrap_or(0),
+                version.get(3).copied(),
+            )
+        };
+
+        Some(Self {
+            major,
+            minor,
+            security,
+            build,
+            name: name.to_string(),
+        })
+    }
+}
+
+/// A Java runtime a launcher can download, as listed by the `net.minecraft.java` component.
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct JavaRuntimeMeta {
+    pub name: String,
+    #[serde(rename = "runtimeOS")]
+    pub runtime_os: String,
+    pub vendor: String,
+    pub url: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub release_time: time::OffsetDateTime,
+    pub checksum: JavaChecksum,
+    pub download_type: String,
+    pub package_type: String,
+    pub version: JavaVersionMeta,
+}
+
+/// The launcher's name of a Mojang runtime platform, `None` for platforms no launcher runs on.
+pub fn mojang_runtime_os(platform: &str) -> Option<&'static str> {
+    match platform {
+        "linux" => Some("linux-x64"),
+        "linux-i386" => Some("linux-x86"),
+        "mac-os" => Some("mac-os-x64"),
+        "mac-os-arm64" => Some("mac-os-arm64"),
+        "windows-x64" => Some("windows-x64"),
+        "windows-x86" => Some("windows-x86"),
+        "windows-arm64" => Some("windows-arm64"),
+        _ => None,
+    }
+}
+
+impl MojangJavaRuntime {
+    /// Describe the runtime for the launcher, `None` if its version can not be understood.
+    pub fn to_runtime_meta(&self, component: &str, runtime_os: &str) -> Option<JavaRuntimeMeta> {
+        Some(JavaRuntimeMeta {
+            name: component.to_string(),
+            runtime_os: runtime_os.to_string(),
+            vendor: "mojang".to_string(),
+            url: self.manifest.url.clone(),
+            release_time: self.version.released,
+            checksum: JavaChecksum {
+                checksum_type: "sha1".to_string(),
+                hash: self.manifest.sha1.clone(),
+            },
+            download_type: "manifest".to_string(),
+            package_type: "jre".to_string(),
+            version: JavaVersionMeta::parse(&self.version.name)?,
+        })
+    }
+}
+
+/// The Java runtimes of one vendor, keyed by Java major.
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct JavaRuntimeIndex {
+    pub vendor: String,
+    pub majors: BTreeMap<i32, Vec<JavaRuntimeMeta>>,
+}
+
+/// The launcher's name of an operating system and architecture, `None` for those no launcher runs on.
+pub fn runtime_os(os: &str, arch: &str) -> Option<String> {
+    let os = match os {
+        "linux" => "linux",
+        "windows" => "windows",
+        "mac" | "macos" => "mac-os",
+        _ => return None,
+    };
+    let arch = match arch {
+        "x64" => "x64",
+        "x86" | "x32" => "x86",
+        "aarch64" | "arm64" => "arm64",
+        "arm" | "arm32" => "arm32",
+        _ => return None,
+    };
+    Some(format!("{}-{}", os, arch))
+}
+
+// The Adoptium and Azul APIs return many more fields than are needed and add new ones
+// without notice, so unlike Mojang's documents their models accept unknown fields.
+
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+pub struct AdoptiumPackage {
+    pub name: String,
+    pub link: String,
+    pub checksum: Option<String>,
+    pub size: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+pub struct AdoptiumBinary {
+    pub os: String,
+    pub architecture: String,
+    pub image_type: String,
+    pub package: AdoptiumPackage,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: time::OffsetDateTime,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+pub struct AdoptiumVersion {
+    pub major: i32,
+    pub minor: i32,
+    pub security: i32,
+    pub build: Option<i32>,
+    pub openjdk_version: String,
+}
+
+/// A build listed by Adoptium's `/v3/assets/latest` endpoint.
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+pub struct AdoptiumAsset {
+    pub binary: AdoptiumBinary,
+    pub release_name: String,
+    pub version: AdoptiumVersion,
+}
+
+impl AdoptiumAsset {
+    /// Describe the build for the launcher, `None` for platforms no launcher runs on or builds without a checksum.
+    pub fn to_runtime_meta(&self) -> Option<JavaRuntimeMeta> {
+        Some(JavaRuntimeMeta {
+            name: self.release_name.clone(),
+            runtime_os: runtime_os(&self.binary.os, &self.binary.architecture)?,
+            vendor: "adoptium".to_string(),
+            url: self.binary.package.link.clone(),
+            release_time: self.binary.updated_at,
+            checksum: JavaChecksum {
+                checksum_type: "sha256".to_string(),
+                hash: self.binary.package.checksum.clone()?,
+            },
+            download_type: "archive".to_string(),
+            package_type: self.binary.image_type.clone(),
+            version: JavaVersionMeta {
+                major: self.version.major,
+                minor: self.version.minor,
+                security: self.version.security,
+                build: self.version.build,
+                name: self.version.openjdk_version.clone(),
+            },
+        })
+    }
+}
+
+/// A package listed by Azul's `/metadata/v1/zulu/packages` endpoint.
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+pub struct AzulPackage {
+    pub package_uuid: String,
+    pub name: String,
+    pub java_version: Vec<i32>,
+    pub openjdk_build_number: Option<i32>,
+    pub download_url: String,
+}
+
+/// The details of a single Azul package, which unlike the listing include its checksum.
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+pub struct AzulPackageDetail {
+    pub package_uuid: String,
+    pub name: String,
+    pub java_version: Vec<i32>,
+    pub openjdk_build_number: Option<i32>,
+    pub download_url: String,
+    pub java_package_type: String,
+    pub sha256_hash: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub build_date: time::OffsetDateTime,
+}
+
+impl AzulPackageDetail {
+    /// Describe the package for the launcher, `None` if it has no Java version.
+    pub fn to_runtime_meta(&self, runtime_os: &str) -> Option<JavaRuntimeMeta> {
+        let version = |i: usize| self.java_version.get(i).copied();
+        Some(JavaRuntimeMeta {
+            name: self.name.clone(),
+            runtime_os: runtime_os.to_string(),
+            vendor: "azul".to_string(),
+            url: self.download_url.clone(),
+            release_time: self.build_date,
+            checksum: JavaChecksum {
+                checksum_type: "sha256".to_string(),
+                hash: self.sha256_hash.clone(),
+            },
+            download_type: "archive".to_string(),
+            package_type: self.java_package_type.clone(),
+            version: JavaVersionMeta {
+                major: version(0)?,
+                minor: version(1).unwrap_or(0),
+                security: version(2).unwrap_or(0),
+                build: self.openjdk_build_number,
+                name: self
+                    .java_version
+                    .iter()
+                    .map(|n| n.to_string())
+                    .collect::<Vec<_>>()
+                    .join("."),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        mojang_runtime_os, runtime_os, AdoptiumAsset, AzulPackageDetail, JavaVersionMeta,
+        MojangJavaRuntimeIndex,
+    };
+
+    #[test]
+    fn test_version_parsing() {
+        let legacy = JavaVersionMeta::parse("8u51").unwrap();
+        assert_eq!((legacy.major, legacy.minor, legacy.security), (8, 0, 51));
+        let old = JavaVersionMeta::parse("1.8.0_51").unwrap();
+        assert_eq!((old.major, old.minor, old.security), (8, 0, 51));
+        let modern = JavaVersionMeta::parse("16.0.1.9.1").unwrap();
+        assert_eq!(
+            (modern.major, modern.minor, modern.security, modern.build),
+            (16, 0, 1, Some(9))
+        );
+        assert!(JavaVersionMeta::parse("latest").is_none());
+    }
+
+    #[test]
+    fn test_runtime_meta() {
+        let index: MojangJavaRuntimeIndex = serde_json::from_str(
+            r#"{
+                "linux": {
+                    "java-runtime-gamma": [
+                        {
+                            "availability": { "group": 10775, "progress": 100 },
+                            "manifest": {
+                                "sha1": "2ed6bff1a7e1e3ff3b8b1a5e4e4a3d1e8d2b2a71",
+                                "size": 127131,
+                                "url": "https://piston-meta.mojang.com/v1/packages/2ed6bff1a7e1e3ff3b8b1a5e4e4a3d1e8d2b2a71/manifest.json"
+                            },
+                            "version": { "name": "17.0.8", "released": "2023-07-18T00:00:00+00:00" }
+                        }
+                    ]
+                },
+                "gamecore": {}
+            }"#,
+        )
+        .unwrap();
+
+        let runtime = &index["linux"]["java-runtime-gamma"][0];
+        let meta = runtime
+            .to_runtime_meta("java-runtime-gamma", mojang_runtime_os("linux").unwrap())
+            .unwrap();
+        assert_eq!(meta.runtime_os, "linux-x64");
+        assert_eq!(meta.version.major, 17);
+        assert_eq!(meta.checksum.hash, runtime.manifest.sha1);
+        assert!(mojang_runtime_os("gamecore").is_none());
+    }
+
+    #[test]
+    fn test_vendor_runtime_meta() {
+        let assets: Vec<AdoptiumAsset> = serde_json::from_str(
+            r#"[
+                {
+                    "binary": {
+                        "architecture": "aarch64",
+                        "heap_size": "normal",
+                        "image_type": "jre",
+                        "jvm_impl": "hotspot",
+                        "os": "mac",
+                        "package": {
+                            "checksum": "a6b07bb5ae4a8b5a5e0e8d1e7d53df5e2f04dbb6b4d1e3ea93f5f0d9e4c0b6f1",
+                            "link": "https://github.com/adoptium/temurin17-binaries/releases/download/jdk-17.0.8.1%2B1/OpenJDK17U-jre_aarch64_mac_hotspot_17.0.8.1_1.tar.gz",
+                            "name": "OpenJDK17U-jre_aarch64_mac_hotspot_17.0.8.1_1.tar.gz",
+                            "size": 43962080
+                        },
+                        "updated_at": "2023-08-25T11:47:16Z"
+                    },
+                    "release_name": "jdk-17.0.8.1+1",
+                    "vendor": "eclipse",
+                    "version": {
+                        "build": 1,
+                        "major": 17,
+                        "minor": 0,
+                        "openjdk_version": "17.0.8.1+1",
+                        "security": 8,
+                        "semver": "17.0.8+101"
+                    }
+                }
+            ]"#,
+        )
+        .unwrap();
+        let adoptium = assets[0].to_runtime_meta().unwrap();
+        assert_eq!(adoptium.runtime_os, "mac-os-arm64");
+        assert_eq!(adoptium.package_type, "jre");
+        assert_eq!(adoptium.version.security, 8);
+
+        let package: AzulPackageDetail = serde_json::from_str(
+            r#"{
+                "package_uuid": "0bd4ac2e-6d61-4c59-b0b4-4e3d17ee4d2c",
+                "name": "zulu17.44.53-ca-jre17.0.8.1-linux_x64.tar.gz",
+                "java_version": [17, 0, 8, 1],
+                "openjdk_build_number": 1,
+                "latest": true,
+                "download_url": "https://cdn.azul.com/zulu/bin/zulu17.44.53-ca-jre17.0.8.1-linux_x64.tar.gz",
+                "java_package_type": "jre",
+                "sha256_hash": "0c7e8e9a2a8ddb5c6e2e8d5dfb3e4c9b0d6f2a3e8e4c1b5a7f9d3e2c1b0a9f8e",
+                "build_date": "2023-08-24T00:00:00Z"
+            }"#,
+        )
+        .unwrap();
+        let azul = package
+            .to_runtime_meta(&runtime_os("linux", "x64").unwrap())
+            .unwrap();
+        assert_eq!(azul.version.name, "17.0.8.1");
+        assert_eq!(azul.checksum.checksum_type, "sha256");
+        assert!(runtime_os("solaris", "sparcv9").is_none());
+    }
+}
@@ -0,0 +1,164 @@
+use crate::models::Library;
+use serde::{Deserialize, Serialize};
+use serde_valid::Validate;
+use serde_with::skip_serializing_none;
+use std::collections::BTreeMap;
+
+/// The development setup a LiteLoader version was built against.
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LiteloaderDev {
+    pub fg_version: Option<String>,
+    pub mappings: Option<String>,
+    pub mcp: Option<String>,
+}
+
+/// The maven repository the builds of a LiteLoader version are published to.
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LiteloaderRepo {
+    pub stream: String,
+    #[serde(rename = "type")]
+    pub repo_type: String,
+    pub url: String,
+    pub classifier: String,
+}
+
+/// A build of LiteLoader.
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LiteloaderArtefact {
+    pub tweak_class: String,
+    pub libraries: Vec<Library>,
+    pub stream: String,
+    pub file: String,
+    pub version: String,
+    pub build: Option<String>,
+    pub md5: String,
+    /// seconds since the epoch, as a string
+    pub timestamp: String,
+    pub src_jar: Option<String>,
+    pub mcp_jar: Option<String>,
+    pub last_successful_build: Option<i64>,
+    /// only set on snapshot builds
+    pub lwjgl: Option<String>,
+}
+
+/// The builds of a LiteLoader version keyed by build, with `latest` pointing at the newest one.
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct LiteloaderArtefacts {
+    #[serde(rename = "com.mumfrey:liteloader")]
+    pub liteloader: BTreeMap<String, LiteloaderArtefact>,
+    pub libraries: Option<Vec<Library>>,
+}
+
+/// Everything known about the LiteLoader builds for one Minecraft version.
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct LiteloaderEntry {
+    pub dev: Option<LiteloaderDev>,
+    pub repo: LiteloaderRepo,
+    pub artefacts: Option<LiteloaderArtefacts>,
+    pub snapshots: Option<LiteloaderArtefacts>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LiteloaderMeta {
+    pub description: String,
+    pub authors: String,
+    pub url: String,
+    pub updated: String,
+    pub updated_time: i64,
+}
+
+/// The `versions.json` of the LiteLoader download site, keyed by Minecraft version.
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct LiteloaderIndex {
+    pub meta: LiteloaderMeta,
+    pub versions: BTreeMap<String, LiteloaderEntry>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LiteloaderIndex;
+
+    #[test]
+    fn test_deserialization() {
+        let index: LiteloaderIndex = serde_json::from_str(
+            r#"{
+                "meta": {
+                    "description": "LiteLoader is a lightweight mod bootstrap designed to provide basic loader functionality for mods which don't need to modify game mechanics.",
+                    "authors": "Mumfrey",
+                    "url": "http://dl.liteloader.com",
+                    "updated": "2019-07-07T21:11:25+01:00",
+                    "updatedTime": 1562530285745
+                },
+                "versions": {
+                    "1.12.2": {
+                        "dev": {
+                            "fgVersion": "2.3",
+                            "mappings": "snapshot_20171120",
+                            "mcp": "1.12.2"
+                        },
+                        "repo": {
+                            "stream": "RELEASE",
+                            "type": "m2",
+                            "url": "http://dl.liteloader.com/repo/",
+                            "classifier": ""
+                        },
+                        "artefacts": {
+                            "com.mumfrey:liteloader": {
+                                "latest": {
+                                    "tweakClass": "com.mumfrey.liteloader.launch.LiteLoaderTweaker",
+                                    "libraries": [
+                                        { "name": "net.minecraft:launchwrapper:1.12" },
+                                        { "name": "org.ow2.asm:asm-all:5.2" }
+                                    ],
+                                    "stream": "RELEASE",
+                                    "file": "liteloader-1.12.2.jar",
+                                    "version": "1.12.2",
+                                    "md5": "3d4d9ed62ef5a9d0d7b0e5c3d2fb6a3c",
+                                    "timestamp": "1511880271"
+                                }
+                            }
+                        },
+                        "snapshots": {
+                            "libraries": [
+                                { "name": "net.minecraft:launchwrapper:1.12" }
+                            ],
+                            "com.mumfrey:liteloader": {
+                                "1.12.2-SNAPSHOT": {
+                                    "tweakClass": "com.mumfrey.liteloader.launch.LiteLoaderTweaker",
+                                    "libraries": [],
+                                    "stream": "SNAPSHOT",
+                                    "file": "liteloader-1.12.2-SNAPSHOT.jar",
+                                    "version": "1.12.2-SNAPSHOT",
+                                    "build": "git-eb7b9f3-b13",
+                                    "md5": "1420785ecbfed5aff4a586c5c9dd97eb",
+                                    "timestamp": "1546120034",
+                                    "lastSuccessfulBuild": 13,
+                                    "lwjgl": "2.9.4-nightly-20150209"
+                                }
+                            }
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let entry = &index.versions["1.12.2"];
+        assert_eq!(entry.repo.repo_type, "m2");
+        let latest = &entry.artefacts.as_ref().unwrap().liteloader["latest"];
+        assert_eq!(latest.libraries.len(), 2);
+        let snapshot = &entry.snapshots.as_ref().unwrap().liteloader["1.12.2-SNAPSHOT"];
+        assert_eq!(snapshot.last_successful_build, Some(13));
+    }
+}
@@ -1,20 +1,29 @@
 use core::ops::Deref;
 use serde::{Deserialize, Serialize};
 use serde_valid::Validate;
+use serde_with::skip_serializing_none;
 use std::collections::HashMap;
 use std::{fmt::Display, str::FromStr};
 use thiserror::Error;
 
+pub mod fabric;
 pub mod forge;
+pub mod java;
+pub mod liteloader;
 pub mod mojang;
+pub mod neoforge;
+pub mod optifine;
+pub mod platform;
 
 #[derive(Error, Debug)]
 pub enum ModelError {
     #[error("Invalid Gradle specifier '{specifier}'")]
     InvalidGradleSpecifier { specifier: String },
+    #[error("Unknown platform '{platform}'")]
+    InvalidPlatform { platform: String },
 }
 
-static META_FORMAT_VERSION: i32 = 1;
+pub static META_FORMAT_VERSION: i32 = 1;
 
 /// A Gradle specifier.
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
@@ -53,15 +62,54 @@ impl GradleSpecifier {
     }
 
     /// Returns the base path of the artifact.
+    ///
+    /// Timestamped snapshot artifacts live in the directory of their `-SNAPSHOT` version.
     pub fn base(&self) -> String {
         format!(
             "{}/{}/{}",
             self.group.replace('.', "/"),
             self.artifact,
-            self.version
+            self.base_version()
         )
     }
 
+    /// Returns `true` if the specifier is a `-SNAPSHOT` or timestamped snapshot version.
+    pub fn is_snapshot(&self) -> bool {
+        self.version.ends_with("-SNAPSHOT") || self.snapshot_timestamp().is_some()
+    }
+
+    /// Splits a unique snapshot version like `1.0-20230405.123456-7` into its
+    /// base version, timestamp and build number.
+    fn snapshot_timestamp(&self) -> Option<(&str, &str, &str)> {
+        let mut parts = self.version.rsplitn(3, '-');
+        let build = parts.next()?;
+        let timestamp = parts.next()?;
+        let base = parts.next()?;
+
+        let (date, time) = timestamp.split_once('.')?;
+        let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if base.is_empty()
+            || build.is_empty()
+            || !is_digits(build)
+            || date.len() != 8
+            || !is_digits(date)
+            || time.len() != 6
+            || !is_digits(time)
+        {
+            return None;
+        }
+        Some((base, timestamp, build))
+    }
+
+    /// Returns the version the artifact directory is named after, `X-SNAPSHOT` for
+    /// timestamped snapshots and the version itself otherwise.
+    pub fn base_version(&self) -> String {
+        match self.snapshot_timestamp() {
+            Some((base, _, _)) => format!("{}-SNAPSHOT", base),
+            None => self.version.clone(),
+        }
+    }
+
     /// Returns the full path of the artifact.
     pub fn path(&self) -> String {
         format!("{}/{}", self.base(), self.filename())
@@ -69,7 +117,7 @@ impl GradleSpecifier {
 
     /// Returns `true` if the specifier is a LWJGL artifact.
     pub fn is_lwjgl(&self) -> bool {
-        vec![
+        [
             "org.lwjgl",
             "org.lwjgl.lwjgl",
             "net.java.jinput",
@@ -80,7 +128,7 @@ impl GradleSpecifier {
 
     /// Returns `true` if the specifier is a Log4j artifact.
     pub fn is_log4j(&self) -> bool {
-        vec!["org.apache.logging.log4j"].contains(&self.group.as_str())
+        ["org.apache.logging.log4j"].contains(&self.group.as_str())
     }
 }
 
@@ -192,6 +240,7 @@ impl<'de> Deserialize<'de> for GradleSpecifier {
     }
 }
 
+#[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, Validate, merge::Merge)]
 #[serde(rename_all = "camelCase")]
 pub struct MojangArtifactBase {
@@ -206,6 +255,7 @@ pub struct MojangArtifactBase {
     pub unknown: HashMap<String, serde_json::Value>,
 }
 
+#[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, Validate, merge::Merge)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct MojangAssets {
@@ -221,6 +271,7 @@ pub struct MojangAssets {
     pub total_size: i32,
 }
 
+#[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, Validate, merge::Merge)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct MojangArtifact {
@@ -254,6 +305,7 @@ pub struct MojangLibraryExtractRules {
     pub exclude: Vec<String>, // TODO maybe drop this completely?
 }
 
+#[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, Validate, merge::Merge)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct MojangLibraryDownloads {
@@ -263,6 +315,7 @@ pub struct MojangLibraryDownloads {
     pub classifiers: Option<HashMap<String, MojangArtifact>>,
 }
 
+#[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, Validate, merge::Merge)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct OSRule {
@@ -274,7 +327,7 @@ pub struct OSRule {
 }
 
 fn os_rule_name_must_be_os(name: &String) -> Result<(), serde_valid::validation::Error> {
-    let valid_os_names = vec![
+    let valid_os_names = [
         "osx",
         "linux",
         "windows",
@@ -293,6 +346,7 @@ fn os_rule_name_must_be_os(name: &String) -> Result<(), serde_valid::validation:
     }
 }
 
+#[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, Validate, merge::Merge)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct MojangRule {
@@ -306,7 +360,7 @@ pub struct MojangRule {
 fn mojang_rule_action_must_be_allow_disallow(
     action: &String,
 ) -> Result<(), serde_valid::validation::Error> {
-    if !vec!["allow", "disallow"].contains(&action.as_str()) {
+    if !["allow", "disallow"].contains(&action.as_str()) {
         Err(serde_valid::validation::Error::Custom(format!(
             "`{}` not a valid action, must be `allow` or `disallow`",
             &action
@@ -317,7 +371,7 @@ fn mojang_rule_action_must_be_allow_disallow(
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Validate, merge::Merge)]
-#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[serde(transparent)]
 pub struct MojangRules {
     #[merge(strategy = merge::vec::append)]
     root: Vec<MojangRule>,
@@ -331,6 +385,7 @@ impl Deref for MojangRules {
     }
 }
 
+#[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, Validate, merge::Merge, Default)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct MojangLibrary {
@@ -346,6 +401,7 @@ pub struct MojangLibrary {
     pub rules: Option<MojangRules>,
 }
 
+#[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, Validate, merge::Merge, Default)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Library {
@@ -360,10 +416,10 @@ pub struct Library {
     #[merge(strategy = merge::option::recurse)]
     pub rules: Option<MojangRules>,
     #[merge(strategy = merge::option::overwrite_some)]
-    url: Option<String>,
+    pub url: Option<String>,
     #[serde(rename = "MMC-hint")]
     #[merge(strategy = merge::option::overwrite_some)]
-    mmc_hint: Option<String>,
+    pub mmc_hint: Option<String>,
 }
 
 impl From<MojangLibrary> for Library {
@@ -418,6 +474,7 @@ impl From<&Library> for MojangLibrary {
     }
 }
 
+#[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, Validate, merge::Merge, Default)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Dependency {
@@ -429,6 +486,7 @@ pub struct Dependency {
     pub suggests: Option<String>,
 }
 
+#[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, Validate, merge::Merge, Default)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct MetaVersion {
@@ -468,10 +526,11 @@ pub struct MetaVersion {
     #[merge(strategy = merge::option::overwrite_some)]
     pub minecraft_arguments: Option<String>,
     #[merge(strategy = merge::option::overwrite_some)]
-    #[serde(with = "time::serde::iso8601::option")]
+    #[serde(with = "time::serde::rfc3339::option", default)]
     pub release_time: Option<time::OffsetDateTime>,
     #[merge(strategy = merge::option_vec::append_some)]
     pub compatible_java_majors: Option<Vec<i32>>,
+    #[serde(rename = "+traits")]
     #[merge(strategy = merge::option_vec::append_some)]
     pub additional_traits: Option<Vec<String>>,
     #[serde(rename = "+tweakers")]
@@ -480,6 +539,54 @@ pub struct MetaVersion {
     #[serde(rename = "+jvmArgs")]
     #[merge(strategy = merge::option_vec::append_some)]
     pub additional_jvm_args: Option<Vec<String>>,
+    /// only set on the `net.minecraft.java` component
+    #[merge(strategy = merge::option_vec::append_some)]
+    pub runtimes: Option<Vec<java::JavaRuntimeMeta>>,
+}
+
+/// An entry of the global `index.json`, pointing at a package.
+#[derive(Deserialize, Serialize, Debug, Clone, Validate)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MetaPackageIndexEntry {
+    pub name: String,
+    pub uid: String,
+    pub sha256: String,
+}
+
+/// The global `index.json` listing every package.
+#[derive(Deserialize, Serialize, Debug, Clone, Validate)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MetaPackageIndex {
+    pub format_version: i32,
+    pub packages: Vec<MetaPackageIndexEntry>,
+}
+
+/// An entry of a package's `index.json`, pointing at a version of the package.
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Debug, Clone, Validate)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MetaVersionIndexEntry {
+    pub version: String,
+    #[serde(rename = "type")]
+    pub version_type: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option", default)]
+    pub release_time: Option<time::OffsetDateTime>,
+    pub requires: Option<Vec<Dependency>>,
+    pub conflicts: Option<Vec<Dependency>>,
+    #[serde(default)]
+    pub recommended: bool,
+    pub volatile: Option<bool>,
+    pub sha256: String,
+}
+
+/// A package's `index.json` listing every version of the package.
+#[derive(Deserialize, Serialize, Debug, Clone, Validate)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MetaVersionIndex {
+    pub format_version: i32,
+    pub name: String,
+    pub uid: String,
+    pub versions: Vec<MetaVersionIndexEntry>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -678,3 +785,87 @@ pub mod merge {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{GradleSpecifier, MetaVersion};
+
+    #[test]
+    fn test_meta_version_traits_round_trip() {
+        let version = serde_json::from_str::<MetaVersion>(
+            r#"{
+                "formatVersion": 1,
+                "name": "Minecraft",
+                "uid": "net.minecraft",
+                "version": "1.12.2",
+                "releaseTime": "2017-09-18T08:39:46+00:00",
+                "+traits": ["FirstThreadOnMacOS"]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            version.additional_traits,
+            Some(vec!["FirstThreadOnMacOS".to_string()])
+        );
+
+        let value = serde_json::to_value(&version).unwrap();
+        assert_eq!(value["+traits"], serde_json::json!(["FirstThreadOnMacOS"]));
+        assert!(value.get("additionalTraits").is_none());
+
+        let reparsed = serde_json::from_value::<MetaVersion>(value).unwrap();
+        assert_eq!(reparsed.additional_traits, version.additional_traits);
+    }
+
+    #[test]
+    fn test_gradle_specifier_release() {
+        let specifier = "org.ow2.asm:asm:9.5".parse::<GradleSpecifier>().unwrap();
+        assert!(!specifier.is_snapshot());
+        assert_eq!(specifier.path(), "org/ow2/asm/asm/9.5/asm-9.5.jar");
+        assert_eq!(specifier.to_string(), "org.ow2.asm:asm:9.5");
+    }
+
+    #[test]
+    fn test_gradle_specifier_snapshot() {
+        let specifier = "net.fabricmc:fabric-loader:0.15.0-SNAPSHOT:sources@zip"
+            .parse::<GradleSpecifier>()
+            .unwrap();
+        assert!(specifier.is_snapshot());
+        assert_eq!(specifier.base_version(), "0.15.0-SNAPSHOT");
+        assert_eq!(
+            specifier.path(),
+            "net/fabricmc/fabric-loader/0.15.0-SNAPSHOT/fabric-loader-0.15.0-SNAPSHOT-sources.zip"
+        );
+        assert_eq!(
+            specifier.to_string(),
+            "net.fabricmc:fabric-loader:0.15.0-SNAPSHOT:sources@zip"
+        );
+    }
+
+    #[test]
+    fn test_gradle_specifier_unique_snapshot() {
+        let specifier = "org.quiltmc:quilt-loader:0.19.0-20230405.123456-7"
+            .parse::<GradleSpecifier>()
+            .unwrap();
+        assert!(specifier.is_snapshot());
+        assert_eq!(specifier.version, "0.19.0-20230405.123456-7");
+        assert_eq!(specifier.base_version(), "0.19.0-SNAPSHOT");
+        assert_eq!(
+            specifier.path(),
+            "org/quiltmc/quilt-loader/0.19.0-SNAPSHOT/quilt-loader-0.19.0-20230405.123456-7.jar"
+        );
+        assert_eq!(
+            specifier.to_string(),
+            "org.quiltmc:quilt-loader:0.19.0-20230405.123456-7"
+        );
+    }
+
+    #[test]
+    fn test_gradle_specifier_not_a_snapshot() {
+        // looks close to a timestamp, but is a regular version
+        let specifier = "com.example:lib:1.0-2023.1-7"
+            .parse::<GradleSpecifier>()
+            .unwrap();
+        assert!(!specifier.is_snapshot());
+        assert_eq!(specifier.base(), "com/example/lib/1.0-2023.1-7");
+    }
+}
@@ -3,11 +3,11 @@ use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use serde_valid::Validate;
 use serde_with::skip_serializing_none;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
 use crate::models::{
-    GradleSpecifier, Library, MetaVersion, MojangArtifact, MojangArtifactBase, MojangAssets,
-    MojangLibrary, MojangLibraryDownloads, META_FORMAT_VERSION,
+    Dependency, GradleSpecifier, Library, MetaVersion, MojangArtifact, MojangArtifactBase,
+    MojangAssets, MojangLibrary, MojangLibraryDownloads, META_FORMAT_VERSION,
 };
 
 static SUPPORTED_LAUNCHER_VERSION: i32 = 21;
@@ -78,6 +78,28 @@ pub struct AssetIndex {
     pub url: String,
 }
 
+/// The asset index document an [`AssetIndex`] points to, listing the objects of the assets.
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Debug, Clone, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct MojangAssetIndex {
+    /// The objects by their path in the game's assets.
+    pub objects: BTreeMap<String, MojangAssetObject>,
+    /// Set by old indexes whose objects are copied to their path in a virtual assets directory.
+    #[serde(rename = "virtual")]
+    pub is_virtual: Option<bool>,
+    /// Set by old indexes whose objects are copied to the `resources` directory of the instance.
+    pub map_to_resources: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct MojangAssetObject {
+    /// The sha1 of the object, which is also where it is stored, e.g. `ab/ab12...`.
+    pub hash: String,
+    pub size: u64,
+}
+
 #[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, Validate)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
@@ -210,22 +232,18 @@ pub struct ManifestRuleFeatures {
     pub is_quick_play_singleplayer: Option<bool>,
     pub is_quick_play_multiplayer: Option<bool>,
     pub is_quick_play_realms: Option<bool>,
+    /// Feature flags Mojang added after this model was written, kept so they survive a round trip.
     #[serde(flatten)]
-    #[validate(custom(validate_empty_unknown_key_map))]
     pub unknown: HashMap<String, serde_json::Value>,
 }
 
-fn validate_empty_unknown_key_map(
-    map: &HashMap<String, serde_json::Value>,
-) -> Result<(), serde_valid::validation::Error> {
-    if !map.is_empty() {
-        return Err(serde_valid::validation::Error::Custom(format!(
-            "There are unknown keys present: {:?}",
-            map
-        )));
+impl ManifestRule {
+    /// Returns the feature keys of this rule that are not known to the model.
+    pub fn unknown_features(&self) -> impl Iterator<Item = &String> {
+        self.features
+            .iter()
+            .flat_map(|features| features.unknown.keys())
     }
-
-    Ok(())
 }
 
 #[skip_serializing_none]
@@ -323,6 +341,30 @@ pub struct MinecraftVersion {
     pub release_type: String,
 }
 
+impl MinecraftVersion {
+    /// Returns every rule feature key used by this version that is not known to the model.
+    pub fn unknown_rule_features(&self) -> BTreeSet<String> {
+        let library_rules = self
+            .libraries
+            .iter()
+            .flat_map(|library| library.rules.iter().flatten());
+        let argument_rules = self
+            .arguments
+            .iter()
+            .flat_map(|arguments| arguments.game.iter().chain(arguments.jvm.iter()))
+            .flat_map(|argument| match argument {
+                VersionArgument::Object(object) => object.rules.iter(),
+                VersionArgument::String(_) => [].iter(),
+            });
+
+        library_rules
+            .chain(argument_rules)
+            .flat_map(|rule| rule.unknown_features())
+            .cloned()
+            .collect()
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, Validate)]
 pub struct ExperimentEntry {
     pub id: String,
@@ -335,14 +377,18 @@ pub struct ExperimentIndex {
     pub experiments: Vec<ExperimentEntry>,
 }
 
+/// A version missing from Mojang's manifest, listed by our static data or an external archive.
+///
+/// Archives may leave out the hash and size of the jar, which are then computed from the jar.
+#[skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, Validate)]
 pub struct OldSnapshotEntry {
     pub id: String,
     pub url: String,
     pub wiki: Option<String>,
     pub jar: String,
-    pub sha1: String,
-    pub size: i32,
+    pub sha1: Option<String>,
+    pub size: Option<i32>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Validate)]
@@ -355,7 +401,7 @@ pub struct OldSnapshotIndex {
 pub struct LegacyOverrideEntry {
     main_class: Option<String>,
     applet_class: Option<String>,
-    #[serde(with = "time::serde::iso8601::option")]
+    #[serde(default, with = "time::serde::iso8601::option")]
     pub release_time: Option<time::OffsetDateTime>,
     #[serde(rename = "+traits")]
     additional_traits: Option<Vec<String>>,
@@ -409,12 +455,14 @@ impl LegacyOverrideEntry {
 
 #[derive(Deserialize, Serialize, Debug, Clone, Validate)]
 pub struct LegacyOverrideIndex {
-    versions: HashMap<String, LegacyOverrideEntry>,
+    pub versions: HashMap<String, LegacyOverrideEntry>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Validate)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct LibraryPatch {
+    #[serde(rename = "_comment")]
+    pub comment: Option<String>,
     #[serde(rename = "match")]
     pub patch_match: Vec<GradleSpecifier>,
     #[serde(rename = "override")]
@@ -439,10 +487,83 @@ impl LibraryPatch {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Validate)]
+#[serde(transparent)]
 pub struct LibraryPatches {
     root: Vec<LibraryPatch>,
 }
 
+impl LibraryPatches {
+    /// Merges the override of every patch matching `library` into it and returns the additional
+    /// libraries those patches bring, along with whether they are to be patched in turn.
+    fn apply_patches(&self, library: &mut Library) -> Vec<(Library, bool)> {
+        let mut additional = vec![];
+        for patch in self.iter() {
+            if !patch.applies(library) {
+                continue;
+            }
+            if let Some(patch_override) = &patch.patch_override {
+                merge::Merge::merge(library, patch_override.clone());
+            }
+            if let Some(additional_libraries) = &patch.additional_libraries {
+                additional.extend(
+                    additional_libraries
+                        .iter()
+                        .map(|new_library| (new_library.clone(), patch.patch_additional_libraries)),
+                );
+            }
+        }
+        additional
+    }
+
+    /// Patches `library` and returns the distinct additional libraries the matching patches bring.
+    pub fn patch_library(&self, library: &mut Library) -> Vec<Library> {
+        let mut additional: Vec<Library> = vec![];
+        let mut pending = VecDeque::from(self.apply_patches(library));
+        while let Some((mut new_library, patch)) = pending.pop_front() {
+            if patch {
+                pending.extend(self.apply_patches(&mut new_library));
+            }
+            push_distinct(&mut additional, new_library);
+        }
+        additional
+    }
+
+    /// Patches every library of a version, adding the additional libraries right after the one
+    /// that brought them unless the version already has them.
+    pub fn apply_onto_meta_version(&self, meta_version: &mut MetaVersion) {
+        let Some(libraries) = meta_version.libraries.take() else {
+            return;
+        };
+        let mut known = libraries
+            .iter()
+            .filter_map(|library| serde_json::to_string(library).ok())
+            .collect::<HashSet<_>>();
+        let mut patched: Vec<Library> = Vec::with_capacity(libraries.len());
+        for mut library in libraries {
+            let additional = self.patch_library(&mut library);
+            patched.push(library);
+            for new_library in additional {
+                if let Ok(value) = serde_json::to_string(&new_library) {
+                    if known.insert(value) {
+                        patched.push(new_library);
+                    }
+                }
+            }
+        }
+        meta_version.libraries = Some(patched);
+    }
+}
+
+fn push_distinct(libraries: &mut Vec<Library>, library: Library) {
+    let value = serde_json::to_value(&library).ok();
+    if libraries
+        .iter()
+        .all(|known| serde_json::to_value(known).ok() != value)
+    {
+        libraries.push(library);
+    }
+}
+
 impl Deref for LibraryPatches {
     type Target = Vec<LibraryPatch>;
 
@@ -484,7 +605,7 @@ pub struct MojangLogging {
 fn mojang_logging_validate_type(
     logging_type: &String,
 ) -> Result<(), serde_valid::validation::Error> {
-    let valid_logging_types = vec!["log4j2-xml"];
+    let valid_logging_types = ["log4j2-xml"];
     if !valid_logging_types.contains(&logging_type.as_str()) {
         Err(serde_valid::validation::Error::Custom(format!(
             "invalid log type: {}",
@@ -594,9 +715,7 @@ impl MojangVersion {
             None => {}
             Some(0) => {}
             Some(1) => {
-                if addn_traits.is_none() {
-                    addn_traits = Some(vec![]);
-                }
+                addn_traits.get_or_insert_with(Vec::new);
             }
             Some(l) => {
                 panic!("Unsupported compliance level {}", l);
@@ -652,15 +771,255 @@ impl MojangVersion {
             applet_class: None,
             additional_tweakers: None,
             additional_jvm_args: None,
+            runtimes: None,
         }
     }
 }
 
+pub const LWJGL2_UID: &str = "org.lwjgl";
+pub const LWJGL3_UID: &str = "org.lwjgl3";
+
+/// Moves the LWJGL libraries of a Minecraft component into a component of their own,
+/// `org.lwjgl` for LWJGL 2 and `org.lwjgl3` for LWJGL 3, which the Minecraft component then suggests.
+///
+/// The LWJGL version is the one of its core `lwjgl` artifact. Versions whose LWJGL libraries
+/// lack it are left untouched and `None` is returned.
+pub fn extract_lwjgl(meta_version: &mut MetaVersion) -> Option<MetaVersion> {
+    let libraries = meta_version.libraries.as_ref()?;
+    let is_lwjgl = |library: &Library| library.name.as_ref().is_some_and(|name| name.is_lwjgl());
+
+    let core = libraries
+        .iter()
+        .filter_map(|library| library.name.as_ref())
+        .find(|name| {
+            name.artifact == "lwjgl"
+                && (name.group == "org.lwjgl" || name.group == "org.lwjgl.lwjgl")
+        })?;
+    // LWJGL 2 is published under org.lwjgl.lwjgl, LWJGL 3 under org.lwjgl
+    let (uid, name, conflict) = if core.group == "org.lwjgl" {
+        (LWJGL3_UID, "LWJGL 3", LWJGL2_UID)
+    } else {
+        (LWJGL2_UID, "LWJGL 2", LWJGL3_UID)
+    };
+    let version = core.version.clone();
+
+    let (lwjgl_libraries, libraries): (Vec<_>, Vec<_>) = meta_version
+        .libraries
+        .take()
+        .unwrap_or_default()
+        .into_iter()
+        .partition(is_lwjgl);
+    meta_version.libraries = Some(libraries);
+    meta_version
+        .requires
+        .get_or_insert_with(Vec::new)
+        .push(Dependency {
+            uid: uid.to_string(),
+            equals: None,
+            suggests: Some(version.clone()),
+        });
+
+    Some(MetaVersion {
+        format_version: META_FORMAT_VERSION,
+        name: name.to_string(),
+        version,
+        uid: uid.to_string(),
+        version_type: Some("release".to_string()),
+        order: Some(-1),
+        volatile: Some(true),
+        conflicts: Some(vec![Dependency {
+            uid: conflict.to_string(),
+            equals: None,
+            suggests: None,
+        }]),
+        libraries: Some(lwjgl_libraries),
+        release_time: meta_version.release_time,
+        ..Default::default()
+    })
+}
+
 #[cfg(test)]
 mod tests {
 
     use serde_valid::Validate;
 
+    #[test]
+    fn test_extract_lwjgl() {
+        let library = |name: &str| crate::models::Library {
+            name: Some(name.parse().unwrap()),
+            ..Default::default()
+        };
+        let mut meta_version = crate::models::MetaVersion {
+            uid: "net.minecraft".to_string(),
+            version: "1.12.2".to_string(),
+            libraries: Some(vec![
+                library("com.mojang:patchy:1.1"),
+                library("org.lwjgl.lwjgl:lwjgl:2.9.4-nightly-20150209"),
+                library("org.lwjgl.lwjgl:lwjgl_util:2.9.4-nightly-20150209"),
+                library("net.java.jinput:jinput:2.0.5"),
+            ]),
+            ..Default::default()
+        };
+
+        let lwjgl = super::extract_lwjgl(&mut meta_version).unwrap();
+        assert_eq!(lwjgl.uid, super::LWJGL2_UID);
+        assert_eq!(lwjgl.version, "2.9.4-nightly-20150209");
+        assert_eq!(lwjgl.libraries.unwrap().len(), 3);
+        assert_eq!(meta_version.libraries.unwrap().len(), 1);
+        let requires = meta_version.requires.unwrap();
+        assert_eq!(requires[0].uid, super::LWJGL2_UID);
+        assert_eq!(
+            requires[0].suggests.as_deref(),
+            Some("2.9.4-nightly-20150209")
+        );
+
+        let mut modern = crate::models::MetaVersion {
+            libraries: Some(vec![
+                library("org.lwjgl:lwjgl:3.3.1"),
+                library("org.lwjgl:lwjgl-glfw:3.3.1"),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            super::extract_lwjgl(&mut modern).unwrap().uid,
+            super::LWJGL3_UID
+        );
+
+        let mut without_lwjgl = crate::models::MetaVersion {
+            libraries: Some(vec![library("com.mojang:patchy:1.1")]),
+            ..Default::default()
+        };
+        assert!(super::extract_lwjgl(&mut without_lwjgl).is_none());
+        assert!(without_lwjgl.requires.is_none());
+    }
+
+    #[test]
+    fn test_asset_index() {
+        let index: super::MojangAssetIndex = serde_json::from_str(
+            r#"{
+                "map_to_resources": true,
+                "objects": {
+                    "sound/ambient/cave/cave1.ogg": {
+                        "hash": "63be67a6a3a2b1ed2ee6e6d05a1fc4e74dcc9c2e",
+                        "size": 17869
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(index.map_to_resources, Some(true));
+        assert_eq!(index.is_virtual, None);
+        assert_eq!(index.objects["sound/ambient/cave/cave1.ogg"].size, 17869);
+        assert!(serde_json::to_string(&index)
+            .unwrap()
+            .contains("\"map_to_resources\":true"));
+    }
+
+    #[test]
+    fn test_legacy_override() {
+        let mut index = serde_json::from_str::<super::LegacyOverrideIndex>(
+            r#"{"versions": {
+                "1.5.2": {"releaseTime": "2013-04-25T17:45:00+02:00", "+traits": ["legacyLaunch"]},
+                "c0.0.11a": {"mainClass": "com.mojang.minecraft.Minecraft", "+traits": ["no-texturepacks"]}
+            }}"#,
+        )
+        .unwrap();
+        let mut meta_version = crate::models::MetaVersion {
+            uid: "net.minecraft".to_string(),
+            version: "1.5.2".to_string(),
+            main_class: Some("net.minecraft.client.Minecraft".to_string()),
+            minecraft_arguments: Some("${auth_player_name} ${auth_session}".to_string()),
+            additional_traits: Some(vec!["texturepacks".to_string()]),
+            libraries: Some(vec![]),
+            ..Default::default()
+        };
+
+        let entry = index.versions.remove("1.5.2").unwrap();
+        entry.apply_onto_meta_version(&mut meta_version, true);
+        assert!(meta_version.main_class.is_none());
+        assert!(meta_version.libraries.is_none());
+        assert!(meta_version.minecraft_arguments.is_none());
+        assert_eq!(
+            meta_version.additional_traits.unwrap(),
+            vec!["texturepacks", "legacyLaunch"]
+        );
+        assert_eq!(
+            meta_version.release_time.unwrap().unix_timestamp(),
+            1366904700
+        );
+        assert!(index.versions["c0.0.11a"].release_time.is_none());
+    }
+
+    #[test]
+    fn test_library_patches() {
+        let patches = serde_json::from_str::<super::LibraryPatches>(
+            r#"[
+                {
+                    "_comment": "Point the broken natives somewhere else and add a missing library",
+                    "match": ["org.lwjgl:lwjgl:3.2.2"],
+                    "override": {"url": "https://example.com/maven/"},
+                    "additionalLibraries": [{"name": "org.lwjgl:lwjgl-tinyfd:3.2.2"}],
+                    "patchAdditionalLibraries": true
+                },
+                {
+                    "_comment": "Patched only because the previous patch asks for it",
+                    "match": ["org.lwjgl:lwjgl-tinyfd:3.2.2"],
+                    "additionalLibraries": [{"name": "org.lwjgl:lwjgl-extra:3.2.2"}]
+                }
+            ]"#,
+        )
+        .unwrap();
+        let library = |name: &str| crate::models::Library {
+            name: Some(name.parse().unwrap()),
+            ..Default::default()
+        };
+        let mut meta_version = crate::models::MetaVersion {
+            libraries: Some(vec![
+                library("org.lwjgl:lwjgl:3.2.2"),
+                library("org.lwjgl:lwjgl-extra:3.2.2"),
+                library("com.mojang:patchy:1.1"),
+            ]),
+            ..Default::default()
+        };
+
+        patches.apply_onto_meta_version(&mut meta_version);
+        let libraries = meta_version.libraries.unwrap();
+        let names = libraries
+            .iter()
+            .map(|library| library.name.as_ref().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "org.lwjgl:lwjgl:3.2.2",
+                "org.lwjgl:lwjgl-tinyfd:3.2.2",
+                "org.lwjgl:lwjgl-extra:3.2.2",
+                "com.mojang:patchy:1.1",
+            ]
+        );
+        assert_eq!(
+            libraries[0].url.as_deref(),
+            Some("https://example.com/maven/")
+        );
+    }
+
+    #[test]
+    fn test_unknown_rule_features() {
+        let rule = serde_json::from_str::<super::ManifestRule>(
+            r#"{"action": "allow", "features": {"is_demo_user": true, "has_new_thing": true}}"#,
+        )
+        .unwrap();
+        assert!(rule.validate().is_ok());
+        assert_eq!(
+            rule.unknown_features().collect::<Vec<_>>(),
+            vec!["has_new_thing"]
+        );
+        assert_eq!(
+            serde_json::to_value(&rule).unwrap()["features"]["has_new_thing"],
+            serde_json::Value::Bool(true)
+        );
+    }
+
     #[test]
     fn test_deserialization() {
         // meta dir is ./meta
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use serde_valid::Validate;
+use serde_with::skip_serializing_none;
+use std::collections::BTreeMap;
+
+pub const DEFAULT_NEOFORGE_MAVEN_URL: &str = "https://maven.neoforged.net/releases";
+
+/// The versions of an artifact, as listed by the `/api/maven/versions` endpoint of the NeoForge maven.
+#[derive(Deserialize, Serialize, Clone, Debug, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct NeoForgeMavenVersions {
+    pub is_snapshot: bool,
+    pub versions: Vec<String>,
+}
+
+/// A NeoForge build, from either the `net.neoforged:neoforge` artifact or the
+/// `net.neoforged:forge` artifact it was published as for Minecraft 1.20.1.
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Validate, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NeoForgeEntry {
+    pub artifact: String,
+    #[serde(rename = "longversion")]
+    pub long_version: String,
+    #[serde(rename = "mcversion")]
+    pub mc_version: String,
+    pub version: String,
+    pub latest: Option<bool>,
+}
+
+impl NeoForgeEntry {
+    /// Parses a version of a NeoForge artifact, or `None` if it does not follow either version scheme.
+    ///
+    /// The `forge` artifact prefixes its versions with the Minecraft version (`1.20.1-47.1.106`),
+    /// the `neoforge` artifact derives it from the first components (`20.4.80-beta` is for 1.20.4,
+    /// `21.0.1` for 1.21 and `26.1.0.3` for 26.1).
+    pub fn parse(artifact: &str, long_version: &str) -> Option<Self> {
+        let (mc_version, version) = if artifact == "forge" {
+            let (mc_version, version) = long_version.split_once('-')?;
+            if !mc_version.starts_with("1.") {
+                return None;
+            }
+            (mc_version.to_string(), version.to_string())
+        } else {
+            let numbers = long_version
+                .split_once('-')
+                .map_or(long_version, |(numbers, _)| numbers)
+                .split('.')
+                .map(|part| part.parse::<u32>().ok())
+                .collect::<Option<Vec<_>>>()?;
+            let mc_version = match numbers.as_slice() {
+                [0, ..] => return None,
+                [major, 0, _] => format!("1.{}", major),
+                [major, minor, _] => format!("1.{}.{}", major, minor),
+                [year, drop, 0, _] => format!("{}.{}", year, drop),
+                [year, drop, hotfix, _] => format!("{}.{}.{}", year, drop, hotfix),
+                _ => return None,
+            };
+            (mc_version, long_version.to_string())
+        };
+
+        Some(Self {
+            artifact: artifact.to_string(),
+            long_version: long_version.to_string(),
+            mc_version,
+            version,
+            latest: None,
+        })
+    }
+
+    pub fn is_beta(&self) -> bool {
+        self.long_version.contains("-beta") || self.long_version.contains("-alpha")
+    }
+
+    pub fn installer_filename(&self) -> String {
+        format!("{}-{}-installer.jar", self.artifact, self.long_version)
+    }
+
+    /// The url of the installer jar on the maven at `maven_url`.
+    pub fn installer_url(&self, maven_url: &str) -> String {
+        format!(
+            "{}/net/neoforged/{}/{}/{}",
+            maven_url.trim_end_matches('/'),
+            self.artifact,
+            self.long_version,
+            self.installer_filename()
+        )
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Validate, Default)]
+#[serde(deny_unknown_fields)]
+pub struct NeoForgeMCVersionInfo {
+    pub latest: Option<String>,
+    pub versions: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Validate, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DerivedNeoForgeIndex {
+    pub versions: BTreeMap<String, NeoForgeEntry>,
+    #[serde(rename = "by_mcversion")]
+    pub by_mc_version: BTreeMap<String, NeoForgeMCVersionInfo>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NeoForgeEntry;
+
+    #[test]
+    fn test_parse_versions() {
+        let legacy = NeoForgeEntry::parse("forge", "1.20.1-47.1.106").unwrap();
+        assert_eq!(legacy.mc_version, "1.20.1");
+        assert_eq!(legacy.version, "47.1.106");
+
+        let cases = [
+            ("20.2.86", "1.20.2"),
+            ("20.4.80-beta", "1.20.4"),
+            ("21.0.1-beta", "1.21"),
+            ("21.1.77", "1.21.1"),
+            ("26.1.0.3", "26.1"),
+            ("26.1.1.0-alpha.2", "26.1.1"),
+        ];
+        for (long_version, mc_version) in cases {
+            let entry = NeoForgeEntry::parse("neoforge", long_version).unwrap();
+            assert_eq!(entry.mc_version, mc_version, "{}", long_version);
+            assert_eq!(entry.version, long_version);
+        }
+        assert!(NeoForgeEntry::parse("neoforge", "20.4.80-beta")
+            .unwrap()
+            .is_beta());
+
+        assert!(NeoForgeEntry::parse("forge", "47.1.3").is_none());
+        assert!(NeoForgeEntry::parse("neoforge", "0.25w14craftmine.3-beta").is_none());
+        assert!(NeoForgeEntry::parse("neoforge", "20.2").is_none());
+    }
+
+    #[test]
+    fn test_installer_url() {
+        let entry = NeoForgeEntry::parse("neoforge", "21.1.77").unwrap();
+        assert_eq!(
+            entry.installer_url("https://maven.neoforged.net/releases/"),
+            "https://maven.neoforged.net/releases/net/neoforged/neoforge/21.1.77/neoforge-21.1.77-installer.jar"
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use serde_valid::Validate;
+use serde_with::skip_serializing_none;
+use std::collections::BTreeMap;
+
+/// A build of OptiFine as listed on the OptiFine download page.
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Validate)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct OptiFineBuild {
+    /// e.g. `OptiFine HD U I6`
+    pub name: String,
+    /// e.g. `OptiFine_1.20.4_HD_U_I6.jar`, prefixed with `preview_` for previews
+    pub filename: String,
+    pub mc_version: String,
+    pub preview: bool,
+    /// the Forge versions the build works with, as the download page words them
+    pub forge: Option<String>,
+    /// `YYYY-MM-DD`
+    pub release_date: Option<String>,
+    /// the download page of the build, the jar itself is behind a link only valid for a while
+    pub url: String,
+    /// only known once the jar has been downloaded
+    pub sha1: Option<String>,
+    pub sha256: Option<String>,
+    pub size: Option<u64>,
+}
+
+impl OptiFineBuild {
+    /// Whether the jar of the build has been hashed.
+    pub fn is_hashed(&self) -> bool {
+        self.sha1.is_some() && self.sha256.is_some() && self.size.is_some()
+    }
+}
+
+/// The OptiFine builds of every Minecraft version, newest first.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct OptiFineIndex {
+    pub versions: BTreeMap<String, Vec<OptiFineBuild>>,
+}
+
+impl OptiFineIndex {
+    /// Looks up a build by its file name.
+    pub fn build(&self, filename: &str) -> Option<&OptiFineBuild> {
+        self.versions
+            .values()
+            .flatten()
+            .find(|build| build.filename == filename)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OptiFineIndex;
+
+    #[test]
+    fn test_deserialization() {
+        let index: OptiFineIndex = serde_json::from_str(
+            r#"{
+                "versions": {
+                    "1.20.4": [
+                        {
+                            "name": "OptiFine HD U I7 pre1",
+                            "filename": "preview_OptiFine_1.20.4_HD_U_I7_pre1.jar",
+                            "mcVersion": "1.20.4",
+                            "preview": true,
+                            "url": "https://optifine.net/adloadx?f=preview_OptiFine_1.20.4_HD_U_I7_pre1.jar"
+                        },
+                        {
+                            "name": "OptiFine HD U I6",
+                            "filename": "OptiFine_1.20.4_HD_U_I6.jar",
+                            "mcVersion": "1.20.4",
+                            "preview": false,
+                            "forge": "Forge 49.0.x",
+                            "releaseDate": "2023-12-24",
+                            "url": "https://optifine.net/adloadx?f=OptiFine_1.20.4_HD_U_I6.jar",
+                            "sha1": "8f2dd2b4a34e1b6e2d1fcc8cc35b8c4bb8a9d3e5",
+                            "sha256": "4bd1ff8f1d0c1b7c1d3e4e4f5c71b0b8e0b1b2c3d4e5f60718293a4b5c6d7e8f",
+                            "size": 6953462
+                        }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let build = index.build("OptiFine_1.20.4_HD_U_I6.jar").unwrap();
+        assert!(build.is_hashed());
+        assert_eq!(build.release_date.as_deref(), Some("2023-12-24"));
+        assert!(!index
+            .build("preview_OptiFine_1.20.4_HD_U_I7_pre1.jar")
+            .unwrap()
+            .is_hashed());
+
+        let json = serde_json::to_value(&index).unwrap();
+        assert!(json["versions"]["1.20.4"][0].get("sha1").is_none());
+    }
+}
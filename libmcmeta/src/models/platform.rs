@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use crate::models::{Library, MetaVersion, ModelError, MojangRules};
+
+/// An operating system and architecture a launcher runs on, spelled the way the rules and natives
+/// of libraries name them, e.g. `linux` and `arm64`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Platform {
+    /// `linux`, `osx` or `windows`
+    pub os: String,
+    /// `x86_64`, `x86`, `arm64` or `arm32`
+    pub arch: String,
+}
+
+impl Platform {
+    /// Parses an operating system and architecture, accepting the usual aliases like `macos` or
+    /// `aarch64`.
+    pub fn new(os: &str, arch: &str) -> Result<Self, ModelError> {
+        let invalid = || ModelError::InvalidPlatform {
+            platform: format!("{}-{}", os, arch),
+        };
+        let normalized_os = match os.to_lowercase().as_str() {
+            "linux" => "linux",
+            "osx" | "macos" | "mac" => "osx",
+            "windows" | "win" => "windows",
+            _ => return Err(invalid()),
+        };
+        let normalized_arch = match arch.to_lowercase().as_str() {
+            "x86_64" | "amd64" | "x64" => "x86_64",
+            "x86" | "i386" | "i686" => "x86",
+            "arm64" | "aarch64" => "arm64",
+            "arm32" | "arm" | "armv7" | "armhf" => "arm32",
+            _ => return Err(invalid()),
+        };
+        Ok(Self {
+            os: normalized_os.to_string(),
+            arch: normalized_arch.to_string(),
+        })
+    }
+
+    /// Whether libraries also name the platform by its operating system alone, as they did before
+    /// ARM builds existed.
+    fn is_legacy_arch(&self) -> bool {
+        matches!(self.arch.as_str(), "x86_64" | "x86")
+    }
+
+    /// The precise name of the platform, like `linux-arm64`.
+    pub fn classifier(&self) -> String {
+        format!("{}-{}", self.os, self.arch)
+    }
+
+    /// Whether an os rule name or natives key names this platform.
+    pub fn matches(&self, name: &str) -> bool {
+        name == self.classifier() || (self.is_legacy_arch() && name == self.os)
+    }
+
+    /// What `${arch}` in a natives classifier stands for.
+    fn arch_bits(&self) -> &'static str {
+        match self.arch.as_str() {
+            "x86" | "arm32" => "32",
+            _ => "64",
+        }
+    }
+
+    /// Evaluates rules the way launchers do: nothing is allowed unless a rule allows it, and the
+    /// last rule applying to the platform wins.
+    pub fn allows(&self, rules: &MojangRules) -> bool {
+        rules
+            .iter()
+            .rev()
+            .find(|rule| rule.os.as_ref().is_none_or(|os| self.matches(&os.name)))
+            .is_some_and(|rule| rule.action == "allow")
+    }
+
+    /// The library as used on this platform, without its rules and with only the natives of the
+    /// platform, or `None` if it is not used on it.
+    pub fn library(&self, library: &Library) -> Option<Library> {
+        if library
+            .rules
+            .as_ref()
+            .is_some_and(|rules| !self.allows(rules))
+        {
+            return None;
+        }
+        let mut library = library.clone();
+        library.rules = None;
+
+        if let Some(natives) = &library.natives {
+            let classifier = self.classifier();
+            let (key, native) = natives
+                .get_key_value(&classifier)
+                .or_else(|| {
+                    self.is_legacy_arch()
+                        .then(|| natives.get_key_value(&self.os))
+                        .flatten()
+                })
+                .map(|(key, native)| (key.clone(), native.replace("${arch}", self.arch_bits())))?;
+            if let Some(classifiers) = library
+                .downloads
+                .as_mut()
+                .and_then(|downloads| downloads.classifiers.as_mut())
+            {
+                classifiers.retain(|name, _| *name == native);
+            }
+            library.natives = Some(HashMap::from([(key, native)]));
+        }
+        Some(library)
+    }
+
+    /// The version with only the libraries and maven files used on this platform.
+    pub fn version(&self, version: &MetaVersion) -> MetaVersion {
+        let filter = |libraries: &Option<Vec<Library>>| {
+            libraries.as_ref().map(|libraries| {
+                libraries
+                    .iter()
+                    .filter_map(|library| self.library(library))
+                    .collect()
+            })
+        };
+        MetaVersion {
+            libraries: filter(&version.libraries),
+            maven_files: filter(&version.maven_files),
+            ..version.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Platform;
+    use crate::models::{Library, MojangRules};
+
+    fn rules(json: &str) -> MojangRules {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_rules() {
+        let linux = Platform::new("linux", "amd64").unwrap();
+        let osx = Platform::new("macos", "x86_64").unwrap();
+        let linux_arm = Platform::new("linux", "aarch64").unwrap();
+
+        let all_but_osx =
+            rules(r#"[{"action": "allow"}, {"action": "disallow", "os": {"name": "osx"}}]"#);
+        assert!(linux.allows(&all_but_osx));
+        assert!(!osx.allows(&all_but_osx));
+
+        let only_linux = rules(r#"[{"action": "allow", "os": {"name": "linux"}}]"#);
+        assert!(linux.allows(&only_linux));
+        assert!(!osx.allows(&only_linux));
+        // a bare os name only stands for the x86 builds
+        assert!(!linux_arm.allows(&only_linux));
+
+        let only_linux_arm = rules(r#"[{"action": "allow", "os": {"name": "linux-arm64"}}]"#);
+        assert!(linux_arm.allows(&only_linux_arm));
+        assert!(!linux.allows(&only_linux_arm));
+
+        assert!(Platform::new("solaris", "x86_64").is_err());
+    }
+
+    #[test]
+    fn test_natives() {
+        let library: Library = serde_json::from_str(
+            r#"{
+                "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4-nightly-20150209",
+                "natives": {"linux": "natives-linux", "windows": "natives-windows-${arch}"},
+                "downloads": {
+                    "classifiers": {
+                        "natives-linux": {"sha1": "a", "size": 1, "url": "https://example.com/linux.jar"},
+                        "natives-windows-32": {"sha1": "b", "size": 1, "url": "https://example.com/windows-32.jar"},
+                        "natives-windows-64": {"sha1": "c", "size": 1, "url": "https://example.com/windows-64.jar"}
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let windows = Platform::new("windows", "x86").unwrap();
+        let narrowed = windows.library(&library).unwrap();
+        assert_eq!(
+            narrowed.natives.unwrap()["windows"],
+            "natives-windows-32".to_string()
+        );
+        let classifiers = narrowed.downloads.unwrap().classifiers.unwrap();
+        assert_eq!(classifiers.len(), 1);
+        assert!(classifiers.contains_key("natives-windows-32"));
+
+        assert!(Platform::new("osx", "x86_64")
+            .unwrap()
+            .library(&library)
+            .is_none());
+        assert!(Platform::new("linux", "arm64")
+            .unwrap()
+            .library(&library)
+            .is_none());
+    }
+}
@@ -1,5 +1,34 @@
-use anyhow::Result;
-use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, Context, Result};
+use libmcmeta::models::forge::{ForgeWrapper, FORGEWRAPPER_LIBRARY, FORGEWRAPPER_MAVEN_URL};
+use serde::{de::Error, Deserialize, Deserializer};
+
+use crate::{listener::PeerAddress, storage::UpdateSource};
+
+/// What an update does when single versions of a source fail to update
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorPolicy {
+    /// fail the whole update, storing none of the versions of the source
+    FailFast,
+    /// store the versions that worked and report the failed ones, which the next update retries
+    Continue,
+}
+
+/// How the log lines written to stdout are formatted
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// compact human readable lines
+    Text,
+    /// a JSON object per line, for log shippers like Loki or Elasticsearch
+    Json,
+}
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case", tag = "type")]
@@ -11,10 +40,329 @@ pub enum StorageFormat {
     Database,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct MetadataConfig {
+    /// downloads a source runs at the same time, unless set for it in `source_parallelism`
     pub max_parallel_fetch_connections: usize,
+    /// downloads each listed source runs at the same time, overriding `max_parallel_fetch_connections`
+    #[serde(default)]
+    pub source_parallelism: HashMap<UpdateSource, usize>,
+    /// cap on the downloads running at the same time across every source, 0 for no cap
+    pub max_concurrent_downloads: usize,
     pub static_directory: String,
+    /// seconds between background metadata updates, 0 to disable them
+    pub update_interval: u64,
+    /// fail instead of warning when upstream uses rule feature keys the models do not know
+    pub strict_rule_features: bool,
+    /// what an update does when single versions fail to update
+    pub error_policy: ErrorPolicy,
+    /// cap on the combined download bandwidth in bytes per second, 0 for no cap
+    pub max_download_bytes_per_second: u64,
+    /// seconds a deep health check result is reused for, limiting how often the check runs
+    pub deep_health_interval: u64,
+    /// urls of external archives listing old versions missing from Mojang's manifest
+    pub old_version_archives: Vec<String>,
+    /// directory the raw bytes of every upstream response are archived in, disabled when unset
+    #[serde(default)]
+    pub archive_directory: Option<String>,
+    /// Java majors Adoptium and Azul runtimes are indexed for, disabled when empty
+    #[serde(deserialize_with = "one_or_many_numbers")]
+    pub java_runtime_majors: Vec<i32>,
+    /// sources that are updated, generated and served, the others are left alone
+    pub sources: Vec<UpdateSource>,
+    /// seconds to wait for a connection to an upstream host, 0 for no limit
+    pub connect_timeout: u64,
+    /// seconds to wait for a response or the next chunk of its body, 0 for no limit
+    pub read_timeout: u64,
+    /// user agent every upstream request is sent with
+    pub user_agent: String,
+    /// proxy every upstream request is sent through, e.g. `http://proxy:3128`
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// PEM files of root certificates trusted next to the system ones, e.g. of a TLS intercepting proxy
+    pub ca_certificates: Vec<String>,
+    pub publish: PublishConfig,
+    pub forge_wrapper: ForgeWrapperConfig,
+    pub assets: AssetsConfig,
+    pub maven_mirror: MavenMirrorConfig,
+    pub forge_jars: ForgeJarsConfig,
+    pub optifine: OptiFineConfig,
+}
+
+/// The sources enabled unless configured otherwise, all but the optional Legacy Fabric, Ornithe
+/// and OptiFine
+const DEFAULT_SOURCES: [&str; 7] = [
+    "mojang",
+    "forge",
+    "neoforge",
+    "fabric",
+    "babric",
+    "liteloader",
+    "java",
+];
+
+impl MetadataConfig {
+    pub fn is_enabled(&self, source: UpdateSource) -> bool {
+        self.sources.contains(&source)
+    }
+
+    /**
+     * How many downloads of a source run at the same time, at least one
+     */
+    pub fn parallelism(&self, source: UpdateSource) -> usize {
+        self.source_parallelism
+            .get(&source)
+            .copied()
+            .unwrap_or(self.max_parallel_fetch_connections)
+            .max(1)
+    }
+
+    /**
+     * The names of the settings that differ from `previous`
+     */
+    pub fn changed_settings(&self, previous: &MetadataConfig) -> Vec<&'static str> {
+        [
+            (
+                "max_parallel_fetch_connections",
+                self.max_parallel_fetch_connections != previous.max_parallel_fetch_connections,
+            ),
+            (
+                "source_parallelism",
+                self.source_parallelism != previous.source_parallelism,
+            ),
+            (
+                "max_concurrent_downloads",
+                self.max_concurrent_downloads != previous.max_concurrent_downloads,
+            ),
+            (
+                "static_directory",
+                self.static_directory != previous.static_directory,
+            ),
+            (
+                "update_interval",
+                self.update_interval != previous.update_interval,
+            ),
+            (
+                "strict_rule_features",
+                self.strict_rule_features != previous.strict_rule_features,
+            ),
+            ("error_policy", self.error_policy != previous.error_policy),
+            (
+                "max_download_bytes_per_second",
+                self.max_download_bytes_per_second != previous.max_download_bytes_per_second,
+            ),
+            (
+                "deep_health_interval",
+                self.deep_health_interval != previous.deep_health_interval,
+            ),
+            (
+                "old_version_archives",
+                self.old_version_archives != previous.old_version_archives,
+            ),
+            (
+                "archive_directory",
+                self.archive_directory != previous.archive_directory,
+            ),
+            (
+                "java_runtime_majors",
+                self.java_runtime_majors != previous.java_runtime_majors,
+            ),
+            ("sources", self.sources != previous.sources),
+            (
+                "connect_timeout",
+                self.connect_timeout != previous.connect_timeout,
+            ),
+            ("read_timeout", self.read_timeout != previous.read_timeout),
+            ("user_agent", self.user_agent != previous.user_agent),
+            ("proxy", self.proxy != previous.proxy),
+            (
+                "ca_certificates",
+                self.ca_certificates != previous.ca_certificates,
+            ),
+            ("publish", self.publish != previous.publish),
+            (
+                "forge_wrapper",
+                self.forge_wrapper != previous.forge_wrapper,
+            ),
+            ("assets", self.assets != previous.assets),
+            ("maven_mirror", self.maven_mirror != previous.maven_mirror),
+            ("forge_jars", self.forge_jars != previous.forge_jars),
+            ("optifine", self.optifine != previous.optifine),
+        ]
+        .into_iter()
+        .filter_map(|(name, changed)| changed.then_some(name))
+        .collect()
+    }
+
+    /**
+     * The enabled sources, in the order a full update goes through them
+     */
+    pub fn enabled_sources(&self) -> Vec<UpdateSource> {
+        UpdateSource::ALL
+            .into_iter()
+            .filter(|source| self.is_enabled(*source))
+            .collect()
+    }
+}
+
+/// Which of Mojang's assets are mirrored next to the metadata
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AssetsConfig {
+    /// download the asset index of every stored Minecraft version, served by `/raw/mojang/assets/:id`
+    pub mirror_indexes: bool,
+    /// serve asset objects under `/assets/objects`, downloading them from upstream on first request
+    pub mirror_objects: bool,
+    /// where asset objects come from, e.g. `https://resources.download.minecraft.net`
+    pub objects_url: String,
+    /// directory downloaded asset objects are kept in
+    pub objects_directory: String,
+}
+
+/// A pull-through mirror of the maven repositories launchers download libraries from
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct MavenMirrorConfig {
+    /// serve `/maven/*path`, downloading each file from upstream on first request
+    pub enable: bool,
+    /// repositories a file not cached yet is looked up on, in order
+    pub upstreams: Vec<String>,
+    /// directory downloaded files are kept in, laid out like the repositories
+    pub directory: String,
+}
+
+/// How long the Forge jars downloaded to extract their metadata are kept around
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ForgeJarsConfig {
+    /// delete jars once their metadata has been extracted, as regenerating Forge does not need them
+    pub delete_processed: bool,
+    /// bytes the jars may take up, deleting the least recently used beyond it, 0 for no limit
+    pub max_bytes: u64,
+    /// seconds a jar is kept after it was last used, 0 to keep them forever
+    pub max_age: u64,
+}
+
+/// The OptiFine builds scraped from its download page
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct OptiFineConfig {
+    /// seconds clients and proxies may reuse `/raw/optifine` responses without revalidating
+    pub max_age: u64,
+}
+
+/// The library modern Forge components run their install processors through
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ForgeWrapperConfig {
+    /// maven coordinates of the library, e.g. `io.github.zekerzhayard:ForgeWrapper:prism-2024-02-29`
+    pub library: String,
+    /// maven repository the library is downloaded from
+    pub maven_url: String,
+    /// sha1 of the library jar, which pins the download along with its size when set
+    #[serde(default)]
+    pub sha1: Option<String>,
+    /// size of the library jar in bytes
+    #[serde(default)]
+    pub size: Option<i32>,
+    /// `MMC-hint` of the library, e.g. `local` for a build the launcher bundles
+    #[serde(default)]
+    pub mmc_hint: Option<String>,
+}
+
+impl ForgeWrapperConfig {
+    /**
+     * The configured library, failing if its maven coordinates do not parse
+     */
+    pub fn forge_wrapper(&self) -> Result<ForgeWrapper> {
+        Ok(ForgeWrapper {
+            name: self.library.parse().with_context(|| {
+                format!("Failure parsing ForgeWrapper library {}", self.library)
+            })?,
+            maven_url: self.maven_url.clone(),
+            sha1: self.sha1.clone(),
+            size: self.size,
+            mmc_hint: self.mmc_hint.clone(),
+        })
+    }
+}
+
+/// Committing the generated metadata to git after every update
+#[derive(Deserialize, Clone, PartialEq)]
+pub struct PublishConfig {
+    pub enable: bool,
+    /// remote the branch is pushed to after every commit, only committed locally when unset
+    #[serde(default)]
+    pub remote_url: Option<String>,
+    pub branch: String,
+    pub author_name: String,
+    pub author_email: String,
+    /// user for pushing over https, defaults to the one in the remote url
+    #[serde(default)]
+    pub username: Option<String>,
+    /// password or access token for pushing over https, ssh remotes use the ssh agent
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// Leaves out the password, as the config is logged
+impl std::fmt::Debug for PublishConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PublishConfig")
+            .field("enable", &self.enable)
+            .field("remote_url", &self.remote_url)
+            .field("branch", &self.branch)
+            .field("author_name", &self.author_name)
+            .field("author_email", &self.author_email)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/**
+ * Read a list of numbers, which the environment gives as a single number when only one is set
+ */
+fn one_or_many_numbers<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<i32>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        Parsed(i32),
+        Text(String),
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Numbers {
+        One(Number),
+        Many(Vec<Number>),
+    }
+
+    let numbers = match Numbers::deserialize(deserializer)? {
+        Numbers::One(number) => vec![number],
+        Numbers::Many(numbers) => numbers,
+    };
+    numbers
+        .into_iter()
+        .map(|number| match number {
+            Number::Parsed(number) => Ok(number),
+            Number::Text(text) => text.trim().parse().map_err(D::Error::custom),
+        })
+        .collect()
+}
+
+/**
+ * Read a list of strings, which a config file may give as a single string
+ */
+fn one_or_many_strings<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Strings {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Strings::deserialize(deserializer)? {
+        Strings::One(string) => vec![string],
+        Strings::Many(strings) => strings,
+    })
 }
 
 #[derive(Deserialize, Debug)]
@@ -25,33 +373,362 @@ pub struct DebugLogConfig {
     pub level: String,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct MetricsConfig {
+    /// Prometheus pushgateway the `update` subcommand pushes its run metrics to
+    #[serde(default)]
+    pub pushgateway_url: Option<String>,
+    pub job_name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RuntimeConfig {
+    /// number of async worker threads, defaults to the number of cpu cores
+    #[serde(default)]
+    pub worker_threads: Option<usize>,
+    pub max_blocking_threads: usize,
+}
+
+impl RuntimeConfig {
+    /**
+     * Refuse thread counts of 0, which the tokio runtime builder panics on
+     */
+    pub fn validate(&self) -> Result<()> {
+        if self.worker_threads == Some(0) {
+            return Err(anyhow!(
+                "runtime.worker_threads must be at least 1, or left unset for one per cpu core"
+            ));
+        }
+        if self.max_blocking_threads == 0 {
+            return Err(anyhow!("runtime.max_blocking_threads must be at least 1"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct HttpConfig {
+    /// accept HTTP/2 with prior knowledge next to HTTP/1.1 on the same port
+    pub http2: bool,
+    /// only speak cleartext HTTP/2 (h2c), for gRPC-aware proxies in front of the server
+    pub h2c_only: bool,
+    /// gzip json and text responses for clients sending `Accept-Encoding: gzip`
+    pub compression: bool,
+    /// responses smaller than this many bytes are sent uncompressed
+    pub compression_min_bytes: usize,
+    /// stream stored files as they are on disk for `?raw=true` requests to the `/raw` routes
+    pub raw_passthrough: bool,
+}
+
+/// Client certificates verified by a TLS terminating proxy in front, which forwards the result
+#[derive(Deserialize, Debug)]
+pub struct AdminMtlsConfig {
+    /// accept admin requests whose client certificate the proxy verified
+    pub enable: bool,
+    /// header the proxy sets to `SUCCESS` for a verified client certificate
+    pub verify_header: String,
+    /// header the proxy forwards the fingerprint of the client certificate in
+    pub fingerprint_header: String,
+    /// hex fingerprints of the accepted client certificates, at least one when enabled
+    pub fingerprints: Vec<String>,
+    /// IP addresses of the proxies trusted to set the headers, `unix` for any connecting over a
+    /// unix socket
+    pub trusted_proxies: Vec<String>,
+}
+
+impl AdminMtlsConfig {
+    /**
+     * The accepted fingerprints as lowercase hex, as proxies give them in upper or lower case and
+     * with or without colons
+     */
+    pub fn accepted_fingerprints(&self) -> Vec<String> {
+        self.fingerprints
+            .iter()
+            .map(|fingerprint| normalize_fingerprint(fingerprint))
+            .filter(|fingerprint| !fingerprint.is_empty())
+            .collect()
+    }
+
+    /**
+     * Whether the headers of a request from `peer` come from a trusted proxy
+     */
+    pub fn trusts(&self, peer: &PeerAddress) -> bool {
+        self.trusted_proxies
+            .iter()
+            .any(|proxy| match (proxy.trim(), peer) {
+                ("unix", PeerAddress::Unix) => true,
+                (proxy, PeerAddress::Tcp(addr)) => proxy
+                    .parse::<IpAddr>()
+                    .is_ok_and(|proxy| proxy == addr.ip().to_canonical()),
+                _ => false,
+            })
+    }
+
+    /**
+     * Refuse to enable mTLS unless the accepted certificates and the proxies verifying them are
+     * configured, as the headers could be set by anyone otherwise
+     */
+    pub fn validate(&self) -> Result<()> {
+        if !self.enable {
+            return Ok(());
+        }
+        if self.accepted_fingerprints().is_empty() {
+            return Err(anyhow!(
+                "admin_mtls.enable requires admin_mtls.fingerprints to list the accepted client certificates"
+            ));
+        }
+        if self.trusted_proxies.is_empty() {
+            return Err(anyhow!(
+                "admin_mtls.enable requires admin_mtls.trusted_proxies to list the proxies verifying client certificates"
+            ));
+        }
+        for proxy in &self.trusted_proxies {
+            let proxy = proxy.trim();
+            if proxy != "unix" && proxy.parse::<IpAddr>().is_err() {
+                return Err(anyhow!(
+                    "Invalid trusted proxy {}, expected an IP address or unix",
+                    proxy
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Normalize a certificate fingerprint to lowercase hex without separators
+ */
+pub fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LimitsConfig {
+    /// concurrent requests to the `/raw` routes before further ones are shed, 0 for no limit
+    pub raw_concurrency: usize,
+    /// concurrent exports before further ones are shed, 0 for no limit
+    pub export_concurrency: usize,
+    /// concurrent `/files` downloads before further ones are shed, 0 for no limit
+    pub files_concurrency: usize,
+    /// seconds shed clients are told to wait before retrying
+    pub retry_after: u64,
+    /// `/raw` responses kept in memory, 0 disables the response cache
+    pub response_cache_entries: usize,
+}
+
+lazy_static! {
+    /// The metadata settings last reloaded on SIGHUP, which updates started since run with
+    static ref RELOADED_METADATA: RwLock<Option<Arc<MetadataConfig>>> = RwLock::new(None);
+}
+
+/**
+ * Have every update started from now on run with these metadata settings instead of the ones the
+ * server started with
+ */
+pub fn reload_metadata(metadata: MetadataConfig) {
+    *RELOADED_METADATA
+        .write()
+        .unwrap_or_else(|err| err.into_inner()) = Some(Arc::new(metadata));
+}
+
 #[derive(Deserialize, Debug)]
 pub struct ServerConfig {
-    pub bind_address: String,
+    /// TCP addresses, or `unix:/path/to/socket` for unix domain sockets, serving every endpoint
+    /// but the private ones while there is a private address
+    #[serde(deserialize_with = "one_or_many_strings")]
+    pub bind_address: Vec<String>,
+    /// addresses serving every endpoint, including the admin endpoints and metrics
+    #[serde(deserialize_with = "one_or_many_strings")]
+    pub private_bind_address: Vec<String>,
+    /// octal permission bits of the unix domain socket the server listens on
+    pub unix_socket_mode: String,
+    /// seconds to wait for a running metadata update when shutting down
+    pub shutdown_timeout: u64,
+    /// bearer token required by the admin endpoints, which are disabled when unset
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    pub admin_mtls: AdminMtlsConfig,
+    /// format of the log lines written to stdout
+    pub log_format: LogFormat,
+    pub http: HttpConfig,
+    pub limits: LimitsConfig,
     pub storage_format: StorageFormat,
     pub metadata: MetadataConfig,
     pub debug_log: DebugLogConfig,
+    pub runtime: RuntimeConfig,
+    pub metrics: MetricsConfig,
 }
 
 impl ServerConfig {
+    /**
+     * The metadata settings updates run with, the ones last reloaded on SIGHUP or else those the
+     * server started with
+     */
+    pub fn current_metadata(&self) -> Arc<MetadataConfig> {
+        RELOADED_METADATA
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+            .unwrap_or_else(|| Arc::new(self.metadata.clone()))
+    }
+
     pub fn from_config(path: &str) -> Result<Self> {
         let config = config::Config::builder()
             .set_default("bind_address", "127.0.0.1:8080")?
+            .set_default("private_bind_address", Vec::<String>::new())?
+            .set_default("unix_socket_mode", "660")?
+            .set_default("shutdown_timeout", 25)?
+            .set_default("log_format", "text")?
+            .set_default("admin_mtls.enable", false)?
+            .set_default("admin_mtls.verify_header", "X-SSL-Client-Verify")?
+            .set_default("admin_mtls.fingerprint_header", "X-SSL-Client-Fingerprint")?
+            .set_default("admin_mtls.fingerprints", Vec::<String>::new())?
+            .set_default("admin_mtls.trusted_proxies", Vec::<String>::new())?
+            .set_default("http.http2", true)?
+            .set_default("http.h2c_only", false)?
+            .set_default("http.compression", true)?
+            .set_default("http.compression_min_bytes", 1024)?
+            .set_default("http.raw_passthrough", false)?
+            .set_default("limits.raw_concurrency", 64)?
+            .set_default("limits.export_concurrency", 2)?
+            .set_default("limits.files_concurrency", 16)?
+            .set_default("limits.retry_after", 1)?
+            .set_default("limits.response_cache_entries", 1024)?
             .set_default("storage_format.type", "json")?
             .set_default("storage_format.meta_directory", "meta")?
             .set_default("storage_format.generated_directory", "generated")?
             .set_default("metadata.max_parallel_fetch_connections", 4)?
+            .set_default("metadata.max_concurrent_downloads", 16)?
             .set_default("metadata.static_directory", "static")?
+            .set_default("metadata.update_interval", 3600)?
+            .set_default("metadata.strict_rule_features", false)?
+            .set_default("metadata.error_policy", "fail_fast")?
+            .set_default("metadata.max_download_bytes_per_second", 0)?
+            .set_default("metadata.deep_health_interval", 60)?
+            .set_default("metadata.old_version_archives", Vec::<String>::new())?
+            .set_default("metadata.java_runtime_majors", Vec::<i32>::new())?
+            .set_default("metadata.sources", DEFAULT_SOURCES.to_vec())?
+            .set_default("metadata.connect_timeout", 10)?
+            .set_default("metadata.read_timeout", 60)?
+            .set_default("metadata.ca_certificates", Vec::<String>::new())?
+            .set_default("metadata.assets.mirror_indexes", false)?
+            .set_default("metadata.assets.mirror_objects", false)?
+            .set_default(
+                "metadata.assets.objects_url",
+                "https://resources.download.minecraft.net",
+            )?
+            .set_default("metadata.assets.objects_directory", "assets")?
+            .set_default("metadata.maven_mirror.enable", false)?
+            .set_default(
+                "metadata.maven_mirror.upstreams",
+                vec![
+                    "https://libraries.minecraft.net",
+                    "https://maven.minecraftforge.net",
+                ],
+            )?
+            .set_default("metadata.maven_mirror.directory", "maven")?
+            .set_default("metadata.forge_jars.delete_processed", false)?
+            .set_default("metadata.forge_jars.max_bytes", 0)?
+            .set_default("metadata.forge_jars.max_age", 0)?
+            .set_default("metadata.optifine.max_age", 86400)?
+            .set_default(
+                "metadata.user_agent",
+                concat!("mcmeta/", env!("CARGO_PKG_VERSION")),
+            )?
+            .set_default("metadata.publish.enable", false)?
+            .set_default("metadata.publish.branch", "main")?
+            .set_default("metadata.publish.author_name", "mcmeta")?
+            .set_default("metadata.publish.author_email", "mcmeta@localhost")?
+            .set_default("metadata.forge_wrapper.library", FORGEWRAPPER_LIBRARY)?
+            .set_default("metadata.forge_wrapper.maven_url", FORGEWRAPPER_MAVEN_URL)?
             .set_default("debug_log.enable", false)?
             .set_default("debug_log.path", "./logs")?
             .set_default("debug_log.prefix", "mcmeta.log")?
             .set_default("debug_log.level", "debug")?
+            .set_default("runtime.max_blocking_threads", 512)?
+            .set_default("metrics.job_name", "mcmeta")?
             // optionally add config from a file. this is optional though
             .add_source(config::File::from(std::path::Path::new(path)).required(false))
             // environment overrides file
-            .add_source(config::Environment::with_prefix("mcmeta").separator("__"))
+            .add_source(
+                config::Environment::with_prefix("mcmeta")
+                    .separator("__")
+                    .try_parsing(true)
+                    .list_separator(",")
+                    .with_list_parse_key("bind_address")
+                    .with_list_parse_key("private_bind_address")
+                    .with_list_parse_key("admin_mtls.fingerprints")
+                    .with_list_parse_key("admin_mtls.trusted_proxies")
+                    .with_list_parse_key("metadata.old_version_archives")
+                    .with_list_parse_key("metadata.java_runtime_majors")
+                    .with_list_parse_key("metadata.sources")
+                    .with_list_parse_key("metadata.ca_certificates")
+                    .with_list_parse_key("metadata.maven_mirror.upstreams"),
+            )
             .build()?;
 
-        config.try_deserialize::<'_, Self>().map_err(Into::into)
+        let config = config.try_deserialize::<'_, Self>()?;
+        config.admin_mtls.validate()?;
+        config.runtime.validate()?;
+        Ok(config)
+    }
+
+    /**
+     * Build the tokio runtime according to the `runtime` config
+     */
+    pub fn build_runtime(&self) -> Result<tokio::runtime::Runtime> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder
+            .enable_all()
+            .max_blocking_threads(self.runtime.max_blocking_threads);
+        if let Some(worker_threads) = self.runtime.worker_threads {
+            builder.worker_threads(worker_threads);
+        }
+        builder.build().map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RuntimeConfig, ServerConfig, UpdateSource};
+
+    #[test]
+    fn test_runtime_config_rejects_zero_threads() {
+        let runtime = |worker_threads, max_blocking_threads| RuntimeConfig {
+            worker_threads,
+            max_blocking_threads,
+        };
+        assert!(runtime(None, 512).validate().is_ok());
+        assert!(runtime(Some(4), 1).validate().is_ok());
+
+        let err = runtime(Some(0), 512).validate().unwrap_err();
+        assert!(err.to_string().contains("runtime.worker_threads"));
+        let err = runtime(None, 0).validate().unwrap_err();
+        assert!(err.to_string().contains("runtime.max_blocking_threads"));
+    }
+
+    #[test]
+    fn test_changed_metadata_settings() {
+        let previous = ServerConfig::from_config("/nonexistent/config.toml")
+            .unwrap()
+            .metadata;
+        let mut metadata = previous.clone();
+        assert!(metadata.changed_settings(&previous).is_empty());
+
+        metadata.max_download_bytes_per_second = 1 << 20;
+        metadata.assets.mirror_objects = !previous.assets.mirror_objects;
+        metadata.source_parallelism.insert(UpdateSource::Forge, 2);
+        assert_eq!(
+            metadata.changed_settings(&previous),
+            vec![
+                "source_parallelism",
+                "max_download_bytes_per_second",
+                "assets"
+            ]
+        );
     }
 }
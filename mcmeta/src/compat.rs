@@ -0,0 +1,175 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use anyhow::{anyhow, Result};
+use libmcmeta::models::{Library, MetaVersion};
+use tracing::info;
+
+use crate::storage::generated::GeneratedDataStorage;
+
+/// A launcher-facing invariant violated by the generated metadata.
+#[derive(Debug, Clone)]
+pub struct CompatProblem {
+    pub uid: String,
+    pub version: Option<String>,
+    pub message: String,
+}
+
+impl Display for CompatProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(version) = &self.version {
+            write!(f, "{} {}: {}", self.uid, version, self.message)
+        } else {
+            write!(f, "{}: {}", self.uid, self.message)
+        }
+    }
+}
+
+fn library_problems(kind: &str, library: &Library) -> Vec<String> {
+    let name = library
+        .name
+        .as_ref()
+        .map(|name| name.to_string())
+        .unwrap_or("<unnamed>".to_string());
+    let mut problems = vec![];
+
+    if let Some(url) = &library.url {
+        if url.is_empty() {
+            problems.push(format!("{} {} has an empty maven url", kind, name));
+        }
+    }
+    if let Some(downloads) = &library.downloads {
+        if let Some(artifact) = &downloads.artifact {
+            if artifact.url.is_empty() {
+                problems.push(format!("{} {} has an empty artifact url", kind, name));
+            }
+        }
+        if let Some(classifiers) = &downloads.classifiers {
+            for (classifier, artifact) in classifiers {
+                if artifact.url.is_empty() {
+                    problems.push(format!(
+                        "{} {} has an empty url for classifier {}",
+                        kind, name, classifier
+                    ));
+                }
+            }
+        }
+    }
+    problems
+}
+
+fn version_problems(
+    version: &MetaVersion,
+    available: &BTreeMap<String, BTreeSet<String>>,
+) -> Vec<String> {
+    let mut problems = vec![];
+
+    // anything providing the game jar has to tell the launcher how to start it
+    if version.main_jar.is_some() {
+        if version.main_class.is_none() && version.applet_class.is_none() {
+            problems.push("has neither a mainClass nor an appletClass".to_string());
+        }
+        if version.asset_index.is_none() {
+            problems.push("has no assetIndex".to_string());
+        }
+    }
+
+    if let Some(asset_index) = &version.asset_index {
+        if asset_index.id.is_empty() || asset_index.url.is_empty() {
+            problems.push("has an incomplete assetIndex".to_string());
+        }
+    }
+
+    for dependency in version.requires.iter().flatten() {
+        match available.get(&dependency.uid) {
+            None => problems.push(format!("requires unknown package {}", dependency.uid)),
+            Some(versions) => {
+                if let Some(equals) = &dependency.equals {
+                    if !versions.contains(equals) {
+                        problems.push(format!(
+                            "requires {} {} which does not exist",
+                            dependency.uid, equals
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    let libraries = [
+        (
+            "library",
+            version.libraries.iter().flatten().collect::<Vec<_>>(),
+        ),
+        ("maven file", version.maven_files.iter().flatten().collect()),
+        ("jar mod", version.jar_mods.iter().flatten().collect()),
+        ("main jar", version.main_jar.iter().collect()),
+    ];
+    for (kind, libraries) in libraries {
+        for library in libraries {
+            problems.append(&mut library_problems(kind, library));
+        }
+    }
+
+    problems
+}
+
+/**
+ * Load the generated metadata and check the invariants Prism Launcher relies on
+ */
+pub fn check_generated(storage: &GeneratedDataStorage) -> Result<Vec<CompatProblem>> {
+    let package_index = storage
+        .load_package_index()?
+        .ok_or(anyhow!("Generated package index is missing"))?;
+
+    let mut problems = vec![];
+    let mut available: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut versions = vec![];
+
+    for package in &package_index.packages {
+        let Some(version_index) = storage.load_version_index(&package.uid)? else {
+            problems.push(CompatProblem {
+                uid: package.uid.clone(),
+                version: None,
+                message: "package index is missing".to_string(),
+            });
+            continue;
+        };
+
+        let package_versions = available.entry(package.uid.clone()).or_default();
+        for entry in &version_index.versions {
+            package_versions.insert(entry.version.clone());
+            match storage.load_meta_version(&package.uid, &entry.version) {
+                Ok(Some(version)) => versions.push(version),
+                Ok(None) => problems.push(CompatProblem {
+                    uid: package.uid.clone(),
+                    version: Some(entry.version.clone()),
+                    message: "listed in the package index but its file is missing".to_string(),
+                }),
+                Err(err) => problems.push(CompatProblem {
+                    uid: package.uid.clone(),
+                    version: Some(entry.version.clone()),
+                    message: format!("{:#}", err),
+                }),
+            }
+        }
+    }
+
+    for version in &versions {
+        for message in version_problems(version, &available) {
+            problems.push(CompatProblem {
+                uid: version.uid.clone(),
+                version: Some(version.version.clone()),
+                message,
+            });
+        }
+    }
+
+    info!(
+        "Checked {} versions in {} packages",
+        versions.len(),
+        package_index.packages.len()
+    );
+
+    Ok(problems)
+}
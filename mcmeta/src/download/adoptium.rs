@@ -0,0 +1,48 @@
+use libmcmeta::models::java::AdoptiumAsset;
+use serde::Deserialize;
+use serde_valid::Validate;
+use tracing::debug;
+
+use anyhow::Result;
+
+use crate::download::{self, errors::MetadataError};
+
+fn default_api_url() -> String {
+    "https://api.adoptium.net".to_string()
+}
+
+#[derive(Deserialize, Debug)]
+struct DownloadConfig {
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+}
+
+impl DownloadConfig {
+    fn from_config() -> Result<Self> {
+        let config = config::Config::builder()
+            .add_source(config::Environment::with_prefix("MCMETA_ADOPTIUM").prefix_separator("__"))
+            .build()?;
+
+        config.try_deserialize::<'_, Self>().map_err(Into::into)
+    }
+}
+
+/**
+ * Fetch the latest Adoptium JRE builds of a Java major for every platform
+ */
+pub async fn load_latest_assets(major: i32) -> Result<Vec<AdoptiumAsset>> {
+    let config = DownloadConfig::from_config()?;
+    let url = format!(
+        "{}/v3/assets/latest/{}/hotspot?image_type=jre&vendor=eclipse",
+        config.api_url.trim_end_matches('/'),
+        major
+    );
+
+    debug!("Fetching Adoptium Java {} builds from {:#?}", major, &url);
+
+    let body = download::download_text(&url).await?;
+    let assets: Vec<AdoptiumAsset> =
+        serde_json::from_str(&body).map_err(|err| MetadataError::from_json_err(err, &body))?;
+    assets.validate()?;
+    Ok(assets)
+}
@@ -0,0 +1,115 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use anyhow::{Context, Result};
+use tracing::{debug, warn};
+
+lazy_static! {
+    /// Shared by every download, so each upstream response ends up in the same archive
+    pub static ref RESPONSE_ARCHIVE: ResponseArchive = ResponseArchive::new();
+}
+
+/**
+ * Keeps the raw bytes of upstream responses under a directory per url, one file per fetch named by its timestamp
+ */
+pub struct ResponseArchive {
+    directory: RwLock<Option<PathBuf>>,
+}
+
+/**
+ * Turn a url into a relative path of its host and path segments, with anything unsafe in a file name replaced
+ */
+fn url_archive_path(url: &str) -> PathBuf {
+    fn sanitize(segment: &str) -> String {
+        let segment: String = segment
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "-_.".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if segment.is_empty() || segment.chars().all(|c| c == '.') {
+            "_".to_string()
+        } else {
+            segment
+        }
+    }
+
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (location, query) = without_scheme
+        .split_once('?')
+        .map_or((without_scheme, None), |(location, query)| {
+            (location, Some(query))
+        });
+
+    let mut path = PathBuf::new();
+    for segment in location.split('/').filter(|segment| !segment.is_empty()) {
+        path.push(sanitize(segment));
+    }
+    if let Some(query) = query {
+        path.push(format!("_query_{}", sanitize(query)));
+    }
+    path
+}
+
+impl ResponseArchive {
+    fn new() -> Self {
+        Self {
+            directory: RwLock::new(None),
+        }
+    }
+
+    /**
+     * Set the directory responses are archived in, `None` disables archiving
+     */
+    pub fn set_directory(&self, directory: Option<String>) {
+        *self.directory.write().expect("Archive lock poisoned") = directory.map(PathBuf::from);
+    }
+
+    fn store(directory: &Path, url: &str, body: &[u8]) -> Result<PathBuf> {
+        let now = time::OffsetDateTime::now_utc();
+        let timestamp = format!(
+            "{:04}{:02}{:02}T{:02}{:02}{:02}.{:06}Z",
+            now.year(),
+            u8::from(now.month()),
+            now.day(),
+            now.hour(),
+            now.minute(),
+            now.second(),
+            now.microsecond()
+        );
+        let url_dir = directory.join(url_archive_path(url));
+        std::fs::create_dir_all(&url_dir)
+            .with_context(|| format!("Failure creating directory {}", url_dir.to_string_lossy()))?;
+
+        let file = url_dir.join(timestamp);
+        std::fs::write(&file, body)
+            .with_context(|| format!("Failure writing file {}", file.to_string_lossy()))?;
+        Ok(file)
+    }
+
+    /**
+     * Save the raw body of a response if archiving is enabled.
+     * Failing to archive is only logged, it never fails the download itself.
+     */
+    pub fn archive(&self, url: &str, body: &[u8]) {
+        let directory = self
+            .directory
+            .read()
+            .expect("Archive lock poisoned")
+            .clone();
+        let Some(directory) = directory else {
+            return;
+        };
+
+        match Self::store(&directory, url, body) {
+            Ok(file) => debug!("Archived response of {} to {}", url, file.display()),
+            Err(err) => warn!("Failure archiving response of {}: {:#}", url, err),
+        }
+    }
+}
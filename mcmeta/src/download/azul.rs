@@ -0,0 +1,150 @@
+use libmcmeta::models::java::{AzulPackage, AzulPackageDetail};
+use serde::Deserialize;
+use serde_valid::Validate;
+use tracing::debug;
+
+use anyhow::Result;
+
+use crate::download::{self, errors::MetadataError};
+
+fn default_api_url() -> String {
+    "https://api.azul.com".to_string()
+}
+
+#[derive(Deserialize, Debug)]
+struct DownloadConfig {
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+}
+
+impl DownloadConfig {
+    fn from_config() -> Result<Self> {
+        let config = config::Config::builder()
+            .add_source(config::Environment::with_prefix("MCMETA_AZUL").prefix_separator("__"))
+            .build()?;
+
+        config.try_deserialize::<'_, Self>().map_err(Into::into)
+    }
+
+    fn packages_url(&self) -> String {
+        format!(
+            "{}/metadata/v1/zulu/packages",
+            self.api_url.trim_end_matches('/')
+        )
+    }
+}
+
+/// A platform Azul is queried for: its os, arch and hw_bitness, and the archive type to download
+#[derive(Debug, Clone, Copy)]
+pub struct AzulPlatform {
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub hw_bitness: u8,
+    pub archive_type: &'static str,
+}
+
+impl AzulPlatform {
+    /// The architecture in the naming of the other vendors
+    pub fn arch_name(&self) -> &'static str {
+        match (self.arch, self.hw_bitness) {
+            ("x86", 64) => "x64",
+            ("x86", _) => "x86",
+            ("arm", 64) => "arm64",
+            _ => "arm32",
+        }
+    }
+}
+
+pub const AZUL_PLATFORMS: [AzulPlatform; 9] = [
+    AzulPlatform {
+        os: "linux",
+        arch: "x86",
+        hw_bitness: 64,
+        archive_type: "tar.gz",
+    },
+    AzulPlatform {
+        os: "linux",
+        arch: "x86",
+        hw_bitness: 32,
+        archive_type: "tar.gz",
+    },
+    AzulPlatform {
+        os: "linux",
+        arch: "arm",
+        hw_bitness: 64,
+        archive_type: "tar.gz",
+    },
+    AzulPlatform {
+        os: "linux",
+        arch: "arm",
+        hw_bitness: 32,
+        archive_type: "tar.gz",
+    },
+    AzulPlatform {
+        os: "windows",
+        arch: "x86",
+        hw_bitness: 64,
+        archive_type: "zip",
+    },
+    AzulPlatform {
+        os: "windows",
+        arch: "x86",
+        hw_bitness: 32,
+        archive_type: "zip",
+    },
+    AzulPlatform {
+        os: "windows",
+        arch: "arm",
+        hw_bitness: 64,
+        archive_type: "zip",
+    },
+    AzulPlatform {
+        os: "macos",
+        arch: "x86",
+        hw_bitness: 64,
+        archive_type: "tar.gz",
+    },
+    AzulPlatform {
+        os: "macos",
+        arch: "arm",
+        hw_bitness: 64,
+        archive_type: "tar.gz",
+    },
+];
+
+/**
+ * Fetch the latest generally available Azul JRE packages of a Java major for a platform
+ */
+pub async fn load_latest_packages(major: i32, platform: &AzulPlatform) -> Result<Vec<AzulPackage>> {
+    let config = DownloadConfig::from_config()?;
+    let url = format!(
+        "{}/?java_version={}&os={}&arch={}&hw_bitness={}&archive_type={}&java_package_type=jre&javafx_bundled=false&latest=true&release_status=ga&availability_types=CA&certifications=tck&page=1&page_size=100",
+        config.packages_url(),
+        major,
+        platform.os,
+        platform.arch,
+        platform.hw_bitness,
+        platform.archive_type
+    );
+
+    debug!("Fetching Azul Java {} packages from {:#?}", major, &url);
+
+    let body = download::download_text(&url).await?;
+    let packages: Vec<AzulPackage> =
+        serde_json::from_str(&body).map_err(|err| MetadataError::from_json_err(err, &body))?;
+    packages.validate()?;
+    Ok(packages)
+}
+
+pub async fn load_package_detail(package_uuid: &str) -> Result<AzulPackageDetail> {
+    let config = DownloadConfig::from_config()?;
+    let url = format!("{}/{}", config.packages_url(), package_uuid);
+
+    debug!("Fetching Azul package details from {:#?}", &url);
+
+    let body = download::download_text(&url).await?;
+    let detail: AzulPackageDetail =
+        serde_json::from_str(&body).map_err(|err| MetadataError::from_json_err(err, &body))?;
+    detail.validate()?;
+    Ok(detail)
+}
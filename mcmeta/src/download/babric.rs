@@ -0,0 +1,47 @@
+use serde::Deserialize;
+
+use crate::download::fabric::FabricMetaServer;
+
+use anyhow::Result;
+
+fn default_meta_url() -> String {
+    "https://meta.babric.glass-launcher.net".to_string()
+}
+
+fn default_maven_url() -> String {
+    "https://maven.glass-launcher.net/babric".to_string()
+}
+
+#[derive(Deserialize, Debug)]
+struct DownloadConfig {
+    #[serde(default = "default_meta_url")]
+    pub meta_url: String,
+    #[serde(default = "default_maven_url")]
+    pub maven_url: String,
+}
+
+impl DownloadConfig {
+    fn from_config() -> Result<Self> {
+        let config = config::Config::builder()
+            .add_source(config::Environment::with_prefix("MCMETA_BABRIC").prefix_separator("__"))
+            .build()?;
+
+        config.try_deserialize::<'_, Self>().map_err(Into::into)
+    }
+}
+
+/**
+ * The Babric meta server, whose components are named after their maven coordinates like the Fabric ones
+ */
+pub fn server() -> Result<FabricMetaServer> {
+    let config = DownloadConfig::from_config()?;
+
+    Ok(FabricMetaServer::new(
+        config.meta_url,
+        config.maven_url,
+        "babric.fabric-loader",
+        "Babric Loader",
+        "babric.intermediary",
+        "Babric Intermediary Mappings",
+    ))
+}
@@ -0,0 +1,102 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+lazy_static! {
+    /// Shared by every download, so a host asking us to back off is left alone by all of them
+    pub static ref HOST_BACKOFF: HostBackoff = HostBackoff::new();
+}
+
+/**
+ * Hosts that answered with a 429 or 503 and a Retry-After, with when they may be contacted again
+ */
+pub struct HostBackoff {
+    paused_until: Mutex<HashMap<String, Instant>>,
+}
+
+pub(crate) fn host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/**
+ * Parse a Retry-After header, either a number of seconds or an HTTP date
+ */
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    // a date in the past means we may retry right away
+    Some(
+        date.duration_since(std::time::SystemTime::now())
+            .unwrap_or_default(),
+    )
+}
+
+impl HostBackoff {
+    fn new() -> Self {
+        Self {
+            paused_until: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /**
+     * Stop contacting the host of `url` for `duration`, extending any pause already in place
+     */
+    pub fn pause(&self, url: &str, duration: Duration) {
+        let until = Instant::now() + duration;
+        let mut paused_until = self.paused_until.lock().expect("Backoff lock poisoned");
+        let entry = paused_until.entry(host(url)).or_insert(until);
+        *entry = (*entry).max(until);
+    }
+
+    /**
+     * Wait until the host of `url` may be contacted again
+     */
+    pub async fn wait(&self, url: &str) {
+        let host = host(url);
+        loop {
+            let until = {
+                let mut paused_until = self.paused_until.lock().expect("Backoff lock poisoned");
+                match paused_until.get(&host) {
+                    Some(until) if *until > Instant::now() => *until,
+                    Some(_) => {
+                        paused_until.remove(&host);
+                        return;
+                    }
+                    None => return,
+                }
+            };
+            // the pause may have been extended while sleeping
+            tokio::time::sleep_until(until).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::parse_retry_after;
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 5 "), Some(Duration::from_secs(5)));
+        assert_eq!(parse_retry_after("0"), Some(Duration::ZERO));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        let in_a_minute = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        let wait = parse_retry_after(&in_a_minute).unwrap();
+        assert!(wait > Duration::from_secs(55) && wait <= Duration::from_secs(60));
+        assert_eq!(parse_retry_after("-1"), None);
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(parse_retry_after(""), None);
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::{Arc, RwLock};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+lazy_static! {
+    /// Shared by every download, so the cap applies to all sources updating at once together
+    pub static ref DOWNLOAD_BUDGET: DownloadBudget = DownloadBudget::new();
+}
+
+/**
+ * A cap on the number of downloads running at the same time across every source
+ */
+pub struct DownloadBudget {
+    /// the cap and its permits, `None` when downloads are not capped
+    permits: RwLock<(usize, Option<Arc<Semaphore>>)>,
+}
+
+impl DownloadBudget {
+    fn new() -> Self {
+        Self {
+            permits: RwLock::new((0, None)),
+        }
+    }
+
+    /**
+     * Set the number of downloads that may run at the same time, 0 disables the cap. Downloads
+     * already running keep the permit of the previous cap until they finish.
+     */
+    pub fn set_limit(&self, max_concurrent: usize) {
+        let mut permits = self.permits.write().unwrap_or_else(|err| err.into_inner());
+        if permits.0 != max_concurrent {
+            *permits = (
+                max_concurrent,
+                (max_concurrent > 0).then(|| Arc::new(Semaphore::new(max_concurrent))),
+            );
+        }
+    }
+
+    /**
+     * The number of downloads that may run at the same time, 0 when not capped
+     */
+    pub fn limit(&self) -> usize {
+        self.permits.read().unwrap_or_else(|err| err.into_inner()).0
+    }
+
+    /**
+     * Wait until another download may start, holding its slot until the permit is dropped
+     */
+    pub async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        let permits = self
+            .permits
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .1
+            .clone()?;
+        // the semaphore is never closed
+        permits.acquire_owned().await.ok()
+    }
+}
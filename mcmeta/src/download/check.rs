@@ -0,0 +1,146 @@
+use std::fmt::Display;
+
+use futures::Future;
+use tracing::info;
+
+use anyhow::Result;
+
+use crate::download;
+
+/// An upstream document that could not be fetched, parsed or validated.
+#[derive(Debug)]
+pub struct UpstreamProblem {
+    pub source: String,
+    pub error: anyhow::Error,
+}
+
+impl Display for UpstreamProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {:#}", self.source, self.error)
+    }
+}
+
+async fn check<T>(
+    source: &str,
+    problems: &mut Vec<UpstreamProblem>,
+    load: impl Future<Output = Result<T>>,
+) -> Option<T> {
+    match load.await {
+        Ok(value) => {
+            info!("{} parsed and validated", source);
+            Some(value)
+        }
+        Err(error) => {
+            problems.push(UpstreamProblem {
+                source: source.to_string(),
+                error,
+            });
+            None
+        }
+    }
+}
+
+/**
+ * Fetch the upstream manifests and check they still parse and validate against our models.
+ * Nothing is stored, so this can run against a live deployment as an early warning for format changes.
+ */
+pub async fn check_upstream() -> Vec<UpstreamProblem> {
+    let mut problems = vec![];
+
+    let manifest = check(
+        "Mojang version manifest",
+        &mut problems,
+        download::mojang::load_manifest(),
+    )
+    .await;
+    // the newest version manifest is the first to pick up format changes
+    if let Some(manifest) = manifest {
+        let latest = manifest
+            .versions
+            .iter()
+            .find(|version| version.id == manifest.latest.snapshot);
+        if let Some(latest) = latest {
+            check(
+                &format!("Mojang version {}", latest.id),
+                &mut problems,
+                download::mojang::load_version_manifest(&latest.url),
+            )
+            .await;
+        }
+    }
+
+    check(
+        "Mojang Java runtimes",
+        &mut problems,
+        download::mojang::load_java_runtimes(),
+    )
+    .await;
+
+    check(
+        "Forge maven metadata",
+        &mut problems,
+        download::forge::load_maven_metadata(),
+    )
+    .await;
+    check(
+        "Forge promotions",
+        &mut problems,
+        download::forge::load_maven_promotions(),
+    )
+    .await;
+
+    for artifact in crate::storage::neoforge::NEOFORGE_ARTIFACTS {
+        check(
+            &format!("NeoForge {} versions", artifact),
+            &mut problems,
+            download::neoforge::load_maven_versions(artifact),
+        )
+        .await;
+    }
+
+    for (name, server) in [
+        ("Fabric", download::fabric::server()),
+        ("Babric", download::babric::server()),
+    ] {
+        let server = match server {
+            Ok(server) => server,
+            Err(error) => {
+                problems.push(UpstreamProblem {
+                    source: format!("{} meta server", name),
+                    error,
+                });
+                continue;
+            }
+        };
+
+        let loaders = check(
+            &format!("{} loader versions", name),
+            &mut problems,
+            server.load_loader_versions(),
+        )
+        .await;
+        if let Some(latest) = loaders.as_ref().and_then(|loaders| loaders.first()) {
+            check(
+                &format!("{} loader {} installer data", name, latest.version),
+                &mut problems,
+                server.load_installer_data(&latest.maven),
+            )
+            .await;
+        }
+        check(
+            &format!("{} intermediary versions", name),
+            &mut problems,
+            server.load_intermediary_versions(),
+        )
+        .await;
+    }
+
+    check(
+        "LiteLoader versions",
+        &mut problems,
+        download::liteloader::load_versions(),
+    )
+    .await;
+
+    problems
+}
@@ -0,0 +1,137 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use tracing::debug;
+
+use crate::app_config::MetadataConfig;
+
+lazy_static! {
+    /// Shared by every download, so connections to the upstream hosts are pooled and reused
+    pub static ref HTTP_CLIENT: HttpClient = HttpClient::new();
+}
+
+/// What the client is built from, so it is only rebuilt, dropping its pool, when they change
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct ClientSettings {
+    connect_timeout: u64,
+    user_agent: String,
+    proxy: Option<String>,
+    ca_certificates: Vec<String>,
+}
+
+impl ClientSettings {
+    fn from_config(metadata_cfg: &MetadataConfig) -> Self {
+        Self {
+            connect_timeout: metadata_cfg.connect_timeout,
+            user_agent: metadata_cfg.user_agent.clone(),
+            proxy: metadata_cfg.proxy.clone(),
+            ca_certificates: metadata_cfg.ca_certificates.clone(),
+        }
+    }
+
+    fn build(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder().user_agent(&self.user_agent);
+        if self.connect_timeout > 0 {
+            builder = builder.connect_timeout(Duration::from_secs(self.connect_timeout));
+        }
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .with_context(|| format!("Invalid download proxy {}", proxy))?;
+            builder = builder.proxy(proxy);
+        }
+        for path in &self.ca_certificates {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("Failure reading CA certificate file {}", path))?;
+            let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("Invalid CA certificate file {}", path))?;
+            if certificates.is_empty() {
+                return Err(anyhow!("No certificate in CA certificate file {}", path));
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        builder.build().map_err(Into::into)
+    }
+}
+
+struct ConfiguredClient {
+    settings: Option<ClientSettings>,
+    client: reqwest::Client,
+}
+
+/**
+ * The reqwest client every download goes through, along with the read timeout applied to each
+ * response and chunk of a body, as a whole request timeout would cut off the large jars
+ */
+pub struct HttpClient {
+    configured: RwLock<ConfiguredClient>,
+    read_timeout: AtomicU64,
+}
+
+impl HttpClient {
+    fn new() -> Self {
+        Self {
+            configured: RwLock::new(ConfiguredClient {
+                settings: None,
+                client: reqwest::Client::new(),
+            }),
+            read_timeout: AtomicU64::new(0),
+        }
+    }
+
+    /**
+     * Apply the download settings of the metadata config, keeping the current client and its
+     * connections if they did not change
+     */
+    pub fn configure(&self, metadata_cfg: &MetadataConfig) -> Result<()> {
+        self.read_timeout
+            .store(metadata_cfg.read_timeout, Ordering::Relaxed);
+
+        let settings = ClientSettings::from_config(metadata_cfg);
+        let mut configured = self
+            .configured
+            .write()
+            .map_err(|_| anyhow!("Http client lock poisoned"))?;
+        if configured.settings.as_ref() != Some(&settings) {
+            debug!("Building http client with {:?}", settings);
+            configured.client = settings.build()?;
+            configured.settings = Some(settings);
+        }
+        Ok(())
+    }
+
+    /**
+     * A handle to the shared client, cloning it is cheap and shares its connection pool
+     */
+    pub fn client(&self) -> reqwest::Client {
+        match self.configured.read() {
+            Ok(configured) => configured.client.clone(),
+            Err(poisoned) => poisoned.into_inner().client.clone(),
+        }
+    }
+
+    /**
+     * Wait for a response or a chunk of its body, failing once nothing arrived for the read timeout
+     */
+    pub async fn read<T>(
+        &self,
+        url: &str,
+        read: impl std::future::Future<Output = reqwest::Result<T>>,
+    ) -> Result<T> {
+        let read_timeout = self.read_timeout.load(Ordering::Relaxed);
+        if read_timeout == 0 {
+            return read.await.map_err(Into::into);
+        }
+        tokio::time::timeout(Duration::from_secs(read_timeout), read)
+            .await
+            .map_err(|_| anyhow!("Timed out after {}s reading {}", read_timeout, url))?
+            .map_err(Into::into)
+    }
+}
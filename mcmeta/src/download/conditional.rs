@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+
+use crate::{download::errors::MetadataError, utils::write_json_atomic};
+
+/**
+ * The validators upstream answered a document with, kept next to the stored copy of it so the
+ * next fetch can ask for the document only if it changed
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamValidators {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+/// The result of a conditional fetch
+pub enum Conditional<T> {
+    /// upstream sent the document, along with its new validators
+    Modified(T, UpstreamValidators),
+    /// upstream answered 304, the stored copy is still current
+    NotModified,
+}
+
+impl UpstreamValidators {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /**
+     * The headers making a request conditional on the document having changed since
+     */
+    pub fn request_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = self.etag.as_ref().and_then(|etag| etag.parse().ok()) {
+            headers.insert(IF_NONE_MATCH, value);
+        }
+        if let Some(value) = self
+            .last_modified
+            .as_ref()
+            .and_then(|last_modified| last_modified.parse().ok())
+        {
+            headers.insert(IF_MODIFIED_SINCE, value);
+        }
+        headers
+    }
+
+    /**
+     * The file the validators of a stored file are kept in, e.g. `version_manifest_v2.json.upstream`
+     */
+    pub fn file_for(file: &Path) -> PathBuf {
+        let mut name = file.file_name().unwrap_or_default().to_os_string();
+        name.push(".upstream");
+        file.with_file_name(name)
+    }
+
+    /**
+     * Load the validators of a stored file, none if the file itself is missing as a 304 would
+     * leave nothing to use instead
+     */
+    pub fn load(file: &Path) -> Result<Option<Self>> {
+        let validators_file = Self::file_for(file);
+        if !file.is_file() || !validators_file.is_file() {
+            return Ok(None);
+        }
+        let body = std::fs::read_to_string(&validators_file).with_context(|| {
+            format!("Failure reading file {}", validators_file.to_string_lossy())
+        })?;
+        let validators = serde_json::from_str::<Self>(&body)
+            .map_err(|err| MetadataError::from_json_err(err, &body))?;
+        Ok(Some(validators))
+    }
+
+    /**
+     * Store the validators of a stored file, only once the file itself was written
+     */
+    pub fn store(&self, file: &Path) -> Result<()> {
+        let validators_file = Self::file_for(file);
+        if self.is_empty() {
+            if validators_file.is_file() {
+                std::fs::remove_file(&validators_file).with_context(|| {
+                    format!(
+                        "Failure removing file {}",
+                        validators_file.to_string_lossy()
+                    )
+                })?;
+            }
+            return Ok(());
+        }
+        write_json_atomic(&validators_file, self)
+    }
+}
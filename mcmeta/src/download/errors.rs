@@ -12,6 +12,11 @@ pub enum MetadataError {
     },
 }
 
+/// A file upstream answered 404 for, which is missing rather than failing to download
+#[derive(Error, Debug, Clone)]
+#[error("{0} does not exist upstream")]
+pub struct NotFoundUpstream(pub String);
+
 impl MetadataError {
     pub fn from_json_err(err: serde_json::Error, body: &str) -> Self {
         Self::BadJsonData {
@@ -0,0 +1,174 @@
+use libmcmeta::models::{
+    fabric::{
+        FabricComponents, FabricInstallerDataV1, FabricIntermediaryVersion, FabricLoaderVersion,
+    },
+    GradleSpecifier,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_valid::Validate;
+use tracing::debug;
+
+use crate::download::{self, errors::MetadataError};
+
+use anyhow::Result;
+
+fn default_meta_url() -> String {
+    "https://meta.fabricmc.net".to_string()
+}
+
+fn default_maven_url() -> String {
+    "https://maven.fabricmc.net".to_string()
+}
+
+#[derive(Deserialize, Debug)]
+struct DownloadConfig {
+    #[serde(default = "default_meta_url")]
+    pub meta_url: String,
+    #[serde(default = "default_maven_url")]
+    pub maven_url: String,
+}
+
+impl DownloadConfig {
+    fn from_config() -> Result<Self> {
+        let config = config::Config::builder()
+            .add_source(config::Environment::with_prefix("MCMETA_FABRIC").prefix_separator("__"))
+            .build()?;
+
+        config.try_deserialize::<'_, Self>().map_err(Into::into)
+    }
+}
+
+/// A fabric-meta compatible server, with the maven its artifacts are published to
+#[derive(Clone, Debug)]
+pub struct FabricMetaServer {
+    pub meta_url: String,
+    pub maven_url: String,
+    /// maven the loader is published to, the same as `maven_url` unless set apart
+    pub loader_maven_url: String,
+    /// path of the loader versions below `meta_url`
+    pub loader_versions_path: String,
+    /// path of the intermediary versions below `meta_url`
+    pub intermediary_versions_path: String,
+    pub components: FabricComponents,
+}
+
+/**
+ * The Fabric meta server and the components generated from it
+ */
+pub fn server() -> Result<FabricMetaServer> {
+    let config = DownloadConfig::from_config()?;
+
+    Ok(FabricMetaServer::new(
+        config.meta_url,
+        config.maven_url,
+        "net.fabricmc.fabric-loader",
+        "Fabric Loader",
+        "net.fabricmc.intermediary",
+        "Intermediary Mappings",
+    ))
+}
+
+async fn load_json<T: DeserializeOwned + Validate>(url: &str) -> Result<T> {
+    let body = download::download_text(url).await?;
+
+    let value: T =
+        serde_json::from_str(&body).map_err(|err| MetadataError::from_json_err(err, &body))?;
+    value.validate()?;
+    Ok(value)
+}
+
+impl FabricMetaServer {
+    pub fn new(
+        meta_url: String,
+        maven_url: String,
+        loader_uid: &str,
+        loader_name: &str,
+        intermediary_uid: &str,
+        intermediary_name: &str,
+    ) -> Self {
+        let meta_url = meta_url.trim_end_matches('/').to_string();
+        let maven_url = maven_url.trim_end_matches('/').to_string();
+        Self {
+            components: FabricComponents {
+                loader_uid: loader_uid.to_string(),
+                loader_name: loader_name.to_string(),
+                intermediary_uid: intermediary_uid.to_string(),
+                intermediary_name: intermediary_name.to_string(),
+                maven_url: format!("{}/", maven_url),
+                loader_maven_url: format!("{}/", maven_url),
+            },
+            meta_url,
+            loader_maven_url: maven_url.clone(),
+            maven_url,
+            loader_versions_path: "v2/versions/loader".to_string(),
+            intermediary_versions_path: "v2/versions/intermediary".to_string(),
+        }
+    }
+
+    /**
+     * Take the loader from another maven, for servers publishing only their intermediaries
+     */
+    pub fn with_loader_maven(mut self, loader_maven_url: String) -> Self {
+        let loader_maven_url = loader_maven_url.trim_end_matches('/').to_string();
+        self.components.loader_maven_url = format!("{}/", loader_maven_url);
+        self.loader_maven_url = loader_maven_url;
+        self
+    }
+
+    /**
+     * List versions from other endpoints than the `v2` ones of fabric-meta
+     */
+    pub fn with_versions_paths(mut self, loader_path: &str, intermediary_path: &str) -> Self {
+        self.loader_versions_path = loader_path.trim_matches('/').to_string();
+        self.intermediary_versions_path = intermediary_path.trim_matches('/').to_string();
+        self
+    }
+
+    /**
+     * The url of an artifact on the maven
+     */
+    pub fn maven_artifact_url(&self, specifier: &GradleSpecifier) -> String {
+        format!("{}/{}", self.maven_url, specifier.path())
+    }
+
+    /**
+     * The url of a loader artifact on the maven of the loader
+     */
+    pub fn loader_artifact_url(&self, specifier: &GradleSpecifier) -> String {
+        format!("{}/{}", self.loader_maven_url, specifier.path())
+    }
+
+    pub async fn load_loader_versions(&self) -> Result<Vec<FabricLoaderVersion>> {
+        let url = format!("{}/{}", self.meta_url, self.loader_versions_path);
+
+        debug!("Fetching loader versions from {:#?}", &url);
+
+        load_json(&url).await
+    }
+
+    pub async fn load_intermediary_versions(&self) -> Result<Vec<FabricIntermediaryVersion>> {
+        let url = format!("{}/{}", self.meta_url, self.intermediary_versions_path);
+
+        debug!("Fetching intermediary versions from {:#?}", &url);
+
+        load_json(&url).await
+    }
+
+    /**
+     * Fetch the launcher json the maven publishes next to a loader jar
+     */
+    pub async fn load_installer_data(
+        &self,
+        loader: &GradleSpecifier,
+    ) -> Result<FabricInstallerDataV1> {
+        let installer_json = GradleSpecifier {
+            extension: Some("json".to_string()),
+            ..loader.clone()
+        };
+        let url = self.loader_artifact_url(&installer_json);
+
+        debug!("Fetching loader installer data from {:#?}", &url);
+
+        load_json(&url).await
+    }
+}
@@ -1,77 +1,128 @@
-use libmcmeta::models::forge::{ForgeMavenMetadata, ForgeMavenPromotions, ForgeVersionMeta};
+use libmcmeta::models::forge::{
+    ForgeMavenMetadata, ForgeMavenPromotions, ForgeVersionMeta, DEFAULT_FORGE_MAVEN_URL,
+};
 use serde::Deserialize;
 use serde_valid::Validate;
 use tracing::debug;
 
-use crate::download::errors::MetadataError;
+use crate::download::{
+    self,
+    conditional::{Conditional, UpstreamValidators},
+    errors::MetadataError,
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
-fn default_maven_url() -> String {
-    "https://files.minecraftforge.net/net/minecraftforge/forge/maven-metadata.json".to_string()
+fn default_files_base_url() -> String {
+    "https://files.minecraftforge.net".to_string()
 }
 
-fn default_promotions_url() -> String {
-    "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json".to_string()
+fn default_maven_base_url() -> String {
+    DEFAULT_FORGE_MAVEN_URL.to_string()
 }
 
 #[derive(Deserialize, Debug)]
 struct DownloadConfig {
-    #[serde(default = "default_maven_url")]
-    pub maven_url: String,
-    #[serde(default = "default_promotions_url")]
-    pub promotions_url: String,
+    /// defaults to the maven metadata on the files host
+    pub maven_url: Option<String>,
+    /// defaults to the promotions on the files host
+    pub promotions_url: Option<String>,
+    /// host of the maven metadata, promotions and per version files manifests
+    #[serde(default = "default_files_base_url")]
+    pub files_base_url: String,
+    /// maven the Forge jars are downloaded from, also used in generated artifact urls
+    #[serde(default = "default_maven_base_url")]
+    pub maven_base_url: String,
 }
 
 impl DownloadConfig {
     fn from_config() -> Result<Self> {
         let config = config::Config::builder()
-            .add_source(config::Environment::with_prefix("MCMETA_FORGE"))
+            .add_source(config::Environment::with_prefix("MCMETA_FORGE").prefix_separator("__"))
             .build()?;
 
         config.try_deserialize::<'_, Self>().map_err(Into::into)
     }
+
+    fn files_url(&self, path: &str) -> String {
+        format!(
+            "{}/net/minecraftforge/forge/{}",
+            self.files_base_url.trim_end_matches('/'),
+            path
+        )
+    }
+
+    fn maven_metadata_url(&self) -> String {
+        self.maven_url
+            .clone()
+            .unwrap_or_else(|| self.files_url("maven-metadata.json"))
+    }
+
+    fn promotions_url(&self) -> String {
+        self.promotions_url
+            .clone()
+            .unwrap_or_else(|| self.files_url("promotions_slim.json"))
+    }
+}
+
+/**
+ * The maven Forge jars are downloaded from and generated artifacts point at
+ */
+pub fn maven_base_url() -> Result<String> {
+    let config = DownloadConfig::from_config()?;
+    Ok(config.maven_base_url.trim_end_matches('/').to_string())
+}
+
+/**
+ * The url of the files manifest (`meta.json`) of a Forge version
+ */
+pub fn files_manifest_url(long_version: &str) -> Result<String> {
+    let config = DownloadConfig::from_config()?;
+    Ok(config.files_url(&format!("{}/meta.json", long_version)))
 }
 
 pub async fn load_maven_metadata() -> Result<ForgeMavenMetadata> {
-    let client = reqwest::Client::new();
+    match load_maven_metadata_if_modified(None).await? {
+        Conditional::Modified(metadata, _) => Ok(metadata),
+        Conditional::NotModified => Err(anyhow!("Unconditional fetch answered 304")),
+    }
+}
+
+/**
+ * Fetch the maven metadata unless it did not change since it was sent with the given validators
+ */
+pub async fn load_maven_metadata_if_modified(
+    validators: Option<&UpstreamValidators>,
+) -> Result<Conditional<ForgeMavenMetadata>> {
     let config = DownloadConfig::from_config()?;
 
-    debug!(
-        "Fetching forge maven manifest from {:#?}",
-        &config.maven_url,
-    );
+    let maven_url = config.maven_metadata_url();
+
+    debug!("Fetching forge maven manifest from {:#?}", &maven_url);
 
-    let body = client
-        .get(&config.maven_url)
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await?;
+    let (body, validators) =
+        match download::download_text_if_modified(&maven_url, validators).await? {
+            Conditional::Modified(body, validators) => (body, validators),
+            Conditional::NotModified => return Ok(Conditional::NotModified),
+        };
 
     let metadata: ForgeMavenMetadata =
         serde_json::from_str(&body).map_err(|err| MetadataError::from_json_err(err, &body))?;
     metadata.validate()?;
-    Ok(metadata)
+    Ok(Conditional::Modified(metadata, validators))
 }
 
 pub async fn load_maven_promotions() -> Result<ForgeMavenPromotions> {
-    let client = reqwest::Client::new();
     let config = DownloadConfig::from_config()?;
 
+    let promotions_url = config.promotions_url();
+
     debug!(
         "Fetching forge promotions manifest from {:#?}",
-        &config.promotions_url,
+        &promotions_url
     );
 
-    let body = client
-        .get(&config.promotions_url)
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await?;
+    let body = download::download_text(&promotions_url).await?;
 
     let promotions: ForgeMavenPromotions =
         serde_json::from_str(&body).map_err(|err| MetadataError::from_json_err(err, &body))?;
@@ -80,17 +131,9 @@ pub async fn load_maven_promotions() -> Result<ForgeMavenPromotions> {
 }
 
 pub async fn load_single_forge_files_manifest(url: &str) -> Result<ForgeVersionMeta> {
-    let client = reqwest::Client::new();
-
     debug!("Fetching forge file manifest from {:#?}", url);
 
-    let body = client
-        .get(url)
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await?;
+    let body = download::download_text(url).await?;
     let manifest: ForgeVersionMeta =
         serde_json::from_str(&body).map_err(|err| MetadataError::from_json_err(err, &body))?;
     manifest.validate()?;
@@ -0,0 +1,56 @@
+use serde::Deserialize;
+
+use crate::download::fabric::FabricMetaServer;
+
+use anyhow::Result;
+
+fn default_meta_url() -> String {
+    "https://meta.legacyfabric.net".to_string()
+}
+
+fn default_maven_url() -> String {
+    "https://repo.legacyfabric.net/repository/legacyfabric".to_string()
+}
+
+fn default_loader_maven_url() -> String {
+    "https://maven.fabricmc.net".to_string()
+}
+
+#[derive(Deserialize, Debug)]
+struct DownloadConfig {
+    #[serde(default = "default_meta_url")]
+    pub meta_url: String,
+    #[serde(default = "default_maven_url")]
+    pub maven_url: String,
+    #[serde(default = "default_loader_maven_url")]
+    pub loader_maven_url: String,
+}
+
+impl DownloadConfig {
+    fn from_config() -> Result<Self> {
+        let config = config::Config::builder()
+            .add_source(
+                config::Environment::with_prefix("MCMETA_LEGACYFABRIC").prefix_separator("__"),
+            )
+            .build()?;
+
+        config.try_deserialize::<'_, Self>().map_err(Into::into)
+    }
+}
+
+/**
+ * The Legacy Fabric meta server, whose intermediaries map versions before 1.14 for the stock Fabric loader
+ */
+pub fn server() -> Result<FabricMetaServer> {
+    let config = DownloadConfig::from_config()?;
+
+    Ok(FabricMetaServer::new(
+        config.meta_url,
+        config.maven_url,
+        "net.legacyfabric.fabric-loader",
+        "Legacy Fabric Loader",
+        "net.legacyfabric.intermediary",
+        "Legacy Fabric Intermediary Mappings",
+    )
+    .with_loader_maven(config.loader_maven_url))
+}
@@ -0,0 +1,50 @@
+use libmcmeta::models::liteloader::LiteloaderIndex;
+use serde::Deserialize;
+use serde_valid::Validate;
+use tracing::debug;
+
+use crate::download::{self, errors::MetadataError};
+
+use anyhow::Result;
+
+fn default_versions_url() -> String {
+    "https://dl.liteloader.com/versions/versions.json".to_string()
+}
+
+#[derive(Deserialize, Debug)]
+struct DownloadConfig {
+    /// the versions.json of the LiteLoader download site
+    #[serde(default = "default_versions_url")]
+    pub versions_url: String,
+}
+
+impl DownloadConfig {
+    fn from_config() -> Result<Self> {
+        let config = config::Config::builder()
+            .add_source(
+                config::Environment::with_prefix("MCMETA_LITELOADER").prefix_separator("__"),
+            )
+            .build()?;
+
+        config.try_deserialize::<'_, Self>().map_err(Into::into)
+    }
+}
+
+/**
+ * Fetch the index of every LiteLoader build, keyed by Minecraft version
+ */
+pub async fn load_versions() -> Result<LiteloaderIndex> {
+    let config = DownloadConfig::from_config()?;
+
+    debug!(
+        "Fetching liteloader versions from {:#?}",
+        &config.versions_url
+    );
+
+    let body = download::download_text(&config.versions_url).await?;
+
+    let index: LiteloaderIndex =
+        serde_json::from_str(&body).map_err(|err| MetadataError::from_json_err(err, &body))?;
+    index.validate()?;
+    Ok(index)
+}
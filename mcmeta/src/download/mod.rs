@@ -1,24 +1,255 @@
+pub mod adoptium;
+pub mod archive;
+pub mod azul;
+pub mod babric;
+pub mod backoff;
+pub mod budget;
+pub mod check;
+pub mod client;
+pub mod conditional;
 pub mod errors;
+pub mod fabric;
 pub mod forge;
+pub mod legacyfabric;
+pub mod liteloader;
 pub mod mojang;
+pub mod neoforge;
+pub mod optifine;
+pub mod ornithe;
+pub mod single_flight;
+pub mod throttle;
 
-use anyhow::Result;
-use std::path::PathBuf;
+use anyhow::{anyhow, Context, Result};
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    StatusCode,
+};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
 
-pub async fn download_binary_file(path: &PathBuf, url: &str) -> Result<()> {
-    let client = reqwest::Client::new();
+use archive::RESPONSE_ARCHIVE;
+use backoff::HOST_BACKOFF;
+use budget::DOWNLOAD_BUDGET;
+use client::HTTP_CLIENT;
+use conditional::{Conditional, UpstreamValidators};
+use errors::NotFoundUpstream;
+use single_flight::SingleFlight;
+use throttle::DOWNLOAD_THROTTLE;
 
+use crate::app_config::MetadataConfig;
+use crate::metrics::SERVER_METRICS;
+use crate::utils::{filehash, HashAlgo};
+
+lazy_static! {
+    /// Text documents being fetched, so concurrent fetches of a manifest share one download
+    static ref TEXT_FETCHES: SingleFlight<String> = SingleFlight::new();
+    /// Files being downloaded, so the same file is never downloaded twice at once
+    static ref FILE_FETCHES: SingleFlight<()> = SingleFlight::new();
+}
+
+/**
+ * Apply the settings every download shares to the download state: the bandwidth cap, the cap on
+ * concurrent downloads, the response archive and the http client
+ */
+pub fn apply_settings(metadata_cfg: &MetadataConfig) -> Result<()> {
+    // first, so invalid client settings change nothing
+    HTTP_CLIENT.configure(metadata_cfg)?;
+    DOWNLOAD_THROTTLE.set_limit(metadata_cfg.max_download_bytes_per_second);
+    DOWNLOAD_BUDGET.set_limit(metadata_cfg.max_concurrent_downloads);
+    RESPONSE_ARCHIVE.set_directory(metadata_cfg.archive_directory.clone());
+    Ok(())
+}
+
+/// How often a request is sent before a 429 or 503 is given up on
+const MAX_ATTEMPTS: usize = 5;
+
+/**
+ * Send a GET request, pausing every request to the host for as long as it asks when it answers
+ * a 429 or 503 with a Retry-After, then resuming
+ */
+async fn send(url: &str, headers: HeaderMap) -> Result<reqwest::Response> {
+    let sent = send_with_backoff(url, headers).await;
+    SERVER_METRICS.record_fetch(&backoff::host(url), sent.is_ok());
+    sent
+}
+
+async fn send_with_backoff(url: &str, headers: HeaderMap) -> Result<reqwest::Response> {
+    let client = HTTP_CLIENT.client();
+    let mut attempt = 1;
+    loop {
+        HOST_BACKOFF.wait(url).await;
+        let response = HTTP_CLIENT
+            .read(url, client.get(url).headers(headers.clone()).send())
+            .await?;
+
+        let status = response.status();
+        if (status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE)
+            && attempt < MAX_ATTEMPTS
+        {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(backoff::parse_retry_after);
+            if let Some(retry_after) = retry_after {
+                warn!(
+                    "{} answered {}, pausing its downloads for {}s",
+                    url,
+                    status,
+                    retry_after.as_secs()
+                );
+                HOST_BACKOFF.pause(url, retry_after);
+                attempt += 1;
+                continue;
+            }
+        }
+        if status == StatusCode::NOT_FOUND {
+            return Err(NotFoundUpstream(url.to_string()).into());
+        }
+        return Ok(response.error_for_status()?);
+    }
+}
+
+/**
+ * Fetch a text document like a json manifest, archiving the raw response when archiving is enabled
+ */
+pub async fn download_text(url: &str) -> Result<String> {
+    TEXT_FETCHES.run(url, fetch_text(url.to_string())).await
+}
+
+async fn fetch_text(url: String) -> Result<String> {
+    let _permit = DOWNLOAD_BUDGET.acquire().await;
+    let response = send(&url, HeaderMap::new()).await?;
+    read_text(&url, response).await
+}
+
+/**
+ * Fetch a text document unless upstream answers that it did not change since it was sent with
+ * the given validators
+ */
+pub async fn download_text_if_modified(
+    url: &str,
+    validators: Option<&UpstreamValidators>,
+) -> Result<Conditional<String>> {
+    let headers = validators.map_or_else(HeaderMap::new, UpstreamValidators::request_headers);
+    let _permit = DOWNLOAD_BUDGET.acquire().await;
+    let response = send(url, headers).await?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Conditional::NotModified);
+    }
+    let validators = UpstreamValidators::from_headers(response.headers());
+    Ok(Conditional::Modified(
+        read_text(url, response).await?,
+        validators,
+    ))
+}
+
+async fn read_text(url: &str, mut response: reqwest::Response) -> Result<String> {
+    let mut body = Vec::new();
+    while let Some(chunk) = HTTP_CLIENT.read(url, response.chunk()).await? {
+        body.extend_from_slice(&chunk);
+    }
+    RESPONSE_ARCHIVE.archive(url, &body);
+
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/**
+ * Download a file, joining a download of the same url to the same path if one is already running
+ */
+pub async fn download_binary_file(path: &Path, url: &str) -> Result<()> {
+    let key = format!("{} {}", url, path.display());
+    FILE_FETCHES
+        .run(&key, fetch_binary_file(path.to_path_buf(), url.to_string()))
+        .await
+}
+
+async fn fetch_binary_file(path: PathBuf, url: String) -> Result<()> {
     if let Some(parent_dir) = path.parent() {
         if !parent_dir.exists() {
             std::fs::create_dir_all(parent_dir)?;
         }
     }
 
-    let file_response = client.get(url).send().await?.error_for_status()?;
+    let _permit = DOWNLOAD_BUDGET.acquire().await;
+    let mut file_response = send(&url, HeaderMap::new()).await?;
 
-    let mut file = std::fs::File::create(path)?;
-    let mut content = std::io::Cursor::new(file_response.bytes().await?);
-    std::io::copy(&mut content, &mut file)?;
+    let mut file = std::fs::File::create(&path)?;
+    while let Some(chunk) = HTTP_CLIENT.read(&url, file_response.chunk()).await? {
+        DOWNLOAD_THROTTLE.consume(chunk.len()).await;
+        file.write_all(&chunk)?;
+    }
 
     Ok(())
 }
+
+/**
+ * Download a file into place through a `.part` file next to it, so a file at `path` is always
+ * complete, deleting the download instead when its sha1 is not the expected one. Concurrent
+ * downloads of the same file join one, which alone verifies and moves it in place.
+ */
+pub async fn download_verified_file(path: &Path, url: &str, sha1: Option<&str>) -> Result<()> {
+    let key = format!("verified {} {}", url, path.display());
+    FILE_FETCHES
+        .run(
+            &key,
+            fetch_verified_file(
+                path.to_path_buf(),
+                url.to_string(),
+                sha1.map(str::to_string),
+            ),
+        )
+        .await
+}
+
+async fn fetch_verified_file(path: PathBuf, url: String, sha1: Option<String>) -> Result<()> {
+    let mut partial_name = path.file_name().unwrap_or_default().to_os_string();
+    partial_name.push(".part");
+    let partial_file = path.with_file_name(partial_name);
+    fetch_binary_file(partial_file.clone(), url.clone())
+        .await
+        .with_context(|| format!("Failure downloading {}", url))?;
+
+    if let Some(expected) = sha1 {
+        let actual = filehash(&partial_file, HashAlgo::Sha1)?;
+        if !actual.eq_ignore_ascii_case(&expected) {
+            let _ = std::fs::remove_file(&partial_file);
+            return Err(anyhow!(
+                "{} has sha1 {}, expected {}",
+                url,
+                actual.to_lowercase(),
+                expected.to_lowercase()
+            ));
+        }
+    }
+    std::fs::rename(&partial_file, &path)
+        .with_context(|| format!("Failure storing {}", path.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_settings, budget::DOWNLOAD_BUDGET, throttle::DOWNLOAD_THROTTLE};
+    use crate::app_config::ServerConfig;
+
+    #[test]
+    fn test_apply_settings_updates_download_caps() {
+        let mut metadata = ServerConfig::from_config("/nonexistent/config.toml")
+            .unwrap()
+            .metadata;
+        metadata.max_download_bytes_per_second = 1 << 20;
+        metadata.max_concurrent_downloads = 3;
+        apply_settings(&metadata).unwrap();
+        assert_eq!(DOWNLOAD_THROTTLE.limit(), 1 << 20);
+        assert_eq!(DOWNLOAD_BUDGET.limit(), 3);
+
+        // as a reload turning the caps off again does
+        metadata.max_download_bytes_per_second = 0;
+        metadata.max_concurrent_downloads = 0;
+        apply_settings(&metadata).unwrap();
+        assert_eq!(DOWNLOAD_THROTTLE.limit(), 0);
+        assert_eq!(DOWNLOAD_BUDGET.limit(), 0);
+    }
+}
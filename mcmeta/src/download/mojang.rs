@@ -1,4 +1,5 @@
-use libmcmeta::models::mojang::{MinecraftVersion, MojangVersionManifest};
+use libmcmeta::models::java::MojangJavaRuntimeIndex;
+use libmcmeta::models::mojang::{MinecraftVersion, MojangVersionManifest, OldSnapshotIndex};
 use serde::Deserialize;
 use serde_valid::Validate;
 use tempdir::TempDir;
@@ -6,22 +7,32 @@ use tracing::debug;
 
 use anyhow::{anyhow, Result};
 
-use crate::download::{self, errors::MetadataError};
+use crate::download::{
+    self,
+    conditional::{Conditional, UpstreamValidators},
+    errors::MetadataError,
+};
 
 fn default_download_url() -> String {
     "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json".to_string()
 }
 
+fn default_java_runtimes_url() -> String {
+    "https://piston-meta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json".to_string()
+}
+
 #[derive(Deserialize, Debug)]
 struct DownloadConfig {
     #[serde(default = "default_download_url")]
     pub manifest_url: String,
+    #[serde(default = "default_java_runtimes_url")]
+    pub java_runtimes_url: String,
 }
 
 impl DownloadConfig {
     fn from_config() -> Result<Self> {
         let config = config::Config::builder()
-            .add_source(config::Environment::with_prefix("MCMETA_MOJANG"))
+            .add_source(config::Environment::with_prefix("MCMETA_MOJANG").prefix_separator("__"))
             .build()?;
 
         config.try_deserialize::<'_, Self>().map_err(Into::into)
@@ -29,7 +40,18 @@ impl DownloadConfig {
 }
 
 pub async fn load_manifest() -> Result<MojangVersionManifest> {
-    let client = reqwest::Client::new();
+    match load_manifest_if_modified(None).await? {
+        Conditional::Modified(manifest, _) => Ok(manifest),
+        Conditional::NotModified => Err(anyhow!("Unconditional fetch answered 304")),
+    }
+}
+
+/**
+ * Fetch the version manifest unless it did not change since it was sent with the given validators
+ */
+pub async fn load_manifest_if_modified(
+    validators: Option<&UpstreamValidators>,
+) -> Result<Conditional<MojangVersionManifest>> {
     let config = DownloadConfig::from_config()?;
 
     debug!(
@@ -37,41 +59,58 @@ pub async fn load_manifest() -> Result<MojangVersionManifest> {
         &config.manifest_url
     );
 
-    let body = client
-        .get(&config.manifest_url)
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await?;
+    let (body, validators) =
+        match download::download_text_if_modified(&config.manifest_url, validators).await? {
+            Conditional::Modified(body, validators) => (body, validators),
+            Conditional::NotModified => return Ok(Conditional::NotModified),
+        };
 
     let manifest: MojangVersionManifest =
         serde_json::from_str(&body).map_err(|err| MetadataError::from_json_err(err, &body))?;
     manifest.validate()?;
-    Ok(manifest)
+    Ok(Conditional::Modified(manifest, validators))
 }
 
-pub async fn load_version_manifest(version_url: &str) -> Result<MinecraftVersion> {
-    let client = reqwest::Client::new();
+pub async fn load_java_runtimes() -> Result<MojangJavaRuntimeIndex> {
+    let config = DownloadConfig::from_config()?;
 
+    debug!(
+        "Fetching Java runtime index from {:#?}",
+        &config.java_runtimes_url
+    );
+
+    let body = download::download_text(&config.java_runtimes_url).await?;
+    let index: MojangJavaRuntimeIndex =
+        serde_json::from_str(&body).map_err(|err| MetadataError::from_json_err(err, &body))?;
+    Ok(index)
+}
+
+pub async fn load_version_manifest(version_url: &str) -> Result<MinecraftVersion> {
     debug!(
         "Fetching minecraft version manifest from {:#?}",
         version_url
     );
 
-    let body = client
-        .get(version_url)
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await?;
+    let body = download::download_text(version_url).await?;
     let manifest: MinecraftVersion =
         serde_json::from_str(&body).map_err(|err| MetadataError::from_json_err(err, &body))?;
     manifest.validate()?;
     Ok(manifest)
 }
 
+/**
+ * Fetch an external archive's list of old versions, in the format of our static `minecraft-old-snapshots.json`
+ */
+pub async fn load_old_snapshot_index(url: &str) -> Result<OldSnapshotIndex> {
+    debug!("Fetching old version archive index from {:#?}", url);
+
+    let body = download::download_text(url).await?;
+    let index: OldSnapshotIndex =
+        serde_json::from_str(&body).map_err(|err| MetadataError::from_json_err(err, &body))?;
+    index.validate()?;
+    Ok(index)
+}
+
 pub async fn load_zipped_version(version_url: &str) -> Result<MinecraftVersion> {
     use std::io::Read;
 
@@ -82,7 +121,7 @@ pub async fn load_zipped_version(version_url: &str) -> Result<MinecraftVersion>
         let url = reqwest::Url::parse(version_url)?;
         let fname = url
             .path_segments()
-            .and_then(|segments| segments.last())
+            .and_then(|mut segments| segments.next_back())
             .and_then(|name| if name.is_empty() { None } else { Some(name) })
             .unwrap_or("tmp.zip");
 
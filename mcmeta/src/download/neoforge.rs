@@ -0,0 +1,65 @@
+use libmcmeta::models::neoforge::{NeoForgeMavenVersions, DEFAULT_NEOFORGE_MAVEN_URL};
+use serde::Deserialize;
+use serde_valid::Validate;
+use tracing::debug;
+
+use crate::download::{self, errors::MetadataError};
+
+use anyhow::Result;
+
+fn default_maven_base_url() -> String {
+    DEFAULT_NEOFORGE_MAVEN_URL.to_string()
+}
+
+fn default_versions_api_url() -> String {
+    "https://maven.neoforged.net/api/maven/versions/releases".to_string()
+}
+
+#[derive(Deserialize, Debug)]
+struct DownloadConfig {
+    /// maven the installers are downloaded from, also used in generated artifact urls
+    #[serde(default = "default_maven_base_url")]
+    pub maven_base_url: String,
+    /// endpoint listing the versions of an artifact of the maven
+    #[serde(default = "default_versions_api_url")]
+    pub versions_api_url: String,
+}
+
+impl DownloadConfig {
+    fn from_config() -> Result<Self> {
+        let config = config::Config::builder()
+            .add_source(config::Environment::with_prefix("MCMETA_NEOFORGE").prefix_separator("__"))
+            .build()?;
+
+        config.try_deserialize::<'_, Self>().map_err(Into::into)
+    }
+}
+
+/**
+ * The maven NeoForge installers are downloaded from and generated artifacts point at
+ */
+pub fn maven_base_url() -> Result<String> {
+    let config = DownloadConfig::from_config()?;
+    Ok(config.maven_base_url.trim_end_matches('/').to_string())
+}
+
+/**
+ * Fetch the versions of a `net.neoforged` artifact, `neoforge` or the legacy `forge` one
+ */
+pub async fn load_maven_versions(artifact: &str) -> Result<NeoForgeMavenVersions> {
+    let config = DownloadConfig::from_config()?;
+    let url = format!(
+        "{}/net/neoforged/{}",
+        config.versions_api_url.trim_end_matches('/'),
+        artifact
+    );
+
+    debug!("Fetching neoforge {} versions from {:#?}", artifact, &url);
+
+    let body = download::download_text(&url).await?;
+
+    let versions: NeoForgeMavenVersions =
+        serde_json::from_str(&body).map_err(|err| MetadataError::from_json_err(err, &body))?;
+    versions.validate()?;
+    Ok(versions)
+}
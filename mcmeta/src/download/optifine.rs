@@ -0,0 +1,143 @@
+use libmcmeta::models::optifine::OptiFineBuild;
+use regex::Regex;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::download;
+
+use anyhow::{anyhow, Result};
+
+fn default_base_url() -> String {
+    "https://optifine.net".to_string()
+}
+
+#[derive(Deserialize, Debug)]
+struct DownloadConfig {
+    /// the OptiFine site, whose `/downloads` page lists every build
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+}
+
+impl DownloadConfig {
+    fn from_config() -> Result<Self> {
+        let config = config::Config::builder()
+            .add_source(config::Environment::with_prefix("MCMETA_OPTIFINE").prefix_separator("__"))
+            .build()?;
+
+        config.try_deserialize::<'_, Self>().map_err(Into::into)
+    }
+
+    fn base_url(&self) -> &str {
+        self.base_url.trim_end_matches('/')
+    }
+}
+
+/**
+ * `DD.MM.YYYY` as written on the download page to `YYYY-MM-DD`
+ */
+fn parse_release_date(date: &str) -> Option<String> {
+    let mut parts = date.trim().split('.');
+    let (day, month, year) = (parts.next()?, parts.next()?, parts.next()?);
+    let all_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    (parts.next().is_none() && all_digits(day) && all_digits(month) && all_digits(year))
+        .then(|| format!("{}-{:0>2}-{:0>2}", year, month, day))
+}
+
+/**
+ * Pick the builds out of the download page, which lists them in a table per Minecraft version
+ * under a `Minecraft <version>` heading. Rows missing a file name are skipped.
+ */
+fn parse_downloads_page(html: &str, base_url: &str) -> Vec<OptiFineBuild> {
+    let section_expression = Regex::new(
+        r#"(?s)<h2>\s*Minecraft\s+([^<]+?)\s*</h2>|<tr\s+class=['"]downloadLine[^'"]*['"]\s*>(.*?)</tr>"#,
+    )
+    .expect("Download page regex must compile");
+    let cell_expression = |class: &str| {
+        Regex::new(&format!(
+            r#"(?s)<td\s+class=['"]{}['"]\s*>(.*?)</td>"#,
+            class
+        ))
+        .expect("Download cell regex must compile")
+    };
+    let name_cell = cell_expression("colFile");
+    let forge_cell = cell_expression("colForge");
+    let date_cell = cell_expression("colDate");
+    let file_expression =
+        Regex::new(r#"adloadx\?f=([^'"&]+)"#).expect("Download link regex must compile");
+    let cell_text = |cell: &Regex, row: &str| {
+        cell.captures(row)
+            .map(|captures| captures[1].trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+
+    let mut builds = vec![];
+    let mut mc_version = None;
+    for section in section_expression.captures_iter(html) {
+        if let Some(heading) = section.get(1) {
+            mc_version = Some(heading.as_str().to_string());
+            continue;
+        }
+        let (Some(mc_version), Some(row)) = (&mc_version, section.get(2)) else {
+            continue;
+        };
+        let row = row.as_str();
+        let Some(filename) = file_expression
+            .captures(row)
+            .map(|captures| captures[1].to_string())
+        else {
+            continue;
+        };
+        builds.push(OptiFineBuild {
+            name: cell_text(&name_cell, row).unwrap_or_else(|| filename.clone()),
+            mc_version: mc_version.clone(),
+            preview: filename.starts_with("preview_"),
+            forge: cell_text(&forge_cell, row).filter(|forge| forge != "N/A"),
+            release_date: cell_text(&date_cell, row).and_then(|date| parse_release_date(&date)),
+            url: format!("{}/adloadx?f={}", base_url, filename),
+            filename,
+            sha1: None,
+            sha256: None,
+            size: None,
+        });
+    }
+    builds
+}
+
+/**
+ * Scrape every build from the OptiFine download page. As the page is not meant to be parsed, a
+ * page without any build is taken as its layout having changed.
+ */
+pub async fn load_builds() -> Result<Vec<OptiFineBuild>> {
+    let config = DownloadConfig::from_config()?;
+    let url = format!("{}/downloads", config.base_url());
+
+    debug!("Fetching OptiFine builds from {:#?}", &url);
+
+    let body = download::download_text(&url).await?;
+    let builds = parse_downloads_page(&body, config.base_url());
+    if builds.is_empty() {
+        return Err(anyhow!(
+            "No OptiFine builds found on {}, the page layout may have changed",
+            url
+        ));
+    }
+    Ok(builds)
+}
+
+/**
+ * Follow the download page of a build to the url of its jar, which expires after a while
+ */
+pub async fn resolve_jar_url(build: &OptiFineBuild) -> Result<String> {
+    let config = DownloadConfig::from_config()?;
+    let link_expression =
+        Regex::new(r#"href=['"](downloadx\?f=[^'"]+)['"]"#).expect("Jar link regex must compile");
+
+    debug!("Fetching OptiFine download page {:#?}", &build.url);
+
+    let body = download::download_text(&build.url).await?;
+    let link = link_expression
+        .captures(&body)
+        .map(|captures| captures[1].replace("&amp;", "&"))
+        .ok_or_else(|| anyhow!("No jar link found on {}", build.url))?;
+    Ok(format!("{}/{}", config.base_url(), link))
+}
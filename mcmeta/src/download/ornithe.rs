@@ -0,0 +1,68 @@
+use serde::Deserialize;
+
+use crate::download::fabric::FabricMetaServer;
+
+use anyhow::Result;
+
+fn default_meta_url() -> String {
+    "https://meta.ornithemc.net".to_string()
+}
+
+fn default_maven_url() -> String {
+    "https://maven.ornithemc.net/releases".to_string()
+}
+
+fn default_loader_maven_url() -> String {
+    "https://maven.fabricmc.net".to_string()
+}
+
+fn default_loader_path() -> String {
+    "v3/versions/fabric-loader".to_string()
+}
+
+fn default_intermediary_path() -> String {
+    "v3/versions/gen2/intermediary".to_string()
+}
+
+#[derive(Deserialize, Debug)]
+struct DownloadConfig {
+    #[serde(default = "default_meta_url")]
+    pub meta_url: String,
+    #[serde(default = "default_maven_url")]
+    pub maven_url: String,
+    #[serde(default = "default_loader_maven_url")]
+    pub loader_maven_url: String,
+    /// the meta server lists versions per loader and intermediary generation
+    #[serde(default = "default_loader_path")]
+    pub loader_path: String,
+    #[serde(default = "default_intermediary_path")]
+    pub intermediary_path: String,
+}
+
+impl DownloadConfig {
+    fn from_config() -> Result<Self> {
+        let config = config::Config::builder()
+            .add_source(config::Environment::with_prefix("MCMETA_ORNITHE").prefix_separator("__"))
+            .build()?;
+
+        config.try_deserialize::<'_, Self>().map_err(Into::into)
+    }
+}
+
+/**
+ * The Ornithe meta server, whose Calamus intermediaries map old versions for the stock Fabric loader
+ */
+pub fn server() -> Result<FabricMetaServer> {
+    let config = DownloadConfig::from_config()?;
+
+    Ok(FabricMetaServer::new(
+        config.meta_url,
+        config.maven_url,
+        "net.ornithemc.fabric-loader",
+        "Ornithe Fabric Loader",
+        "net.ornithemc.calamus-intermediary",
+        "Calamus Intermediary Mappings",
+    )
+    .with_loader_maven(config.loader_maven_url)
+    .with_versions_paths(&config.loader_path, &config.intermediary_path))
+}
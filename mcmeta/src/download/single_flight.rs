@@ -0,0 +1,143 @@
+use std::{collections::HashMap, sync::Arc, sync::Mutex};
+
+use anyhow::{anyhow, Result};
+use futures::{
+    future::{BoxFuture, Shared},
+    Future, FutureExt,
+};
+
+use crate::download::errors::NotFoundUpstream;
+
+type SharedFetch<T> = Shared<BoxFuture<'static, Result<T, Arc<anyhow::Error>>>>;
+
+/**
+ * Coalesces concurrent fetches of the same key into a single one whose result every caller shares
+ */
+pub struct SingleFlight<T> {
+    in_flight: Mutex<HashMap<String, SharedFetch<T>>>,
+}
+
+impl<T: Clone + Send + Sync + 'static> SingleFlight<T> {
+    pub fn new() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /**
+     * Run `fetch` unless a fetch of `key` is already in flight, in which case its result is awaited instead.
+     * Once the fetch is done the next call for `key` fetches again.
+     */
+    pub async fn run<F>(&self, key: &str, fetch: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>> + Send + 'static,
+    {
+        let shared = {
+            let mut in_flight = self.in_flight.lock().expect("Single flight lock poisoned");
+            in_flight
+                .entry(key.to_string())
+                .or_insert_with(|| {
+                    fetch
+                        .map(|result| result.map_err(Arc::new))
+                        .boxed()
+                        .shared()
+                })
+                .clone()
+        };
+
+        let result = shared.clone().await;
+
+        let mut in_flight = self.in_flight.lock().expect("Single flight lock poisoned");
+        // a newer fetch of the same key may have started already, only forget our own
+        if in_flight
+            .get(key)
+            .is_some_and(|current| current.ptr_eq(&shared))
+        {
+            in_flight.remove(key);
+        }
+
+        // a missing file stays recognizable for every caller, other errors are shared as text
+        result.map_err(|err| match err.downcast_ref::<NotFoundUpstream>() {
+            Some(not_found) => not_found.clone().into(),
+            None => anyhow!("{:#}", err),
+        })
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use anyhow::anyhow;
+
+    use super::SingleFlight;
+    use crate::download::errors::NotFoundUpstream;
+
+    #[tokio::test]
+    async fn test_single_flight_coalesces() {
+        let flights = SingleFlight::<usize>::new();
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let fetch = |fetches: Arc<AtomicUsize>| async move {
+            Ok(fetches.fetch_add(1, Ordering::SeqCst) + 1)
+        };
+
+        let first = flights.run("manifest", {
+            let fetches = fetches.clone();
+            async move {
+                released.await.ok();
+                fetch(fetches).await
+            }
+        });
+        let second = flights.run("manifest", fetch(fetches.clone()));
+        let other = flights.run("other", fetch(fetches.clone()));
+        let (first, second, other, ()) = tokio::join!(first, second, other, async {
+            tokio::task::yield_now().await;
+            release.send(()).unwrap();
+        });
+        assert_eq!(first.unwrap(), second.unwrap());
+        other.unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        // once done, the next run fetches again
+        flights
+            .run("manifest", fetch(fetches.clone()))
+            .await
+            .unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_single_flight_errors() {
+        let flights = SingleFlight::<()>::new();
+        let err = flights
+            .run("missing", async {
+                Err(NotFoundUpstream("net/minecraft/missing.jar".to_string()).into())
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<NotFoundUpstream>()
+                .map(|not_found| not_found.0.as_str()),
+            Some("net/minecraft/missing.jar")
+        );
+
+        let err = flights
+            .run("broken", async {
+                Err(anyhow!("connection reset").context("Failure downloading"))
+            })
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<NotFoundUpstream>().is_none());
+        assert_eq!(err.to_string(), "Failure downloading: connection reset");
+    }
+}
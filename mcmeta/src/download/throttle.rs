@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::{sync::Mutex, time::Instant};
+
+lazy_static! {
+    /// Shared by every download, so the cap applies to all of them together
+    pub static ref DOWNLOAD_THROTTLE: BandwidthThrottle = BandwidthThrottle::new();
+}
+
+struct Bucket {
+    available: f64,
+    last_refill: Instant,
+}
+
+/**
+ * A token bucket limiting the combined rate of downloads, allowing bursts of up to one second worth of bytes
+ */
+pub struct BandwidthThrottle {
+    bytes_per_second: AtomicU64,
+    bucket: Mutex<Bucket>,
+}
+
+impl BandwidthThrottle {
+    fn new() -> Self {
+        Self {
+            bytes_per_second: AtomicU64::new(0),
+            bucket: Mutex::new(Bucket {
+                available: 0.0,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /**
+     * Set the download bandwidth cap in bytes per second, 0 disables it
+     */
+    pub fn set_limit(&self, bytes_per_second: u64) {
+        self.bytes_per_second
+            .store(bytes_per_second, Ordering::Relaxed);
+    }
+
+    /**
+     * The download bandwidth cap in bytes per second, 0 when not capped
+     */
+    pub fn limit(&self) -> u64 {
+        self.bytes_per_second.load(Ordering::Relaxed)
+    }
+
+    /**
+     * Wait until `bytes` more bytes may be downloaded without exceeding the cap
+     */
+    pub async fn consume(&self, bytes: usize) {
+        let limit = self.bytes_per_second.load(Ordering::Relaxed);
+        if limit == 0 {
+            return;
+        }
+        let limit = limit as f64;
+
+        let wait = {
+            let mut bucket = self.bucket.lock().await;
+            let now = Instant::now();
+            let refilled =
+                bucket.available + now.duration_since(bucket.last_refill).as_secs_f64() * limit;
+            bucket.last_refill = now;
+            // going into debt makes the following downloads wait for this chunk too
+            bucket.available = refilled.min(limit) - bytes as f64;
+            if bucket.available < 0.0 {
+                Some(std::time::Duration::from_secs_f64(
+                    -bucket.available / limit,
+                ))
+            } else {
+                None
+            }
+        };
+
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
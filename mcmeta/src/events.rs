@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::debug;
+
+use crate::storage::UpdateSource;
+
+/// Events buffered per subscriber before a slow one starts missing them
+const EVENT_BUFFER: usize = 1024;
+
+lazy_static! {
+    /// Published to by the updater, subscribed to by every `/events` stream
+    pub static ref METADATA_EVENTS: broadcast::Sender<VersionEvent> =
+        broadcast::channel(EVENT_BUFFER).0;
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionChange {
+    Added,
+    Changed,
+    Removed,
+}
+
+/// A generated version an update wrote or dropped
+#[derive(Serialize, Debug, Clone)]
+pub struct VersionEvent {
+    pub source: UpdateSource,
+    pub uid: String,
+    pub version: String,
+    pub change: VersionChange,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: time::OffsetDateTime,
+}
+
+/**
+ * Whether anyone listens, so the updater can skip publishing events otherwise
+ */
+pub fn has_subscribers() -> bool {
+    METADATA_EVENTS.receiver_count() > 0
+}
+
+/**
+ * Every generated version that differs between the hashes from before and after an update
+ */
+pub fn version_changes<'a>(
+    before: &'a BTreeMap<(String, String), String>,
+    after: &'a BTreeMap<(String, String), String>,
+) -> impl Iterator<Item = (&'a (String, String), VersionChange)> + 'a {
+    after
+        .iter()
+        .filter_map(|(key, hash)| match before.get(key) {
+            None => Some((key, VersionChange::Added)),
+            Some(previous) if previous != hash => Some((key, VersionChange::Changed)),
+            Some(_) => None,
+        })
+        .chain(
+            before
+                .keys()
+                .filter(|key| !after.contains_key(*key))
+                .map(|key| (key, VersionChange::Removed)),
+        )
+}
+
+/**
+ * Publish an event for every generated version that differs between the hashes from before and
+ * after a source was updated
+ */
+pub fn publish_changes(
+    source: UpdateSource,
+    before: &BTreeMap<(String, String), String>,
+    after: &BTreeMap<(String, String), String>,
+) {
+    let timestamp = time::OffsetDateTime::now_utc();
+    let mut published = 0;
+    for ((uid, version), change) in version_changes(before, after) {
+        // without subscribers there is nobody to miss the event
+        let _ = METADATA_EVENTS.send(VersionEvent {
+            source,
+            uid: uid.clone(),
+            version: version.clone(),
+            change,
+            timestamp,
+        });
+        published += 1;
+    }
+    if published > 0 {
+        debug!(
+            "Published {} version events for {}",
+            published,
+            source.name()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{version_changes, VersionChange};
+
+    fn hashes(versions: &[(&str, &str)]) -> BTreeMap<(String, String), String> {
+        versions
+            .iter()
+            .map(|(version, hash)| {
+                (
+                    ("net.minecraft".to_string(), version.to_string()),
+                    hash.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_version_changes() {
+        let before = hashes(&[("1.20", "a"), ("1.20.1", "b"), ("1.20.2", "c")]);
+        let after = hashes(&[("1.20", "a"), ("1.20.1", "d"), ("1.20.3", "e")]);
+        let changes = version_changes(&before, &after)
+            .map(|((_, version), change)| (version.as_str(), change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                ("1.20.1", VersionChange::Changed),
+                ("1.20.3", VersionChange::Added),
+                ("1.20.2", VersionChange::Removed),
+            ]
+        );
+        assert_eq!(version_changes(&after, &after).count(), 0);
+    }
+}
@@ -0,0 +1,19 @@
+//! The internals of the mcmeta server: configuration, metadata storage, the upstream
+//! updater and the HTTP router, so they can be embedded in other binaries.
+
+pub mod app_config;
+pub mod compat;
+pub mod download;
+pub mod events;
+pub mod lint;
+pub mod listener;
+pub mod logging;
+pub mod metrics;
+pub mod publish;
+pub mod routes;
+pub mod storage;
+pub mod tasks;
+pub mod utils;
+
+#[macro_use]
+extern crate lazy_static;
@@ -0,0 +1,162 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use anyhow::Result;
+use libmcmeta::models::{Library, MetaVersion};
+use serde::Serialize;
+use tracing::info;
+
+use crate::storage::generated::GeneratedDataStorage;
+
+/// Suspicious generated output, which may still be valid but usually points at a generation bug
+#[derive(Serialize, Debug, Clone)]
+pub struct Lint {
+    pub uid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub message: String,
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(version) = &self.version {
+            write!(f, "{} {}: {}", self.uid, version, self.message)
+        } else {
+            write!(f, "{}: {}", self.uid, self.message)
+        }
+    }
+}
+
+fn library_lints(kind: &str, library: &Library) -> Option<String> {
+    if library.downloads.is_some() || library.url.is_some() {
+        return None;
+    }
+    let name = library
+        .name
+        .as_ref()
+        .map(|name| name.to_string())
+        .unwrap_or("<unnamed>".to_string());
+    Some(format!("{} {} has neither downloads nor a url", kind, name))
+}
+
+fn version_lints(
+    version: &MetaVersion,
+    available: &BTreeMap<String, BTreeSet<String>>,
+) -> Vec<String> {
+    let mut lints = vec![];
+
+    // only the components providing the game jar are expected to say how to start it
+    if (version.main_jar.is_some() || version.uid == "net.minecraft")
+        && version.main_class.is_none()
+        && version.applet_class.is_none()
+    {
+        lints.push("has neither a mainClass nor an appletClass".to_string());
+    }
+
+    if let Some(asset_index) = &version.asset_index {
+        if asset_index.id.is_empty() || asset_index.url.is_empty() || asset_index.total_size <= 0 {
+            lints.push(format!("has an empty assetIndex {:?}", asset_index.id));
+        }
+    }
+
+    let mut required = BTreeSet::new();
+    for dependency in version.requires.iter().flatten() {
+        if !required.insert(&dependency.uid) {
+            lints.push(format!("requires {} more than once", dependency.uid));
+        }
+        match available.get(&dependency.uid) {
+            None => lints.push(format!("requires unknown package {}", dependency.uid)),
+            Some(versions) => {
+                if let Some(equals) = dependency
+                    .equals
+                    .as_ref()
+                    .filter(|v| !versions.contains(*v))
+                {
+                    lints.push(format!(
+                        "requires {} {} which does not exist",
+                        dependency.uid, equals
+                    ));
+                }
+            }
+        }
+    }
+
+    let libraries = version
+        .libraries
+        .iter()
+        .flatten()
+        .map(|library| ("library", library))
+        .chain(
+            version
+                .maven_files
+                .iter()
+                .flatten()
+                .map(|library| ("maven file", library)),
+        );
+    lints.extend(libraries.filter_map(|(kind, library)| library_lints(kind, library)));
+
+    lints
+}
+
+/**
+ * Look through the generated metadata for suspicious output, like dangling `requires` or libraries nobody can download.
+ * Missing generated metadata has nothing to lint.
+ */
+pub fn lint_generated(storage: &GeneratedDataStorage) -> Result<Vec<Lint>> {
+    let Some(package_index) = storage.load_package_index()? else {
+        return Ok(vec![]);
+    };
+
+    let mut lints = vec![];
+    let mut available: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut versions = vec![];
+
+    for package in &package_index.packages {
+        if available.contains_key(&package.uid) {
+            lints.push(Lint {
+                uid: package.uid.clone(),
+                version: None,
+                message: "is listed more than once in the package index".to_string(),
+            });
+            continue;
+        }
+        let package_versions = available.entry(package.uid.clone()).or_default();
+
+        let Some(version_index) = storage.load_version_index(&package.uid)? else {
+            continue;
+        };
+        for entry in &version_index.versions {
+            if !package_versions.insert(entry.version.clone()) {
+                lints.push(Lint {
+                    uid: package.uid.clone(),
+                    version: Some(entry.version.clone()),
+                    message: "is listed more than once in the version index".to_string(),
+                });
+                continue;
+            }
+            // unreadable files are reported by the validation, not as lints
+            if let Ok(Some(version)) = storage.load_meta_version(&package.uid, &entry.version) {
+                versions.push(version);
+            }
+        }
+    }
+
+    for version in &versions {
+        for message in version_lints(version, &available) {
+            lints.push(Lint {
+                uid: version.uid.clone(),
+                version: Some(version.version.clone()),
+                message,
+            });
+        }
+    }
+
+    info!(
+        "Linted {} versions in {} packages, {} findings",
+        versions.len(),
+        package_index.packages.len(),
+        lints.len()
+    );
+
+    Ok(lints)
+}
@@ -0,0 +1,167 @@
+use std::{fmt, future::Future, net::SocketAddr, path::PathBuf, str::FromStr};
+
+use anyhow::{anyhow, Context, Result};
+use axum::{extract::connect_info::Connected, Router};
+use hyper::server::{conn::AddrStream, Builder};
+use tracing::{info, warn};
+
+use crate::app_config::{HttpConfig, ServerConfig};
+
+/// Where the server listens, a TCP address like `127.0.0.1:8080` or a unix domain socket given as
+/// `unix:/path/to/socket`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl FromStr for BindAddress {
+    type Err = anyhow::Error;
+
+    fn from_str(address: &str) -> Result<Self> {
+        match address.strip_prefix("unix:") {
+            Some("") => Err(anyhow!("Unix socket bind address {} has no path", address)),
+            Some(path) => Ok(BindAddress::Unix(PathBuf::from(path))),
+            None => address
+                .parse()
+                .map(BindAddress::Tcp)
+                .with_context(|| format!("Invalid bind address {}", address)),
+        }
+    }
+}
+
+impl fmt::Display for BindAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindAddress::Tcp(addr) => write!(f, "{}", addr),
+            BindAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Where a connection comes from, available to handlers as `ConnectInfo<PeerAddress>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerAddress {
+    Tcp(SocketAddr),
+    /// a peer on a unix domain socket, which has no address worth telling apart
+    Unix,
+}
+
+impl Connected<&AddrStream> for PeerAddress {
+    fn connect_info(target: &AddrStream) -> Self {
+        PeerAddress::Tcp(target.remote_addr())
+    }
+}
+
+#[cfg(unix)]
+impl Connected<&tokio::net::UnixStream> for PeerAddress {
+    fn connect_info(_target: &tokio::net::UnixStream) -> Self {
+        PeerAddress::Unix
+    }
+}
+
+/**
+ * Restrict a server to the HTTP versions the config allows, hyper detects the HTTP/2 preface on
+ * its own otherwise
+ */
+pub fn with_protocols<I>(builder: Builder<I>, http: &HttpConfig) -> Result<Builder<I>> {
+    match (http.http2, http.h2c_only) {
+        (true, false) => Ok(builder),
+        (true, true) => Ok(builder.http2_only(true)),
+        (false, false) => Ok(builder.http1_only(true)),
+        (false, true) => Err(anyhow!(
+            "h2c_only requires http2 to be enabled in the http config"
+        )),
+    }
+}
+
+/**
+ * Listen on a unix domain socket, replacing a socket left behind by a previous run and giving it
+ * the permission bits of `mode`, e.g. so only the group of a reverse proxy can connect
+ */
+#[cfg(unix)]
+pub fn bind_unix(
+    path: &std::path::Path,
+    mode: u32,
+) -> Result<impl hyper::server::accept::Accept<Conn = tokio::net::UnixStream, Error = std::io::Error>>
+{
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)
+            .with_context(|| format!("Failure removing stale socket {}", path.display()))?,
+        Ok(_) => {
+            return Err(anyhow!(
+                "Cannot bind to {}, it exists and is not a socket",
+                path.display()
+            ))
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(anyhow::Error::from(err)
+                .context(format!("Failure checking socket path {}", path.display())))
+        }
+    }
+
+    let listener = tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("Failure binding to socket {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .with_context(|| format!("Failure setting permissions of socket {}", path.display()))?;
+
+    Ok(hyper::server::accept::from_stream(
+        futures::stream::poll_fn(move |cx| {
+            listener
+                .poll_accept(cx)
+                .map(|accepted| Some(accepted.map(|(stream, _)| stream)))
+        }),
+    ))
+}
+
+/**
+ * Serve a router on an address until `shutdown` resolves, then finish the requests in flight
+ */
+pub async fn serve(
+    address: &BindAddress,
+    router: Router,
+    config: &ServerConfig,
+    shutdown: impl Future<Output = ()>,
+) -> Result<()> {
+    match address {
+        BindAddress::Tcp(addr) => {
+            let server = axum::Server::try_bind(addr)
+                .with_context(|| format!("Failure binding to {}", addr))?;
+            info!("Starting server on {}", addr);
+            with_protocols(server, &config.http)?
+                .serve(router.into_make_service_with_connect_info::<PeerAddress>())
+                .with_graceful_shutdown(shutdown)
+                .await?;
+        }
+        #[cfg(unix)]
+        BindAddress::Unix(path) => {
+            let mode = u32::from_str_radix(&config.unix_socket_mode, 8)
+                .with_context(|| format!("Invalid unix socket mode {}", config.unix_socket_mode))?;
+            let server = axum::Server::builder(bind_unix(path, mode)?);
+            info!("Starting server on {}", address);
+            let served = match with_protocols(server, &config.http) {
+                Ok(server) => server
+                    .serve(router.into_make_service_with_connect_info::<PeerAddress>())
+                    .with_graceful_shutdown(shutdown)
+                    .await
+                    .map_err(anyhow::Error::from),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = std::fs::remove_file(path) {
+                warn!("Failure removing socket {}: {}", path.display(), err);
+            }
+            served?;
+        }
+        #[cfg(not(unix))]
+        BindAddress::Unix(_) => {
+            return Err(anyhow!(
+                "Cannot listen on {}, unix domain sockets need a unix platform",
+                address
+            ))
+        }
+    }
+    Ok(())
+}
@@ -1,40 +1,82 @@
-use std::{str::FromStr, sync::Arc};
+use std::{path::PathBuf, str::FromStr, sync::Arc};
 
-use app_config::ServerConfig;
-use axum::{routing::get, Extension, Router};
+use mcmeta::{
+    app_config::{LogFormat, ServerConfig},
+    compat, download,
+    listener::{self, BindAddress},
+    logging::{JsonFields, JsonFormat},
+    metrics, routes, storage, tasks,
+};
 
 use tracing::{debug, info};
 
 use anyhow::Result;
 use dotenv::dotenv;
+use futures::FutureExt;
 use tracing_subscriber::{filter, prelude::*};
 
-mod app_config;
-mod download;
-mod routes;
-mod storage;
-mod utils;
-
-#[macro_use]
-extern crate lazy_static;
-
-use clap::Parser;
+use clap::{CommandFactory, Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct CliArgs {
-    #[arg(short, long, value_name = "FILE")]
+    #[arg(short, long, value_name = "FILE", global = true)]
     config: Option<String>,
-    #[arg(long)]
+    #[arg(long, global = true)]
     use_dotenv: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Update the upstream metadata and serve it (default)
+    Serve {
+        /// Serve the stored metadata without ever updating it, for when another process updates it
+        #[arg(long)]
+        no_update: bool,
+    },
+    /// Update the upstream metadata once and exit, for cron deployments
+    Update,
+    /// Check the generated metadata against the invariants Prism Launcher expects
+    CompatCheck,
+    /// Re-parse and validate every stored metadata file against the models, reporting the invalid ones
+    Validate,
+    /// Package the stored and generated metadata into an archive, compressed by its extension
+    Export {
+        /// e.g. `meta.tar.zst`, `meta.tar.gz` or `meta.tar`
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// Unpack an archive made by `export` into the metadata directories
+    Import {
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+    },
+    /// Fetch the upstream manifests and report any that no longer parse, without storing anything
+    CheckUpstream,
+    /// Print shell completions for the given shell to stdout
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
     let mut config_path = String::new();
 
     let args = CliArgs::parse();
 
+    if let Some(Command::Completions { shell }) = args.command {
+        clap_complete::generate(
+            shell,
+            &mut CliArgs::command(),
+            env!("CARGO_BIN_NAME"),
+            &mut std::io::stdout(),
+        );
+        return Ok(());
+    }
+
     if args.use_dotenv {
         dotenv().ok();
     }
@@ -48,7 +90,17 @@ async fn main() -> Result<()> {
     let file_appender =
         tracing_appender::rolling::hourly(&config.debug_log.path, &config.debug_log.prefix);
     let (non_blocking_file, _guard) = tracing_appender::non_blocking(file_appender);
-    let stdout_log = tracing_subscriber::fmt::layer().compact();
+    let stdout_log = match config.log_format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .compact()
+            .with_filter(filter::EnvFilter::from_default_env())
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .fmt_fields(JsonFields)
+            .event_format(JsonFormat)
+            .with_filter(filter::EnvFilter::from_default_env())
+            .boxed(),
+    };
 
     let debug_log = tracing_subscriber::fmt::layer()
         .with_ansi(false)
@@ -62,48 +114,171 @@ async fn main() -> Result<()> {
 
     if config.debug_log.enable {
         tracing_subscriber::registry()
-            .with(stdout_log.with_filter(filter::EnvFilter::from_default_env()))
+            .with(stdout_log)
             .with(debug_log)
             .init();
     } else {
-        tracing_subscriber::registry()
-            .with(stdout_log.with_filter(filter::EnvFilter::from_default_env()))
-            .init();
+        tracing_subscriber::registry().with(stdout_log).init();
     }
 
     debug!("Config: {:#?}", config);
 
-    config
+    let runtime = config.build_runtime()?;
+
+    match args.command.unwrap_or(Command::Serve { no_update: false }) {
+        Command::Serve { no_update } => runtime.block_on(serve(config, config_path, no_update)),
+        Command::Update => runtime.block_on(update(config)),
+        Command::CompatCheck => compat_check(config),
+        Command::Validate => validate(config),
+        Command::Export { output } => config.storage_format.export_bundle(&output),
+        Command::Import { input } => config.storage_format.import_bundle(&input),
+        Command::CheckUpstream => runtime.block_on(check_upstream()),
+        Command::Completions { shell: _ } => unreachable!("completions are generated before setup"),
+    }
+}
+
+fn compat_check(config: Arc<ServerConfig>) -> Result<()> {
+    let storage =
+        storage::generated::GeneratedDataStorage::new(Arc::new(config.storage_format.clone()));
+    let problems = compat::check_generated(&storage)?;
+
+    for problem in &problems {
+        println!("{}", problem);
+    }
+
+    if problems.is_empty() {
+        info!("Generated metadata passed all compatibility checks");
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Generated metadata has {} compatibility problems",
+            problems.len()
+        ))
+    }
+}
+
+fn validate(config: Arc<ServerConfig>) -> Result<()> {
+    let report = config.storage_format.validate_metadata()?;
+
+    for invalid in &report.invalid_files {
+        println!("{}", invalid);
+    }
+
+    if report.is_valid() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "{} of {} stored metadata files are invalid",
+            report.invalid_files.len(),
+            report.checked_files
+        ))
+    }
+}
+
+async fn check_upstream() -> Result<()> {
+    let problems = download::check::check_upstream().await;
+
+    for problem in &problems {
+        println!("{}", problem);
+    }
+
+    if problems.is_empty() {
+        info!("Upstream metadata passed all checks");
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Upstream metadata has {} problems",
+            problems.len()
+        ))
+    }
+}
+
+async fn update(config: Arc<ServerConfig>) -> Result<()> {
+    let started = std::time::Instant::now();
+    let result = config
         .storage_format
         .update_upstream_metadata(&config.metadata)
-        .await?;
-
-    let raw_mojang_routes = Router::new()
-        .route("/", get(routes::mojang::raw_mojang_manifest))
-        .route("/:version", get(routes::mojang::raw_mojang_version));
-    let raw_forge_routes = Router::new()
-        .route("/", get(routes::forge::raw_forge_maven_meta))
-        .route("/promotions", get(routes::forge::raw_forge_promotions))
-        .route("/:version", get(routes::forge::raw_forge_version))
-        .route("/:version/meta", get(routes::forge::raw_forge_version_meta))
-        .route(
-            "/:version/installer",
-            get(routes::forge::raw_forge_version_installer),
-        );
+        .await;
 
-    let raw_routes = Router::new()
-        .nest("/mojang", raw_mojang_routes)
-        .nest("/forge", raw_forge_routes);
+    let run_metrics = metrics::UpdateRunMetrics {
+        duration: started.elapsed(),
+        report: result.as_ref().ok().cloned(),
+        failures: usize::from(result.is_err()),
+    };
+    // a failing push must not hide the outcome of the update itself
+    if let Err(err) = metrics::push_update_metrics(&config.metrics, &run_metrics).await {
+        tracing::warn!("{:#}", err);
+    }
+
+    result?;
+    info!(
+        "Finished updating in {:.1}s",
+        run_metrics.duration.as_secs_f64()
+    );
+    Ok(())
+}
 
-    let http = Router::new()
-        .nest("/raw", raw_routes)
-        .layer(Extension(config.clone()));
+fn parse_addresses(addresses: &[String]) -> Result<Vec<BindAddress>> {
+    addresses
+        .iter()
+        .map(|address| address.trim())
+        .filter(|address| !address.is_empty())
+        .map(|address| address.parse())
+        .collect()
+}
 
-    let addr = config.bind_address.parse()?;
-    info!("Starting server on {}", addr);
-    axum::Server::bind(&addr)
-        .serve(http.into_make_service())
-        .await?;
+async fn serve(config: Arc<ServerConfig>, config_path: String, no_update: bool) -> Result<()> {
+    let update_tasks = if no_update {
+        info!("Serving the stored metadata without updating it");
+        // whatever is stored is as synced as this instance gets
+        storage::METADATA_SYNCED.store(true, std::sync::atomic::Ordering::Relaxed);
+        vec![]
+    } else {
+        let initial_update = tasks::spawn_initial_update(config.clone());
+        let scheduler = tasks::spawn_update_scheduler(config.clone());
+        scheduler.into_iter().chain([initial_update]).collect()
+    };
+    storage::coordinator::UPDATE_COORDINATOR.restore_history(&config.storage_format);
+    #[cfg(unix)]
+    tasks::spawn_reload_handler(config.clone(), config_path);
+    #[cfg(not(unix))]
+    let _ = config_path;
+
+    let public_addresses = parse_addresses(&config.bind_address)?;
+    let private_addresses = parse_addresses(&config.private_bind_address)?;
+    if public_addresses.is_empty() && private_addresses.is_empty() {
+        return Err(anyhow::anyhow!("No bind address configured"));
+    }
+    // with a private listener the admin endpoints and metrics are only mounted there
+    let public_router = if private_addresses.is_empty() {
+        routes::router(config.clone())
+    } else {
+        routes::public_router(config.clone())
+    };
+    let private_router = routes::router(config.clone());
+
+    let shutdown = async {
+        tasks::shutdown_signal().await;
+        tasks::start_shutdown();
+    }
+    .shared();
+    let servers = public_addresses
+        .iter()
+        .map(|address| (address, public_router.clone()))
+        .chain(
+            private_addresses
+                .iter()
+                .map(|address| (address, private_router.clone())),
+        )
+        .map(|(address, router)| listener::serve(address, router, &config, shutdown.clone()));
+    futures::future::try_join_all(servers).await?;
 
+    info!("Stopped serving, waiting for a running metadata update");
+    let _updates = tasks::stop_updates(
+        update_tasks,
+        std::time::Duration::from_secs(config.shutdown_timeout),
+    )
+    .await;
+    info!("Shut down");
     Ok(())
 }
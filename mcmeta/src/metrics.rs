@@ -0,0 +1,313 @@
+use std::{collections::BTreeMap, fmt::Write, sync::Mutex, time::Duration};
+
+use anyhow::{Context, Result};
+use tracing::{debug, info};
+
+use crate::app_config::MetricsConfig;
+use crate::storage::{
+    coordinator::{SourceState, SourceStatus},
+    UpdateReport,
+};
+
+/// Metrics of a single headless update run
+#[derive(Debug, Clone, Default)]
+pub struct UpdateRunMetrics {
+    pub duration: std::time::Duration,
+    pub report: Option<UpdateReport>,
+    pub failures: usize,
+}
+
+impl UpdateRunMetrics {
+    /**
+     * Render the metrics in the Prometheus text exposition format
+     */
+    pub fn to_prometheus_text(&self) -> String {
+        let mut text = String::new();
+        let finished = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+
+        let _ = writeln!(text, "# TYPE mcmeta_update_duration_seconds gauge");
+        let _ = writeln!(
+            text,
+            "mcmeta_update_duration_seconds {}",
+            self.duration.as_secs_f64()
+        );
+        let _ = writeln!(text, "# TYPE mcmeta_update_failures gauge");
+        let _ = writeln!(text, "mcmeta_update_failures {}", self.failures);
+        let _ = writeln!(
+            text,
+            "# TYPE mcmeta_update_last_run_timestamp_seconds gauge"
+        );
+        let _ = writeln!(
+            text,
+            "mcmeta_update_last_run_timestamp_seconds {}",
+            finished
+        );
+        if let Some(report) = &self.report {
+            let _ = writeln!(text, "# TYPE mcmeta_update_versions_processed gauge");
+            for (uid, count) in &report.updated_versions {
+                let _ = writeln!(
+                    text,
+                    "mcmeta_update_versions_processed{{uid=\"{}\"}} {}",
+                    uid, count
+                );
+            }
+            let _ = writeln!(text, "# TYPE mcmeta_update_unknown_rule_features gauge");
+            let _ = writeln!(
+                text,
+                "mcmeta_update_unknown_rule_features {}",
+                report.unknown_rule_features.len()
+            );
+            let _ = writeln!(text, "# TYPE mcmeta_update_lints gauge");
+            let _ = writeln!(text, "mcmeta_update_lints {}", report.lints.len());
+            let _ = writeln!(text, "# TYPE mcmeta_update_versions_failed gauge");
+            for (uid, versions) in &report.failed_versions {
+                let _ = writeln!(
+                    text,
+                    "mcmeta_update_versions_failed{{uid=\"{}\"}} {}",
+                    uid,
+                    versions.len()
+                );
+            }
+        }
+        text
+    }
+}
+
+lazy_static! {
+    /// Shared by the routes, downloads and updater, and rendered by the `/metrics` route
+    pub static ref SERVER_METRICS: ServerMetrics = ServerMetrics::default();
+}
+
+/// Upper bounds in seconds of the request latency histogram buckets
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+
+#[derive(Default)]
+struct RequestStats {
+    count: u64,
+    latency_sum: f64,
+    /// requests per bucket, not cumulative
+    buckets: [u64; LATENCY_BUCKETS.len()],
+}
+
+/// Requests by route, method and status
+type RequestKey = (String, String, u16);
+
+/// A gauge of every source, by its name and how to read it from the status of a source
+type SourceGauge = (&'static str, fn(&SourceStatus) -> f64);
+
+/**
+ * Metrics of a running server, counted since it started
+ */
+#[derive(Default)]
+pub struct ServerMetrics {
+    requests: Mutex<BTreeMap<RequestKey, RequestStats>>,
+    /// upstream fetches by host and whether they succeeded
+    fetches: Mutex<BTreeMap<(String, bool), u64>>,
+    /// versions that failed to update under the `continue` error policy, by uid
+    failed_versions: Mutex<BTreeMap<String, u64>>,
+}
+
+impl ServerMetrics {
+    pub fn record_request(&self, route: &str, method: &str, status: u16, latency: Duration) {
+        let Ok(mut requests) = self.requests.lock() else {
+            return;
+        };
+        let stats = requests
+            .entry((route.to_string(), method.to_string(), status))
+            .or_default();
+        let latency = latency.as_secs_f64();
+        stats.count += 1;
+        stats.latency_sum += latency;
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|&le| latency <= le) {
+            stats.buckets[bucket] += 1;
+        }
+    }
+
+    pub fn record_fetch(&self, host: &str, success: bool) {
+        if let Ok(mut fetches) = self.fetches.lock() {
+            *fetches.entry((host.to_string(), success)).or_default() += 1;
+        }
+    }
+
+    pub fn record_failed_versions(
+        &self,
+        failed_versions: &BTreeMap<String, BTreeMap<String, String>>,
+    ) {
+        if let Ok(mut failed) = self.failed_versions.lock() {
+            for (uid, versions) in failed_versions {
+                *failed.entry(uid.clone()).or_default() += versions.len() as u64;
+            }
+        }
+    }
+
+    /**
+     * Render the metrics in the Prometheus text exposition format, along with the number of
+     * versions stored for every uid and the update status of every source
+     */
+    pub fn to_prometheus_text(
+        &self,
+        stored_versions: &BTreeMap<String, usize>,
+        sources: &[SourceStatus],
+    ) -> String {
+        let mut text = String::new();
+
+        if let Ok(requests) = self.requests.lock() {
+            let _ = writeln!(text, "# TYPE mcmeta_http_requests_total counter");
+            for ((route, method, status), stats) in requests.iter() {
+                let _ = writeln!(
+                    text,
+                    "mcmeta_http_requests_total{{route=\"{}\",method=\"{}\",status=\"{}\"}} {}",
+                    route, method, status, stats.count
+                );
+            }
+            let _ = writeln!(
+                text,
+                "# TYPE mcmeta_http_request_duration_seconds histogram"
+            );
+            for ((route, method, status), stats) in requests.iter() {
+                let labels = format!(
+                    "route=\"{}\",method=\"{}\",status=\"{}\"",
+                    route, method, status
+                );
+                let mut cumulative = 0;
+                for (le, count) in LATENCY_BUCKETS.iter().zip(stats.buckets) {
+                    cumulative += count;
+                    let _ = writeln!(
+                        text,
+                        "mcmeta_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                        labels, le, cumulative
+                    );
+                }
+                let _ = writeln!(
+                    text,
+                    "mcmeta_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                    labels, stats.count
+                );
+                let _ = writeln!(
+                    text,
+                    "mcmeta_http_request_duration_seconds_sum{{{}}} {}",
+                    labels, stats.latency_sum
+                );
+                let _ = writeln!(
+                    text,
+                    "mcmeta_http_request_duration_seconds_count{{{}}} {}",
+                    labels, stats.count
+                );
+            }
+        }
+
+        if let Ok(fetches) = self.fetches.lock() {
+            let _ = writeln!(text, "# TYPE mcmeta_upstream_fetches_total counter");
+            for ((host, success), count) in fetches.iter() {
+                let _ = writeln!(
+                    text,
+                    "mcmeta_upstream_fetches_total{{host=\"{}\",outcome=\"{}\"}} {}",
+                    host,
+                    if *success { "success" } else { "failure" },
+                    count
+                );
+            }
+        }
+
+        let _ = writeln!(text, "# TYPE mcmeta_stored_versions gauge");
+        for (uid, count) in stored_versions {
+            let _ = writeln!(text, "mcmeta_stored_versions{{uid=\"{}\"}} {}", uid, count);
+        }
+
+        let _ = writeln!(
+            text,
+            "# TYPE mcmeta_last_successful_update_timestamp_seconds gauge"
+        );
+        for status in sources {
+            if let Some(last_success) = status.history.last_success {
+                let _ = writeln!(
+                    text,
+                    "mcmeta_last_successful_update_timestamp_seconds{{source=\"{}\"}} {}",
+                    status.source.name(),
+                    last_success.unix_timestamp()
+                );
+            }
+        }
+        let history_gauges: [SourceGauge; 4] = [
+            ("mcmeta_last_update_duration_seconds", |status| {
+                status.history.duration
+            }),
+            ("mcmeta_last_update_versions_added", |status| {
+                status.history.versions_added as f64
+            }),
+            ("mcmeta_last_update_versions_updated", |status| {
+                status.history.versions_updated as f64
+            }),
+            ("mcmeta_last_update_failed", |status| {
+                status
+                    .last_result
+                    .as_ref()
+                    .map_or(0.0, |result| if result.success { 0.0 } else { 1.0 })
+            }),
+        ];
+        for (name, value) in history_gauges {
+            let _ = writeln!(text, "# TYPE {} gauge", name);
+            for status in sources {
+                let _ = writeln!(
+                    text,
+                    "{}{{source=\"{}\"}} {}",
+                    name,
+                    status.source.name(),
+                    value(status)
+                );
+            }
+        }
+        let _ = writeln!(text, "# TYPE mcmeta_update_running gauge");
+        for status in sources {
+            let _ = writeln!(
+                text,
+                "mcmeta_update_running{{source=\"{}\"}} {}",
+                status.source.name(),
+                u8::from(matches!(status.state, SourceState::Running { .. }))
+            );
+        }
+
+        if let Ok(failed_versions) = self.failed_versions.lock() {
+            let _ = writeln!(text, "# TYPE mcmeta_update_versions_failed_total counter");
+            for (uid, count) in failed_versions.iter() {
+                let _ = writeln!(
+                    text,
+                    "mcmeta_update_versions_failed_total{{uid=\"{}\"}} {}",
+                    uid, count
+                );
+            }
+        }
+        text
+    }
+}
+
+/**
+ * Push the metrics of an update run to the configured Prometheus pushgateway, if there is one
+ */
+pub async fn push_update_metrics(config: &MetricsConfig, metrics: &UpdateRunMetrics) -> Result<()> {
+    let Some(pushgateway_url) = &config.pushgateway_url else {
+        debug!("No pushgateway configured, not pushing update metrics");
+        return Ok(());
+    };
+    let url = format!(
+        "{}/metrics/job/{}",
+        pushgateway_url.trim_end_matches('/'),
+        config.job_name
+    );
+
+    reqwest::Client::new()
+        .put(&url)
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .body(metrics.to_prometheus_text())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("Failure pushing update metrics to {}", url))?;
+
+    info!("Pushed update metrics to {}", url);
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use anyhow::{Context, Result};
+use git2::{
+    Cred, CredentialType, Delta, IndexAddOption, PushOptions, RemoteCallbacks, Repository,
+    Signature, Tree,
+};
+use tracing::info;
+
+use crate::app_config::PublishConfig;
+
+/// Versions listed by name per component in a commit message before the rest are only counted
+const LISTED_VERSIONS: usize = 10;
+
+/// The generated files a commit changes, by component uid
+#[derive(Debug, Default)]
+struct ChangeSummary {
+    added: BTreeMap<String, Vec<String>>,
+    changed: BTreeMap<String, usize>,
+    removed: BTreeMap<String, usize>,
+}
+
+impl ChangeSummary {
+    fn between(repo: &Repository, old: Option<&Tree>, new: &Tree) -> Result<Self> {
+        let diff = repo.diff_tree_to_tree(old, Some(new), None)?;
+        let mut summary = Self::default();
+        for delta in diff.deltas() {
+            let file = match delta.status() {
+                Delta::Deleted => delta.old_file(),
+                _ => delta.new_file(),
+            };
+            let Some(path) = file.path() else {
+                continue;
+            };
+            // versions live in `<uid>/<version>.json`, next to the version index of the uid
+            let (Some(uid), Some(name)) = (path.parent(), path.file_name()) else {
+                continue;
+            };
+            let uid = uid.to_string_lossy().to_string();
+            let version = name.to_string_lossy();
+            let Some(version) = version.strip_suffix(".json") else {
+                continue;
+            };
+            if uid.is_empty() || uid.contains('/') || version == "index" {
+                continue;
+            }
+            match delta.status() {
+                Delta::Added => summary
+                    .added
+                    .entry(uid)
+                    .or_default()
+                    .push(version.to_string()),
+                Delta::Deleted => *summary.removed.entry(uid).or_default() += 1,
+                _ => *summary.changed.entry(uid).or_default() += 1,
+            }
+        }
+        Ok(summary)
+    }
+
+    fn message(&self) -> String {
+        let added: usize = self.added.values().map(Vec::len).sum();
+        let changed: usize = self.changed.values().sum();
+        let removed: usize = self.removed.values().sum();
+        let mut message = format!(
+            "Update metadata: {} added, {} changed, {} removed\n",
+            added, changed, removed
+        );
+
+        let uids: std::collections::BTreeSet<&String> = self
+            .added
+            .keys()
+            .chain(self.changed.keys())
+            .chain(self.removed.keys())
+            .collect();
+        if !uids.is_empty() {
+            message.push('\n');
+        }
+        for uid in uids {
+            let mut parts = vec![];
+            if let Some(versions) = self.added.get(uid) {
+                let mut listed = versions
+                    .iter()
+                    .take(LISTED_VERSIONS)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if versions.len() > LISTED_VERSIONS {
+                    let _ = write!(listed, " and {} more", versions.len() - LISTED_VERSIONS);
+                }
+                parts.push(format!("added {}", listed));
+            }
+            if let Some(count) = self.changed.get(uid) {
+                parts.push(format!("{} changed", count));
+            }
+            if let Some(count) = self.removed.get(uid) {
+                parts.push(format!("{} removed", count));
+            }
+            let _ = writeln!(message, "{}: {}", uid, parts.join("; "));
+        }
+        message
+    }
+}
+
+fn open_or_init(directory: &Path, branch: &str) -> Result<Repository> {
+    match Repository::open(directory) {
+        Ok(repo) => Ok(repo),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => {
+            info!(
+                "Initializing a git repository in {}",
+                directory.to_string_lossy()
+            );
+            let repo = Repository::init(directory)?;
+            repo.set_head(&format!("refs/heads/{}", branch))?;
+            Ok(repo)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn push(repo: &Repository, config: &PublishConfig, remote_url: &str) -> Result<()> {
+    let mut remote = repo.remote_anonymous(remote_url)?;
+    let mut attempts = 0;
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(|_url, username_from_url, allowed| {
+        // libgit2 asks again for as long as the credentials are rejected
+        attempts += 1;
+        if attempts > 3 {
+            return Err(git2::Error::from_str(
+                "the remote rejected every credential",
+            ));
+        }
+        let username = config
+            .username
+            .as_deref()
+            .or(username_from_url)
+            .unwrap_or("git");
+        match &config.password {
+            Some(password) if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) => {
+                Cred::userpass_plaintext(username, password)
+            }
+            _ if allowed.contains(CredentialType::SSH_KEY) => Cred::ssh_key_from_agent(username),
+            _ => Cred::default(),
+        }
+    });
+    callbacks.push_update_reference(|reference, status| match status {
+        Some(status) => Err(git2::Error::from_str(&format!(
+            "the remote rejected {}: {}",
+            reference, status
+        ))),
+        None => Ok(()),
+    });
+
+    let refspec = format!("refs/heads/{0}:refs/heads/{0}", config.branch);
+    remote
+        .push(
+            &[refspec.as_str()],
+            Some(PushOptions::new().remote_callbacks(callbacks)),
+        )
+        .with_context(|| format!("Failure pushing to {}", remote_url))?;
+    Ok(())
+}
+
+/**
+ * Commit every change to the generated metadata in `directory`, which becomes a git repository if
+ * it is not one yet, and push the branch to the configured remote. Returns whether anything was
+ * committed; a branch left unpushed by a failure is pushed along with the next commit.
+ */
+pub fn publish_generated(directory: &Path, config: &PublishConfig) -> Result<bool> {
+    let repo = open_or_init(directory, &config.branch).with_context(|| {
+        format!(
+            "Failure opening git repository {}",
+            directory.to_string_lossy()
+        )
+    })?;
+
+    let mut index = repo.index()?;
+    index.add_all(["*"], IndexAddOption::DEFAULT, None)?;
+    index.update_all(["*"], None)?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+
+    let parent = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(err) if err.code() == git2::ErrorCode::UnbornBranch => None,
+        Err(err) => return Err(err.into()),
+    };
+    let parent_tree = parent.as_ref().map(|parent| parent.tree()).transpose()?;
+
+    let committed = if parent_tree.as_ref().map(|tree| tree.id()) == Some(tree.id()) {
+        info!("Generated metadata unchanged, nothing to publish");
+        false
+    } else {
+        let summary = ChangeSummary::between(&repo, parent_tree.as_ref(), &tree)?;
+        let signature = Signature::now(&config.author_name, &config.author_email)?;
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        let commit = repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            &summary.message(),
+            &tree,
+            &parents,
+        )?;
+        info!("Committed generated metadata as {}", commit);
+        true
+    };
+
+    if let Some(remote_url) = &config.remote_url {
+        push(&repo, config, remote_url)?;
+        info!("Pushed generated metadata to {}", remote_url);
+    }
+    Ok(committed)
+}
@@ -0,0 +1,536 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use axum::{
+    extract::{ConnectInfo, Path, Query},
+    http::{header, HeaderMap, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{error, info, warn, Instrument};
+
+use crate::app_config::{normalize_fingerprint, AdminMtlsConfig, ServerConfig, StorageFormat};
+use crate::lint::{self, Lint};
+use crate::listener::PeerAddress;
+use crate::routes::{cache::RESPONSE_CACHE, errors::APIError, APIResponse, APIResult};
+use crate::storage::{
+    coordinator::{SourceStatus, UPDATE_COORDINATOR},
+    generated::GeneratedDataStorage,
+    validation::MetadataValidationReport,
+    UpdateSource,
+};
+
+fn tokens_match(expected: &str, provided: &str) -> bool {
+    // compare every byte so the comparison time does not depend on the matching prefix
+    expected.len() == provided.len()
+        && expected
+            .bytes()
+            .zip(provided.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/**
+ * Whether a trusted TLS terminating proxy in front verified the client certificate of a request
+ * and it is one of the accepted ones. The headers of anyone but the trusted proxies are ignored,
+ * as clients could set them themselves.
+ */
+fn client_cert_accepted(
+    config: &AdminMtlsConfig,
+    peer: Option<&PeerAddress>,
+    headers: &HeaderMap,
+) -> bool {
+    if !peer.is_some_and(|peer| config.trusts(peer)) {
+        return false;
+    }
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+    if header(&config.verify_header) != Some("SUCCESS") {
+        return false;
+    }
+    let accepted = config.accepted_fingerprints();
+    header(&config.fingerprint_header)
+        .map(normalize_fingerprint)
+        .is_some_and(|fingerprint| {
+            !fingerprint.is_empty()
+                && accepted
+                    .iter()
+                    .any(|accepted| tokens_match(accepted, &fingerprint))
+        })
+}
+
+/**
+ * Middleware rejecting requests that neither carry the configured admin bearer token nor a client
+ * certificate the proxy in front verified, when mTLS is enabled. Admin endpoints are disabled
+ * entirely when neither is configured.
+ */
+pub async fn require_admin_auth<B>(request: Request<B>, next: Next<B>) -> Response {
+    let Some(config) = request.extensions().get::<Arc<ServerConfig>>().cloned() else {
+        return APIError::new(StatusCode::FORBIDDEN, "Admin endpoints are disabled")
+            .into_response();
+    };
+    let admin_token = config
+        .admin_token
+        .as_deref()
+        .filter(|token| !token.is_empty());
+    if admin_token.is_none() && !config.admin_mtls.enable {
+        return APIError::new(StatusCode::FORBIDDEN, "Admin endpoints are disabled")
+            .into_response();
+    }
+
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let token_accepted = admin_token
+        .zip(provided)
+        .is_some_and(|(admin_token, token)| tokens_match(admin_token, token));
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<PeerAddress>>()
+        .map(|ConnectInfo(peer)| peer);
+    let cert_accepted = config.admin_mtls.enable
+        && client_cert_accepted(&config.admin_mtls, peer, request.headers());
+
+    if token_accepted || cert_accepted {
+        next.run(request).await
+    } else {
+        warn!("Rejected unauthorized request to {}", request.uri());
+        (
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            APIError::new(
+                StatusCode::UNAUTHORIZED,
+                "Invalid or missing admin token or client certificate",
+            ),
+        )
+            .into_response()
+    }
+}
+
+/// The state of the revalidation started through the admin endpoint
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum RevalidationStatus {
+    Idle,
+    Running {
+        #[serde(with = "time::serde::iso8601")]
+        since: OffsetDateTime,
+    },
+    Finished {
+        #[serde(with = "time::serde::iso8601")]
+        started: OffsetDateTime,
+        #[serde(with = "time::serde::iso8601")]
+        finished: OffsetDateTime,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        report: Option<MetadataValidationReport>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+/// Only one revalidation runs at a time, reading every stored file is expensive enough
+static REVALIDATION: Mutex<RevalidationStatus> = Mutex::new(RevalidationStatus::Idle);
+
+fn revalidation() -> MutexGuard<'static, RevalidationStatus> {
+    REVALIDATION.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RevalidationStarted {
+    #[serde(with = "time::serde::iso8601")]
+    pub started: OffsetDateTime,
+    /// where to poll for the report
+    pub status: &'static str,
+}
+
+/**
+ * Start revalidating the stored metadata in the background, refusing while a revalidation is
+ * still running
+ */
+fn start_revalidation(storage_format: StorageFormat) -> APIResult<RevalidationStarted> {
+    let started = OffsetDateTime::now_utc();
+    {
+        let mut status = revalidation();
+        if matches!(*status, RevalidationStatus::Running { .. }) {
+            return Err(APIError::new(
+                StatusCode::CONFLICT,
+                "A revalidation is already running",
+            ));
+        }
+        *status = RevalidationStatus::Running { since: started };
+    }
+
+    info!("Revalidating stored metadata on request");
+    tokio::spawn(
+        async move {
+            let result =
+                tokio::task::spawn_blocking(move || storage_format.validate_metadata()).await;
+            let (report, error) = match result {
+                Ok(Ok(report)) => {
+                    info!(
+                        "Revalidated {} stored files, {} invalid",
+                        report.checked_files,
+                        report.invalid_files.len()
+                    );
+                    (Some(report), None)
+                }
+                Ok(Err(err)) => {
+                    error!("Failure revalidating stored metadata: {:?}", err);
+                    (None, Some(format!("{:#}", err)))
+                }
+                Err(err) => {
+                    error!("Revalidation task failed: {:?}", err);
+                    (None, Some(err.to_string()))
+                }
+            };
+            *revalidation() = RevalidationStatus::Finished {
+                started,
+                finished: OffsetDateTime::now_utc(),
+                report,
+                error,
+            };
+        }
+        .in_current_span(),
+    );
+
+    Ok(APIResponse::ok(RevalidationStarted {
+        started,
+        status: "/admin/revalidate",
+    })
+    .with_status(StatusCode::ACCEPTED))
+}
+
+pub async fn revalidate_metadata(
+    config: Extension<Arc<ServerConfig>>,
+) -> APIResult<RevalidationStarted> {
+    start_revalidation(config.storage_format.clone())
+}
+
+/**
+ * Whether a revalidation is running, or the report of the last one
+ */
+pub async fn revalidation_status() -> APIResponse<RevalidationStatus> {
+    APIResponse::ok(revalidation().clone())
+}
+
+pub async fn lint_metadata(config: Extension<Arc<ServerConfig>>) -> APIResult<Vec<Lint>> {
+    let storage = GeneratedDataStorage::new(Arc::new(config.storage_format.clone()));
+    let result = tokio::task::spawn_blocking(move || lint::lint_generated(&storage)).await;
+
+    match result {
+        Ok(Ok(lints)) => Ok(APIResponse::ok(lints)),
+        Ok(Err(err)) => Err(err.context("Failure linting generated metadata").into()),
+        Err(err) => Err(anyhow::Error::from(err).context("Lint task failed").into()),
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RefreshStarted {
+    pub sources: Vec<UpdateSource>,
+}
+
+/**
+ * Start updating the given sources in the background, refusing while any of them is being updated
+ */
+fn start_refresh(
+    config: Arc<ServerConfig>,
+    sources: Vec<UpdateSource>,
+) -> APIResult<RefreshStarted> {
+    if let Some(busy) = sources
+        .iter()
+        .find(|source| UPDATE_COORDINATOR.is_busy(**source))
+    {
+        return Err(APIError::new(
+            StatusCode::CONFLICT,
+            format!("An update of {} is already running", busy.name()),
+        ));
+    }
+
+    info!(
+        "Refreshing {:?} on request",
+        sources
+            .iter()
+            .map(|source| source.name())
+            .collect::<Vec<_>>()
+    );
+    let update_sources = sources.clone();
+    // the update keeps the span of the request, so its logs carry the request id
+    tokio::spawn(
+        async move {
+            match config
+                .storage_format
+                .update_upstream_sources(&update_sources, &config.current_metadata())
+                .await
+            {
+                Ok(_) => info!("Finished requested metadata refresh"),
+                Err(err) => error!("Failure refreshing metadata: {:?}", err),
+            }
+        }
+        .in_current_span(),
+    );
+
+    Ok(APIResponse::ok(RefreshStarted { sources }).with_status(StatusCode::ACCEPTED))
+}
+
+pub async fn refresh_metadata(config: Extension<Arc<ServerConfig>>) -> APIResult<RefreshStarted> {
+    let sources = config.current_metadata().enabled_sources();
+    start_refresh(config.0, sources)
+}
+
+pub async fn refresh_source(
+    config: Extension<Arc<ServerConfig>>,
+    Path(source): Path<String>,
+) -> APIResult<RefreshStarted> {
+    let Some(update_source) = UpdateSource::from_name(&source) else {
+        return Err(APIError::not_found(format!("Unknown source {}", source)));
+    };
+    if !config.current_metadata().is_enabled(update_source) {
+        return Err(APIError::not_found(format!(
+            "Source {} is not enabled",
+            update_source.name()
+        )));
+    }
+    start_refresh(config.0, vec![update_source])
+}
+
+/**
+ * Whether each source is being updated and how its last update ended
+ */
+pub async fn update_status() -> APIResponse<Vec<SourceStatus>> {
+    APIResponse::ok(UPDATE_COORDINATOR.status())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PurgeQuery {
+    /// only purge the responses to request uris starting with it, like `/raw/mojang`
+    pub prefix: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CachePurged {
+    pub purged: usize,
+}
+
+/**
+ * Drop cached responses, so files edited in the metadata directory by hand are read again even
+ * when their modification time and size did not change
+ */
+pub async fn purge_cache(Query(query): Query<PurgeQuery>) -> APIResponse<CachePurged> {
+    let prefix = query.prefix.filter(|prefix| !prefix.is_empty());
+    let purged = RESPONSE_CACHE.purge(prefix.as_deref());
+    info!(
+        "Purged {} cached responses{}",
+        purged,
+        prefix
+            .map(|prefix| format!(" under {}", prefix))
+            .unwrap_or_default()
+    );
+    APIResponse::ok(CachePurged { purged })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use axum::{
+        body::Body,
+        extract::{Path, Query},
+        http::{HeaderMap, Request, StatusCode},
+        middleware,
+        response::IntoResponse,
+        routing::get,
+        Router,
+    };
+    use tempdir::TempDir;
+    use tower::ServiceExt;
+
+    use super::{
+        client_cert_accepted, purge_cache, revalidation, start_revalidation, PurgeQuery,
+        RevalidationStatus,
+    };
+    use crate::app_config::{AdminMtlsConfig, StorageFormat};
+    use crate::listener::PeerAddress;
+    use crate::routes::cache::{cache_response, ResponseFile, RESPONSE_CACHE};
+
+    const FINGERPRINT: &str = "AB:CD:EF:01";
+
+    fn config(fingerprints: &[&str]) -> AdminMtlsConfig {
+        AdminMtlsConfig {
+            enable: true,
+            verify_header: "X-SSL-Client-Verify".to_string(),
+            fingerprint_header: "X-SSL-Client-Fingerprint".to_string(),
+            fingerprints: fingerprints.iter().map(|f| f.to_string()).collect(),
+            trusted_proxies: vec!["127.0.0.1".to_string(), "unix".to_string()],
+        }
+    }
+
+    fn headers(verify: &str, fingerprint: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-SSL-Client-Verify", verify.parse().unwrap());
+        headers.insert("X-SSL-Client-Fingerprint", fingerprint.parse().unwrap());
+        headers
+    }
+
+    fn proxy() -> PeerAddress {
+        PeerAddress::Tcp("127.0.0.1:41000".parse().unwrap())
+    }
+
+    #[test]
+    fn test_client_cert_accepted() {
+        let config = config(&[FINGERPRINT]);
+        assert!(client_cert_accepted(
+            &config,
+            Some(&proxy()),
+            &headers("SUCCESS", "abcdef01")
+        ));
+        assert!(client_cert_accepted(
+            &config,
+            Some(&PeerAddress::Unix),
+            &headers("SUCCESS", FINGERPRINT)
+        ));
+        assert!(!client_cert_accepted(
+            &config,
+            Some(&proxy()),
+            &headers("FAILED:unable to verify", FINGERPRINT)
+        ));
+    }
+
+    #[test]
+    fn test_client_cert_spoofed_headers() {
+        let config = config(&[FINGERPRINT]);
+        let client = PeerAddress::Tcp("203.0.113.7:52000".parse().unwrap());
+        assert!(!client_cert_accepted(
+            &config,
+            Some(&client),
+            &headers("SUCCESS", FINGERPRINT)
+        ));
+        assert!(!client_cert_accepted(
+            &config,
+            None,
+            &headers("SUCCESS", FINGERPRINT)
+        ));
+    }
+
+    #[test]
+    fn test_client_cert_fingerprint_mismatch() {
+        let config = config(&[FINGERPRINT]);
+        assert!(!client_cert_accepted(
+            &config,
+            Some(&proxy()),
+            &headers("SUCCESS", "AB:CD:EF:02")
+        ));
+        assert!(!client_cert_accepted(
+            &config,
+            Some(&proxy()),
+            &headers("SUCCESS", "")
+        ));
+    }
+
+    #[test]
+    fn test_client_cert_without_fingerprints() {
+        let config = config(&[]);
+        assert!(config.validate().is_err());
+        assert!(!client_cert_accepted(
+            &config,
+            Some(&proxy()),
+            &headers("SUCCESS", FINGERPRINT)
+        ));
+
+        let config = AdminMtlsConfig {
+            trusted_proxies: vec![],
+            ..self::config(&[FINGERPRINT])
+        };
+        assert!(config.validate().is_err());
+        assert!(self::config(&[FINGERPRINT]).validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_purge_cache_prefix() {
+        let tmp_dir = TempDir::new("mcmeta_purge_cache").unwrap();
+        let file = tmp_dir.path().join("index.json");
+        std::fs::write(&file, "[]").unwrap();
+        RESPONSE_CACHE.set_capacity(1024);
+        let handled = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route(
+                "/purge/:source/index.json",
+                get({
+                    let handled = handled.clone();
+                    move |Path(_source): Path<String>| async move {
+                        handled.fetch_add(1, Ordering::Relaxed);
+                        let mut response = "[]".into_response();
+                        response.extensions_mut().insert(ResponseFile(file));
+                        response
+                    }
+                }),
+            )
+            .route_layer(middleware::from_fn(cache_response));
+        let get = |source: &str| {
+            let request = Request::builder()
+                .uri(format!("/purge/{}/index.json", source))
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        get("mojang").await.unwrap();
+        get("forge").await.unwrap();
+        assert_eq!(handled.load(Ordering::Relaxed), 2);
+
+        let purged = purge_cache(Query(PurgeQuery {
+            prefix: Some("/purge/mojang".to_string()),
+        }))
+        .await;
+        assert_eq!(purged.data.unwrap().purged, 1);
+
+        // only the purged response is handled again
+        get("mojang").await.unwrap();
+        get("forge").await.unwrap();
+        assert_eq!(handled.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn test_revalidation_runs_in_background() {
+        let tmp_dir = TempDir::new("mcmeta_revalidate").unwrap();
+        let storage_format = StorageFormat::Json {
+            meta_directory: tmp_dir.path().join("meta").to_string_lossy().into_owned(),
+            generated_directory: tmp_dir
+                .path()
+                .join("generated")
+                .to_string_lossy()
+                .into_owned(),
+        };
+
+        *revalidation() = RevalidationStatus::Running {
+            since: time::OffsetDateTime::now_utc(),
+        };
+        let refused = start_revalidation(storage_format.clone()).unwrap_err();
+        assert_eq!(refused.status, StatusCode::CONFLICT);
+
+        *revalidation() = RevalidationStatus::Idle;
+        let started = start_revalidation(storage_format).unwrap();
+        assert_eq!(started.status, StatusCode::ACCEPTED);
+        let finished = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                let status = revalidation().clone();
+                if let RevalidationStatus::Finished { report, error, .. } = status {
+                    break (report, error);
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(finished.0.is_some() || finished.1.is_some());
+    }
+}
@@ -0,0 +1,358 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock,
+    },
+    time::SystemTime,
+};
+
+use axum::{
+    body::{boxed, Bytes, Full},
+    extract::OriginalUri,
+    http::{header, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::{debug, warn};
+
+lazy_static! {
+    /// Shared by every route, so the updater can invalidate it after rewriting files
+    pub static ref RESPONSE_CACHE: ResponseCache = ResponseCache::new();
+}
+
+/// The stored file a response was serialized from, attached to the response by its handler
+#[derive(Clone, Debug)]
+pub struct ResponseFile(pub PathBuf);
+
+/// The cache entry a response body came from, so compressed variants of it can be kept alongside
+#[derive(Clone, Debug)]
+pub struct CachedBody {
+    uri: String,
+    stamp: FileStamp,
+}
+
+/// Identifies a version of a file without reading it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+        })
+    }
+}
+
+struct CachedResponse {
+    file: PathBuf,
+    stamp: FileStamp,
+    body: Bytes,
+    /// the `Cache-Control` the handler answered with, if any
+    cache_control: Option<HeaderValue>,
+    /// the body compressed by content coding, filled as clients ask for them
+    encoded: HashMap<&'static str, Bytes>,
+}
+
+/**
+ * Serialized responses keyed by the full request uri, each valid for as long as the file it was
+ * serialized from keeps its modification time and size
+ */
+pub struct ResponseCache {
+    capacity: AtomicUsize,
+    entries: RwLock<HashMap<String, CachedResponse>>,
+}
+
+impl ResponseCache {
+    fn new() -> Self {
+        Self {
+            capacity: AtomicUsize::new(0),
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /**
+     * Set the number of responses kept, 0 disables the cache
+     */
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        if capacity == 0 {
+            self.clear();
+        }
+    }
+
+    /**
+     * Drop every cached response, e.g. once the updater is done rewriting the stored files
+     */
+    pub fn clear(&self) {
+        match self.entries.write() {
+            Ok(mut entries) => entries.clear(),
+            Err(err) => warn!("Response cache lock poisoned: {}", err),
+        }
+    }
+
+    /**
+     * Drop the cached responses whose request uri starts with `prefix`, or all of them without
+     * one, returning how many were dropped
+     */
+    pub fn purge(&self, prefix: Option<&str>) -> usize {
+        let Ok(mut entries) = self.entries.write() else {
+            warn!("Response cache lock poisoned");
+            return 0;
+        };
+        let before = entries.len();
+        match prefix {
+            Some(prefix) => entries.retain(|uri, _| !uri.starts_with(prefix)),
+            None => entries.clear(),
+        }
+        before - entries.len()
+    }
+
+    fn get(&self, uri: &str) -> Option<(Bytes, Option<HeaderValue>, CachedBody)> {
+        let entries = self.entries.read().ok()?;
+        let cached = entries.get(uri)?;
+        (FileStamp::of(&cached.file) == Some(cached.stamp)).then(|| {
+            let from = CachedBody {
+                uri: uri.to_string(),
+                stamp: cached.stamp,
+            };
+            (cached.body.clone(), cached.cache_control.clone(), from)
+        })
+    }
+
+    /**
+     * The body of a cached response compressed with `coding`, if it was compressed before and the
+     * entry still holds the body the response was sent with
+     */
+    pub fn encoded(&self, from: &CachedBody, coding: &str) -> Option<Bytes> {
+        let entries = self.entries.read().ok()?;
+        let cached = entries.get(&from.uri)?;
+        (cached.stamp == from.stamp)
+            .then(|| cached.encoded.get(coding).cloned())
+            .flatten()
+    }
+
+    /**
+     * Keep the body of a cached response compressed with `coding`, unless the entry was replaced
+     * or dropped since the response was sent
+     */
+    pub fn insert_encoded(&self, from: &CachedBody, coding: &'static str, body: Bytes) {
+        let Ok(mut entries) = self.entries.write() else {
+            return;
+        };
+        if let Some(cached) = entries.get_mut(&from.uri) {
+            if cached.stamp == from.stamp {
+                cached.encoded.insert(coding, body);
+            }
+        }
+    }
+
+    fn insert(
+        &self,
+        uri: String,
+        file: PathBuf,
+        stamp: FileStamp,
+        body: Bytes,
+        cache_control: Option<HeaderValue>,
+    ) {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        let Ok(mut entries) = self.entries.write() else {
+            return;
+        };
+        if entries.len() >= capacity && !entries.contains_key(&uri) {
+            // make room for the new response, which entry goes does not matter much
+            let Some(evicted) = entries.keys().next().cloned() else {
+                return;
+            };
+            entries.remove(&evicted);
+        }
+        entries.insert(
+            uri,
+            CachedResponse {
+                file,
+                stamp,
+                body,
+                cache_control,
+                encoded: HashMap::new(),
+            },
+        );
+    }
+}
+
+/**
+ * Middleware serving responses from the cache while their file is unchanged, caching the
+ * successful responses of handlers that name their file with a `ResponseFile` extension
+ */
+pub async fn cache_response<B>(request: Request<B>, next: Next<B>) -> Response {
+    if RESPONSE_CACHE.capacity.load(Ordering::Relaxed) == 0 {
+        return next.run(request).await;
+    }
+
+    // nested routers see the uri without their prefix, the original one is what purges refer to
+    let uri = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or_else(|| request.uri().to_string(), |uri| uri.0.to_string());
+    if let Some((body, cache_control, from)) = RESPONSE_CACHE.get(&uri) {
+        let mut response = (
+            [(header::CONTENT_TYPE, "application/json")],
+            Full::from(body),
+        )
+            .into_response();
+        if let Some(cache_control) = cache_control {
+            response
+                .headers_mut()
+                .insert(header::CACHE_CONTROL, cache_control);
+        }
+        response.extensions_mut().insert(from);
+        return response;
+    }
+
+    let response = next.run(request).await;
+    let Some(ResponseFile(file)) = response.extensions().get::<ResponseFile>().cloned() else {
+        return response;
+    };
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    let Some(stamp) = FileStamp::of(&file) else {
+        return response;
+    };
+
+    let (parts, body) = response.into_parts();
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(err) => {
+            warn!("Failure reading response body: {:?}", err);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    debug!("Caching response to {} from {}", uri, file.display());
+    let cache_control = parts.headers.get(header::CACHE_CONTROL).cloned();
+    RESPONSE_CACHE.insert(uri.clone(), file, stamp, body.clone(), cache_control);
+    let mut response = Response::from_parts(parts, boxed(Full::from(body)));
+    response.extensions_mut().insert(CachedBody { uri, stamp });
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use axum::{
+        body::{Body, Bytes},
+        http::{Request, StatusCode},
+        middleware,
+        response::IntoResponse,
+        routing::get,
+        Router,
+    };
+    use tempdir::TempDir;
+    use tower::ServiceExt;
+
+    use super::{cache_response, FileStamp, ResponseCache, ResponseFile, RESPONSE_CACHE};
+
+    fn insert(cache: &ResponseCache, uri: &str, file: &std::path::Path) {
+        let stamp = FileStamp::of(file).unwrap();
+        cache.insert(
+            uri.to_string(),
+            file.to_path_buf(),
+            stamp,
+            Bytes::from(uri.to_string()),
+            None,
+        );
+    }
+
+    #[test]
+    fn test_response_cache_until_file_changes() {
+        let tmp_dir = TempDir::new("mcmeta_response_cache").unwrap();
+        let file = tmp_dir.path().join("index.json");
+        std::fs::write(&file, "[]").unwrap();
+        let cache = ResponseCache::new();
+        cache.set_capacity(2);
+
+        insert(&cache, "/raw/a", &file);
+        assert_eq!(cache.get("/raw/a").unwrap().0, "/raw/a");
+        assert!(cache.get("/raw/b").is_none());
+
+        std::fs::write(&file, "[1]").unwrap();
+        assert!(cache.get("/raw/a").is_none());
+    }
+
+    #[test]
+    fn test_response_cache_capacity_and_purge() {
+        let tmp_dir = TempDir::new("mcmeta_response_cache").unwrap();
+        let file = tmp_dir.path().join("index.json");
+        std::fs::write(&file, "[]").unwrap();
+        let cache = ResponseCache::new();
+        cache.set_capacity(2);
+
+        insert(&cache, "/raw/mojang/a", &file);
+        insert(&cache, "/raw/mojang/b", &file);
+        insert(&cache, "/raw/forge/a", &file);
+        let cached = ["/raw/mojang/a", "/raw/mojang/b", "/raw/forge/a"]
+            .into_iter()
+            .filter(|uri| cache.get(uri).is_some())
+            .count();
+        assert_eq!(cached, 2);
+        assert!(cache.get("/raw/forge/a").is_some());
+
+        assert_eq!(cache.purge(Some("/raw/forge")), 1);
+        assert!(cache.get("/raw/forge/a").is_none());
+        assert_eq!(cache.purge(None), 1);
+
+        insert(&cache, "/raw/mojang/a", &file);
+        cache.set_capacity(0);
+        assert!(cache.get("/raw/mojang/a").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cache_response_serves_until_file_changes() {
+        let tmp_dir = TempDir::new("mcmeta_response_cache").unwrap();
+        let file = tmp_dir.path().join("index.json");
+        std::fs::write(&file, "[]").unwrap();
+        RESPONSE_CACHE.set_capacity(1024);
+        let handled = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route(
+                "/cache/index.json",
+                get({
+                    let file = file.clone();
+                    let handled = handled.clone();
+                    move || async move {
+                        handled.fetch_add(1, Ordering::Relaxed);
+                        let mut response = std::fs::read_to_string(&file).unwrap().into_response();
+                        response.extensions_mut().insert(ResponseFile(file));
+                        response
+                    }
+                }),
+            )
+            .route_layer(middleware::from_fn(cache_response));
+        let get_body = |app: Router| async move {
+            let request = Request::builder()
+                .uri("/cache/index.json")
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            hyper::body::to_bytes(response.into_body()).await.unwrap()
+        };
+
+        assert_eq!(get_body(app.clone()).await, "[]");
+        assert_eq!(get_body(app.clone()).await, "[]");
+        assert_eq!(handled.load(Ordering::Relaxed), 1);
+
+        std::fs::write(&file, "[1]").unwrap();
+        assert_eq!(get_body(app).await, "[1]");
+        assert_eq!(handled.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,24 @@
+use std::sync::Arc;
+
+use axum::Extension;
+
+use crate::app_config::ServerConfig;
+use crate::routes::{errors::APIError, APIResponse, APIResult};
+use crate::storage::{checksums::ChecksumIndex, UpdateSource};
+
+/**
+ * The sha256, size and modification time of every file stored for a source, as of its last
+ * update, so mirrors can tell which files changed since they last synced
+ */
+pub async fn raw_checksums(
+    source: UpdateSource,
+    config: Extension<Arc<ServerConfig>>,
+) -> APIResult<ChecksumIndex> {
+    match config.storage_format.load_checksums(source)? {
+        Some(index) => Ok(APIResponse::ok(index)),
+        None => Err(APIError::not_found(format!(
+            "No checksums of {} are stored yet",
+            source.name()
+        ))),
+    }
+}
@@ -0,0 +1,422 @@
+use std::io::Write;
+
+use axum::{
+    body::{boxed, Bytes, Full},
+    extract::State,
+    http::{header, response::Parts, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use flate2::{write::GzEncoder, Compression};
+use tracing::warn;
+
+use crate::routes::cache::{CachedBody, RESPONSE_CACHE};
+
+/// Whether and from which size on responses are compressed
+#[derive(Clone, Copy, Debug)]
+pub struct CompressionSettings {
+    pub enable: bool,
+    pub min_bytes: usize,
+}
+
+/// A content coding of responses, in order of preference when clients accept several equally
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Brotli,
+    Gzip,
+    Identity,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+            Encoding::Identity => "identity",
+        }
+    }
+
+    fn encode(self, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli => brotli::compress(body),
+            #[cfg(not(feature = "brotli"))]
+            Encoding::Brotli => {
+                unreachable!("brotli is never negotiated without the brotli feature")
+            }
+            Encoding::Gzip => gzip(body),
+            Encoding::Identity => Ok(body.to_vec()),
+        }
+    }
+}
+
+/**
+ * The encoding to send a response with for an `Accept-Encoding` header: the supported coding with
+ * the highest q-value, given by name or through `*`, preferring brotli over gzip at equal q-values.
+ * A coding is only picked over identity if its q-value is above 0 and not below the one identity
+ * is given, identity is sent for anything else.
+ */
+fn negotiate_encoding(accept_encoding: &HeaderValue) -> Encoding {
+    let Ok(accept_encoding) = accept_encoding.to_str() else {
+        return Encoding::Identity;
+    };
+    let mut brotli = None;
+    let mut gzip = None;
+    let mut identity = None;
+    let mut any = None;
+    for coding in accept_encoding.split(',') {
+        let mut params = coding.split(';').map(str::trim);
+        let name = params.next().unwrap_or_default().to_ascii_lowercase();
+        let quality = params
+            .find_map(|param| {
+                param
+                    .strip_prefix("q=")
+                    .or_else(|| param.strip_prefix("Q="))
+            })
+            .map_or(1.0, |quality| quality.parse::<f32>().unwrap_or(0.0));
+        match name.as_str() {
+            "br" => brotli = Some(quality),
+            "gzip" | "x-gzip" => gzip = Some(quality),
+            "identity" => identity = Some(quality),
+            "*" => any = Some(quality),
+            _ => {}
+        }
+    }
+    let brotli = if cfg!(feature = "brotli") {
+        brotli.or(any).unwrap_or(0.0)
+    } else {
+        0.0
+    };
+    let gzip = gzip.or(any).unwrap_or(0.0);
+    let (encoding, quality) = if brotli >= gzip {
+        (Encoding::Brotli, brotli)
+    } else {
+        (Encoding::Gzip, gzip)
+    };
+    if quality > 0.0 && identity.or(any).is_none_or(|identity| quality >= identity) {
+        encoding
+    } else {
+        Encoding::Identity
+    }
+}
+
+fn is_compressible(content_type: Option<&HeaderValue>) -> bool {
+    content_type
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| {
+            // an event stream never ends, so it could not be buffered to compress it
+            content_type.starts_with("application/json")
+                || (content_type.starts_with("text/")
+                    && !content_type.starts_with("text/event-stream"))
+        })
+}
+
+fn gzip(body: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body)?;
+    encoder.finish()
+}
+
+/// Brotli through the system libbrotlienc
+#[cfg(feature = "brotli")]
+mod brotli {
+    use std::os::raw::c_int;
+
+    /// fast enough to compress every response on the fly, unlike the default of 11
+    const QUALITY: c_int = 5;
+    /// the default window of 4 MiB
+    const WINDOW_BITS: c_int = 22;
+    /// BROTLI_MODE_TEXT, every compressed response is json or text
+    const MODE_TEXT: c_int = 1;
+
+    #[link(name = "brotlienc")]
+    extern "C" {
+        fn BrotliEncoderMaxCompressedSize(input_size: usize) -> usize;
+        fn BrotliEncoderCompress(
+            quality: c_int,
+            lgwin: c_int,
+            mode: c_int,
+            input_size: usize,
+            input_buffer: *const u8,
+            encoded_size: *mut usize,
+            encoded_buffer: *mut u8,
+        ) -> c_int;
+    }
+
+    pub fn compress(body: &[u8]) -> std::io::Result<Vec<u8>> {
+        // SAFETY: only computes a size
+        let mut encoded_size = unsafe { BrotliEncoderMaxCompressedSize(body.len()) };
+        if encoded_size == 0 {
+            return Err(std::io::Error::other(
+                "body too large to compress with brotli",
+            ));
+        }
+        let mut encoded = vec![0; encoded_size];
+        // SAFETY: both buffers are valid for the sizes passed, encoded_size is set to the number
+        // of bytes written, at most the size of the output buffer
+        let compressed = unsafe {
+            BrotliEncoderCompress(
+                QUALITY,
+                WINDOW_BITS,
+                MODE_TEXT,
+                body.len(),
+                body.as_ptr(),
+                &mut encoded_size,
+                encoded.as_mut_ptr(),
+            )
+        };
+        if compressed == 0 {
+            return Err(std::io::Error::other("failure compressing with brotli"));
+        }
+        encoded.truncate(encoded_size);
+        Ok(encoded)
+    }
+}
+
+/**
+ * Middleware compressing successful json and text responses with brotli or gzip, whichever the
+ * client prefers of those it accepts. Bodies from the response cache are only compressed once per
+ * coding, the result is kept with their cache entry. The ETag of a
+ * compressed response is weakened, as its bytes differ from those the tag was computed over.
+ */
+pub async fn compress_response<B>(
+    State(settings): State<CompressionSettings>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let encoding = request
+        .headers()
+        .get(header::ACCEPT_ENCODING)
+        .map_or(Encoding::Identity, negotiate_encoding);
+    let mut response = next.run(request).await;
+    if !settings.enable
+        || response.status() != StatusCode::OK
+        || response.headers().contains_key(header::CONTENT_ENCODING)
+        || !is_compressible(response.headers().get(header::CONTENT_TYPE))
+    {
+        return response;
+    }
+    // caches must not hand a compressed response to clients not accepting it, or the other way around
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    if encoding == Encoding::Identity {
+        return response;
+    }
+
+    let cached_body = response.extensions().get::<CachedBody>().cloned();
+    let (parts, body) = response.into_parts();
+    if let Some(compressed) = cached_body
+        .as_ref()
+        .and_then(|from| RESPONSE_CACHE.encoded(from, encoding.name()))
+    {
+        return encoded_response(parts, encoding, compressed);
+    }
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(err) => {
+            warn!("Failure reading response body: {:?}", err);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    if body.len() < settings.min_bytes {
+        return Response::from_parts(parts, boxed(Full::from(body)));
+    }
+
+    let compressed = match tokio::task::spawn_blocking({
+        let body = body.clone();
+        move || encoding.encode(&body)
+    })
+    .await
+    {
+        Ok(Ok(compressed)) => Bytes::from(compressed),
+        Ok(Err(err)) => {
+            warn!("Failure compressing response body: {:?}", err);
+            return Response::from_parts(parts, boxed(Full::from(body)));
+        }
+        Err(err) => {
+            warn!("Compression task failed: {:?}", err);
+            return Response::from_parts(parts, boxed(Full::from(body)));
+        }
+    };
+    if let Some(from) = &cached_body {
+        RESPONSE_CACHE.insert_encoded(from, encoding.name(), compressed.clone());
+    }
+    encoded_response(parts, encoding, compressed)
+}
+
+fn encoded_response(mut parts: Parts, encoding: Encoding, compressed: Bytes) -> Response {
+    parts.headers.insert(
+        header::CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.name()),
+    );
+    parts.headers.remove(header::CONTENT_LENGTH);
+    if let Some(etag) = parts.headers.get(header::ETAG).cloned() {
+        if let Ok(etag) = etag.to_str() {
+            if !etag.starts_with("W/") {
+                if let Ok(weak) = HeaderValue::from_str(&format!("W/{}", etag)) {
+                    parts.headers.insert(header::ETAG, weak);
+                }
+            }
+        }
+    }
+    Response::from_parts(parts, boxed(Full::from(compressed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use axum::{
+        body::{Body, Bytes},
+        http::{header, HeaderValue, Request, StatusCode},
+        middleware,
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use flate2::read::GzDecoder;
+    use tempdir::TempDir;
+    use tower::ServiceExt;
+
+    use super::{compress_response, negotiate_encoding, CompressionSettings, Encoding};
+    use crate::routes::cache::{cache_response, CachedBody, ResponseFile, RESPONSE_CACHE};
+
+    fn negotiate(accept_encoding: &str) -> Encoding {
+        negotiate_encoding(&HeaderValue::from_str(accept_encoding).unwrap())
+    }
+
+    #[test]
+    fn test_negotiate_encoding() {
+        assert_eq!(negotiate("gzip"), Encoding::Gzip);
+        assert_eq!(negotiate("br;q=0.5, gzip;q=0.8"), Encoding::Gzip);
+        assert_eq!(negotiate("br;q=0, gzip"), Encoding::Gzip);
+        assert_eq!(negotiate("gzip;q=0"), Encoding::Identity);
+        assert_eq!(negotiate("*;q=0.5, br;q=0"), Encoding::Gzip);
+        assert_eq!(negotiate("gzip;q=0.5, identity"), Encoding::Identity);
+        assert_eq!(negotiate("deflate"), Encoding::Identity);
+        assert_eq!(negotiate("gzip;q=nonsense"), Encoding::Identity);
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn test_negotiate_encoding_brotli() {
+        assert_eq!(negotiate("gzip, deflate, br"), Encoding::Brotli);
+        assert_eq!(negotiate("br;q=0.8, gzip;q=0.8"), Encoding::Brotli);
+        assert_eq!(negotiate("*"), Encoding::Brotli);
+        assert_eq!(negotiate("br"), Encoding::Brotli);
+    }
+
+    #[cfg(not(feature = "brotli"))]
+    #[test]
+    fn test_negotiate_encoding_without_brotli() {
+        assert_eq!(negotiate("gzip, br"), Encoding::Gzip);
+        assert_eq!(negotiate("br"), Encoding::Identity);
+    }
+
+    async fn get_compressed(accept_encoding: &str) -> (Option<String>, Vec<u8>, Vec<u8>) {
+        let versions = (0..200)
+            .map(|version| format!("1.{}", version))
+            .collect::<Vec<_>>();
+        let expected = serde_json::to_vec(&versions).unwrap();
+        let app = Router::new()
+            .route("/", get(move || async move { Json(versions) }))
+            .layer(middleware::from_fn_with_state(
+                CompressionSettings {
+                    enable: true,
+                    min_bytes: 1024,
+                },
+                compress_response,
+            ));
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header(header::ACCEPT_ENCODING, accept_encoding)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::VARY], "accept-encoding");
+        let content_encoding = response
+            .headers()
+            .get(header::CONTENT_ENCODING)
+            .map(|encoding| encoding.to_str().unwrap().to_string());
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (content_encoding, body.to_vec(), expected)
+    }
+
+    #[tokio::test]
+    async fn test_compress_response_gzip() {
+        let (encoding, body, expected) = get_compressed("br;q=0.1, gzip").await;
+        assert_eq!(encoding.as_deref(), Some("gzip"));
+        let mut decoded = vec![];
+        GzDecoder::new(body.as_slice())
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, expected);
+    }
+
+    #[cfg(feature = "brotli")]
+    #[tokio::test]
+    async fn test_compress_response_brotli() {
+        let (encoding, body, expected) = get_compressed("gzip, br").await;
+        assert_eq!(encoding.as_deref(), Some("br"));
+        assert!(!body.is_empty() && body.len() < expected.len());
+    }
+
+    #[tokio::test]
+    async fn test_compress_response_identity() {
+        let (encoding, body, expected) = get_compressed("gzip;q=0, identity").await;
+        assert_eq!(encoding, None);
+        assert_eq!(body, expected);
+    }
+
+    #[tokio::test]
+    async fn test_compress_response_reuses_cached_encoding() {
+        let tmp_dir = TempDir::new("mcmeta_compression").unwrap();
+        let file = tmp_dir.path().join("versions.json");
+        let versions = (0..200)
+            .map(|version| format!("1.{}", version))
+            .collect::<Vec<_>>();
+        std::fs::write(&file, serde_json::to_vec(&versions).unwrap()).unwrap();
+        RESPONSE_CACHE.set_capacity(1024);
+        let app = Router::new()
+            .route(
+                "/compression/cached",
+                get(move || async move {
+                    let mut response = Json(versions).into_response();
+                    response.extensions_mut().insert(ResponseFile(file));
+                    response
+                }),
+            )
+            .route_layer(middleware::from_fn(cache_response))
+            .layer(middleware::from_fn_with_state(
+                CompressionSettings {
+                    enable: true,
+                    min_bytes: 1024,
+                },
+                compress_response,
+            ));
+        let request = || {
+            Request::builder()
+                .uri("/compression/cached")
+                .header(header::ACCEPT_ENCODING, "gzip")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        let from = response.extensions().get::<CachedBody>().unwrap().clone();
+        // swap the kept variant, so the next response shows it was not compressed again
+        RESPONSE_CACHE.insert_encoded(&from, "gzip", Bytes::from_static(b"kept"));
+
+        let response = app.oneshot(request()).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, "kept");
+    }
+}
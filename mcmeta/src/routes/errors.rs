@@ -0,0 +1,108 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use tracing::{debug, error};
+
+use crate::routes::APIResponse;
+
+/// An error answered with its status code and an `APIResponse` carrying its message
+#[derive(Debug)]
+pub struct APIError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl APIError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    /// The machine readable `code` of the error, so clients need not match on messages
+    pub fn code(&self) -> &'static str {
+        match self.status {
+            StatusCode::BAD_REQUEST => "bad_request",
+            StatusCode::UNAUTHORIZED => "unauthorized",
+            StatusCode::FORBIDDEN => "forbidden",
+            StatusCode::NOT_FOUND => "not_found",
+            StatusCode::CONFLICT => "conflict",
+            StatusCode::BAD_GATEWAY => "bad_gateway",
+            StatusCode::SERVICE_UNAVAILABLE => "unavailable",
+            status if status.is_client_error() => "client_error",
+            _ => "internal_error",
+        }
+    }
+}
+
+/// Failures reading or parsing stored files are the server's fault, except for a file that
+/// disappeared between being looked up and being read. The chain of the error is only logged, as
+/// it names storage paths and quotes the contents of files that failed to parse.
+impl From<anyhow::Error> for APIError {
+    fn from(err: anyhow::Error) -> Self {
+        let not_found = err.chain().any(|cause| {
+            cause
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|err| err.kind() == std::io::ErrorKind::NotFound)
+        });
+        if not_found {
+            debug!("Failure handling request: {:#}", err);
+            Self::not_found("Not found")
+        } else {
+            error!("Failure handling request: {:#}", err);
+            Self::new(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+        }
+    }
+}
+
+impl IntoResponse for APIError {
+    fn into_response(self) -> Response {
+        APIResponse::<()> {
+            data: None,
+            code: Some(self.code()),
+            error: Some(self.message),
+            meta: None,
+            status: self.status,
+        }
+        .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+    use axum::http::StatusCode;
+
+    use super::APIError;
+    use crate::download::errors::MetadataError;
+
+    #[test]
+    fn test_api_error_hides_internal_details() {
+        let body = "{\"id\": \"1.20\", \"secret\": ";
+        let err = serde_json::from_str::<serde_json::Value>(body)
+            .map_err(|err| MetadataError::from_json_err(err, body))
+            .context("Failure reading file /srv/mcmeta/meta/mojang/versions/1.20.json")
+            .unwrap_err();
+        let err = APIError::from(err);
+        assert_eq!(err.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.message, "Internal server error");
+        assert_eq!(err.code(), "internal_error");
+
+        let err = std::fs::read_to_string("/nonexistent/mcmeta/1.20.json")
+            .context("Failure reading file /nonexistent/mcmeta/1.20.json")
+            .unwrap_err();
+        let err = APIError::from(err);
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+        assert_eq!(err.message, "Not found");
+    }
+}
@@ -0,0 +1,79 @@
+use axum::{
+    body::{boxed, Full},
+    http::{header, HeaderMap, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::warn;
+
+use crate::utils::{hash, HashAlgo};
+
+/**
+ * Whether an `If-None-Match` header lists the entity tag, weak comparison as RFC 9110 asks for
+ */
+pub(crate) fn none_match(if_none_match: &HeaderValue, etag: &str) -> bool {
+    let Ok(if_none_match) = if_none_match.to_str() else {
+        return false;
+    };
+    if_none_match.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
+/**
+ * An empty 304 for a response, keeping its `Cache-Control` as RFC 9110 asks for
+ */
+fn not_modified(etag: String, headers: &HeaderMap) -> Response {
+    let mut response = (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    if let Some(cache_control) = headers.get(header::CACHE_CONTROL) {
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, cache_control.clone());
+    }
+    response
+}
+
+/**
+ * Middleware tagging successful responses with an ETag, the sha256 of their body, and answering
+ * requests whose `If-None-Match` lists it with an empty 304. As every response is serialized from
+ * a stored file, the tag only changes when the file does. Responses their handler already tagged
+ * keep their tag and are not buffered.
+ */
+pub async fn tag_response<B>(request: Request<B>, next: Next<B>) -> Response {
+    let if_none_match = request.headers().get(header::IF_NONE_MATCH).cloned();
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    if let Some(etag) = response.headers().get(header::ETAG) {
+        let etag = etag.to_str().unwrap_or_default();
+        if if_none_match.is_some_and(|if_none_match| none_match(&if_none_match, etag)) {
+            return not_modified(etag.to_string(), response.headers());
+        }
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(err) => {
+            warn!("Failure reading response body: {:?}", err);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let etag = match hash(&body, HashAlgo::Sha256) {
+        Ok(sha256) => format!("\"{}\"", sha256.to_lowercase()),
+        Err(err) => {
+            warn!("Failure hashing response body: {:?}", err);
+            return Response::from_parts(parts, boxed(Full::from(body)));
+        }
+    };
+
+    if if_none_match.is_some_and(|if_none_match| none_match(&if_none_match, &etag)) {
+        return not_modified(etag, &parts.headers);
+    }
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        parts.headers.insert(header::ETAG, value);
+    }
+    Response::from_parts(parts, boxed(Full::from(body)))
+}
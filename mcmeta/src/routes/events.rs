@@ -0,0 +1,90 @@
+use std::{convert::Infallible, time::Duration};
+
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::{stream, Stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+
+use crate::events::METADATA_EVENTS;
+use crate::tasks;
+
+/// Seconds between comments keeping idle streams from being closed by proxies
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/**
+ * Stream a `version` event for every generated version an update adds, changes or removes. A client
+ * too slow to keep up is sent a `lagged` event with the number of events it missed. The stream
+ * ends when the server shuts down, which would wait for it forever otherwise.
+ */
+pub async fn events() -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = METADATA_EVENTS.subscribe();
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => Event::default().event("version").json_data(&event),
+                Err(RecvError::Lagged(missed)) => {
+                    Ok(Event::default().event("lagged").data(missed.to_string()))
+                }
+                Err(RecvError::Closed) => return None,
+            };
+            match event {
+                Ok(event) => return Some((Ok(event), receiver)),
+                Err(err) => warn!("Failure serializing version event: {:?}", err),
+            }
+        }
+    })
+    .take_until(tasks::shutdown_started());
+    Sse::new(events).keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use axum::{
+        body::{Body, HttpBody},
+        http::{header, Request},
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    use super::events;
+    use crate::events::publish_changes;
+    use crate::storage::UpdateSource;
+
+    #[tokio::test]
+    async fn test_events_stream_version_changes() {
+        let app = Router::new().route("/events", get(events));
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/events")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+
+        let key = ("org.example.events".to_string(), "1.0".to_string());
+        let after = BTreeMap::from([(key, "a".to_string())]);
+        publish_changes(UpdateSource::Fabric, &BTreeMap::new(), &after);
+
+        // other tests may publish on the same channel, skip their events
+        let mut body = response.into_body();
+        let event = loop {
+            let chunk = body.data().await.unwrap().unwrap();
+            let chunk = String::from_utf8(chunk.to_vec()).unwrap();
+            if chunk.contains("org.example.events") {
+                break chunk;
+            }
+        };
+        assert!(event.starts_with("event:version\n"));
+        assert!(event.contains(r#""change":"added""#));
+        assert!(event.contains(r#""version":"1.0""#));
+    }
+}
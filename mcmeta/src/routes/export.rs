@@ -0,0 +1,147 @@
+use std::{io::Write, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use axum::{
+    body::StreamBody,
+    extract::Query,
+    http::header,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use flate2::{write::GzEncoder, Compression};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::error;
+
+use crate::app_config::ServerConfig;
+use crate::routes::errors::APIError;
+use crate::storage::generated::GeneratedDataStorage;
+
+#[derive(Deserialize, Debug)]
+pub struct ExportQuery {
+    /// only export the generated files of this component uid
+    pub component: Option<String>,
+}
+
+/**
+ * A writer handing every written chunk to the response body stream
+ */
+struct ChannelWriter {
+    sender: mpsc::Sender<std::io::Result<Vec<u8>>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.sender
+            .blocking_send(Ok(buf.to_vec()))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "client went away"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn write_tarball(
+    source: std::path::PathBuf,
+    prefix: std::path::PathBuf,
+    sender: mpsc::Sender<std::io::Result<Vec<u8>>>,
+) -> Result<()> {
+    let encoder = GzEncoder::new(ChannelWriter { sender }, Compression::default());
+    let mut tarball = tar::Builder::new(encoder);
+    tarball.follow_symlinks(false);
+    // the git repository the generated metadata may be published from is not part of it
+    tarball.append_dir(&prefix, &source)?;
+    for entry in std::fs::read_dir(&source)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let name = prefix.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            tarball.append_dir_all(name, entry.path())?;
+        } else {
+            tarball.append_path_with_name(entry.path(), name)?;
+        }
+    }
+    tarball.into_inner()?.finish()?;
+    Ok(())
+}
+
+/**
+ * Resolve the directory to export, either the whole generated tree or a single component in it
+ */
+fn export_source(
+    generated_dir: std::path::PathBuf,
+    component: Option<&str>,
+) -> Result<Option<(std::path::PathBuf, std::path::PathBuf)>> {
+    match component {
+        None => Ok(Some((generated_dir, std::path::PathBuf::from(".")))),
+        Some(uid) => {
+            if uid.is_empty() || uid.starts_with('.') || uid.contains(['/', '\\']) {
+                return Err(anyhow!("Invalid component {}", uid));
+            }
+            let component_dir = generated_dir.join(uid);
+            if component_dir.is_dir() {
+                Ok(Some((component_dir, std::path::PathBuf::from(uid))))
+            } else {
+                Ok(None)
+            }
+        }
+    }
+}
+
+pub async fn export_generated(
+    config: Extension<Arc<ServerConfig>>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let storage = GeneratedDataStorage::new(Arc::new(config.storage_format.clone()));
+    let generated_dir = match storage.generated_dir() {
+        Ok(dir) => dir,
+        Err(err) => {
+            return APIError::from(err.context("Failure locating generated metadata"))
+                .into_response()
+        }
+    };
+
+    let (source, prefix) = match export_source(generated_dir, query.component.as_deref()) {
+        Ok(Some(source)) => source,
+        Ok(None) => {
+            return APIError::not_found(format!(
+                "Component {} does not exist",
+                query.component.unwrap_or_default()
+            ))
+            .into_response()
+        }
+        Err(err) => return APIError::bad_request(format!("{:#}", err)).into_response(),
+    };
+
+    let (sender, mut receiver) = mpsc::channel(16);
+    let error_sender = sender.clone();
+    tokio::task::spawn_blocking(move || {
+        if let Err(err) = write_tarball(source, prefix, sender) {
+            error!("Failure exporting generated metadata: {:?}", err);
+            // abort the response instead of ending it with a truncated tarball
+            let _ = error_sender.blocking_send(Err(std::io::Error::other(format!("{:#}", err))));
+        }
+    });
+
+    let body = StreamBody::new(futures::stream::poll_fn(move |cx| receiver.poll_recv(cx)));
+    let filename = match query.component {
+        Some(uid) => format!("{}.tar.gz", uid),
+        None => "generated.tar.gz".to_string(),
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/gzip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
@@ -0,0 +1,55 @@
+use axum::{extract::Query, response::IntoResponse, Extension};
+
+use crate::routes::{segments::SegmentPath, stored_response, ResponseOptions, ResponseSource};
+use crate::storage::fabric::FabricDataStorage;
+
+const FABRIC_LOADER_UID: &str = "net.fabricmc.fabric-loader";
+const FABRIC_INTERMEDIARY_UID: &str = "net.fabricmc.intermediary";
+
+pub async fn raw_fabric_loader_versions(
+    storage: Extension<FabricDataStorage>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_loader_versions(),
+        "Fabric loader versions do not exist".to_string(),
+        &options,
+        || ResponseSource {
+            uid: FABRIC_LOADER_UID.to_string(),
+            path: storage.meta_dir().map(|dir| dir.join("loader.json")),
+        },
+    )
+}
+
+pub async fn raw_fabric_loader_version(
+    storage: Extension<FabricDataStorage>,
+    SegmentPath(version): SegmentPath<String>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_installer_data(&version),
+        format!("Fabric loader {} does not exist", version),
+        &options,
+        || ResponseSource {
+            uid: FABRIC_LOADER_UID.to_string(),
+            path: storage
+                .installer_data_dir()
+                .map(|dir| dir.join(format!("{}.json", version))),
+        },
+    )
+}
+
+pub async fn raw_fabric_intermediary_versions(
+    storage: Extension<FabricDataStorage>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_intermediary_versions(),
+        "Fabric intermediary versions do not exist".to_string(),
+        &options,
+        || ResponseSource {
+            uid: FABRIC_INTERMEDIARY_UID.to_string(),
+            path: storage.meta_dir().map(|dir| dir.join("intermediary.json")),
+        },
+    )
+}
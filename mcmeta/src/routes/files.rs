@@ -0,0 +1,354 @@
+use std::{io::SeekFrom, path::Path as FilePath, sync::Arc, time::UNIX_EPOCH};
+
+use axum::{
+    body::StreamBody,
+    extract::Path,
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::{AppendHeaders, IntoResponse, Response},
+    Extension,
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+use tracing::warn;
+
+use crate::app_config::ServerConfig;
+use crate::download::errors::NotFoundUpstream;
+use crate::routes::{errors::APIError, etag::none_match, segments::SegmentPath};
+use crate::storage::{
+    assets::{is_object_hash, AssetObjectCache},
+    forge::ForgeDataStorage,
+    maven::{is_repository_path, MavenMirror},
+};
+
+/**
+ * An ETag derived from the size and modification time of a file, so serving it needs no hashing
+ */
+pub(crate) fn file_etag(metadata: &std::fs::Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    format!("\"{:x}-{:x}\"", modified.as_nanos(), metadata.len())
+}
+
+/**
+ * The first and last byte a `Range` header asks for out of `len`. `None` for a header to ignore,
+ * which includes requests for several ranges, and `Some(Err(()))` for a range outside the file.
+ */
+fn byte_range(range: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let range = range.trim().strip_prefix("bytes=")?;
+    if range.contains(',') {
+        return None;
+    }
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix = suffix.parse::<u64>().ok()?;
+            if suffix == 0 {
+                return Some(Err(()));
+            }
+            (len.saturating_sub(suffix), len.checked_sub(1))
+        }
+        (start, "") => (start.parse().ok()?, len.checked_sub(1)),
+        (start, end) => {
+            let (start, end) = (start.parse().ok()?, end.parse::<u64>().ok()?);
+            if end < start {
+                return None;
+            }
+            (start, Some(end.min(len.saturating_sub(1))))
+        }
+    };
+    match end {
+        Some(end) if start < len => Some(Ok((start, end))),
+        _ => Some(Err(())),
+    }
+}
+
+/**
+ * Stream a file as a download, answering a single byte range of it for a `Range` header so
+ * interrupted downloads can be resumed
+ */
+async fn file_download(
+    file: &FilePath,
+    content_type: &'static str,
+    headers: &HeaderMap,
+) -> Response {
+    let read_error = |err: std::io::Error| {
+        APIError::from(
+            anyhow::Error::from(err)
+                .context(format!("Failure reading file {}", file.to_string_lossy())),
+        )
+        .into_response()
+    };
+    let mut opened = match tokio::fs::File::open(file).await {
+        Ok(opened) => opened,
+        Err(err) => return read_error(err),
+    };
+    let metadata = match opened.metadata().await {
+        Ok(metadata) => metadata,
+        Err(err) => return read_error(err),
+    };
+    let len = metadata.len();
+    let etag = file_etag(&metadata);
+
+    if headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|if_none_match| none_match(if_none_match, &etag))
+    {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+
+    let mut response_headers: Vec<(HeaderName, String)> = vec![
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (header::ACCEPT_RANGES, "bytes".to_string()),
+        (header::ETAG, etag.clone()),
+    ];
+    if let Some(modified) = metadata.modified().ok().map(httpdate::fmt_http_date) {
+        response_headers.push((header::LAST_MODIFIED, modified));
+    }
+    if let Some(name) = file.file_name() {
+        response_headers.push((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", name.to_string_lossy()),
+        ));
+    }
+
+    // a range of a file that changed since the client started downloading it would be garbage
+    let range_applies = headers
+        .get(header::IF_RANGE)
+        .is_none_or(|if_range| if_range.to_str().ok() == Some(etag.as_str()));
+    let range = headers
+        .get(header::RANGE)
+        .filter(|_| range_applies)
+        .and_then(|range| range.to_str().ok())
+        .and_then(|range| byte_range(range, len));
+
+    match range {
+        None => {
+            response_headers.push((header::CONTENT_LENGTH, len.to_string()));
+            (
+                AppendHeaders(response_headers),
+                StreamBody::new(ReaderStream::new(opened)),
+            )
+                .into_response()
+        }
+        Some(Ok((start, end))) => {
+            if let Err(err) = opened.seek(SeekFrom::Start(start)).await {
+                return read_error(err);
+            }
+            response_headers.push((header::CONTENT_LENGTH, (end - start + 1).to_string()));
+            response_headers.push((
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", start, end, len),
+            ));
+            (
+                StatusCode::PARTIAL_CONTENT,
+                AppendHeaders(response_headers),
+                StreamBody::new(ReaderStream::new(opened.take(end - start + 1))),
+            )
+                .into_response()
+        }
+        Some(Err(())) => (
+            [(header::CONTENT_RANGE, format!("bytes */{}", len))],
+            APIError::new(
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                format!("Range outside of the {} bytes of the file", len),
+            ),
+        )
+            .into_response(),
+    }
+}
+
+/**
+ * Serve the installer jar of a Forge build the updater downloaded, so launchers that cannot reach
+ * Forge's maven can fetch it from here
+ */
+pub async fn forge_installer(
+    storage: Extension<ForgeDataStorage>,
+    SegmentPath(version): SegmentPath<String>,
+    headers: HeaderMap,
+) -> Response {
+    match storage.installer_jar(&version) {
+        Ok(Some(jar)) => file_download(&jar, "application/java-archive", &headers).await,
+        Ok(None) => APIError::not_found(format!("Installer of Forge {} is not available", version))
+            .into_response(),
+        Err(err) => APIError::from(err).into_response(),
+    }
+}
+
+/**
+ * Serve an asset object by its sha1 the way resources.download.minecraft.net does, downloading it
+ * from upstream the first time it is asked for
+ */
+pub async fn asset_object(
+    config: Extension<Arc<ServerConfig>>,
+    cache: Extension<AssetObjectCache>,
+    Path((prefix, hash)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Response {
+    if !config.metadata.assets.mirror_objects {
+        return APIError::not_found("Asset objects are not mirrored").into_response();
+    }
+    if prefix.len() != 2 || !is_object_hash(&hash) || !hash[..2].eq_ignore_ascii_case(&prefix) {
+        return APIError::bad_request("Invalid asset object path").into_response();
+    }
+    match cache.fetch(&hash).await {
+        Ok(object_file) => file_download(&object_file, "application/octet-stream", &headers).await,
+        Err(err) if err.downcast_ref::<NotFoundUpstream>().is_some() => {
+            APIError::not_found(format!("Asset object {} does not exist", hash)).into_response()
+        }
+        Err(err) => {
+            warn!("Failure fetching asset object {}: {:?}", hash, err);
+            APIError::new(
+                StatusCode::BAD_GATEWAY,
+                format!("Failure fetching asset object {} from upstream", hash),
+            )
+            .into_response()
+        }
+    }
+}
+
+/**
+ * The content type of a file of a maven repository by its extension
+ */
+fn maven_content_type(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("jar") => "application/java-archive",
+        Some("pom") | Some("xml") => "application/xml",
+        Some("sha1") | Some("md5") | Some("sha256") | Some("sha512") | Some("asc") => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/**
+ * Serve a file of the library maven repositories, downloading it from the first upstream that has
+ * it the first time it is asked for
+ */
+pub async fn maven_file(
+    config: Extension<Arc<ServerConfig>>,
+    mirror: Extension<MavenMirror>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    if !config.metadata.maven_mirror.enable {
+        return APIError::not_found("Maven repositories are not mirrored").into_response();
+    }
+    let path = path.trim_start_matches('/');
+    if !is_repository_path(path) {
+        return APIError::bad_request("Invalid maven repository path").into_response();
+    }
+    match mirror.fetch(path).await {
+        Ok(file) => file_download(&file, maven_content_type(path), &headers).await,
+        Err(err) if err.downcast_ref::<NotFoundUpstream>().is_some() => {
+            APIError::not_found(format!("{} does not exist", path)).into_response()
+        }
+        Err(err) => {
+            warn!("Failure fetching maven file {}: {:?}", path, err);
+            APIError::new(
+                StatusCode::BAD_GATEWAY,
+                format!("Failure fetching {} from upstream", path),
+            )
+            .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+    use tempdir::TempDir;
+
+    use super::{byte_range, file_download, file_etag};
+
+    #[test]
+    fn test_byte_range() {
+        assert_eq!(byte_range("bytes=0-99", 1000), Some(Ok((0, 99))));
+        assert_eq!(byte_range("bytes=900-", 1000), Some(Ok((900, 999))));
+        assert_eq!(byte_range("bytes=900-5000", 1000), Some(Ok((900, 999))));
+        // suffix ranges count from the end, and cover the whole file when longer than it
+        assert_eq!(byte_range("bytes=-100", 1000), Some(Ok((900, 999))));
+        assert_eq!(byte_range("bytes=-5000", 1000), Some(Ok((0, 999))));
+        assert_eq!(byte_range("bytes=-0", 1000), Some(Err(())));
+        // several ranges, overlapping or not, are answered with the whole file
+        assert_eq!(byte_range("bytes=0-499,400-999", 1000), None);
+        assert_eq!(byte_range("bytes=0-9,20-29", 1000), None);
+        assert_eq!(byte_range("bytes=1000-", 1000), Some(Err(())));
+        assert_eq!(byte_range("bytes=1000-1999", 1000), Some(Err(())));
+        assert_eq!(byte_range("bytes=0-", 0), Some(Err(())));
+        assert_eq!(byte_range("bytes=99-0", 1000), None);
+        assert_eq!(byte_range("items=0-99", 1000), None);
+        assert_eq!(byte_range("bytes=a-b", 1000), None);
+    }
+
+    fn jar() -> (TempDir, PathBuf) {
+        let tmp_dir = TempDir::new("mcmeta_file_download").unwrap();
+        let file = tmp_dir.path().join("installer.jar");
+        std::fs::write(&file, (0..100u8).collect::<Vec<_>>()).unwrap();
+        (tmp_dir, file)
+    }
+
+    async fn download(
+        file: &Path,
+        headers: &[(header::HeaderName, &str)],
+    ) -> (StatusCode, HeaderMap, Vec<u8>) {
+        let request_headers = headers
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_str(value).unwrap()))
+            .collect::<HeaderMap>();
+        let response = file_download(file, "application/java-archive", &request_headers).await;
+        let status = response.status();
+        let response_headers = response.headers().clone();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, response_headers, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_file_download_ranges() {
+        let (_tmp_dir, jar) = jar();
+        let (status, headers, body) = download(&jar, &[]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::ACCEPT_RANGES], "bytes");
+        assert_eq!(body, (0..100u8).collect::<Vec<_>>());
+
+        let (status, headers, body) = download(&jar, &[(header::RANGE, "bytes=-10")]).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes 90-99/100");
+        assert_eq!(body, (90..100u8).collect::<Vec<_>>());
+
+        let (status, _, body) = download(&jar, &[(header::RANGE, "bytes=0-49,25-74")]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.len(), 100);
+
+        let (status, headers, _) = download(&jar, &[(header::RANGE, "bytes=100-")]).await;
+        assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes */100");
+    }
+
+    #[tokio::test]
+    async fn test_file_download_if_range() {
+        let (_tmp_dir, jar) = jar();
+        // the file changed since the client got its tag, so it gets all of it
+        let (status, _, body) = download(
+            &jar,
+            &[
+                (header::RANGE, "bytes=10-19"),
+                (header::IF_RANGE, "\"0-64\""),
+            ],
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.len(), 100);
+
+        let etag = file_etag(&std::fs::metadata(&jar).unwrap());
+        let (status, _, body) = download(
+            &jar,
+            &[(header::RANGE, "bytes=10-19"), (header::IF_RANGE, &etag)],
+        )
+        .await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, (10..20u8).collect::<Vec<_>>());
+    }
+}
@@ -1,180 +1,182 @@
+use axum::{extract::Query, response::IntoResponse, Extension};
+
 use std::sync::Arc;
 
-use axum::{extract::Path, response::IntoResponse, Extension};
+use crate::app_config::ServerConfig;
+use crate::routes::{segments::SegmentPath, stored_response, ResponseOptions, ResponseSource};
+use crate::storage::forge::ForgeDataStorage;
 
-use libmcmeta::models::forge::{
-    ForgeInstallerManifestVersion, ForgeMavenMetadata, ForgeMavenPromotions, ForgeVersion,
-    ForgeVersionMeta,
-};
+pub(crate) const FORGE_UID: &str = "net.minecraftforge";
 
-use crate::app_config::{ServerConfig, StorageFormat};
-use crate::routes::APIResponse;
+pub async fn raw_forge_maven_meta(
+    storage: Extension<ForgeDataStorage>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_maven_metadata(),
+        "Forge maven metadata does not exist".to_string(),
+        &options,
+        || ResponseSource {
+            uid: FORGE_UID.to_string(),
+            path: storage
+                .meta_dir()
+                .map(|dir| dir.join("maven-metadata.json")),
+        },
+    )
+}
 
-pub async fn raw_forge_maven_meta(config: Extension<Arc<ServerConfig>>) -> impl IntoResponse {
-    match &config.storage_format {
-        StorageFormat::Json {
-            meta_directory,
-            generated_directory: _,
-        } => {
-            let metadata_dir = std::path::Path::new(meta_directory);
-            let forge_meta_dir = metadata_dir.join("forge");
-            let maven_meta_file = forge_meta_dir.join("maven-metadata.json");
-            let manifest = serde_json::from_str::<ForgeMavenMetadata>(
-                &std::fs::read_to_string(maven_meta_file).unwrap(),
-            )
-            .unwrap();
+pub async fn raw_forge_promotions(
+    storage: Extension<ForgeDataStorage>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_forge_promotions(),
+        "Forge promotions do not exist".to_string(),
+        &options,
+        || ResponseSource {
+            uid: FORGE_UID.to_string(),
+            path: storage
+                .meta_dir()
+                .map(|dir| dir.join("promotions_slim.json")),
+        },
+    )
+}
 
-            (
-                axum::http::StatusCode::OK,
-                axum::Json(APIResponse {
-                    data: Some(manifest),
-                    error: None,
-                }),
-            )
-        }
-        StorageFormat::Database => todo!(),
-    }
+pub async fn raw_forge_version(
+    storage: Extension<ForgeDataStorage>,
+    SegmentPath(version): SegmentPath<String>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_mojang_version(&version),
+        format!("Version {} does not exist", version),
+        &options,
+        || ResponseSource {
+            uid: FORGE_UID.to_string(),
+            path: storage
+                .version_manifests_dir()
+                .map(|dir| dir.join(format!("{}.json", version))),
+        },
+    )
 }
 
-pub async fn raw_forge_promotions(config: Extension<Arc<ServerConfig>>) -> impl IntoResponse {
-    match &config.storage_format {
-        StorageFormat::Json {
-            meta_directory,
-            generated_directory: _,
-        } => {
-            let metadata_dir = std::path::Path::new(meta_directory);
-            let forge_meta_dir = metadata_dir.join("forge");
-            let promotions_file = forge_meta_dir.join("promotions_slim.json");
-            let manifest = serde_json::from_str::<ForgeMavenPromotions>(
-                &std::fs::read_to_string(promotions_file).unwrap(),
-            )
-            .unwrap();
+pub async fn raw_forge_version_meta(
+    storage: Extension<ForgeDataStorage>,
+    SegmentPath(version): SegmentPath<String>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_files_manifest(&version),
+        format!("Version {} does not exist", version),
+        &options,
+        || ResponseSource {
+            uid: FORGE_UID.to_string(),
+            path: storage
+                .manifests_dir()
+                .map(|dir| dir.join(format!("{}.json", version))),
+        },
+    )
+}
 
-            (
-                axum::http::StatusCode::OK,
-                axum::Json(APIResponse {
-                    data: Some(manifest),
-                    error: None,
-                }),
-            )
-        }
-        StorageFormat::Database => todo!(),
-    }
+pub async fn raw_forge_version_installer(
+    storage: Extension<ForgeDataStorage>,
+    SegmentPath(version): SegmentPath<String>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_installer_manifest(&version),
+        format!("Version {} does not exist", version),
+        &options,
+        || ResponseSource {
+            uid: FORGE_UID.to_string(),
+            path: storage
+                .installer_manifests_dir()
+                .map(|dir| dir.join(format!("{}.json", version))),
+        },
+    )
 }
 
-pub async fn raw_forge_version(
-    config: Extension<Arc<ServerConfig>>,
-    Path(version): Path<String>,
+pub async fn raw_forge_derived_index(
+    storage: Extension<ForgeDataStorage>,
+    Query(options): Query<ResponseOptions>,
 ) -> impl IntoResponse {
-    match &config.storage_format {
-        StorageFormat::Json {
-            meta_directory,
-            generated_directory: _,
-        } => {
-            let metadata_dir = std::path::Path::new(meta_directory);
-            let forge_meta_dir = metadata_dir.join("forge");
-            let versions_dir = forge_meta_dir.join("version_manifests");
-            let version_file = versions_dir.join(format!("{}.json", version));
-            if !version_file.exists() {
-                return (
-                    axum::http::StatusCode::NOT_FOUND,
-                    axum::Json(APIResponse {
-                        data: None,
-                        error: Some(format!("Version {} does not exist", version)),
-                    }),
-                );
-            }
-            let manifest = serde_json::from_str::<ForgeVersion>(
-                &std::fs::read_to_string(&version_file).unwrap(),
-            )
-            .unwrap();
+    stored_response(
+        || storage.load_index(),
+        "Forge derived index does not exist".to_string(),
+        &options,
+        || ResponseSource {
+            uid: FORGE_UID.to_string(),
+            path: storage.meta_dir().map(|dir| dir.join("derived_index.json")),
+        },
+    )
+}
 
-            (
-                axum::http::StatusCode::OK,
-                axum::Json(APIResponse {
-                    data: Some(manifest),
-                    error: None,
-                }),
-            )
-        }
-        StorageFormat::Database => todo!(),
-    }
+pub async fn raw_forge_derived_index_entry(
+    storage: Extension<ForgeDataStorage>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_index_entry(),
+        "Forge installers were not processed yet".to_string(),
+        &options,
+        || ResponseSource {
+            uid: FORGE_UID.to_string(),
+            path: storage
+                .meta_dir()
+                .map(|dir| dir.join("derived_index.last_index.json")),
+        },
+    )
 }
 
-pub async fn raw_forge_version_meta(
+pub async fn raw_forge_legacy_info(
+    storage: Extension<ForgeDataStorage>,
     config: Extension<Arc<ServerConfig>>,
-    Path(version): Path<String>,
+    Query(options): Query<ResponseOptions>,
 ) -> impl IntoResponse {
-    match &config.storage_format {
-        StorageFormat::Json {
-            meta_directory,
-            generated_directory: _,
-        } => {
-            let metadata_dir = std::path::Path::new(meta_directory);
-            let forge_meta_dir = metadata_dir.join("forge");
-            let versions_dir = forge_meta_dir.join("files_manifests");
-            let version_file = versions_dir.join(format!("{}.json", version));
-            if !version_file.exists() {
-                return (
-                    axum::http::StatusCode::NOT_FOUND,
-                    axum::Json(APIResponse {
-                        data: None,
-                        error: Some(format!("Version {} does not exist", version)),
-                    }),
-                );
-            }
-            let manifest = serde_json::from_str::<ForgeVersionMeta>(
-                &std::fs::read_to_string(&version_file).unwrap(),
-            )
-            .unwrap();
-
-            (
-                axum::http::StatusCode::OK,
-                axum::Json(APIResponse {
-                    data: Some(manifest),
-                    error: None,
-                }),
-            )
-        }
-        StorageFormat::Database => todo!(),
-    }
+    let static_directory = &config.metadata.static_directory;
+    stored_response(
+        || storage.load_legacy_info(static_directory),
+        "Forge legacy info does not exist".to_string(),
+        &options,
+        || ResponseSource {
+            uid: FORGE_UID.to_string(),
+            path: Ok(ForgeDataStorage::legacy_info_file(static_directory)),
+        },
+    )
 }
 
-pub async fn raw_forge_version_installer(
-    config: Extension<Arc<ServerConfig>>,
-    Path(version): Path<String>,
+/**
+ * The Minecraft versions and Forge builds of every MCP version named in V2 installers
+ */
+pub async fn raw_forge_mcp_index(
+    storage: Extension<ForgeDataStorage>,
+    Query(options): Query<ResponseOptions>,
 ) -> impl IntoResponse {
-    match &config.storage_format {
-        StorageFormat::Json {
-            meta_directory,
-            generated_directory: _,
-        } => {
-            let metadata_dir = std::path::Path::new(meta_directory);
-            let forge_meta_dir = metadata_dir.join("forge");
-            let versions_dir = forge_meta_dir.join("installer_manifests");
-            let version_file = versions_dir.join(format!("{}.json", version));
-            if !version_file.exists() {
-                return (
-                    axum::http::StatusCode::NOT_FOUND,
-                    axum::Json(APIResponse {
-                        data: None,
-                        error: Some(format!("Version {} does not exist", version)),
-                    }),
-                );
-            }
-            let manifest = serde_json::from_str::<ForgeInstallerManifestVersion>(
-                &std::fs::read_to_string(&version_file).unwrap(),
-            )
-            .unwrap();
+    stored_response(
+        || storage.load_mcp_index(),
+        "Forge MCP index does not exist".to_string(),
+        &options,
+        || ResponseSource {
+            uid: FORGE_UID.to_string(),
+            path: storage.mcp_index_file(),
+        },
+    )
+}
 
-            (
-                axum::http::StatusCode::OK,
-                axum::Json(APIResponse {
-                    data: Some(manifest),
-                    error: None,
-                }),
-            )
-        }
-        StorageFormat::Database => todo!(),
-    }
+pub async fn raw_forge_version_installer_info(
+    storage: Extension<ForgeDataStorage>,
+    SegmentPath(version): SegmentPath<String>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_installer_info(&version),
+        format!("Version {} does not exist", version),
+        &options,
+        || ResponseSource {
+            uid: FORGE_UID.to_string(),
+            path: storage
+                .installer_info_dir()
+                .map(|dir| dir.join(format!("{}.json", version))),
+        },
+    )
 }
@@ -0,0 +1,313 @@
+use axum::{
+    extract::Query,
+    http::header,
+    response::{IntoResponse, Response},
+    Extension,
+};
+
+use libmcmeta::models::platform::Platform;
+use serde::Deserialize;
+
+use crate::routes::{
+    derived_response,
+    errors::APIError,
+    segments::{invalid_segment, SegmentPath},
+    ResponseOptions, ResponseSource,
+};
+use crate::storage::generated::GeneratedDataStorage;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PlatformQuery {
+    /// only keep the libraries used on this operating system, e.g. `linux`
+    pub os: Option<String>,
+    /// the architecture for `os`, `x86_64` if left out
+    pub arch: Option<String>,
+}
+
+impl PlatformQuery {
+    fn platform(&self) -> Result<Option<Platform>, APIError> {
+        match (&self.os, &self.arch) {
+            (None, None) => Ok(None),
+            (None, Some(_)) => Err(APIError::bad_request("arch requires os")),
+            (Some(os), arch) => Platform::new(os, arch.as_deref().unwrap_or("x86_64"))
+                .map(Some)
+                .map_err(|err| APIError::bad_request(err.to_string())),
+        }
+    }
+}
+
+/**
+ * Whether path segments from a request could leave the generated directory
+ */
+fn invalid_path(parts: &[&str]) -> bool {
+    parts.iter().any(|part| invalid_segment(part))
+}
+
+/**
+ * Serve a generated file as is, so its bytes match the sha256 the indexes record for it.
+ * `parts` are path segments from the request and must not leave the generated directory.
+ */
+fn generated_file(storage: &GeneratedDataStorage, parts: &[&str]) -> Response {
+    if invalid_path(parts) {
+        return APIError::bad_request("Invalid path").into_response();
+    }
+    let generated_dir = match storage.generated_dir() {
+        Ok(generated_dir) => generated_dir,
+        Err(err) => return APIError::from(err).into_response(),
+    };
+    let file = parts
+        .iter()
+        .fold(generated_dir, |path, part| path.join(part));
+
+    match std::fs::read(&file) {
+        Ok(body) => ([(header::CONTENT_TYPE, "application/json")], body).into_response(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            APIError::not_found(format!("{} does not exist", parts.join("/"))).into_response()
+        }
+        Err(err) => APIError::from(
+            anyhow::Error::from(err).context(format!("Failure reading {}", parts.join("/"))),
+        )
+        .into_response(),
+    }
+}
+
+pub async fn package_index(storage: Extension<GeneratedDataStorage>) -> Response {
+    generated_file(&storage, &["index.json"])
+}
+
+pub async fn version_index(
+    storage: Extension<GeneratedDataStorage>,
+    SegmentPath(uid): SegmentPath<String>,
+) -> Response {
+    generated_file(&storage, &[&uid, "index.json"])
+}
+
+/**
+ * Serve a generated version as is, or with `os` and `arch` only the libraries used on that
+ * platform, with their rules evaluated and their natives narrowed down to it
+ */
+pub async fn version(
+    storage: Extension<GeneratedDataStorage>,
+    SegmentPath((uid, file)): SegmentPath<(String, String)>,
+    Query(query): Query<PlatformQuery>,
+) -> Response {
+    let platform = match query.platform() {
+        Ok(Some(platform)) => platform,
+        Ok(None) => return generated_file(&storage, &[&uid, &file]),
+        Err(err) => return err.into_response(),
+    };
+    let Some(version) = file.strip_suffix(".json") else {
+        return APIError::not_found(format!("{}/{} does not exist", uid, file)).into_response();
+    };
+    if invalid_path(&[&uid, version]) {
+        return APIError::bad_request("Invalid path").into_response();
+    }
+    derived_response(
+        storage
+            .load_meta_version(&uid, version)
+            .map(|loaded| loaded.map(|meta_version| platform.version(&meta_version))),
+        format!("Version {} of {} does not exist", version, uid),
+        &ResponseOptions {
+            meta: false,
+            raw: true,
+        },
+        || ResponseSource {
+            path: storage
+                .generated_dir()
+                .map(|dir| dir.join(&uid).join(&file)),
+            uid,
+        },
+    )
+}
+
+pub async fn version_closure(
+    storage: Extension<GeneratedDataStorage>,
+    SegmentPath((uid, version)): SegmentPath<(String, String)>,
+    Query(options): Query<ResponseOptions>,
+    Query(query): Query<PlatformQuery>,
+) -> Response {
+    if invalid_path(&[&uid, &version]) {
+        return APIError::bad_request("Invalid path").into_response();
+    }
+    let platform = match query.platform() {
+        Ok(platform) => platform,
+        Err(err) => return err.into_response(),
+    };
+    let closure = storage.resolve_closure(&uid, &version).map(|closure| {
+        closure.map(|closure| match &platform {
+            Some(platform) => closure
+                .iter()
+                .map(|meta_version| platform.version(meta_version))
+                .collect(),
+            None => closure,
+        })
+    });
+    derived_response(
+        closure,
+        format!("Version {} of {} does not exist", version, uid),
+        &options,
+        || ResponseSource {
+            path: storage
+                .generated_dir()
+                .map(|dir| dir.join(&uid).join(format!("{}.json", version))),
+            uid,
+        },
+    )
+}
+
+/**
+ * Download a version along with every component it requires as one tarball, for offline
+ * installers and launchers without network access
+ */
+pub async fn version_bundle(
+    storage: Extension<GeneratedDataStorage>,
+    SegmentPath((uid, file)): SegmentPath<(String, String)>,
+) -> Response {
+    let Some(version) = file.strip_suffix(".tar.gz").map(str::to_string) else {
+        return APIError::not_found(format!("{} does not exist", file)).into_response();
+    };
+    if invalid_path(&[&uid, &version]) {
+        return APIError::bad_request("Invalid path").into_response();
+    }
+
+    let missing = format!("Version {} of {} does not exist", version, uid);
+    let filename = format!("{}-{}.tar.gz", uid, version);
+    let bundled = tokio::task::spawn_blocking({
+        let storage = storage.clone();
+        move || storage.closure_tarball(&uid, &version)
+    })
+    .await
+    .map_err(anyhow::Error::from);
+    match bundled.and_then(|bundled| bundled) {
+        Ok(Some(tarball)) => (
+            [
+                (header::CONTENT_TYPE, "application/gzip".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", filename),
+                ),
+            ],
+            tarball,
+        )
+            .into_response(),
+        Ok(None) => APIError::not_found(missing).into_response(),
+        Err(err) => APIError::from(err).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        routing::get,
+        Extension, Router,
+    };
+    use flate2::read::GzDecoder;
+    use libmcmeta::models::{Dependency, MetaVersion};
+    use tempdir::TempDir;
+    use tower::ServiceExt;
+
+    use super::{version_bundle, version_closure};
+    use crate::app_config::StorageFormat;
+    use crate::storage::generated::GeneratedDataStorage;
+
+    #[tokio::test]
+    async fn test_closure_rejects_encoded_traversal() {
+        let storage = GeneratedDataStorage::new(Arc::new(StorageFormat::Json {
+            meta_directory: "/nonexistent/meta".to_string(),
+            generated_directory: "/nonexistent/generated".to_string(),
+        }));
+        let router = Router::new()
+            .route("/:uid/:version/closure", get(version_closure))
+            .layer(Extension(storage));
+
+        for uri in [
+            "/..%2F..%2Fmeta/x/closure",
+            "/net.minecraft/..%2Findex/closure",
+        ] {
+            let response = router
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bundle_packs_required_components() {
+        let tmp_dir = TempDir::new("mcmeta_bundle").unwrap();
+        let storage = GeneratedDataStorage::new(Arc::new(StorageFormat::Json {
+            meta_directory: tmp_dir.path().join("meta").to_string_lossy().into_owned(),
+            generated_directory: tmp_dir
+                .path()
+                .join("generated")
+                .to_string_lossy()
+                .into_owned(),
+        }));
+        let minecraft = MetaVersion {
+            format_version: 1,
+            name: "Minecraft".to_string(),
+            uid: "net.minecraft".to_string(),
+            version: "1.20.1".to_string(),
+            ..Default::default()
+        };
+        let loader = MetaVersion {
+            format_version: 1,
+            name: "Loader".to_string(),
+            uid: "org.example.loader".to_string(),
+            version: "1.0".to_string(),
+            requires: Some(vec![Dependency {
+                uid: "net.minecraft".to_string(),
+                equals: Some("1.20.1".to_string()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        storage
+            .store_package("net.minecraft", "Minecraft", vec![(minecraft, false)])
+            .unwrap();
+        storage
+            .store_package("org.example.loader", "Loader", vec![(loader, false)])
+            .unwrap();
+        let router = Router::new()
+            .route("/bundle/:uid/:file", get(version_bundle))
+            .layer(Extension(storage));
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/bundle/org.example.loader/1.0.tar.gz")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/gzip");
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let mut tarball = tar::Archive::new(GzDecoder::new(body.as_ref()));
+        let names = tarball
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["net.minecraft/1.20.1.json", "org.example.loader/1.0.json"]
+        );
+
+        let response = router
+            .oneshot(
+                Request::get("/bundle/org.example.loader/2.0.tar.gz")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,84 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use axum::{http::StatusCode, Extension};
+use tokio::{sync::Mutex, time::Instant};
+use tracing::error;
+
+use crate::app_config::ServerConfig;
+use crate::routes::{errors::APIError, APIResponse, APIResult};
+use crate::storage::{health::DeepHealthReport, METADATA_SYNCED};
+
+/// The last deep health report, reused until it is older than the configured interval
+#[derive(Default)]
+pub struct DeepHealthCache {
+    last: Mutex<Option<(Instant, DeepHealthReport)>>,
+}
+
+pub async fn health() -> APIResponse<&'static str> {
+    APIResponse::ok("ok")
+}
+
+/**
+ * Ready once the first full metadata update finished and the storage can be read, so traffic can
+ * be held back from an instance still syncing
+ */
+pub async fn ready(config: Extension<Arc<ServerConfig>>) -> APIResult<&'static str> {
+    let not_ready = |error: String| APIError::new(StatusCode::SERVICE_UNAVAILABLE, error);
+
+    if !METADATA_SYNCED.load(Ordering::Relaxed) {
+        return Err(not_ready(
+            "Initial metadata update has not finished".to_string(),
+        ));
+    }
+    let storage_format = config.storage_format.clone();
+    match tokio::task::spawn_blocking(move || storage_format.check_reachable()).await {
+        Ok(Ok(())) => Ok(APIResponse::ok("ready")),
+        Ok(Err(err)) => {
+            error!("Storage is unreachable: {:?}", err);
+            Err(not_ready(format!("Storage is unreachable: {:#}", err)))
+        }
+        Err(err) => {
+            error!("Readiness check task failed: {:?}", err);
+            Err(not_ready("Readiness check task failed".to_string()))
+        }
+    }
+}
+
+pub async fn deep_health(
+    config: Extension<Arc<ServerConfig>>,
+    cache: Extension<Arc<DeepHealthCache>>,
+) -> APIResult<DeepHealthReport> {
+    let interval = std::time::Duration::from_secs(config.metadata.deep_health_interval);
+    // holding the lock while checking keeps concurrent requests from running their own checks
+    let mut last = cache.last.lock().await;
+
+    let report = match &*last {
+        Some((checked_at, report)) if checked_at.elapsed() < interval => Ok(report.clone()),
+        _ => {
+            let storage_format = config.storage_format.clone();
+            match tokio::task::spawn_blocking(move || storage_format.check_health()).await {
+                Ok(Ok(report)) => {
+                    *last = Some((Instant::now(), report.clone()));
+                    Ok(report)
+                }
+                Ok(Err(err)) => {
+                    error!("Failure checking metadata health: {:?}", err);
+                    Err(format!("Failure checking metadata health: {:#}", err))
+                }
+                Err(err) => {
+                    error!("Health check task failed: {:?}", err);
+                    Err("Health check task failed".to_string())
+                }
+            }
+        }
+    };
+
+    let report = report.map_err(|error| APIError::new(StatusCode::SERVICE_UNAVAILABLE, error))?;
+    // an unhealthy report is still answered, with a status load balancers act on
+    let status = if report.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok(APIResponse::ok(report).with_status(status))
+}
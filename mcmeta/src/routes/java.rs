@@ -0,0 +1,27 @@
+use anyhow::anyhow;
+use axum::{extract::Query, response::IntoResponse, Extension};
+
+use crate::routes::{segments::SegmentPath, stored_response, ResponseOptions, ResponseSource};
+use crate::storage::java::{JavaDataStorage, JavaVendor};
+
+pub async fn raw_java_runtimes(
+    storage: Extension<JavaDataStorage>,
+    SegmentPath(vendor_name): SegmentPath<String>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    let vendor = JavaVendor::from_name(&vendor_name);
+    stored_response(
+        || vendor.map_or(Ok(None), |vendor| storage.load_index(vendor)),
+        format!("Java runtimes of {} do not exist", vendor_name),
+        &options,
+        || ResponseSource {
+            uid: vendor
+                .map(|vendor| vendor.uid())
+                .unwrap_or_default()
+                .to_string(),
+            path: vendor
+                .ok_or_else(|| anyhow!("Unknown Java vendor {}", vendor_name))
+                .and_then(|vendor| storage.index_file(vendor)),
+        },
+    )
+}
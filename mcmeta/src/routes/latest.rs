@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+
+use axum::Extension;
+use libmcmeta::models::mojang::MojangVersionManifestLatest;
+use serde::Serialize;
+
+use crate::routes::{errors::APIError, APIResponse, APIResult};
+use crate::storage::{forge::ForgeDataStorage, mojang::MojangDataStorage};
+
+/// The newest Forge builds of a Minecraft version
+#[derive(Serialize, Debug, Clone)]
+pub struct ForgeLatest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recommended: Option<String>,
+}
+
+/// The newest versions of every source with a notion of latest, null for those not stored yet
+#[derive(Serialize, Debug, Clone)]
+pub struct LatestVersions {
+    pub mojang: Option<MojangVersionManifestLatest>,
+    /// by Minecraft version, only those with a promoted build
+    pub forge: Option<BTreeMap<String, ForgeLatest>>,
+}
+
+/**
+ * Summarize the newest Mojang release and snapshot and the latest and recommended Forge build of
+ * every Minecraft version, from the stored manifest and Forge index
+ */
+pub async fn raw_latest(
+    mojang_storage: Extension<MojangDataStorage>,
+    forge_storage: Extension<ForgeDataStorage>,
+) -> APIResult<LatestVersions> {
+    let mojang = mojang_storage
+        .load_manifest()?
+        .map(|manifest| manifest.latest);
+    let forge = forge_storage.load_index()?.map(|index| {
+        index
+            .by_mc_version
+            .into_iter()
+            .filter(|(_, info)| info.latest.is_some() || info.recommended.is_some())
+            .map(|(mc_version, info)| {
+                (
+                    mc_version,
+                    ForgeLatest {
+                        latest: info.latest,
+                        recommended: info.recommended,
+                    },
+                )
+            })
+            .collect()
+    });
+    if mojang.is_none() && forge.is_none() {
+        return Err(APIError::not_found(
+            "Neither the Mojang manifest nor the Forge index exist",
+        ));
+    }
+
+    Ok(APIResponse::ok(LatestVersions { mojang, forge }))
+}
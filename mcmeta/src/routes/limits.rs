@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use axum::{
+    body::{boxed, HttpBody, StreamBody},
+    extract::State,
+    http::{header, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures::stream;
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+use crate::routes::errors::APIError;
+
+/// The requests of one class of routes being handled at the same time
+#[derive(Clone)]
+pub struct ConcurrencyLimit {
+    class: &'static str,
+    /// `None` when the class is not limited
+    permits: Option<Arc<Semaphore>>,
+    retry_after: u64,
+}
+
+impl ConcurrencyLimit {
+    /**
+     * Limit a class of routes to `max_concurrent` requests at a time, 0 disabling the limit
+     */
+    pub fn new(class: &'static str, max_concurrent: usize, retry_after: u64) -> Self {
+        Self {
+            class,
+            permits: (max_concurrent > 0).then(|| Arc::new(Semaphore::new(max_concurrent))),
+            retry_after,
+        }
+    }
+}
+
+/**
+ * Middleware shedding requests beyond the concurrency limit of their route class
+ * with a 503 and a Retry-After header, instead of queueing them up
+ */
+pub async fn shed_load<B>(
+    State(limit): State<ConcurrencyLimit>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Some(permits) = &limit.permits else {
+        return next.run(request).await;
+    };
+
+    match permits.clone().try_acquire_owned() {
+        Ok(permit) => {
+            let response = next.run(request).await;
+            if response.body().size_hint().exact().is_some() {
+                return response;
+            }
+
+            // streamed bodies, like exports, keep their permit until they are fully sent
+            let (parts, body) = response.into_parts();
+            let body = stream::unfold((body, permit), |(mut body, permit)| async move {
+                body.data().await.map(|chunk| (chunk, (body, permit)))
+            });
+            Response::from_parts(parts, boxed(StreamBody::new(body)))
+        }
+        Err(_) => {
+            warn!(
+                "Shedding request to {}, too many concurrent {} requests",
+                request.uri(),
+                limit.class
+            );
+            (
+                [(header::RETRY_AFTER, limit.retry_after.to_string())],
+                APIError::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Server is overloaded, retry later",
+                ),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        middleware,
+        routing::get,
+        Router,
+    };
+    use tokio::sync::Semaphore;
+    use tower::ServiceExt;
+
+    use super::{shed_load, ConcurrencyLimit};
+
+    /// A route announcing every request it handles and finishing one per permit of `release`
+    fn limited_app(
+        max_concurrent: usize,
+        started: Arc<Semaphore>,
+        release: Arc<Semaphore>,
+    ) -> Router {
+        Router::new()
+            .route(
+                "/",
+                get(move || async move {
+                    started.add_permits(1);
+                    release.acquire().await.unwrap().forget();
+                    "done"
+                }),
+            )
+            .route_layer(middleware::from_fn_with_state(
+                ConcurrencyLimit::new("test", max_concurrent, 7),
+                shed_load,
+            ))
+    }
+
+    fn request() -> Request<Body> {
+        Request::builder().uri("/").body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_shed_load_beyond_limit() {
+        let started = Arc::new(Semaphore::new(0));
+        let release = Arc::new(Semaphore::new(0));
+        let app = limited_app(1, started.clone(), release.clone());
+
+        let in_flight = tokio::spawn(app.clone().oneshot(request()));
+        started.acquire().await.unwrap().forget();
+
+        let shed = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(shed.headers()[header::RETRY_AFTER], "7");
+
+        release.add_permits(1);
+        assert_eq!(in_flight.await.unwrap().unwrap().status(), StatusCode::OK);
+
+        // the permit is back once the first request is done
+        release.add_permits(1);
+        let served = app.oneshot(request()).await.unwrap();
+        assert_eq!(served.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_shed_load_unlimited() {
+        let started = Arc::new(Semaphore::new(0));
+        let release = Arc::new(Semaphore::new(0));
+        let app = limited_app(0, started.clone(), release.clone());
+
+        let in_flight = (0..3)
+            .map(|_| tokio::spawn(app.clone().oneshot(request())))
+            .collect::<Vec<_>>();
+        for _ in 0..3 {
+            started.acquire().await.unwrap().forget();
+        }
+        release.add_permits(3);
+        for request in in_flight {
+            assert_eq!(request.await.unwrap().unwrap().status(), StatusCode::OK);
+        }
+    }
+}
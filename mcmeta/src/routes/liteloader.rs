@@ -0,0 +1,37 @@
+use axum::{extract::Query, response::IntoResponse, Extension};
+
+use crate::routes::{
+    derived_response, segments::SegmentPath, stored_response, ResponseOptions, ResponseSource,
+};
+use crate::storage::liteloader::{LiteLoaderDataStorage, LITELOADER_UID};
+
+pub async fn raw_liteloader_versions(
+    storage: Extension<LiteLoaderDataStorage>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_index(),
+        "LiteLoader versions do not exist".to_string(),
+        &options,
+        || ResponseSource {
+            uid: LITELOADER_UID.to_string(),
+            path: storage.versions_file(),
+        },
+    )
+}
+
+pub async fn raw_liteloader_mc_version(
+    storage: Extension<LiteLoaderDataStorage>,
+    SegmentPath(mc_version): SegmentPath<String>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    derived_response(
+        storage.load_entry(&mc_version),
+        format!("LiteLoader for Minecraft {} does not exist", mc_version),
+        &options,
+        || ResponseSource {
+            uid: LITELOADER_UID.to_string(),
+            path: storage.versions_file(),
+        },
+    )
+}
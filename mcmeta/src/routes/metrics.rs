@@ -0,0 +1,58 @@
+use axum::{
+    extract::MatchedPath,
+    http::{header, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::metrics::SERVER_METRICS;
+use crate::routes::errors::APIError;
+use crate::storage::{coordinator::UPDATE_COORDINATOR, generated::GeneratedDataStorage};
+
+/**
+ * Middleware counting requests and their latency by the route they matched, so the labels stay
+ * bounded however many versions are requested
+ */
+pub async fn track_request<B>(request: Request<B>, next: Next<B>) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "unmatched".to_string(), |path| path.as_str().to_string());
+    let method = request.method().to_string();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+    SERVER_METRICS.record_request(
+        &route,
+        &method,
+        response.status().as_u16(),
+        started.elapsed(),
+    );
+    response
+}
+
+pub async fn metrics(
+    generated_storage: Extension<GeneratedDataStorage>,
+) -> Result<Response, APIError> {
+    let generated_storage = generated_storage.0;
+    let stored_versions =
+        match tokio::task::spawn_blocking(move || generated_storage.count_versions())
+            .await
+            .map_err(anyhow::Error::from)?
+        {
+            Ok(stored_versions) => stored_versions,
+            // the other metrics are still worth scraping without the stored versions
+            Err(err) => {
+                warn!("Failure counting stored versions: {:#}", err);
+                Default::default()
+            }
+        };
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        SERVER_METRICS.to_prometheus_text(&stored_versions, &UPDATE_COORDINATOR.status()),
+    )
+        .into_response())
+}
@@ -1,10 +1,447 @@
-use serde::Serialize;
+use std::sync::Arc;
 
+use axum::{
+    http::StatusCode,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::app_config::{ServerConfig, StorageFormat};
+use crate::storage::{
+    assets::AssetObjectCache,
+    fabric::{FabricDataStorage, FabricSource},
+    forge::ForgeDataStorage,
+    generated::GeneratedDataStorage,
+    java::JavaDataStorage,
+    liteloader::LiteLoaderDataStorage,
+    maven::MavenMirror,
+    mojang::MojangDataStorage,
+    neoforge::NeoForgeDataStorage,
+    optifine::OptiFineDataStorage,
+    UpdateSource,
+};
+use crate::utils::{hash, HashAlgo};
+
+pub mod admin;
+pub mod cache;
+pub mod checksums;
+pub mod compression;
+pub mod errors;
+pub mod etag;
+pub mod events;
+pub mod export;
+pub mod fabric;
+pub mod files;
 pub mod forge;
+pub mod generated;
+pub mod health;
+pub mod java;
+pub mod latest;
+pub mod limits;
+pub mod liteloader;
+pub mod metrics;
 pub mod mojang;
+pub mod neoforge;
+pub mod openapi;
+pub mod optifine;
+pub mod passthrough;
+pub mod quarantine;
+pub mod request_id;
+pub mod search;
+pub mod segments;
+pub mod sync;
 
+/// The envelope of json responses, carrying either the `data` or an `error` and its `code`
 #[derive(Serialize, Debug, Clone)]
 pub struct APIResponse<T> {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// machine readable kind of the `error`, e.g. `not_found`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ResponseMeta>,
+    /// answered with, not part of the body
+    #[serde(skip)]
+    pub status: StatusCode,
+}
+
+impl<T> APIResponse<T> {
+    /// A 200 carrying `data`
+    pub fn ok(data: T) -> Self {
+        Self {
+            data: Some(data),
+            error: None,
+            code: None,
+            meta: None,
+            status: StatusCode::OK,
+        }
+    }
+
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn with_meta(mut self, meta: Option<ResponseMeta>) -> Self {
+        self.meta = meta;
+        self
+    }
+}
+
+impl<T: Serialize> IntoResponse for APIResponse<T> {
+    fn into_response(self) -> Response {
+        (self.status, Json(self)).into_response()
+    }
+}
+
+/// What handlers answering with an `APIResponse` return
+pub type APIResult<T> = Result<APIResponse<T>, errors::APIError>;
+
+/// Details about the `data` of a response, so clients can verify and cache it
+#[derive(Serialize, Debug, Clone)]
+pub struct ResponseMeta {
+    /// sha256 of the json serialized `data`
+    pub sha256: String,
+    /// RFC 3339 modification time of the file the data was loaded from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ResponseOptions {
+    /// include a `meta` object in the response
+    #[serde(default)]
+    pub meta: bool,
+    /// answer with the document itself instead of an `APIResponse` wrapping it
+    #[serde(default)]
+    pub raw: bool,
+}
+
+/// Where the data of a response was loaded from
+pub struct ResponseSource {
+    pub uid: String,
+    pub path: anyhow::Result<std::path::PathBuf>,
+}
+
+impl ResponseMeta {
+    fn describe<T: Serialize>(data: &T, source: ResponseSource) -> anyhow::Result<Self> {
+        let sha256 = hash(serde_json::to_vec(data)?, HashAlgo::Sha256)?.to_lowercase();
+        let modified = source
+            .path
+            .and_then(|path| Ok(std::fs::metadata(path)?.modified()?))
+            .ok()
+            .and_then(|modified| {
+                time::OffsetDateTime::from(modified)
+                    .format(&time::format_description::well_known::Rfc3339)
+                    .ok()
+            });
+        Ok(Self {
+            sha256,
+            modified,
+            uid: Some(source.uid),
+        })
+    }
+}
+
+/**
+ * Load a stored file into a response, `missing` being the error for a file that is not stored. With `raw` and
+ * passthrough enabled the file of the source is streamed as it is instead of being loaded.
+ */
+pub fn stored_response<T: Serialize>(
+    load: impl FnOnce() -> anyhow::Result<Option<T>>,
+    missing: String,
+    options: &ResponseOptions,
+    source: impl FnOnce() -> ResponseSource,
+) -> Response {
+    if options.raw && passthrough::is_enabled() {
+        return passthrough::file_response(source().path, missing);
+    }
+    derived_response(load(), missing, options, source)
+}
+
+/**
+ * Turn the result of loading a stored file, or data derived from one, into a response, `missing` being the error
+ * for a file that is not stored. The source is only inspected for loaded data, whose file is attached to the
+ * response for the response cache. With `raw` the data is answered without an envelope, errors are always wrapped.
+ */
+pub fn derived_response<T: Serialize>(
+    loaded: anyhow::Result<Option<T>>,
+    missing: String,
+    options: &ResponseOptions,
+    source: impl FnOnce() -> ResponseSource,
+) -> Response {
+    match loaded {
+        Ok(Some(data)) => {
+            let source = source();
+            let file = source.path.as_ref().ok().cloned();
+            let mut response = if options.raw {
+                Json(data).into_response()
+            } else {
+                let meta = if options.meta {
+                    ResponseMeta::describe(&data, source)
+                        .map_err(|err| warn!("Failure describing response data: {:?}", err))
+                        .ok()
+                } else {
+                    None
+                };
+                APIResponse::ok(data).with_meta(meta).into_response()
+            };
+            if let Some(file) = file {
+                response.extensions_mut().insert(cache::ResponseFile(file));
+            }
+            response
+        }
+        Ok(None) => errors::APIError::not_found(missing).into_response(),
+        Err(err) => errors::APIError::from(err).into_response(),
+    }
+}
+
+/**
+ * Build the router serving every endpoint of the metadata server
+ */
+pub fn router(config: Arc<ServerConfig>) -> Router {
+    build_router(config, true)
+}
+
+/**
+ * Build the router serving every endpoint but the admin endpoints and metrics, for listeners
+ * exposed to the public next to a private one
+ */
+pub fn public_router(config: Arc<ServerConfig>) -> Router {
+    build_router(config, false)
+}
+
+fn build_router(config: Arc<ServerConfig>, private: bool) -> Router {
+    cache::RESPONSE_CACHE.set_capacity(config.limits.response_cache_entries);
+    if config.http.raw_passthrough {
+        match &config.storage_format {
+            StorageFormat::Json {
+                meta_directory,
+                generated_directory: _,
+            } => passthrough::set_roots(&[
+                std::path::Path::new(meta_directory),
+                std::path::Path::new(&config.metadata.static_directory),
+            ]),
+            StorageFormat::Database => todo!(),
+        }
+    } else {
+        passthrough::set_roots(&[]);
+    }
+
+    let storage_format = Arc::new(config.storage_format.clone());
+    let mojang_storage = MojangDataStorage::new(storage_format.clone());
+    let forge_storage = ForgeDataStorage::new(storage_format.clone());
+    let neoforge_storage = NeoForgeDataStorage::new(storage_format.clone());
+    let fabric_storage = FabricDataStorage::new(storage_format.clone(), FabricSource::Fabric);
+    let liteloader_storage = LiteLoaderDataStorage::new(storage_format.clone());
+    let optifine_storage = OptiFineDataStorage::new(storage_format.clone());
+    let java_storage = JavaDataStorage::new(storage_format.clone());
+    let generated_storage = GeneratedDataStorage::new(storage_format);
+
+    let raw_mojang_routes = Router::new()
+        .route("/", get(mojang::raw_mojang_manifest))
+        .route("/java", get(mojang::raw_mojang_java_runtimes))
+        .route("/versions", get(mojang::raw_mojang_versions))
+        .route("/experiments", get(mojang::raw_mojang_experiments))
+        .route("/old-snapshots", get(mojang::raw_mojang_old_snapshots))
+        .route("/assets/:id", get(mojang::raw_mojang_asset_index))
+        .route("/:version", get(mojang::raw_mojang_version));
+    let raw_forge_routes = Router::new()
+        .route("/", get(forge::raw_forge_maven_meta))
+        .route("/promotions", get(forge::raw_forge_promotions))
+        .route("/derived_index", get(forge::raw_forge_derived_index))
+        .route(
+            "/derived_index/last_index",
+            get(forge::raw_forge_derived_index_entry),
+        )
+        .route("/legacyinfo", get(forge::raw_forge_legacy_info))
+        .route("/mcp", get(forge::raw_forge_mcp_index))
+        .route("/:version", get(forge::raw_forge_version))
+        .route("/:version/meta", get(forge::raw_forge_version_meta))
+        .route(
+            "/:version/installer",
+            get(forge::raw_forge_version_installer),
+        )
+        .route(
+            "/:version/installer_info",
+            get(forge::raw_forge_version_installer_info),
+        );
+
+    let raw_neoforge_routes = Router::new()
+        .route("/", get(neoforge::raw_neoforge_index))
+        .route("/:version", get(neoforge::raw_neoforge_version))
+        .route(
+            "/:version/installer",
+            get(neoforge::raw_neoforge_version_installer),
+        );
+    let raw_fabric_routes = Router::new()
+        .route("/loader", get(fabric::raw_fabric_loader_versions))
+        .route("/loader/:version", get(fabric::raw_fabric_loader_version))
+        .route(
+            "/intermediary",
+            get(fabric::raw_fabric_intermediary_versions),
+        );
+    let raw_liteloader_routes = Router::new()
+        .route("/", get(liteloader::raw_liteloader_versions))
+        .route("/:version", get(liteloader::raw_liteloader_mc_version));
+    let raw_optifine_routes = Router::new()
+        .route("/", get(optifine::raw_optifine_versions))
+        .route("/:version", get(optifine::raw_optifine_mc_version));
+
+    let raw_limit = limits::ConcurrencyLimit::new(
+        "raw",
+        config.limits.raw_concurrency,
+        config.limits.retry_after,
+    );
+    let mut raw_routes = Router::new()
+        .route("/latest", get(latest::raw_latest))
+        .route("/quarantine", get(quarantine::raw_quarantine));
+    // the routes of disabled sources are left out, answering with a 404 like any unknown path
+    let metadata_cfg = &config.metadata;
+    if metadata_cfg.is_enabled(UpdateSource::Mojang) {
+        raw_routes = raw_routes.nest("/mojang", raw_mojang_routes);
+    }
+    if metadata_cfg.is_enabled(UpdateSource::Forge) {
+        raw_routes = raw_routes.nest("/forge", raw_forge_routes);
+    }
+    if metadata_cfg.is_enabled(UpdateSource::NeoForge) {
+        raw_routes = raw_routes.nest("/neoforge", raw_neoforge_routes);
+    }
+    if metadata_cfg.is_enabled(UpdateSource::Fabric) {
+        raw_routes = raw_routes.nest("/fabric", raw_fabric_routes);
+    }
+    if metadata_cfg.is_enabled(UpdateSource::LiteLoader) {
+        raw_routes = raw_routes.nest("/liteloader", raw_liteloader_routes);
+    }
+    if metadata_cfg.is_enabled(UpdateSource::OptiFine) {
+        raw_routes = raw_routes.nest("/optifine", raw_optifine_routes);
+    }
+    if metadata_cfg.is_enabled(UpdateSource::Java) {
+        raw_routes = raw_routes.route("/java/:vendor", get(java::raw_java_runtimes));
+    }
+    // listed one by one, a `/:source/checksums` route would lose to the `/:version` routes
+    for source in metadata_cfg.enabled_sources() {
+        raw_routes = raw_routes.route(
+            &format!("/{}/checksums", source.name()),
+            get(move |config| checksums::raw_checksums(source, config)),
+        );
+    }
+    let raw_routes = raw_routes
+        .route_layer(middleware::from_fn(cache::cache_response))
+        .route_layer(middleware::from_fn(etag::tag_response))
+        .route_layer(middleware::from_fn_with_state(raw_limit, limits::shed_load));
+
+    let admin_routes = Router::new()
+        .route(
+            "/revalidate",
+            get(admin::revalidation_status).post(admin::revalidate_metadata),
+        )
+        .route("/lints", get(admin::lint_metadata))
+        .route("/refresh", post(admin::refresh_metadata))
+        .route("/refresh/:source", post(admin::refresh_source))
+        .route("/status", get(admin::update_status))
+        .route("/cache/purge", post(admin::purge_cache))
+        .route_layer(middleware::from_fn(admin::require_admin_auth));
+    // bundles are packed on a blocking thread like exports, so they share their limit
+    let export_limit = limits::ConcurrencyLimit::new(
+        "export",
+        config.limits.export_concurrency,
+        config.limits.retry_after,
+    );
+    let export_routes = Router::new()
+        .route("/export.tar.gz", get(export::export_generated))
+        .route_layer(middleware::from_fn_with_state(
+            export_limit.clone(),
+            limits::shed_load,
+        ))
+        .route_layer(middleware::from_fn(admin::require_admin_auth));
+
+    let bundle_routes = Router::new()
+        .route("/bundle/:uid/:file", get(generated::version_bundle))
+        .route_layer(middleware::from_fn_with_state(
+            export_limit,
+            limits::shed_load,
+        ));
+
+    let files_limit = limits::ConcurrencyLimit::new(
+        "files",
+        config.limits.files_concurrency,
+        config.limits.retry_after,
+    );
+    let files_routes = Router::new()
+        .route(
+            "/files/forge/:version/installer",
+            get(files::forge_installer),
+        )
+        .route("/assets/objects/:prefix/:hash", get(files::asset_object))
+        .route("/maven/*path", get(files::maven_file))
+        .route_layer(middleware::from_fn_with_state(
+            files_limit,
+            limits::shed_load,
+        ));
+
+    let v1_routes = Router::new()
+        .route("/index.json", get(generated::package_index))
+        .route("/:uid/index.json", get(generated::version_index))
+        .route("/:uid/:version", get(generated::version))
+        .route("/:uid/:version/closure", get(generated::version_closure))
+        .route_layer(middleware::from_fn(etag::tag_response));
+
+    let health_routes = Router::new()
+        .route("/", get(health::health))
+        .route("/deep", get(health::deep_health));
+
+    let router = Router::new()
+        .nest("/raw", raw_routes)
+        .nest("/v1", v1_routes)
+        .merge(files_routes)
+        .nest("/healthz", health_routes)
+        .route("/health", get(health::health))
+        .route("/ready", get(health::ready))
+        .route("/search", get(search::search))
+        .route("/sync/changes", get(sync::sync_changes))
+        .merge(bundle_routes)
+        .route("/events", get(events::events))
+        .route("/openapi.json", get(openapi::openapi_json))
+        .route("/docs", get(openapi::swagger_ui));
+    let router = if private {
+        router
+            .nest("/admin", admin_routes)
+            .merge(export_routes)
+            .route("/metrics", get(metrics::metrics))
+    } else {
+        router
+    };
+
+    router
+        .route_layer(middleware::from_fn(metrics::track_request))
+        .layer(middleware::from_fn_with_state(
+            compression::CompressionSettings {
+                enable: config.http.compression,
+                min_bytes: config.http.compression_min_bytes,
+            },
+            compression::compress_response,
+        ))
+        .layer(middleware::from_fn(request_id::trace_request))
+        .layer(Extension(mojang_storage))
+        .layer(Extension(forge_storage))
+        .layer(Extension(neoforge_storage))
+        .layer(Extension(fabric_storage))
+        .layer(Extension(liteloader_storage))
+        .layer(Extension(optifine_storage))
+        .layer(Extension(java_storage))
+        .layer(Extension(generated_storage))
+        .layer(Extension(AssetObjectCache::new(&config.metadata.assets)))
+        .layer(Extension(MavenMirror::new(&config.metadata.maven_mirror)))
+        .layer(Extension(Arc::new(health::DeepHealthCache::default())))
+        .layer(Extension(config))
 }
@@ -1,68 +1,261 @@
-use axum::{extract::Path, response::IntoResponse, Extension};
-use libmcmeta::models::mojang::{MinecraftVersion, MojangVersionManifest};
+use axum::{
+    extract::Query,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use libmcmeta::models::{java::JAVA_UID, mojang::MojangVersionManifest};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use crate::app_config::{ServerConfig, StorageFormat};
-use crate::routes::APIResponse;
-
-pub async fn raw_mojang_manifest(config: Extension<Arc<ServerConfig>>) -> impl IntoResponse {
-    match &config.storage_format {
-        StorageFormat::Json {
-            meta_directory,
-            generated_directory: _,
-        } => {
-            let metadata_dir = std::path::Path::new(meta_directory);
-            let mojang_meta_dir = metadata_dir.join("mojang");
-            let local_manifest = mojang_meta_dir.join("version_manifest_v2.json");
-            let manifest = serde_json::from_str::<MojangVersionManifest>(
-                &std::fs::read_to_string(local_manifest).unwrap(),
-            )
-            .unwrap();
+use crate::app_config::ServerConfig;
+use crate::routes::{
+    derived_response, errors::APIError, segments::SegmentPath, stored_response, ResponseOptions,
+    ResponseSource,
+};
+use crate::storage::mojang::{MojangDataStorage, EXPERIMENTS_FILE, OLD_SNAPSHOTS_FILE};
+
+pub(crate) const MINECRAFT_UID: &str = "net.minecraft";
+
+/// Versions listed per page unless the request asks for another `limit`
+const DEFAULT_VERSION_LIMIT: usize = 50;
+/// The most versions a single page lists
+const MAX_VERSION_LIMIT: usize = 1000;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct VersionListQuery {
+    /// comma separated version types to list, e.g. `release,snapshot`
+    #[serde(rename = "type")]
+    pub version_type: Option<String>,
+    /// only list versions released on or after this date (`2023-01-01`) or RFC 3339 time
+    pub since: Option<String>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+/// A version as listed by `/raw/mojang/versions`, without its url and hashes
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionSummary {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub version_type: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub release_time: time::OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: time::OffsetDateTime,
+}
+
+/// A page of the versions matching a query, newest first as in the manifest
+#[derive(Serialize, Debug, Clone)]
+pub struct VersionList {
+    /// versions matching the query across all pages
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub versions: Vec<VersionSummary>,
+}
 
-            axum::Json(APIResponse {
-                data: Some(manifest),
-                error: None,
+fn parse_since(since: &str) -> Option<time::OffsetDateTime> {
+    use time::format_description::well_known::{Iso8601, Rfc3339};
+
+    if let Ok(since) = time::OffsetDateTime::parse(since, &Rfc3339) {
+        return Some(since);
+    }
+    time::Date::parse(since, &Iso8601::DATE)
+        .ok()
+        .map(|date| date.midnight().assume_utc())
+}
+
+impl VersionListQuery {
+    fn list(
+        &self,
+        manifest: MojangVersionManifest,
+        since: Option<time::OffsetDateTime>,
+    ) -> VersionList {
+        let types = self.version_type.as_ref().map(|types| {
+            types
+                .split(',')
+                .map(str::trim)
+                .filter(|version_type| !version_type.is_empty())
+                .collect::<Vec<_>>()
+        });
+        let matching = manifest
+            .versions
+            .into_iter()
+            .filter(|version| {
+                types
+                    .as_ref()
+                    .is_none_or(|types| types.contains(&version.version_type.as_str()))
             })
+            .filter(|version| since.is_none_or(|since| version.release_time >= since))
+            .collect::<Vec<_>>();
+
+        let offset = self.offset.unwrap_or(0);
+        let limit = self
+            .limit
+            .unwrap_or(DEFAULT_VERSION_LIMIT)
+            .min(MAX_VERSION_LIMIT);
+        VersionList {
+            total: matching.len(),
+            offset,
+            limit,
+            versions: matching
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .map(|version| VersionSummary {
+                    id: version.id,
+                    version_type: version.version_type,
+                    release_time: version.release_time,
+                    time: version.time,
+                })
+                .collect(),
         }
-        StorageFormat::Database => todo!(),
     }
 }
 
-pub async fn raw_mojang_version(
+pub async fn raw_mojang_manifest(
+    storage: Extension<MojangDataStorage>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_manifest(),
+        "Mojang version manifest does not exist".to_string(),
+        &options,
+        || ResponseSource {
+            uid: MINECRAFT_UID.to_string(),
+            path: storage
+                .meta_dir()
+                .map(|dir| dir.join("version_manifest_v2.json")),
+        },
+    )
+}
+
+pub async fn raw_mojang_java_runtimes(
+    storage: Extension<MojangDataStorage>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_java_runtimes(),
+        "Mojang Java runtime index does not exist".to_string(),
+        &options,
+        || ResponseSource {
+            uid: JAVA_UID.to_string(),
+            path: storage.java_runtimes_file(),
+        },
+    )
+}
+
+pub async fn raw_mojang_experiments(
+    storage: Extension<MojangDataStorage>,
     config: Extension<Arc<ServerConfig>>,
-    Path(version): Path<String>,
+    Query(options): Query<ResponseOptions>,
 ) -> impl IntoResponse {
-    match &config.storage_format {
-        StorageFormat::Json {
-            meta_directory,
-            generated_directory: _,
-        } => {
-            let metadata_dir = std::path::Path::new(meta_directory);
-            let mojang_meta_dir = metadata_dir.join("mojang");
-            let versions_dir = mojang_meta_dir.join("versions");
-            let version_file = versions_dir.join(format!("{}.json", version));
-            if !version_file.exists() {
-                return (
-                    axum::http::StatusCode::NOT_FOUND,
-                    axum::Json(APIResponse {
-                        data: None,
-                        error: Some(format!("Version {} does not exist", version)),
-                    }),
-                );
-            }
-            let manifest = serde_json::from_str::<MinecraftVersion>(
-                &std::fs::read_to_string(&version_file).unwrap(),
-            )
-            .unwrap();
-
-            (
-                axum::http::StatusCode::OK,
-                axum::Json(APIResponse {
-                    data: Some(manifest),
-                    error: None,
-                }),
+    let static_directory = &config.metadata.static_directory;
+    stored_response(
+        || storage.load_experiments(static_directory),
+        "Mojang experiment index does not exist".to_string(),
+        &options,
+        || ResponseSource {
+            uid: MINECRAFT_UID.to_string(),
+            path: Ok(MojangDataStorage::static_file(
+                static_directory,
+                EXPERIMENTS_FILE,
+            )),
+        },
+    )
+}
+
+pub async fn raw_mojang_old_snapshots(
+    storage: Extension<MojangDataStorage>,
+    config: Extension<Arc<ServerConfig>>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    let static_directory = &config.metadata.static_directory;
+    stored_response(
+        || storage.load_old_snapshots(static_directory),
+        "Mojang old snapshot index does not exist".to_string(),
+        &options,
+        || ResponseSource {
+            uid: MINECRAFT_UID.to_string(),
+            path: Ok(MojangDataStorage::static_file(
+                static_directory,
+                OLD_SNAPSHOTS_FILE,
+            )),
+        },
+    )
+}
+
+/**
+ * An asset index by its id, as mirrored when `metadata.assets.mirror_indexes` is enabled
+ */
+pub async fn raw_mojang_asset_index(
+    storage: Extension<MojangDataStorage>,
+    SegmentPath(id): SegmentPath<String>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_asset_index(&id),
+        format!("Asset index {} does not exist", id),
+        &options,
+        || ResponseSource {
+            uid: MINECRAFT_UID.to_string(),
+            path: storage.asset_index_file(&id),
+        },
+    )
+}
+
+pub async fn raw_mojang_version(
+    storage: Extension<MojangDataStorage>,
+    SegmentPath(version): SegmentPath<String>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_minecraft_version(&version),
+        format!("Version {} does not exist", version),
+        &options,
+        || ResponseSource {
+            uid: MINECRAFT_UID.to_string(),
+            path: storage
+                .versions_dir()
+                .map(|dir| dir.join(format!("{}.json", version))),
+        },
+    )
+}
+
+/**
+ * List the versions of the stored manifest, filtered by type and release time and paginated
+ */
+pub async fn raw_mojang_versions(
+    storage: Extension<MojangDataStorage>,
+    Query(query): Query<VersionListQuery>,
+    Query(options): Query<ResponseOptions>,
+) -> Response {
+    let since = match query.since.as_deref().map(parse_since) {
+        Some(None) => {
+            return APIError::bad_request(
+                "since must be a date like 2023-01-01 or an RFC 3339 time",
             )
+            .into_response()
         }
-        StorageFormat::Database => todo!(),
-    }
+        Some(since) => since,
+        None => None,
+    };
+    derived_response(
+        storage
+            .load_manifest()
+            .map(|manifest| manifest.map(|manifest| query.list(manifest, since))),
+        "Mojang version manifest does not exist".to_string(),
+        // a page is not a stored file, so it has no `meta` to describe
+        &ResponseOptions {
+            meta: false,
+            ..options
+        },
+        || ResponseSource {
+            uid: MINECRAFT_UID.to_string(),
+            path: storage
+                .meta_dir()
+                .map(|dir| dir.join("version_manifest_v2.json")),
+        },
+    )
 }
@@ -0,0 +1,57 @@
+use axum::{extract::Query, response::IntoResponse, Extension};
+
+use crate::routes::{segments::SegmentPath, stored_response, ResponseOptions, ResponseSource};
+use crate::storage::neoforge::NeoForgeDataStorage;
+
+pub(crate) const NEOFORGE_UID: &str = "net.neoforged";
+
+pub async fn raw_neoforge_index(
+    storage: Extension<NeoForgeDataStorage>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_index(),
+        "NeoForge index does not exist".to_string(),
+        &options,
+        || ResponseSource {
+            uid: NEOFORGE_UID.to_string(),
+            path: storage.meta_dir().map(|dir| dir.join("derived_index.json")),
+        },
+    )
+}
+
+pub async fn raw_neoforge_version(
+    storage: Extension<NeoForgeDataStorage>,
+    SegmentPath(version): SegmentPath<String>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_mojang_version(&version),
+        format!("Version {} does not exist", version),
+        &options,
+        || ResponseSource {
+            uid: NEOFORGE_UID.to_string(),
+            path: storage
+                .version_manifests_dir()
+                .map(|dir| dir.join(format!("{}.json", version))),
+        },
+    )
+}
+
+pub async fn raw_neoforge_version_installer(
+    storage: Extension<NeoForgeDataStorage>,
+    SegmentPath(version): SegmentPath<String>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    stored_response(
+        || storage.load_installer_manifest(&version),
+        format!("Version {} does not exist", version),
+        &options,
+        || ResponseSource {
+            uid: NEOFORGE_UID.to_string(),
+            path: storage
+                .installer_manifests_dir()
+                .map(|dir| dir.join(format!("{}.json", version))),
+        },
+    )
+}
@@ -0,0 +1,727 @@
+use axum::{response::Html, Json};
+use serde_json::{json, Map, Value};
+
+lazy_static! {
+    /// Built once, the endpoints only change with the code
+    static ref OPENAPI_DOCUMENT: Value = openapi_document();
+}
+
+/// What an endpoint answers with
+#[derive(Clone, Copy)]
+enum Body {
+    /// an `APIResponse` wrapping the stored file, which may carry a `meta` object
+    Stored,
+    /// an `APIResponse` wrapping a report or status
+    Wrapped,
+    /// an `APIResponse` wrapping a filtered page of a stored file
+    Listed,
+    /// an `APIResponse` wrapping the versions matching a search
+    Search,
+    /// an `APIResponse` wrapping the number of purged cache entries
+    Purge,
+    /// an `APIResponse` wrapping the files changed since a sync cursor
+    Sync,
+    /// a generated file served as is
+    Generated,
+    /// a generated version served as is, or filtered to the libraries of a platform
+    Version,
+    /// an `APIResponse` wrapping the components of a closure, which may be filtered to a platform
+    Closure,
+    /// Prometheus text exposition format
+    Metrics,
+    /// a gzipped tarball
+    Tarball,
+    /// a gzipped tarball of a version and the components it requires
+    Bundle,
+    /// a cached file, or a byte range of it
+    Download,
+    /// a stream of Server-Sent Events
+    Events,
+}
+
+struct Endpoint {
+    method: &'static str,
+    /// in axum syntax, `:name` for path parameters
+    path: &'static str,
+    tag: &'static str,
+    summary: &'static str,
+    body: Body,
+    admin: bool,
+}
+
+const fn get(tag: &'static str, path: &'static str, summary: &'static str, body: Body) -> Endpoint {
+    Endpoint {
+        method: "get",
+        path,
+        tag,
+        summary,
+        body,
+        admin: false,
+    }
+}
+
+const fn admin(
+    method: &'static str,
+    path: &'static str,
+    summary: &'static str,
+    body: Body,
+) -> Endpoint {
+    Endpoint {
+        method,
+        path,
+        tag: "admin",
+        summary,
+        body,
+        admin: true,
+    }
+}
+
+/// Every endpoint of the router, in the order the README lists them
+const ENDPOINTS: &[Endpoint] = &[
+    get(
+        "raw",
+        "/raw/mojang",
+        "Mojang version manifest",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/mojang/java",
+        "Mojang Java runtime index",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/mojang/versions",
+        "Mojang versions filtered by type and release time, paginated",
+        Body::Listed,
+    ),
+    get(
+        "raw",
+        "/raw/mojang/experiments",
+        "Experimental Minecraft versions from the static data",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/mojang/old-snapshots",
+        "Old snapshots missing from Mojang's manifest, from the static data",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/mojang/assets/:id",
+        "Mirrored asset index of Minecraft versions",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/mojang/:version",
+        "Mojang version",
+        Body::Stored,
+    ),
+    get("raw", "/raw/forge", "Forge maven metadata", Body::Stored),
+    get(
+        "raw",
+        "/raw/forge/promotions",
+        "Forge promotions",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/forge/derived_index",
+        "Forge index derived from the maven metadata and promotions",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/forge/derived_index/last_index",
+        "Hash and time of the derived index the installers were last processed for",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/forge/legacyinfo",
+        "Release times and hashes of the ancient Forge builds",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/forge/mcp",
+        "Minecraft versions and Forge builds of every MCP version",
+        Body::Stored,
+    ),
+    get("raw", "/raw/forge/:version", "Forge version", Body::Stored),
+    get(
+        "raw",
+        "/raw/forge/:version/meta",
+        "Forge files manifest",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/forge/:version/installer",
+        "Forge installer manifest",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/forge/:version/installer_info",
+        "Hashes and sizes of a Forge installer jar",
+        Body::Stored,
+    ),
+    get("raw", "/raw/neoforge", "NeoForge index", Body::Stored),
+    get(
+        "raw",
+        "/raw/neoforge/:version",
+        "NeoForge version",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/neoforge/:version/installer",
+        "NeoForge installer manifest",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/fabric/loader",
+        "Fabric loader versions",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/fabric/loader/:version",
+        "Fabric loader version",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/fabric/intermediary",
+        "Fabric intermediary versions",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/liteloader",
+        "LiteLoader versions",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/liteloader/:version",
+        "LiteLoader versions of a Minecraft version",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/optifine",
+        "OptiFine builds by Minecraft version",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/optifine/:version",
+        "OptiFine builds of a Minecraft version",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/java/:vendor",
+        "Java runtimes of adoptium or azul by Java major",
+        Body::Stored,
+    ),
+    get(
+        "raw",
+        "/raw/latest",
+        "Newest Mojang release and snapshot, latest and recommended Forge by Minecraft version",
+        Body::Wrapped,
+    ),
+    get(
+        "raw",
+        "/raw/quarantine",
+        "Upstream versions updates skip as known to be broken, by source",
+        Body::Wrapped,
+    ),
+    get(
+        "raw",
+        "/raw/:source/checksums",
+        "Sha256, size and modification time of every file stored for a source",
+        Body::Wrapped,
+    ),
+    get(
+        "v1",
+        "/v1/index.json",
+        "Generated package index",
+        Body::Generated,
+    ),
+    get(
+        "v1",
+        "/v1/:uid/index.json",
+        "Generated version index of a package",
+        Body::Generated,
+    ),
+    get(
+        "v1",
+        "/v1/:uid/:version",
+        "Generated version, e.g. 1.20.1.json",
+        Body::Version,
+    ),
+    get(
+        "v1",
+        "/v1/:uid/:version/closure",
+        "Every component needed to launch a version",
+        Body::Closure,
+    ),
+    get(
+        "bundle",
+        "/bundle/:uid/:version.tar.gz",
+        "Tarball of a version and every component needed to launch it",
+        Body::Bundle,
+    ),
+    get(
+        "files",
+        "/files/forge/:version/installer",
+        "Installer jar of a Forge build, downloaded by the updater",
+        Body::Download,
+    ),
+    get(
+        "files",
+        "/assets/objects/:prefix/:hash",
+        "Asset object by its sha1, fetched from upstream on first request",
+        Body::Download,
+    ),
+    get(
+        "files",
+        "/maven/*path",
+        "File of a library maven repository, fetched from upstream on first request",
+        Body::Download,
+    ),
+    get(
+        "search",
+        "/search",
+        "Version ids of every source matching a query",
+        Body::Search,
+    ),
+    get(
+        "sync",
+        "/sync/changes",
+        "Stored files changed or removed since a cursor, for mirrors",
+        Body::Sync,
+    ),
+    get(
+        "events",
+        "/events",
+        "Stream of the generated versions updates add, change or remove",
+        Body::Events,
+    ),
+    get("health", "/health", "Liveness check", Body::Wrapped),
+    get("health", "/healthz", "Liveness check", Body::Wrapped),
+    get(
+        "health",
+        "/healthz/deep",
+        "Sample the stored files of every component",
+        Body::Wrapped,
+    ),
+    get(
+        "health",
+        "/ready",
+        "Readiness check, 503 until the first update finished",
+        Body::Wrapped,
+    ),
+    get("health", "/metrics", "Prometheus metrics", Body::Metrics),
+    admin(
+        "post",
+        "/admin/revalidate",
+        "Start re-parsing and re-validating all stored metadata",
+        Body::Wrapped,
+    ),
+    admin(
+        "get",
+        "/admin/revalidate",
+        "State and report of the last revalidation",
+        Body::Wrapped,
+    ),
+    admin(
+        "get",
+        "/admin/lints",
+        "Lint the generated metadata",
+        Body::Wrapped,
+    ),
+    admin(
+        "post",
+        "/admin/refresh",
+        "Start an upstream metadata update",
+        Body::Wrapped,
+    ),
+    admin(
+        "post",
+        "/admin/refresh/:source",
+        "Start an update of a single source",
+        Body::Wrapped,
+    ),
+    admin(
+        "get",
+        "/admin/status",
+        "State and last update result of every source",
+        Body::Wrapped,
+    ),
+    admin(
+        "post",
+        "/admin/cache/purge",
+        "Drop cached responses",
+        Body::Purge,
+    ),
+    admin(
+        "get",
+        "/export.tar.gz",
+        "Tarball of the generated metadata",
+        Body::Tarball,
+    ),
+];
+
+fn api_response_ref() -> Value {
+    json!({ "$ref": "#/components/schemas/APIResponse" })
+}
+
+fn responses(body: Body) -> Value {
+    let error = json!({
+        "description": "An error, with its message in `error` and its kind in `code`",
+        "content": { "application/json": { "schema": api_response_ref() } }
+    });
+    let ok = match body {
+        Body::Stored
+        | Body::Wrapped
+        | Body::Listed
+        | Body::Search
+        | Body::Purge
+        | Body::Sync
+        | Body::Closure => {
+            json!({
+                "description": "The requested data in `data`, or the data itself for `?raw=true`",
+                "content": { "application/json": { "schema": api_response_ref() } }
+            })
+        }
+        Body::Generated => json!({
+            "description": "The generated file, byte for byte as the indexes hash it",
+            "content": { "application/json": { "schema": { "type": "object" } } }
+        }),
+        Body::Version => json!({
+            "description": "The generated file, byte for byte as the indexes hash it unless filtered by `os`",
+            "content": { "application/json": { "schema": { "type": "object" } } }
+        }),
+        Body::Metrics => json!({
+            "description": "Metrics in the Prometheus text exposition format",
+            "content": { "text/plain": { "schema": { "type": "string" } } }
+        }),
+        Body::Events => json!({
+            "description": "A `version` event with a json object for every added, changed or removed generated version",
+            "content": { "text/event-stream": { "schema": { "type": "string" } } }
+        }),
+        Body::Tarball => json!({
+            "description": "A gzipped tarball of the generated files",
+            "content": { "application/gzip": { "schema": { "type": "string", "format": "binary" } } }
+        }),
+        Body::Bundle => json!({
+            "description": "A gzipped tarball of the generated files of the version and every component it requires, as `<uid>/<version>.json`",
+            "content": { "application/gzip": { "schema": { "type": "string", "format": "binary" } } }
+        }),
+        Body::Download => json!({
+            "description": "The file, as downloaded from upstream",
+            "content": { "application/octet-stream": { "schema": { "type": "string", "format": "binary" } } }
+        }),
+    };
+    let mut responses = json!({
+        "200": ok,
+        "404": error.clone(),
+        "500": error,
+    });
+    if matches!(
+        body,
+        Body::Listed | Body::Search | Body::Sync | Body::Version | Body::Closure
+    ) {
+        responses["400"] = json!({
+            "description": "An invalid query parameter, named in `error`",
+            "content": { "application/json": { "schema": api_response_ref() } }
+        });
+    }
+    if matches!(body, Body::Download) {
+        responses["206"] = json!({
+            "description": "The byte range of the file asked for in the Range header",
+            "content": { "application/octet-stream": { "schema": { "type": "string", "format": "binary" } } }
+        });
+        responses["416"] = json!({
+            "description": "A Range outside of the file",
+            "content": { "application/json": { "schema": api_response_ref() } }
+        });
+        responses["502"] = json!({
+            "description": "The file is not cached and could not be fetched from upstream",
+            "content": { "application/json": { "schema": api_response_ref() } }
+        });
+    }
+    if matches!(
+        body,
+        Body::Stored | Body::Listed | Body::Generated | Body::Version | Body::Download
+    ) {
+        responses["304"] = json!({ "description": "Unchanged since the ETag in If-None-Match" });
+    }
+    if matches!(
+        body,
+        Body::Stored | Body::Listed | Body::Tarball | Body::Download
+    ) {
+        responses["503"] = json!({
+            "description": "Too many concurrent requests, retry after the Retry-After header",
+            "content": { "application/json": { "schema": api_response_ref() } }
+        });
+    }
+    responses
+}
+
+/**
+ * The name of the path parameter a segment is, and the literal suffix following it, like the
+ * `.tar.gz` of `:version.tar.gz`, which the route matches as part of the parameter
+ */
+fn path_parameter(segment: &str) -> Option<(&str, &str)> {
+    let parameter = segment.strip_prefix(':').or(segment.strip_prefix('*'))?;
+    Some(
+        parameter
+            .find('.')
+            .map_or((parameter, ""), |dot| parameter.split_at(dot)),
+    )
+}
+
+fn parameters(endpoint: &Endpoint) -> Vec<Value> {
+    let mut parameters: Vec<Value> = endpoint
+        .path
+        .split('/')
+        .filter_map(path_parameter)
+        .map(|(name, _)| {
+            json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": { "type": "string" }
+            })
+        })
+        .collect();
+    let raw = json!({
+        "name": "raw",
+        "in": "query",
+        "description": "Answer with the document itself instead of an `APIResponse` wrapping it",
+        "schema": { "type": "boolean" }
+    });
+    match endpoint.body {
+        Body::Stored => parameters.extend([
+            json!({
+                "name": "meta",
+                "in": "query",
+                "description": "Add a `meta` object with the sha256 and modification time of the data",
+                "schema": { "type": "boolean" }
+            }),
+            raw,
+        ]),
+        Body::Listed => parameters.extend([
+            raw,
+            json!({
+                "name": "type",
+                "in": "query",
+                "description": "Comma separated version types to list, e.g. `release,snapshot`",
+                "schema": { "type": "string" }
+            }),
+            json!({
+                "name": "since",
+                "in": "query",
+                "description": "Only list versions released on or after this date or RFC 3339 time",
+                "schema": { "type": "string" }
+            }),
+            json!({
+                "name": "limit",
+                "in": "query",
+                "description": "Versions per page, 50 by default and at most 1000",
+                "schema": { "type": "integer", "minimum": 0 }
+            }),
+            json!({
+                "name": "offset",
+                "in": "query",
+                "description": "Matching versions to skip",
+                "schema": { "type": "integer", "minimum": 0 }
+            }),
+        ]),
+        Body::Search => parameters.extend([
+            json!({
+                "name": "q",
+                "in": "query",
+                "required": true,
+                "description": "Matched against version ids, ignoring case",
+                "schema": { "type": "string" }
+            }),
+            json!({
+                "name": "source",
+                "in": "query",
+                "description": "Comma separated sources to search, e.g. `mojang,forge`",
+                "schema": { "type": "string" }
+            }),
+            json!({
+                "name": "limit",
+                "in": "query",
+                "description": "Hits to answer, 50 by default and at most 1000",
+                "schema": { "type": "integer", "minimum": 0 }
+            }),
+        ]),
+        Body::Download => parameters.push(json!({
+            "name": "Range",
+            "in": "header",
+            "description": "A single byte range to resume a download with, e.g. `bytes=1024-`",
+            "schema": { "type": "string" }
+        })),
+        Body::Purge => parameters.push(json!({
+            "name": "prefix",
+            "in": "query",
+            "description": "Only drop the responses to request uris starting with it, e.g. `/raw/mojang`",
+            "schema": { "type": "string" }
+        })),
+        Body::Version | Body::Closure => parameters.extend([
+            json!({
+                "name": "os",
+                "in": "query",
+                "description": "Only keep the libraries used on `linux`, `osx` or `windows`, with their rules evaluated and natives narrowed down",
+                "schema": { "type": "string" }
+            }),
+            json!({
+                "name": "arch",
+                "in": "query",
+                "description": "The architecture for `os`, `x86_64` by default, or `x86`, `arm64` and `arm32`",
+                "schema": { "type": "string" }
+            }),
+        ]),
+        Body::Sync => parameters.push(json!({
+            "name": "since",
+            "in": "query",
+            "description": "The `cursor` of the previous sync or an RFC 3339 timestamp, every stored file if left out",
+            "schema": { "type": "string" }
+        })),
+        Body::Tarball => parameters.push(json!({
+            "name": "component",
+            "in": "query",
+            "description": "Only export the generated files of this component uid",
+            "schema": { "type": "string" }
+        })),
+        _ => {}
+    }
+    parameters
+}
+
+/**
+ * Build the OpenAPI 3 document describing every endpoint
+ */
+fn openapi_document() -> Value {
+    let mut paths = Map::new();
+    for endpoint in ENDPOINTS {
+        let path = endpoint
+            .path
+            .split('/')
+            .map(|segment| match path_parameter(segment) {
+                Some((name, suffix)) => format!("{{{}}}{}", name, suffix),
+                None => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        let mut operation = json!({
+            "tags": [endpoint.tag],
+            "summary": endpoint.summary,
+            "parameters": parameters(endpoint),
+            "responses": responses(endpoint.body),
+        });
+        if endpoint.admin {
+            operation["security"] = json!([{ "adminToken": [] }]);
+        }
+        let item = paths
+            .entry(path)
+            .or_insert_with(|| Value::Object(Map::new()));
+        item[endpoint.method] = operation;
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "mcmeta",
+            "description": env!("CARGO_PKG_DESCRIPTION"),
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": {
+                "APIResponse": {
+                    "type": "object",
+                    "description": "Carries either `data` or an `error` and its `code`",
+                    "properties": {
+                        "data": {},
+                        "error": { "type": "string" },
+                        "code": {
+                            "type": "string",
+                            "enum": [
+                                "bad_request",
+                                "unauthorized",
+                                "forbidden",
+                                "not_found",
+                                "conflict",
+                                "bad_gateway",
+                                "unavailable",
+                                "client_error",
+                                "internal_error",
+                            ],
+                        },
+                        "meta": { "$ref": "#/components/schemas/ResponseMeta" },
+                    },
+                },
+                "ResponseMeta": {
+                    "type": "object",
+                    "properties": {
+                        "sha256": {
+                            "type": "string",
+                            "description": "sha256 of the json serialized `data`",
+                        },
+                        "modified": {
+                            "type": "string",
+                            "format": "date-time",
+                            "description": "modification time of the file the data was loaded from",
+                        },
+                        "uid": { "type": "string" },
+                    },
+                    "required": ["sha256"],
+                },
+            },
+            "securitySchemes": {
+                "adminToken": { "type": "http", "scheme": "bearer" },
+            },
+        },
+    })
+}
+
+pub async fn openapi_json() -> Json<Value> {
+    Json(OPENAPI_DOCUMENT.clone())
+}
+
+/**
+ * A Swagger UI page for the OpenAPI document, loading the UI itself from a CDN
+ */
+pub async fn swagger_ui() -> Html<&'static str> {
+    Html(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>mcmeta API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##,
+    )
+}
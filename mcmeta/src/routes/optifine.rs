@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use axum::{extract::Query, http::header, response::IntoResponse, Extension};
+
+use crate::app_config::ServerConfig;
+use crate::routes::{
+    derived_response, segments::SegmentPath, stored_response, ResponseOptions, ResponseSource,
+};
+use crate::storage::optifine::{OptiFineDataStorage, OPTIFINE_UID};
+
+/**
+ * The `Cache-Control` of OptiFine responses. Builds are only ever added, so clients and proxies
+ * may hold on to responses for a while rather than asking for a scraped page on every request.
+ */
+fn cache_control(config: &ServerConfig) -> [(header::HeaderName, String); 1] {
+    [(
+        header::CACHE_CONTROL,
+        format!("public, max-age={}", config.metadata.optifine.max_age),
+    )]
+}
+
+pub async fn raw_optifine_versions(
+    storage: Extension<OptiFineDataStorage>,
+    config: Extension<Arc<ServerConfig>>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    (
+        cache_control(&config),
+        stored_response(
+            || storage.load_index(),
+            "OptiFine versions do not exist".to_string(),
+            &options,
+            || ResponseSource {
+                uid: OPTIFINE_UID.to_string(),
+                path: storage.versions_file(),
+            },
+        ),
+    )
+}
+
+pub async fn raw_optifine_mc_version(
+    storage: Extension<OptiFineDataStorage>,
+    config: Extension<Arc<ServerConfig>>,
+    SegmentPath(mc_version): SegmentPath<String>,
+    Query(options): Query<ResponseOptions>,
+) -> impl IntoResponse {
+    (
+        cache_control(&config),
+        derived_response(
+            storage.load_builds(&mc_version),
+            format!("OptiFine for Minecraft {} does not exist", mc_version),
+            &options,
+            || ResponseSource {
+                uid: OPTIFINE_UID.to_string(),
+                path: storage.versions_file(),
+            },
+        ),
+    )
+}
@@ -0,0 +1,112 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use axum::{
+    body::StreamBody,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use tokio_util::io::ReaderStream;
+use tracing::warn;
+
+use crate::routes::{errors::APIError, files::file_etag};
+
+lazy_static! {
+    /// The canonical directories stored files are passed through from, empty while disabled
+    static ref PASSTHROUGH_ROOTS: RwLock<Vec<PathBuf>> = RwLock::new(vec![]);
+}
+
+/**
+ * Pass stored files through from the given directories for `?raw=true` requests, no directories
+ * disabling it
+ */
+pub fn set_roots(roots: &[&Path]) {
+    let roots = roots
+        .iter()
+        .filter_map(|root| {
+            root.canonicalize()
+                .map_err(|err| {
+                    warn!(
+                        "Not passing files through from {}: {}",
+                        root.to_string_lossy(),
+                        err
+                    )
+                })
+                .ok()
+        })
+        .collect();
+    match PASSTHROUGH_ROOTS.write() {
+        Ok(mut passthrough_roots) => *passthrough_roots = roots,
+        Err(err) => warn!("Passthrough roots lock poisoned: {}", err),
+    }
+}
+
+pub fn is_enabled() -> bool {
+    PASSTHROUGH_ROOTS
+        .read()
+        .is_ok_and(|roots| !roots.is_empty())
+}
+
+/**
+ * Whether a canonical path is inside one of the directories files are passed through from, so
+ * version names from the request cannot reach anything else
+ */
+fn is_passed_through(file: &Path) -> bool {
+    PASSTHROUGH_ROOTS
+        .read()
+        .is_ok_and(|roots| roots.iter().any(|root| file.starts_with(root)))
+}
+
+/**
+ * Stream a stored file as it is on disk, without parsing and serializing it again, so mirrors get
+ * upstream's bytes and hashes. Its ETag is derived from the size and modification time of the file
+ * instead of hashing it.
+ */
+pub fn file_response(path: anyhow::Result<PathBuf>, missing: String) -> Response {
+    let path = match path {
+        Ok(path) => path,
+        Err(err) => return APIError::from(err).into_response(),
+    };
+    let file = match path.canonicalize() {
+        Ok(file) if file.is_file() && is_passed_through(&file) => file,
+        Ok(_) => return APIError::not_found(missing).into_response(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return APIError::not_found(missing).into_response()
+        }
+        Err(err) => {
+            return APIError::from(
+                anyhow::Error::from(err)
+                    .context(format!("Failure reading file {}", path.to_string_lossy())),
+            )
+            .into_response()
+        }
+    };
+
+    let opened = std::fs::File::open(&file).and_then(|opened| {
+        let metadata = opened.metadata()?;
+        Ok((opened, metadata))
+    });
+    let (opened, metadata) = match opened {
+        Ok(opened) => opened,
+        Err(err) => {
+            return APIError::from(
+                anyhow::Error::from(err)
+                    .context(format!("Failure reading file {}", file.to_string_lossy())),
+            )
+            .into_response()
+        }
+    };
+    let etag = file_etag(&metadata);
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::CONTENT_LENGTH, metadata.len().to_string()),
+            (header::ETAG, etag),
+        ],
+        StreamBody::new(ReaderStream::new(tokio::fs::File::from_std(opened))),
+    )
+        .into_response()
+}
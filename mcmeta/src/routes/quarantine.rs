@@ -0,0 +1,17 @@
+use std::sync::Arc;
+
+use axum::Extension;
+
+use crate::app_config::ServerConfig;
+use crate::routes::{APIResponse, APIResult};
+use crate::storage::quarantine::Quarantine;
+
+/**
+ * The upstream versions updates currently skip, as listed in the quarantine file of the static
+ * directory, which is read again for every request like it is for every update
+ */
+pub async fn raw_quarantine(config: Extension<Arc<ServerConfig>>) -> APIResult<Quarantine> {
+    Ok(APIResponse::ok(Quarantine::load(
+        &config.metadata.static_directory,
+    )?))
+}
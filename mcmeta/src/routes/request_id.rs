@@ -0,0 +1,77 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use axum::{
+    http::{HeaderName, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use tokio::time::Instant;
+use tracing::{debug, info_span, Instrument};
+
+pub static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+lazy_static! {
+    /// Random per process, so ids stay unique across restarts
+    static ref ID_PREFIX: u32 = RandomState::new().build_hasher().finish() as u32;
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn generate_id() -> String {
+    format!(
+        "{:08x}-{:08x}",
+        *ID_PREFIX,
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/**
+ * Only keep ids a proxy in front generated if they are short and printable, they end up in logs
+ */
+fn accept_id(id: &HeaderValue) -> Option<String> {
+    let id = id.to_str().ok()?;
+    (!id.is_empty()
+        && id.len() <= 128
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c)))
+    .then(|| id.to_string())
+}
+
+/**
+ * Middleware running every request in a span carrying its id, taken from an `X-Request-Id` header
+ * or generated, and logging its method, path, status and latency once answered. The id is sent
+ * back in the response, and tasks spawned in the span, like a requested refresh, log it too.
+ */
+pub async fn trace_request<B>(request: Request<B>, next: Next<B>) -> Response {
+    let id = request
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(accept_id)
+        .unwrap_or_else(generate_id);
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let span = info_span!("request", request_id = %id, method = %method, path = %path);
+
+    async move {
+        let started = Instant::now();
+        let mut response = next.run(request).await;
+        debug!(
+            status = response.status().as_u16(),
+            latency_ms = started.elapsed().as_secs_f64() * 1000.0,
+            "Answered {} {}",
+            method,
+            path
+        );
+        if let Ok(value) = HeaderValue::from_str(&id) {
+            response.headers_mut().insert(X_REQUEST_ID.clone(), value);
+        }
+        response
+    }
+    .instrument(span)
+    .await
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use axum::{extract::Query, Extension};
+use serde::{Deserialize, Serialize};
+
+use crate::app_config::ServerConfig;
+use crate::routes::{errors::APIError, APIResponse, APIResult};
+use crate::storage::{
+    search::{current_index, SearchHit},
+    UpdateSource,
+};
+
+/// Hits answered unless the request asks for another `limit`
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+/// The most hits a single search answers
+const MAX_SEARCH_LIMIT: usize = 1000;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchQuery {
+    /// matched against version ids, ignoring case
+    #[serde(default)]
+    pub q: String,
+    /// comma separated sources to search, e.g. `mojang,forge`, all of them if left out
+    pub source: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// The best matching versions, exact matches first
+#[derive(Serialize, Debug, Clone)]
+pub struct SearchResults {
+    /// versions matching the query, including those past the limit
+    pub total: usize,
+    pub hits: Vec<SearchHit>,
+}
+
+fn parse_sources(sources: &str) -> Result<Vec<UpdateSource>, APIError> {
+    sources
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            UpdateSource::from_name(name)
+                .ok_or_else(|| APIError::bad_request(format!("Unknown source {}", name)))
+        })
+        .collect()
+}
+
+pub async fn search(
+    config: Extension<Arc<ServerConfig>>,
+    Query(query): Query<SearchQuery>,
+) -> APIResult<SearchResults> {
+    let needle = query.q.trim().to_string();
+    if needle.is_empty() {
+        return Err(APIError::bad_request("q must not be empty"));
+    }
+    let sources = query.source.as_deref().map(parse_sources).transpose()?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .min(MAX_SEARCH_LIMIT);
+
+    let storage_format = Arc::new(config.storage_format.clone());
+    let index = tokio::task::spawn_blocking(move || current_index(storage_format))
+        .await
+        .map_err(anyhow::Error::from)??;
+
+    let mut hits = index.search(&needle, sources.as_deref());
+    let total = hits.len();
+    hits.truncate(limit);
+    Ok(APIResponse::ok(SearchResults { total, hits }))
+}
@@ -0,0 +1,115 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, RawPathParams},
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use serde::de::DeserializeOwned;
+
+use crate::routes::errors::APIError;
+
+/**
+ * Whether a path segment from a request could leave the directory it is looked up in, or name a
+ * hidden file in it
+ */
+pub fn invalid_segment(segment: &str) -> bool {
+    segment.is_empty() || segment.starts_with('.') || segment.contains(['/', '\\'])
+}
+
+/**
+ * `Path` for parameters naming stored files, like version ids and uids, answering a 400 for any
+ * that is empty or could leave the directory it is looked up in once decoded
+ */
+pub struct SegmentPath<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for SegmentPath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let params = RawPathParams::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        if params.iter().any(|(_, value)| invalid_segment(value)) {
+            return Err(APIError::bad_request("Invalid path").into_response());
+        }
+        let Path(params) = Path::<T>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        Ok(Self(params))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    use super::{invalid_segment, SegmentPath};
+
+    #[test]
+    fn test_invalid_segment() {
+        assert!(!invalid_segment("1.20.1"));
+        assert!(!invalid_segment("1.20.1-47.1.0"));
+        assert!(invalid_segment(""));
+        assert!(invalid_segment(".."));
+        assert!(invalid_segment(".hidden"));
+        assert!(invalid_segment("../index"));
+        assert!(invalid_segment("..\\index"));
+        assert!(invalid_segment("versions/1.20"));
+    }
+
+    #[tokio::test]
+    async fn test_segment_path_rejects_encoded_traversal() {
+        let router = Router::new()
+            .route(
+                "/raw/mojang/:version",
+                get(|SegmentPath(version): SegmentPath<String>| async move { version }),
+            )
+            .route(
+                "/v1/:uid/:file",
+                get(
+                    |SegmentPath((uid, file)): SegmentPath<(String, String)>| async move {
+                        format!("{}/{}", uid, file)
+                    },
+                ),
+            );
+
+        for uri in [
+            "/raw/mojang/..%2F..%2Fsecrets",
+            "/raw/mojang/..",
+            "/raw/mojang/..%5Cindex",
+            "/raw/mojang/%2E%2E",
+            "/v1/net.minecraft/..%2F..%2Fmeta%2Fmojang.json",
+            "/v1/..%2Fmeta/1.20.json",
+        ] {
+            let response = router
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+
+        let response = router
+            .oneshot(
+                Request::get("/v1/net.minecraft/1.20.1.json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"net.minecraft/1.20.1.json");
+    }
+}
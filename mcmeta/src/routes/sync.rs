@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use axum::{extract::Query, Extension};
+use serde::Deserialize;
+
+use crate::app_config::ServerConfig;
+use crate::routes::{errors::APIError, APIResponse, APIResult};
+use crate::storage::checksums::{SyncChanges, SyncCursor};
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SyncQuery {
+    /// the `cursor` of the previous sync or an RFC 3339 timestamp, every file if left out
+    pub since: Option<String>,
+}
+
+/**
+ * The generated and upstream files that changed or were removed since a cursor, by tree and path
+ * relative to it, so mirrors can replicate them incrementally instead of walking every file
+ */
+pub async fn sync_changes(
+    config: Extension<Arc<ServerConfig>>,
+    Query(query): Query<SyncQuery>,
+) -> APIResult<SyncChanges> {
+    let since = query
+        .since
+        .as_deref()
+        .map(str::parse::<SyncCursor>)
+        .transpose()
+        .map_err(|err| APIError::bad_request(err.to_string()))?;
+
+    let storage_format = config.storage_format.clone();
+    let changes = tokio::task::spawn_blocking(move || storage_format.changes_since(since))
+        .await
+        .map_err(anyhow::Error::from)??;
+    Ok(APIResponse::ok(changes))
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, Context, Result};
+use futures::{stream, StreamExt};
+use libmcmeta::models::mojang::{AssetIndex, MojangAssetIndex};
+use tracing::{debug, info};
+
+use crate::{
+    app_config::AssetsConfig,
+    download::{self, errors::MetadataError},
+    storage::{
+        mojang::MojangDataStorage, validation::json_file_stems, StorageFormat, UpdateSource,
+        UpstreamMetadataUpdater,
+    },
+    utils::{filehash, hash, process_results, write_atomic, HashAlgo},
+};
+
+impl MojangDataStorage {
+    pub fn asset_indexes_dir(&self) -> Result<std::path::PathBuf> {
+        Ok(self.meta_dir()?.join("asset_indexes"))
+    }
+
+    pub fn asset_index_file(&self, id: &str) -> Result<std::path::PathBuf> {
+        Ok(self.asset_indexes_dir()?.join(format!("{}.json", id)))
+    }
+
+    pub fn load_asset_index(&self, id: &str) -> Result<Option<MojangAssetIndex>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let index_file = self.asset_index_file(id)?;
+                if index_file.is_file() {
+                    let body = std::fs::read_to_string(&index_file).with_context(|| {
+                        format!("Failure reading file {}", index_file.to_string_lossy())
+                    })?;
+                    let index = serde_json::from_str::<MojangAssetIndex>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
+                    Ok(Some(index))
+                } else {
+                    Ok(None)
+                }
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    /**
+     * The asset indexes the stored versions refer to, taking the one of the newest version for an
+     * id several versions share, as Mojang updates an index under the same id
+     */
+    fn referenced_asset_indexes(&self) -> Result<Vec<AssetIndex>> {
+        let parse_time = |time: &str| {
+            time::OffsetDateTime::parse(
+                time,
+                &time::format_description::well_known::Iso8601::DEFAULT,
+            )
+            .ok()
+        };
+        let mut indexes = HashMap::new();
+        for id in json_file_stems(&self.versions_dir()?)? {
+            let Some(version) = self.load_minecraft_version(&id)? else {
+                continue;
+            };
+            let release_time = parse_time(&version.release_time);
+            match indexes.entry(version.asset_index.id.clone()) {
+                Entry::Vacant(entry) => {
+                    entry.insert((release_time, version.asset_index));
+                }
+                Entry::Occupied(mut entry) => {
+                    if release_time > entry.get().0 {
+                        entry.insert((release_time, version.asset_index));
+                    }
+                }
+            }
+        }
+        Ok(indexes.into_values().map(|(_, index)| index).collect())
+    }
+}
+
+/**
+ * Download an asset index unless the stored one has the expected sha1, keeping the downloaded
+ * bytes as they are. Returns whether it was downloaded.
+ */
+async fn update_asset_index(local_storage: &MojangDataStorage, index: &AssetIndex) -> Result<bool> {
+    let index_file = local_storage.asset_index_file(&index.id)?;
+    if index_file.is_file()
+        && filehash(&index_file, HashAlgo::Sha1)?.eq_ignore_ascii_case(&index.sha1)
+    {
+        return Ok(false);
+    }
+
+    info!("Downloading Mojang asset index {}", index.id);
+    let body = download::download_text(&index.url).await?;
+    let sha1 = hash(&body, HashAlgo::Sha1)?;
+    if !sha1.eq_ignore_ascii_case(&index.sha1) {
+        return Err(anyhow!(
+            "Asset index {} has sha1 {}, expected {}",
+            index.id,
+            sha1.to_lowercase(),
+            index.sha1
+        ));
+    }
+    serde_json::from_str::<MojangAssetIndex>(&body)
+        .map_err(|err| MetadataError::from_json_err(err, &body))?;
+
+    std::fs::create_dir_all(local_storage.asset_indexes_dir()?)?;
+    write_atomic(&index_file, &body)?;
+    Ok(true)
+}
+
+impl UpstreamMetadataUpdater {
+    /**
+     * Mirror the asset index of every stored Minecraft version, so launchers can resolve them
+     * through this server
+     */
+    pub async fn update_mojang_asset_indexes(&self) -> Result<()> {
+        let local_storage = MojangDataStorage::new(self.storage_format.clone());
+        let indexes = {
+            let local_storage = local_storage.clone();
+            tokio::task::spawn_blocking(move || local_storage.referenced_asset_indexes()).await??
+        };
+        debug!("Stored versions refer to {} asset indexes", indexes.len());
+
+        let tasks = stream::iter(indexes)
+            .map(|index| {
+                let local_storage = local_storage.clone();
+                tokio::spawn(async move {
+                    update_asset_index(&local_storage, &index)
+                        .await
+                        .with_context(|| format!("Failed to update asset index {}", index.id))
+                })
+            })
+            .buffer_unordered(self.metadata_cfg.parallelism(UpdateSource::Mojang));
+        let results = tasks
+            .map(|task| task.map_err(anyhow::Error::from).and_then(|result| result))
+            .collect::<Vec<_>>()
+            .await;
+        let downloaded = process_results(results)?
+            .into_iter()
+            .filter(|downloaded| *downloaded)
+            .count();
+        if downloaded > 0 {
+            info!("Downloaded {} Mojang asset indexes", downloaded);
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Whether a string is the sha1 of an asset object, the name it is stored under
+ */
+pub fn is_object_hash(hash: &str) -> bool {
+    hash.len() == 40 && hash.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+/// Mojang's asset objects, downloaded from upstream when first asked for and kept on disk
+#[derive(Clone, Debug)]
+pub struct AssetObjectCache {
+    directory: PathBuf,
+    upstream_url: String,
+}
+
+impl AssetObjectCache {
+    pub fn new(config: &AssetsConfig) -> Self {
+        Self {
+            directory: PathBuf::from(&config.objects_directory),
+            upstream_url: config.objects_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /**
+     * The file of an asset object by its sha1, laid out like upstream as `<first two digits>/<sha1>`.
+     * An object that is not cached yet is downloaded and checked against its sha1 first.
+     */
+    pub async fn fetch(&self, hash: &str) -> Result<PathBuf> {
+        let hash = hash.to_lowercase();
+        let prefix = &hash[..2];
+        let object_file = self.directory.join(prefix).join(&hash);
+        if object_file.is_file() {
+            return Ok(object_file);
+        }
+
+        let url = format!("{}/{}/{}", self.upstream_url, prefix, hash);
+        debug!("Downloading asset object from {}", url);
+        download::download_verified_file(&object_file, &url, Some(&hash)).await?;
+        Ok(object_file)
+    }
+}
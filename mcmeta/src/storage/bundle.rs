@@ -0,0 +1,171 @@
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use tar::EntryType;
+use tracing::{info, warn};
+
+use crate::storage::{StorageFormat, UpdateSource};
+
+/// How a bundle is compressed, picked by the extension of its file name
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BundleCompression {
+    Zstd,
+    Gzip,
+    None,
+}
+
+impl BundleCompression {
+    fn for_file(path: &Path) -> Self {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name.ends_with(".zst") || name.ends_with(".tzst") {
+            BundleCompression::Zstd
+        } else if name.ends_with(".gz") || name.ends_with(".tgz") {
+            BundleCompression::Gzip
+        } else {
+            BundleCompression::None
+        }
+    }
+}
+
+fn write_bundle<W: Write>(writer: W, directories: &[(&str, PathBuf)]) -> Result<W> {
+    let mut bundle = tar::Builder::new(writer);
+    bundle.follow_symlinks(false);
+    for (prefix, directory) in directories {
+        if directory.is_dir() {
+            bundle.append_dir_all(prefix, directory).with_context(|| {
+                format!(
+                    "Failure packaging directory {}",
+                    directory.to_string_lossy()
+                )
+            })?;
+        } else {
+            warn!(
+                "Directory {} does not exist, leaving it out of the bundle",
+                directory.to_string_lossy()
+            );
+        }
+    }
+    Ok(bundle.into_inner()?)
+}
+
+/**
+ * Unpack every file of a bundle into the directory its top level directory names, refusing
+ * anything that would land outside of it. Returns the number of files unpacked.
+ */
+fn unpack_bundle<R: Read>(reader: R, directories: &[(&str, PathBuf)]) -> Result<usize> {
+    let mut bundle = tar::Archive::new(reader);
+    let mut files = 0;
+    for entry in bundle.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let mut components = path.components();
+        let directory = match components.next() {
+            Some(Component::Normal(prefix)) => directories
+                .iter()
+                .find(|(name, _)| prefix == *name)
+                .map(|(_, directory)| directory),
+            _ => None,
+        };
+        let Some(directory) = directory else {
+            warn!("Skipping {}, it is not part of a bundle", path.display());
+            continue;
+        };
+        let relative = components.as_path();
+        if relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(anyhow!(
+                "Refusing to unpack {} out of place",
+                path.display()
+            ));
+        }
+
+        let entry_type = entry.header().entry_type();
+        if entry_type != EntryType::Regular && entry_type != EntryType::Directory {
+            warn!(
+                "Skipping {}, it is neither a file nor a directory",
+                path.display()
+            );
+            continue;
+        }
+        let destination = directory.join(relative);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("Failure creating directory {}", parent.to_string_lossy())
+            })?;
+        }
+        entry
+            .unpack(&destination)
+            .with_context(|| format!("Failure unpacking {}", destination.to_string_lossy()))?;
+        if entry_type == EntryType::Regular {
+            files += 1;
+        }
+    }
+    Ok(files)
+}
+
+impl StorageFormat {
+    /// The directories a bundle holds, by the name of their top level directory in it
+    fn bundle_directories(&self) -> Vec<(&'static str, PathBuf)> {
+        match self {
+            StorageFormat::Json {
+                meta_directory,
+                generated_directory,
+            } => vec![
+                ("meta", PathBuf::from(meta_directory)),
+                ("generated", PathBuf::from(generated_directory)),
+            ],
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    /**
+     * Package the stored upstream metadata and the generated metadata into a tar bundle, compressed
+     * with zstd or gzip for a `.zst` or `.gz` output file
+     */
+    pub fn export_bundle(&self, output: &Path) -> Result<()> {
+        let directories = self.bundle_directories();
+        let file = File::create(output)
+            .with_context(|| format!("Failure creating file {}", output.to_string_lossy()))?;
+        let file = match BundleCompression::for_file(output) {
+            BundleCompression::Zstd => {
+                write_bundle(zstd::Encoder::new(file, 0)?, &directories)?.finish()?
+            }
+            BundleCompression::Gzip => {
+                write_bundle(GzEncoder::new(file, Compression::default()), &directories)?
+                    .finish()?
+            }
+            BundleCompression::None => write_bundle(file, &directories)?,
+        };
+        file.sync_all()?;
+        info!("Exported metadata to {}", output.to_string_lossy());
+        Ok(())
+    }
+
+    /**
+     * Unpack a bundle made by `export_bundle` into the metadata directories, replacing the files
+     * it contains and keeping the others
+     */
+    pub fn import_bundle(&self, input: &Path) -> Result<()> {
+        let directories = self.bundle_directories();
+        let file = File::open(input)
+            .with_context(|| format!("Failure opening file {}", input.to_string_lossy()))?;
+        let files = match BundleCompression::for_file(input) {
+            BundleCompression::Zstd => unpack_bundle(zstd::Decoder::new(file)?, &directories)?,
+            BundleCompression::Gzip => unpack_bundle(GzDecoder::new(file), &directories)?,
+            BundleCompression::None => unpack_bundle(file, &directories)?,
+        };
+        info!("Imported {} files from {}", files, input.to_string_lossy());
+        self.refresh_checksums_of(&UpdateSource::ALL);
+        Ok(())
+    }
+}
@@ -0,0 +1,537 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{debug, warn};
+
+use crate::{
+    app_config::StorageFormat,
+    download::errors::MetadataError,
+    storage::UpdateSource,
+    utils::{filehash, write_json_atomic, HashAlgo},
+};
+
+/// Held while checksum indexes are written or read for sync, so every entry indexed after a
+/// sync cursor was handed out is stamped later than it
+static INDEXING: Mutex<()> = Mutex::new(());
+
+/// The hash of a stored file along with what tells whether it changed since it was hashed
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileChecksum {
+    /// lowercase hex sha256 of the contents
+    pub sha256: String,
+    pub size: u64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub modified: OffsetDateTime,
+    /// when the index picked up this version of the file
+    #[serde(default = "unix_epoch", with = "time::serde::rfc3339")]
+    pub indexed: OffsetDateTime,
+}
+
+/// A file the index no longer finds, kept so mirrors learn to delete it
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RemovedFile {
+    #[serde(with = "time::serde::rfc3339")]
+    pub indexed: OffsetDateTime,
+}
+
+/// The checksums of every file stored for a source, by path relative to its directory
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ChecksumIndex {
+    pub files: BTreeMap<String, FileChecksum>,
+    #[serde(default)]
+    pub removed: BTreeMap<String, RemovedFile>,
+}
+
+/// The directory a synced path is relative to
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncTree {
+    /// the generated metadata served below `/v1`
+    Generated,
+    /// the upstream metadata stored for each source, below the metadata directory
+    Upstream,
+}
+
+/// A file stored or changed since a sync cursor, by path relative to its tree
+#[derive(Serialize, Debug, Clone)]
+pub struct ChangedFile {
+    pub tree: SyncTree,
+    pub path: String,
+    pub sha256: String,
+    pub size: u64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub modified: OffsetDateTime,
+}
+
+/// A file removed since a sync cursor, by path relative to its tree
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RemovedPath {
+    pub tree: SyncTree,
+    pub path: String,
+}
+
+/// What changed in the synced trees since a sync cursor, and the cursor to ask with next
+#[derive(Serialize, Debug, Clone)]
+pub struct SyncChanges {
+    pub cursor: String,
+    pub changed: Vec<ChangedFile>,
+    pub removed: Vec<RemovedPath>,
+}
+
+/**
+ * Where a sync starts, as handed out in `SyncChanges::cursor`, or as an RFC 3339 timestamp
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncCursor(OffsetDateTime);
+
+impl std::str::FromStr for SyncCursor {
+    type Err = anyhow::Error;
+
+    fn from_str(cursor: &str) -> Result<Self> {
+        if !cursor.is_empty() && cursor.bytes().all(|byte| byte.is_ascii_digit()) {
+            let nanos = cursor
+                .parse::<i128>()
+                .with_context(|| format!("Invalid sync cursor {}", cursor))?;
+            OffsetDateTime::from_unix_timestamp_nanos(nanos)
+                .map(SyncCursor)
+                .with_context(|| format!("Invalid sync cursor {}", cursor))
+        } else {
+            OffsetDateTime::parse(cursor, &time::format_description::well_known::Rfc3339)
+                .map(SyncCursor)
+                .with_context(|| format!("Invalid sync cursor or timestamp {}", cursor))
+        }
+    }
+}
+
+impl std::fmt::Display for SyncCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.unix_timestamp_nanos())
+    }
+}
+
+/// The name the checksum index of the generated metadata is stored under, next to the sources
+const GENERATED_INDEX: &str = "generated";
+
+fn unix_epoch() -> OffsetDateTime {
+    OffsetDateTime::UNIX_EPOCH
+}
+
+/// Directories of a source holding downloaded upstream artifacts, like the installer jars, which
+/// grow without bound and are no metadata mirrors need
+const UNINDEXED_DIRECTORIES: [&str; 1] = ["jars"];
+
+/**
+ * Whether a file is state of the updater rather than metadata: the ledger of processed installers,
+ * the validators upstream answered with and downloads in progress
+ */
+fn is_internal_state(name: &str) -> bool {
+    name == "installer_ledger.json" || name.ends_with(".upstream") || name.ends_with(".part")
+}
+
+/**
+ * Collect the files below `dir` by their path relative to `root`, with `/` as separator. Hidden
+ * files are skipped, they are the temporary files of writes in progress, along with the artifact
+ * directories and internal state of the updater.
+ */
+fn stored_files(root: &Path, dir: &Path, files: &mut Vec<(String, PathBuf)>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)
+        .with_context(|| format!("Failure reading directory {}", dir.display()))?
+    {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if dir == root && UNINDEXED_DIRECTORIES.contains(&name.as_str()) {
+                continue;
+            }
+            stored_files(root, &path, files)?;
+        } else if file_type.is_file() && !is_internal_state(&name) {
+            let relative = path
+                .strip_prefix(root)?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((relative, path));
+        }
+    }
+    Ok(())
+}
+
+impl StorageFormat {
+    pub fn source_directory(&self, source: UpdateSource) -> Result<PathBuf> {
+        match self {
+            StorageFormat::Json {
+                meta_directory,
+                generated_directory: _,
+            } => Ok(Path::new(meta_directory).join(source.name())),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn checksums_file(&self, source: UpdateSource) -> Result<PathBuf> {
+        self.index_file(source.name())
+    }
+
+    fn index_file(&self, name: &str) -> Result<PathBuf> {
+        match self {
+            StorageFormat::Json {
+                meta_directory,
+                generated_directory: _,
+            } => Ok(Path::new(meta_directory)
+                .join("checksums")
+                .join(format!("{}.json", name))),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn load_checksums(&self, source: UpdateSource) -> Result<Option<ChecksumIndex>> {
+        self.load_index(source.name())
+    }
+
+    fn load_index(&self, name: &str) -> Result<Option<ChecksumIndex>> {
+        match self {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let checksums_file = self.index_file(name)?;
+                if checksums_file.is_file() {
+                    let body = std::fs::read_to_string(&checksums_file).with_context(|| {
+                        format!("Failure reading file {}", checksums_file.to_string_lossy())
+                    })?;
+                    let index = serde_json::from_str::<ChecksumIndex>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
+                    Ok(Some(index))
+                } else {
+                    Ok(None)
+                }
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    /**
+     * Bring the checksum index of a source up to date with its directory. Files whose size and
+     * modification time match the previous index keep their hash, so only files written since
+     * are read again, and files no longer stored are dropped.
+     */
+    pub fn refresh_checksums(&self, source: UpdateSource) -> Result<ChecksumIndex> {
+        self.refresh_index(source.name(), &self.source_directory(source)?)
+    }
+
+    /**
+     * Bring the checksum index of the generated metadata up to date, like `refresh_checksums`
+     */
+    pub fn refresh_generated_checksums(&self) -> Result<ChecksumIndex> {
+        match self {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory,
+            } => self.refresh_index(GENERATED_INDEX, Path::new(generated_directory)),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    fn refresh_index(&self, name: &str, dir: &Path) -> Result<ChecksumIndex> {
+        match self {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let previous = self.load_index(name).unwrap_or_else(|err| {
+                    debug!(
+                        "Failure loading the checksums of {}, hashing everything: {:#}",
+                        name, err
+                    );
+                    None
+                });
+                let previous = previous.unwrap_or_default();
+                let mut removed = previous.removed;
+                let mut previous = previous.files;
+
+                let mut files = vec![];
+                if dir.is_dir() {
+                    stored_files(dir, dir, &mut files)?;
+                }
+                let mut index = ChecksumIndex::default();
+                let mut changed = vec![];
+                for (name, path) in files {
+                    let metadata = std::fs::metadata(&path).with_context(|| {
+                        format!("Failure reading metadata of {}", path.display())
+                    })?;
+                    let size = metadata.len();
+                    let modified = OffsetDateTime::from(metadata.modified()?);
+                    let known = previous.remove(&name);
+                    let checksum = match known {
+                        Some(known) if known.size == size && known.modified == modified => known,
+                        known => {
+                            let sha256 = filehash(&path, HashAlgo::Sha256)
+                                .with_context(|| {
+                                    format!("Failure hashing file {}", path.display())
+                                })?
+                                .to_lowercase();
+                            match known {
+                                // rewritten with the same contents, mirrors have it already
+                                Some(known) if known.sha256 == sha256 => {
+                                    FileChecksum { modified, ..known }
+                                }
+                                _ => {
+                                    changed.push(name.clone());
+                                    FileChecksum {
+                                        sha256,
+                                        size,
+                                        modified,
+                                        indexed: OffsetDateTime::UNIX_EPOCH,
+                                    }
+                                }
+                            }
+                        }
+                    };
+                    removed.remove(&name);
+                    index.files.insert(name, checksum);
+                }
+                debug!(
+                    "Indexed {} changed of {} files of {}, {} removed",
+                    changed.len(),
+                    index.files.len(),
+                    name,
+                    previous.len()
+                );
+
+                // stamped last, a cursor handed out before is older than every new entry
+                let _indexing = INDEXING.lock().unwrap_or_else(|err| err.into_inner());
+                let indexed = OffsetDateTime::now_utc();
+                for name in changed {
+                    if let Some(checksum) = index.files.get_mut(&name) {
+                        checksum.indexed = indexed;
+                    }
+                }
+                for name in previous.into_keys() {
+                    removed.insert(name, RemovedFile { indexed });
+                }
+                index.removed = removed;
+
+                let checksums_file = self.index_file(name)?;
+                if let Some(checksums_dir) = checksums_file.parent() {
+                    std::fs::create_dir_all(checksums_dir)?;
+                }
+                write_json_atomic(&checksums_file, &index).with_context(|| {
+                    format!("Failure writing file {}", checksums_file.to_string_lossy())
+                })?;
+                Ok(index)
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    /**
+     * Bring the checksum indexes of several sources up to date, along with the one of the
+     * generated metadata written from them, e.g. after they were updated, regenerated or imported.
+     * Failures are only logged, mirrors keep syncing against the previous index.
+     */
+    pub fn refresh_checksums_of(&self, sources: &[UpdateSource]) {
+        for source in sources {
+            match self.refresh_checksums(*source) {
+                Ok(index) => debug!(
+                    "Indexed the checksums of {} files of {}",
+                    index.files.len(),
+                    source.name()
+                ),
+                Err(err) => warn!(
+                    "Failure indexing the checksums of {}: {:#}",
+                    source.name(),
+                    err
+                ),
+            }
+        }
+        match self.refresh_generated_checksums() {
+            Ok(index) => debug!(
+                "Indexed the checksums of {} generated files",
+                index.files.len()
+            ),
+            Err(err) => warn!(
+                "Failure indexing the checksums of the generated metadata: {:#}",
+                err
+            ),
+        }
+    }
+
+    /**
+     * The generated files and the files of every source indexed after `since`, or every indexed
+     * file without it, along with the files removed since. The cursor returned is the newest entry
+     * seen, so asking with it next misses nothing indexed in between.
+     */
+    pub fn changes_since(&self, since: Option<SyncCursor>) -> Result<SyncChanges> {
+        let _indexing = INDEXING.lock().unwrap_or_else(|err| err.into_inner());
+        let after = |indexed: OffsetDateTime| since.is_none_or(|since| indexed > since.0);
+        let mut cursor = since.map_or(OffsetDateTime::UNIX_EPOCH, |since| since.0);
+        let mut changed = vec![];
+        let mut removed = vec![];
+        let trees = std::iter::once((GENERATED_INDEX, SyncTree::Generated, None)).chain(
+            UpdateSource::ALL
+                .into_iter()
+                .map(|source| (source.name(), SyncTree::Upstream, Some(source.name()))),
+        );
+        for (index_name, tree, prefix) in trees {
+            let Some(index) = self.load_index(index_name)? else {
+                continue;
+            };
+            let path = |name: String| match prefix {
+                Some(prefix) => format!("{}/{}", prefix, name),
+                None => name,
+            };
+            for (name, checksum) in index.files {
+                cursor = cursor.max(checksum.indexed);
+                if after(checksum.indexed) {
+                    changed.push(ChangedFile {
+                        tree,
+                        path: path(name),
+                        sha256: checksum.sha256,
+                        size: checksum.size,
+                        modified: checksum.modified,
+                    });
+                }
+            }
+            for (name, file) in index.removed {
+                cursor = cursor.max(file.indexed);
+                // without a cursor the listing is complete, there is nothing to delete
+                if since.is_some() && after(file.indexed) {
+                    removed.push(RemovedPath {
+                        tree,
+                        path: path(name),
+                    });
+                }
+            }
+        }
+        Ok(SyncChanges {
+            cursor: SyncCursor(cursor).to_string(),
+            changed,
+            removed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tempdir::TempDir;
+    use time::OffsetDateTime;
+
+    use super::{RemovedPath, SyncCursor, SyncTree};
+    use crate::{app_config::StorageFormat, storage::UpdateSource};
+
+    fn write(path: &Path, contents: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_sync_cursor() {
+        let cursor = "1700000000123456789".parse::<SyncCursor>().unwrap();
+        assert_eq!(
+            cursor.0,
+            OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_456_789).unwrap()
+        );
+        assert_eq!(cursor.to_string(), "1700000000123456789");
+
+        let timestamp = "2023-11-14T23:13:20.5+01:00".parse::<SyncCursor>().unwrap();
+        assert_eq!(
+            timestamp.0,
+            OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_500_000_000).unwrap()
+        );
+        assert_eq!(timestamp.to_string(), "1700000000500000000");
+        assert_eq!(
+            timestamp.to_string().parse::<SyncCursor>().unwrap(),
+            timestamp
+        );
+
+        assert!("".parse::<SyncCursor>().is_err());
+        assert!("yesterday".parse::<SyncCursor>().is_err());
+        assert!("-5".parse::<SyncCursor>().is_err());
+        assert!("99999999999999999999999999999999999"
+            .parse::<SyncCursor>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_changes_since_pages_both_trees() {
+        let tmp_dir = TempDir::new("mcmeta_checksums").unwrap();
+        let meta_dir = tmp_dir.path().join("meta");
+        let generated_dir = tmp_dir.path().join("generated");
+        let storage_format = StorageFormat::Json {
+            meta_directory: meta_dir.to_string_lossy().into_owned(),
+            generated_directory: generated_dir.to_string_lossy().into_owned(),
+        };
+        write(&meta_dir.join("forge/derived_index.json"), "{}");
+        write(&meta_dir.join("forge/jars/installer.jar"), "jar");
+        write(&meta_dir.join("forge/installer_ledger.json"), "{}");
+        write(&generated_dir.join("index.json"), "{}");
+        write(&generated_dir.join("net.minecraft/1.20.json"), "{}");
+        storage_format.refresh_checksums_of(&[UpdateSource::Forge]);
+
+        let changes = storage_format.changes_since(None).unwrap();
+        let mut paths = changes
+            .changed
+            .iter()
+            .map(|file| (file.tree, file.path.as_str()))
+            .collect::<Vec<_>>();
+        paths.sort_by_key(|(_, path)| *path);
+        assert_eq!(
+            paths,
+            [
+                (SyncTree::Upstream, "forge/derived_index.json"),
+                (SyncTree::Generated, "index.json"),
+                (SyncTree::Generated, "net.minecraft/1.20.json"),
+            ]
+        );
+        assert!(changes.removed.is_empty());
+
+        write(&generated_dir.join("index.json"), "{\"packages\": []}");
+        write(&generated_dir.join("net.minecraft/1.21.json"), "{}");
+        std::fs::remove_file(generated_dir.join("net.minecraft/1.20.json")).unwrap();
+        storage_format.refresh_checksums_of(&[UpdateSource::Forge]);
+
+        let next = storage_format
+            .changes_since(Some(changes.cursor.parse().unwrap()))
+            .unwrap();
+        let mut paths = next
+            .changed
+            .iter()
+            .map(|file| (file.tree, file.path.as_str()))
+            .collect::<Vec<_>>();
+        paths.sort_by_key(|(_, path)| *path);
+        assert_eq!(
+            paths,
+            [
+                (SyncTree::Generated, "index.json"),
+                (SyncTree::Generated, "net.minecraft/1.21.json"),
+            ]
+        );
+        assert_eq!(
+            next.removed,
+            [RemovedPath {
+                tree: SyncTree::Generated,
+                path: "net.minecraft/1.20.json".to_string(),
+            }]
+        );
+
+        let last = storage_format
+            .changes_since(Some(next.cursor.parse().unwrap()))
+            .unwrap();
+        assert!(last.changed.is_empty());
+        assert!(last.removed.is_empty());
+        assert_eq!(last.cursor, next.cursor);
+    }
+}
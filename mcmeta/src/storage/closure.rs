@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Context, Result};
+use flate2::{write::GzEncoder, Compression};
+use libmcmeta::models::{Dependency, MetaVersion};
+
+use crate::storage::generated::GeneratedDataStorage;
+
+/// The components picked so far while resolving a closure
+#[derive(Default)]
+struct Closure {
+    /// the version picked for every uid, so a uid is only resolved once
+    picked: BTreeMap<String, String>,
+    /// resolved components, each after the components it requires
+    resolved: Vec<MetaVersion>,
+}
+
+impl GeneratedDataStorage {
+    /**
+     * Pick the version of a required component without an `equals`:
+     * its `suggests`, otherwise the newest recommended version, otherwise the newest version
+     */
+    fn pick_version(&self, dependency: &Dependency) -> Result<String> {
+        if let Some(suggests) = &dependency.suggests {
+            return Ok(suggests.clone());
+        }
+        let index = self
+            .load_version_index(&dependency.uid)?
+            .ok_or_else(|| anyhow!("Required component {} does not exist", dependency.uid))?;
+        index
+            .versions
+            .iter()
+            .find(|entry| entry.recommended)
+            .or_else(|| index.versions.first())
+            .map(|entry| entry.version.clone())
+            .ok_or_else(|| anyhow!("Required component {} has no versions", dependency.uid))
+    }
+
+    fn resolve(&self, closure: &mut Closure, uid: &str, version: &str) -> Result<bool> {
+        if let Some(picked) = closure.picked.get(uid) {
+            if picked == version {
+                return Ok(true);
+            }
+            return Err(anyhow!(
+                "Conflicting versions of {} are required: {} and {}",
+                uid,
+                picked,
+                version
+            ));
+        }
+        closure.picked.insert(uid.to_string(), version.to_string());
+
+        let Some(meta_version) = self.load_meta_version(uid, version)? else {
+            return Ok(false);
+        };
+
+        let requires = meta_version.requires.clone().unwrap_or_default();
+        // exact requirements first, so a suggestion never shadows a pinned version
+        let (exact, loose): (Vec<_>, Vec<_>) = requires
+            .into_iter()
+            .partition(|dependency| dependency.equals.is_some());
+        for dependency in exact.iter().chain(loose.iter()) {
+            let required_version = match &dependency.equals {
+                Some(equals) => equals.clone(),
+                None if closure.picked.contains_key(&dependency.uid) => continue,
+                None => self.pick_version(dependency)?,
+            };
+            if !self.resolve(closure, &dependency.uid, &required_version)? {
+                return Err(anyhow!(
+                    "{} {} requires {} {} which does not exist",
+                    uid,
+                    version,
+                    dependency.uid,
+                    required_version
+                ));
+            }
+        }
+
+        closure.resolved.push(meta_version);
+        Ok(true)
+    }
+
+    /**
+     * Resolve every component needed to launch a version by following its `requires` transitively,
+     * returned in the order they are applied, or `None` if the version does not exist
+     */
+    pub fn resolve_closure(&self, uid: &str, version: &str) -> Result<Option<Vec<MetaVersion>>> {
+        let mut closure = Closure::default();
+        if !self.resolve(&mut closure, uid, version)? {
+            return Ok(None);
+        }
+
+        // components apply by their order, ties keep dependencies before their dependents
+        let mut resolved = closure.resolved;
+        resolved.sort_by_key(|meta_version| meta_version.order.unwrap_or(0));
+        Ok(Some(resolved))
+    }
+
+    /**
+     * Pack the generated files of a version and every component it requires into a gzipped
+     * tarball, laid out as `<uid>/<version>.json` like the generated directory, or `None` if the
+     * version does not exist
+     */
+    pub fn closure_tarball(&self, uid: &str, version: &str) -> Result<Option<Vec<u8>>> {
+        let Some(closure) = self.resolve_closure(uid, version)? else {
+            return Ok(None);
+        };
+        let generated_dir = self.generated_dir()?;
+        let mut tarball = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+        for meta_version in &closure {
+            let name = std::path::Path::new(&meta_version.uid)
+                .join(format!("{}.json", meta_version.version));
+            tarball
+                .append_path_with_name(generated_dir.join(&name), &name)
+                .with_context(|| format!("Failure packing {}", name.display()))?;
+        }
+        Ok(Some(tarball.into_inner()?.finish()?))
+    }
+}
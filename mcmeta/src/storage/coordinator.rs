@@ -0,0 +1,477 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, Once},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::sync::{Mutex as AsyncMutex, MutexGuard as AsyncMutexGuard};
+use tracing::warn;
+
+use crate::{
+    download::errors::MetadataError,
+    storage::{StorageFormat, UpdateSource},
+    utils::write_json_atomic,
+};
+
+lazy_static! {
+    /// Serializes updates of each source, so scheduled and manually triggered updates never race
+    /// on the same files
+    pub static ref UPDATE_COORDINATOR: UpdateCoordinator = UpdateCoordinator::new();
+}
+
+/// What a source is doing right now
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SourceState {
+    Idle,
+    Running {
+        #[serde(with = "time::serde::iso8601")]
+        since: OffsetDateTime,
+    },
+}
+
+/// How the last update of a source ended
+#[derive(Serialize, Debug, Clone)]
+pub struct UpdateResult {
+    #[serde(with = "time::serde::iso8601")]
+    pub started: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601")]
+    pub finished: OffsetDateTime,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The generated versions an update added and changed
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VersionChanges {
+    pub added: usize,
+    pub updated: usize,
+}
+
+/// What the updates of a source did over time, kept in storage so it survives restarts
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct SourceHistory {
+    /// when the last successful update finished
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub last_success: Option<OffsetDateTime>,
+    /// how long the last successful update took, in seconds
+    #[serde(default)]
+    pub duration: f64,
+    /// generated versions the last successful update added
+    #[serde(default)]
+    pub versions_added: usize,
+    /// generated versions the last successful update changed
+    #[serde(default)]
+    pub versions_updated: usize,
+    /// why the last failed update failed, kept after later updates succeed
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub last_error_time: Option<OffsetDateTime>,
+}
+
+/// The history of every source, by source name
+pub type UpdateHistory = BTreeMap<String, SourceHistory>;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SourceStatus {
+    pub source: UpdateSource,
+    #[serde(flatten)]
+    pub state: SourceState,
+    pub last_result: Option<UpdateResult>,
+    pub history: SourceHistory,
+}
+
+#[derive(Debug, Clone)]
+struct SlotStatus {
+    state: SourceState,
+    last_result: Option<UpdateResult>,
+    history: SourceHistory,
+}
+
+struct SourceSlot {
+    lock: AsyncMutex<()>,
+    status: Mutex<SlotStatus>,
+}
+
+pub struct UpdateCoordinator {
+    sources: HashMap<UpdateSource, SourceSlot>,
+    /// held while the steps shared by every source run after an update, like rebuilding the
+    /// search index and publishing
+    finishing: AsyncMutex<()>,
+    /// held while a source updates the generated metadata, from the version hashes taken before
+    /// until those taken after, so changes of another source are never attributed to it
+    generating: AsyncMutex<()>,
+    history_restored: Once,
+    /// held while the history is written, so a stale snapshot never overwrites a newer one
+    storing_history: Mutex<()>,
+}
+
+/// An update of a source holding its lock, recording how it ended when dropped
+pub struct RunningUpdate<'a> {
+    slot: &'a SourceSlot,
+    started: OffsetDateTime,
+    result: Option<UpdateResult>,
+    _lock: AsyncMutexGuard<'a, ()>,
+}
+
+/// The locks of every source, keeping any update from running while held
+pub struct UpdatesLocked<'a> {
+    _sources: Vec<AsyncMutexGuard<'a, ()>>,
+    _finishing: AsyncMutexGuard<'a, ()>,
+}
+
+impl UpdateCoordinator {
+    fn new() -> Self {
+        Self {
+            sources: UpdateSource::ALL
+                .into_iter()
+                .map(|source| {
+                    (
+                        source,
+                        SourceSlot {
+                            lock: AsyncMutex::new(()),
+                            status: Mutex::new(SlotStatus {
+                                state: SourceState::Idle,
+                                last_result: None,
+                                history: SourceHistory::default(),
+                            }),
+                        },
+                    )
+                })
+                .collect(),
+            finishing: AsyncMutex::new(()),
+            generating: AsyncMutex::new(()),
+            history_restored: Once::new(),
+            storing_history: Mutex::new(()),
+        }
+    }
+
+    fn slot(&self, source: UpdateSource) -> &SourceSlot {
+        &self.sources[&source]
+    }
+
+    /**
+     * Wait for any running update of a source to finish, then mark it as running until the
+     * returned update is dropped
+     */
+    pub async fn begin(&self, source: UpdateSource) -> RunningUpdate<'_> {
+        let slot = self.slot(source);
+        let lock = slot.lock.lock().await;
+        let started = OffsetDateTime::now_utc();
+        set_status(slot, |status| {
+            status.state = SourceState::Running { since: started }
+        });
+        RunningUpdate {
+            slot,
+            started,
+            result: None,
+            _lock: lock,
+        }
+    }
+
+    /**
+     * Whether a source is being updated, or otherwise locked, right now
+     */
+    pub fn is_busy(&self, source: UpdateSource) -> bool {
+        self.slot(source).lock.try_lock().is_err()
+    }
+
+    /**
+     * Wait for updates of other sources to finish writing the generated metadata
+     */
+    pub async fn lock_generating(&self) -> AsyncMutexGuard<'_, ()> {
+        self.generating.lock().await
+    }
+
+    /**
+     * Wait for other updates to finish the steps shared by every source
+     */
+    pub async fn lock_finishing(&self) -> AsyncMutexGuard<'_, ()> {
+        self.finishing.lock().await
+    }
+
+    /**
+     * Wait for every running update to finish and keep new ones from starting while the returned
+     * locks are held. Sources are locked in the same order updates go through them.
+     */
+    pub async fn lock_all(&self) -> UpdatesLocked<'_> {
+        let mut sources = vec![];
+        for source in UpdateSource::ALL {
+            sources.push(self.slot(source).lock.lock().await);
+        }
+        UpdatesLocked {
+            _sources: sources,
+            _finishing: self.finishing.lock().await,
+        }
+    }
+
+    /**
+     * The state, last update result and history of every source
+     */
+    pub fn status(&self) -> Vec<SourceStatus> {
+        UpdateSource::ALL
+            .into_iter()
+            .map(|source| {
+                let status = slot_status(self.slot(source));
+                SourceStatus {
+                    source,
+                    state: status.state,
+                    last_result: status.last_result,
+                    history: status.history,
+                }
+            })
+            .collect()
+    }
+
+    /**
+     * Load the history of every source from storage, once per process. A missing or unreadable
+     * history starts over empty.
+     */
+    pub fn restore_history(&self, storage_format: &StorageFormat) {
+        self.history_restored
+            .call_once(|| match storage_format.load_update_history() {
+                Ok(history) => {
+                    for (name, source_history) in history.unwrap_or_default() {
+                        if let Some(source) = UpdateSource::from_name(&name) {
+                            set_status(self.slot(source), |status| status.history = source_history);
+                        }
+                    }
+                }
+                Err(err) => warn!("Failure loading the update history: {:#}", err),
+            });
+    }
+
+    /**
+     * Write the history of every source to storage
+     */
+    pub fn store_history(&self, storage_format: &StorageFormat) -> Result<()> {
+        let _storing = self
+            .storing_history
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let history: UpdateHistory = UpdateSource::ALL
+            .into_iter()
+            .map(|source| {
+                (
+                    source.name().to_string(),
+                    slot_status(self.slot(source)).history,
+                )
+            })
+            .collect();
+        storage_format.store_update_history(&history)
+    }
+}
+
+fn slot_status(slot: &SourceSlot) -> SlotStatus {
+    match slot.status.lock() {
+        Ok(status) => status.clone(),
+        Err(err) => err.into_inner().clone(),
+    }
+}
+
+fn set_status(slot: &SourceSlot, update: impl FnOnce(&mut SlotStatus)) {
+    match slot.status.lock() {
+        Ok(mut status) => update(&mut status),
+        Err(err) => warn!("Update status lock poisoned: {}", err),
+    }
+}
+
+impl RunningUpdate<'_> {
+    /**
+     * Record how the update ended, along with the generated versions it changed if they are known
+     */
+    pub fn finish(mut self, result: &Result<()>, changes: Option<VersionChanges>) {
+        let finished = OffsetDateTime::now_utc();
+        let error = result.as_ref().err().map(|err| format!("{:#}", err));
+        let started = self.started;
+        set_status(self.slot, |status| {
+            let history = &mut status.history;
+            match &error {
+                None => {
+                    let changes = changes.unwrap_or_default();
+                    history.last_success = Some(finished);
+                    history.duration = (finished - started).as_seconds_f64();
+                    history.versions_added = changes.added;
+                    history.versions_updated = changes.updated;
+                }
+                Some(error) => {
+                    history.last_error = Some(error.clone());
+                    history.last_error_time = Some(finished);
+                }
+            }
+        });
+        self.result = Some(UpdateResult {
+            started,
+            finished,
+            success: error.is_none(),
+            error,
+        });
+    }
+}
+
+impl Drop for RunningUpdate<'_> {
+    fn drop(&mut self) {
+        // an update dropped without finishing was cancelled, e.g. on shutdown, or panicked
+        let result = self.result.take().unwrap_or_else(|| UpdateResult {
+            started: self.started,
+            finished: OffsetDateTime::now_utc(),
+            success: false,
+            error: Some("Update was interrupted".to_string()),
+        });
+        set_status(self.slot, |status| {
+            status.state = SourceState::Idle;
+            status.last_result = Some(result);
+        });
+    }
+}
+
+impl StorageFormat {
+    pub fn update_history_file(&self) -> Result<std::path::PathBuf> {
+        match self {
+            StorageFormat::Json {
+                meta_directory,
+                generated_directory: _,
+            } => Ok(std::path::Path::new(meta_directory).join("update_history.json")),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn load_update_history(&self) -> Result<Option<UpdateHistory>> {
+        match self {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let history_file = self.update_history_file()?;
+                if history_file.is_file() {
+                    let body = std::fs::read_to_string(&history_file).with_context(|| {
+                        format!("Failure reading file {}", history_file.to_string_lossy())
+                    })?;
+                    let history = serde_json::from_str::<UpdateHistory>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
+                    Ok(Some(history))
+                } else {
+                    Ok(None)
+                }
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn store_update_history(&self, history: &UpdateHistory) -> Result<()> {
+        match self {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let history_file = self.update_history_file()?;
+                write_json_atomic(&history_file, history).with_context(|| {
+                    format!("Failure writing file {}", history_file.to_string_lossy())
+                })
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::anyhow;
+
+    use super::{SourceState, SourceStatus, UpdateCoordinator, VersionChanges};
+    use crate::storage::UpdateSource;
+
+    fn status(coordinator: &UpdateCoordinator, source: UpdateSource) -> SourceStatus {
+        coordinator
+            .status()
+            .into_iter()
+            .find(|status| status.source == source)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_begin_serializes_updates_of_a_source() {
+        let coordinator = UpdateCoordinator::new();
+        let update = coordinator.begin(UpdateSource::Forge).await;
+        assert!(coordinator.is_busy(UpdateSource::Forge));
+        assert!(!coordinator.is_busy(UpdateSource::Mojang));
+        assert!(matches!(
+            status(&coordinator, UpdateSource::Forge).state,
+            SourceState::Running { .. }
+        ));
+
+        // another source runs alongside, the same source waits
+        drop(coordinator.begin(UpdateSource::Mojang).await);
+        assert!(tokio::time::timeout(
+            Duration::from_millis(50),
+            coordinator.begin(UpdateSource::Forge)
+        )
+        .await
+        .is_err());
+
+        update.finish(
+            &Ok(()),
+            Some(VersionChanges {
+                added: 2,
+                updated: 1,
+            }),
+        );
+        assert!(!coordinator.is_busy(UpdateSource::Forge));
+        let status = status(&coordinator, UpdateSource::Forge);
+        assert!(matches!(status.state, SourceState::Idle));
+        assert!(status.last_result.unwrap().success);
+        assert_eq!(status.history.versions_added, 2);
+        assert_eq!(status.history.versions_updated, 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_and_interrupted_updates() {
+        let coordinator = UpdateCoordinator::new();
+        coordinator
+            .begin(UpdateSource::Fabric)
+            .await
+            .finish(&Err(anyhow!("upstream is down")), None);
+        let failed = status(&coordinator, UpdateSource::Fabric);
+        assert_eq!(
+            failed.last_result.as_ref().unwrap().error.as_deref(),
+            Some("upstream is down")
+        );
+        assert_eq!(
+            failed.history.last_error.as_deref(),
+            Some("upstream is down")
+        );
+        assert!(failed.history.last_success.is_none());
+
+        drop(coordinator.begin(UpdateSource::Fabric).await);
+        let result = status(&coordinator, UpdateSource::Fabric)
+            .last_result
+            .unwrap();
+        assert!(!result.success);
+        assert_eq!(result.error.as_deref(), Some("Update was interrupted"));
+    }
+
+    #[tokio::test]
+    async fn test_generating_and_lock_all_exclude_updates() {
+        let coordinator = UpdateCoordinator::new();
+        let generating = coordinator.lock_generating().await;
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), coordinator.lock_generating())
+                .await
+                .is_err()
+        );
+        drop(generating);
+
+        let locked = coordinator.lock_all().await;
+        for source in UpdateSource::ALL {
+            assert!(coordinator.is_busy(source));
+        }
+        drop(locked);
+        assert!(!coordinator.is_busy(UpdateSource::Java));
+    }
+}
@@ -0,0 +1,452 @@
+use std::sync::Arc;
+
+use futures::{stream, StreamExt};
+use libmcmeta::models::{
+    fabric::{
+        FabricInstallerDataV1, FabricIntermediaryVersion, FabricJarInfo, FabricLoaderVersion,
+    },
+    GradleSpecifier,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tempdir::TempDir;
+use tracing::{debug, info, info_span, warn, Instrument};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{
+    download::{self, errors::MetadataError, fabric::FabricMetaServer},
+    storage::{
+        generated::GeneratedDataStorage,
+        validation::{json_file_stems, MetadataValidationReport},
+        StorageFormat, UpdateReport, UpdateSource, UpstreamMetadataUpdater,
+    },
+    utils::{filehash, latest_zip_timestamp, write_json_atomic, HashAlgo},
+};
+
+/// The fabric-meta compatible servers we mirror
+#[derive(Clone, Copy, Debug)]
+pub enum FabricSource {
+    Fabric,
+    Babric,
+    LegacyFabric,
+    Ornithe,
+}
+
+impl FabricSource {
+    pub const ALL: [FabricSource; 4] = [
+        FabricSource::Fabric,
+        FabricSource::Babric,
+        FabricSource::LegacyFabric,
+        FabricSource::Ornithe,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FabricSource::Fabric => "Fabric",
+            FabricSource::Babric => "Babric",
+            FabricSource::LegacyFabric => "Legacy Fabric",
+            FabricSource::Ornithe => "Ornithe",
+        }
+    }
+
+    fn dir_name(&self) -> &'static str {
+        match self {
+            FabricSource::Fabric => "fabric",
+            FabricSource::Babric => "babric",
+            FabricSource::LegacyFabric => "legacyfabric",
+            FabricSource::Ornithe => "ornithe",
+        }
+    }
+
+    pub fn update_source(&self) -> UpdateSource {
+        match self {
+            FabricSource::Fabric => UpdateSource::Fabric,
+            FabricSource::Babric => UpdateSource::Babric,
+            FabricSource::LegacyFabric => UpdateSource::LegacyFabric,
+            FabricSource::Ornithe => UpdateSource::Ornithe,
+        }
+    }
+
+    pub fn server(&self) -> Result<FabricMetaServer> {
+        match self {
+            FabricSource::Fabric => download::fabric::server(),
+            FabricSource::Babric => download::babric::server(),
+            FabricSource::LegacyFabric => download::legacyfabric::server(),
+            FabricSource::Ornithe => download::ornithe::server(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FabricDataStorage {
+    storage_format: Arc<StorageFormat>,
+    source: FabricSource,
+}
+
+impl FabricDataStorage {
+    pub fn new(storage_format: Arc<StorageFormat>, source: FabricSource) -> Self {
+        Self {
+            storage_format,
+            source,
+        }
+    }
+
+    pub fn meta_dir(&self) -> Result<std::path::PathBuf> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                ref meta_directory,
+                generated_directory: _,
+            } => {
+                let metadata_dir = std::path::Path::new(&meta_directory);
+                let source_meta_dir = metadata_dir.join(self.source.dir_name());
+
+                if !source_meta_dir.exists() {
+                    info!(
+                        "{} metadata directory at {} does not exist, creating it",
+                        self.source.name(),
+                        source_meta_dir.display()
+                    );
+                    std::fs::create_dir_all(&source_meta_dir)?;
+                }
+                Ok(source_meta_dir)
+            }
+            StorageFormat::Database => Err(anyhow!("Wrong storage format")),
+        }
+    }
+
+    fn sub_dir(&self, name: &str) -> Result<std::path::PathBuf> {
+        let dir = self.meta_dir()?.join(name);
+        if !dir.is_dir() {
+            info!(
+                "{} {} directory at {} does not exist, creating it",
+                self.source.name(),
+                name,
+                dir.display()
+            );
+            std::fs::create_dir_all(&dir)?;
+        }
+        Ok(dir)
+    }
+
+    pub fn installer_data_dir(&self) -> Result<std::path::PathBuf> {
+        self.sub_dir("loader-installer-json")
+    }
+
+    pub fn jar_info_dir(&self) -> Result<std::path::PathBuf> {
+        self.sub_dir("jars")
+    }
+
+    fn load_json<T: DeserializeOwned>(&self, file: &std::path::Path) -> Result<Option<T>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                if file.is_file() {
+                    let body = std::fs::read_to_string(file).with_context(|| {
+                        format!("Failure reading file {}", file.to_string_lossy())
+                    })?;
+                    let value = serde_json::from_str::<T>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
+                    Ok(Some(value))
+                } else {
+                    Ok(None)
+                }
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    fn store_json<T: Serialize>(&self, file: &std::path::Path, value: &T) -> Result<()> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                write_json_atomic(file, value)?;
+                Ok(())
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn load_loader_versions(&self) -> Result<Option<Vec<FabricLoaderVersion>>> {
+        self.load_json(&self.meta_dir()?.join("loader.json"))
+    }
+
+    pub fn store_loader_versions(&self, versions: &Vec<FabricLoaderVersion>) -> Result<()> {
+        self.store_json(&self.meta_dir()?.join("loader.json"), versions)
+    }
+
+    pub fn load_intermediary_versions(&self) -> Result<Option<Vec<FabricIntermediaryVersion>>> {
+        self.load_json(&self.meta_dir()?.join("intermediary.json"))
+    }
+
+    pub fn store_intermediary_versions(
+        &self,
+        versions: &Vec<FabricIntermediaryVersion>,
+    ) -> Result<()> {
+        self.store_json(&self.meta_dir()?.join("intermediary.json"), versions)
+    }
+
+    pub fn load_installer_data(&self, version: &str) -> Result<Option<FabricInstallerDataV1>> {
+        self.load_json(&self.installer_data_dir()?.join(format!("{}.json", version)))
+    }
+
+    pub fn store_installer_data(
+        &self,
+        version: &str,
+        installer_data: &FabricInstallerDataV1,
+    ) -> Result<()> {
+        self.store_json(
+            &self.installer_data_dir()?.join(format!("{}.json", version)),
+            installer_data,
+        )
+    }
+
+    pub fn load_jar_info(&self, specifier: &GradleSpecifier) -> Result<Option<FabricJarInfo>> {
+        self.load_json(
+            &self
+                .jar_info_dir()?
+                .join(format!("{}.json", specifier.filename())),
+        )
+    }
+
+    pub fn store_jar_info(
+        &self,
+        specifier: &GradleSpecifier,
+        jar_info: &FabricJarInfo,
+    ) -> Result<()> {
+        self.store_json(
+            &self
+                .jar_info_dir()?
+                .join(format!("{}.json", specifier.filename())),
+            jar_info,
+        )
+    }
+
+    pub fn validate_stored(&self, report: &mut MetadataValidationReport) -> Result<()> {
+        let meta_dir = self.meta_dir()?;
+        report.check_parse(&meta_dir.join("loader.json"), self.load_loader_versions());
+        report.check_parse(
+            &meta_dir.join("intermediary.json"),
+            self.load_intermediary_versions(),
+        );
+
+        let installer_data_dir = self.installer_data_dir()?;
+        for version in json_file_stems(&installer_data_dir)? {
+            report.check(
+                &installer_data_dir.join(format!("{}.json", version)),
+                self.load_installer_data(&version),
+            );
+        }
+        Ok(())
+    }
+
+    /**
+     * Generate the loader and intermediary components from the stored metadata of the source
+     */
+    pub fn generate(&self) -> Result<bool> {
+        let (Some(loader_versions), Some(intermediary_versions)) = (
+            self.load_loader_versions()?,
+            self.load_intermediary_versions()?,
+        ) else {
+            return Ok(false);
+        };
+        let components = self.source.server()?.components;
+
+        let mut loaders = vec![];
+        for loader in &loader_versions {
+            let (Some(installer_data), Some(jar_info)) = (
+                self.load_installer_data(&loader.version)?,
+                self.load_jar_info(&loader.maven)?,
+            ) else {
+                warn!(
+                    "Skipping {} loader {}, its metadata was not fetched",
+                    self.source.name(),
+                    loader.version
+                );
+                continue;
+            };
+            loaders.push((
+                components.loader_meta_version(loader, &installer_data, &jar_info),
+                loader.stable,
+            ));
+        }
+
+        let mut intermediaries = vec![];
+        for intermediary in &intermediary_versions {
+            let Some(jar_info) = self.load_jar_info(&intermediary.maven)? else {
+                warn!(
+                    "Skipping {} intermediary {}, its jar was not fetched",
+                    self.source.name(),
+                    intermediary.version
+                );
+                continue;
+            };
+            intermediaries.push((
+                components.intermediary_meta_version(intermediary, &jar_info),
+                intermediary.stable,
+            ));
+        }
+
+        let generated_storage = GeneratedDataStorage::new(self.storage_format.clone());
+        generated_storage.store_package(
+            &components.loader_uid,
+            &components.loader_name,
+            loaders,
+        )?;
+        generated_storage.store_package(
+            &components.intermediary_uid,
+            &components.intermediary_name,
+            intermediaries,
+        )?;
+        Ok(true)
+    }
+}
+
+impl UpstreamMetadataUpdater {
+    pub async fn update_upstream_fabric(&self, report: &mut UpdateReport) -> Result<()> {
+        self.update_upstream_fabric_source(FabricSource::Fabric, report)
+            .await
+    }
+
+    pub async fn update_upstream_babric(&self, report: &mut UpdateReport) -> Result<()> {
+        self.update_upstream_fabric_source(FabricSource::Babric, report)
+            .await
+    }
+
+    pub async fn update_upstream_legacy_fabric(&self, report: &mut UpdateReport) -> Result<()> {
+        self.update_upstream_fabric_source(FabricSource::LegacyFabric, report)
+            .await
+    }
+
+    pub async fn update_upstream_ornithe(&self, report: &mut UpdateReport) -> Result<()> {
+        self.update_upstream_fabric_source(FabricSource::Ornithe, report)
+            .await
+    }
+
+    async fn update_upstream_fabric_source(
+        &self,
+        source: FabricSource,
+        report: &mut UpdateReport,
+    ) -> Result<()> {
+        info!("Checking for {} metadata", source.name());
+
+        self.update_fabric_metadata(source, report)
+            .await
+            .with_context(|| format!("Failed to update {} metadata.", source.name()))?;
+
+        let local_storage = FabricDataStorage::new(self.storage_format.clone(), source);
+        tokio::task::spawn_blocking(move || local_storage.generate())
+            .await?
+            .with_context(|| format!("Failed to generate {} metadata.", source.name()))?;
+        Ok(())
+    }
+
+    async fn update_fabric_metadata(
+        &self,
+        source: FabricSource,
+        report: &mut UpdateReport,
+    ) -> Result<()> {
+        let local_storage = FabricDataStorage::new(self.storage_format.clone(), source);
+        let server = source.server()?;
+        let components = &server.components;
+
+        info!("Acquiring remote {} metadata", source.name());
+        let loader_versions = server.load_loader_versions().await?;
+        let intermediary_versions = server.load_intermediary_versions().await?;
+        // every artifact not fetched yet, with the uid it is generated under
+        let mut pending: Vec<(String, String, GradleSpecifier)> = vec![];
+        for loader in &loader_versions {
+            if local_storage.load_jar_info(&loader.maven)?.is_none()
+                || local_storage
+                    .load_installer_data(&loader.version)?
+                    .is_none()
+            {
+                pending.push((
+                    components.loader_uid.clone(),
+                    loader.version.clone(),
+                    loader.maven.clone(),
+                ));
+            }
+        }
+        for intermediary in &intermediary_versions {
+            if local_storage.load_jar_info(&intermediary.maven)?.is_none() {
+                pending.push((
+                    components.intermediary_uid.clone(),
+                    intermediary.version.clone(),
+                    intermediary.maven.clone(),
+                ));
+            }
+        }
+
+        let tasks = stream::iter(pending)
+            .map(|(uid, version, specifier)| {
+                let ls = local_storage.clone();
+                let server = server.clone();
+                let is_loader = uid == components.loader_uid;
+                let key = (uid.clone(), version.clone());
+                let span = info_span!("version", uid = %uid, version = %version);
+                let task = tokio::spawn(
+                    async move {
+                        update_fabric_artifact(&ls, &server, &version, &specifier, is_loader)
+                            .await
+                            .with_context(|| format!("Failed to update {} {}", uid, version))
+                            .map(|_| uid)
+                    }
+                    .instrument(span),
+                );
+                async move { (key.0, key.1, task.await) }
+            })
+            .buffer_unordered(self.metadata_cfg.parallelism(source.update_source()));
+        let results = tasks.collect::<Vec<_>>().await;
+        for uid in self.collect_version_results(results, report)? {
+            report.record_updated_versions(&uid, 1);
+        }
+
+        local_storage.store_loader_versions(&loader_versions)?;
+        local_storage.store_intermediary_versions(&intermediary_versions)?;
+        Ok(())
+    }
+}
+
+/**
+ * Fetch the installer data of a loader and inspect the jar of a loader or intermediary artifact
+ */
+async fn update_fabric_artifact(
+    local_storage: &FabricDataStorage,
+    server: &FabricMetaServer,
+    version: &str,
+    specifier: &GradleSpecifier,
+    is_loader: bool,
+) -> Result<()> {
+    if is_loader && local_storage.load_installer_data(version)?.is_none() {
+        let installer_data = server.load_installer_data(specifier).await?;
+        local_storage.store_installer_data(version, &installer_data)?;
+    }
+
+    if local_storage.load_jar_info(specifier)?.is_none() {
+        let url = if is_loader {
+            server.loader_artifact_url(specifier)
+        } else {
+            server.maven_artifact_url(specifier)
+        };
+        let tmp_dir = TempDir::new("mcmeta_fabric_jar")?;
+        let jar_path = tmp_dir.path().join(specifier.filename());
+
+        debug!("Downloading jar from {}", &url);
+        download::download_binary_file(&jar_path, &url)
+            .await
+            .with_context(|| format!("Failure downloading {}", &url))?;
+
+        let jar_info = FabricJarInfo {
+            release_time: Some(latest_zip_timestamp(&jar_path)?),
+            sha1: Some(filehash(&jar_path, HashAlgo::Sha1)?),
+            sha256: Some(filehash(&jar_path, HashAlgo::Sha256)?),
+            size: Some(jar_path.metadata()?.len()),
+        };
+        local_storage.store_jar_info(specifier, &jar_info)?;
+    }
+    Ok(())
+}
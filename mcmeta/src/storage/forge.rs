@@ -2,24 +2,88 @@ use std::sync::Arc;
 
 use anyhow::{anyhow, Context, Result};
 use futures::{stream, StreamExt};
-use std::collections::{BTreeMap, HashSet};
-use tracing::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tracing::{debug, info, info_span, warn, Instrument};
 
 use crate::{
-    download,
-    storage::{StorageFormat, UpstreamMetadataUpdater},
-    utils::{filehash, hash, process_results, process_results_ok, HashAlgo},
+    download::{
+        self,
+        conditional::{Conditional, UpstreamValidators},
+        errors::MetadataError,
+    },
+    storage::{
+        generated::GeneratedDataStorage,
+        mojang::MojangDataStorage,
+        quarantine::Quarantine,
+        validation::{json_file_stems, MetadataValidationReport},
+        StorageFormat, UpdateReport, UpdateSource, UpstreamMetadataUpdater,
+    },
+    utils::{filehash, hash, latest_zip_timestamp, write_json_atomic, HashAlgo},
 };
 use libmcmeta::models::forge::{
     DerivedForgeIndex, ForgeEntry, ForgeFile, ForgeInstallerProfile, ForgeLegacyInfo,
     ForgeLegacyInfoList, ForgeMCVersionInfo, ForgeMavenMetadata, ForgeMavenPromotions,
-    ForgeProcessedVersion, ForgeVersionMeta, InstallerInfo,
+    ForgeMcpIndex, ForgeProcessedVersion, ForgeVersionMeta, ForgeWrapper, InstallerInfo,
 };
 use libmcmeta::models::mojang::MojangVersion;
-use libmcmeta::models::MetaMcIndexEntry;
+use libmcmeta::models::{GradleSpecifier, MetaMcIndexEntry};
+
+/// How processing the installer (or jar mod) of a Forge build went
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallerState {
+    /// its metadata was extracted, it is not processed again
+    Processed,
+    /// processing it failed, it is retried on the next update
+    Failed,
+    /// it cannot be generated, it is not processed again
+    Unsupported,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct InstallerLedgerEntry {
+    pub state: InstallerState,
+    #[serde(with = "time::serde::iso8601")]
+    pub update_time: time::OffsetDateTime,
+    /// why processing it failed last
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The state of every Forge build whose installer was processed, by long version
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct InstallerLedger {
+    pub versions: BTreeMap<String, InstallerLedgerEntry>,
+}
+
+impl InstallerLedger {
+    /**
+     * Whether a build needs processing: it was never processed or failed last time
+     */
+    pub fn is_pending(&self, long_version: &str) -> bool {
+        self.versions
+            .get(long_version)
+            .is_none_or(|entry| entry.state == InstallerState::Failed)
+    }
+
+    pub fn record(&mut self, long_version: &str, state: InstallerState, error: Option<String>) {
+        self.versions.insert(
+            long_version.to_string(),
+            InstallerLedgerEntry {
+                state,
+                update_time: time::OffsetDateTime::now_utc(),
+                error,
+            },
+        );
+    }
 
-lazy_static! {
-    pub static ref BAD_FORGE_VERSIONS: Vec<&'static str> = vec!["1.12.2-14.23.5.2851"];
+    pub fn count(&self, state: InstallerState) -> usize {
+        self.versions
+            .values()
+            .filter(|entry| entry.state == state)
+            .count()
+    }
 }
 
 #[derive(Clone)]
@@ -28,6 +92,10 @@ pub struct ForgeDataStorage {
 }
 
 impl ForgeDataStorage {
+    pub fn new(storage_format: Arc<StorageFormat>) -> Self {
+        Self { storage_format }
+    }
+
     pub fn meta_dir(&self) -> Result<std::path::PathBuf> {
         match *self.storage_format {
             StorageFormat::Json {
@@ -79,14 +147,15 @@ impl ForgeDataStorage {
             } => {
                 let maven_metadata_file = self.meta_dir()?.join("maven-metadata.json");
                 if maven_metadata_file.is_file() {
-                    let metadata = serde_json::from_str::<ForgeMavenMetadata>(
-                        &std::fs::read_to_string(&maven_metadata_file).with_context(|| {
+                    let body =
+                        std::fs::read_to_string(&maven_metadata_file).with_context(|| {
                             format!(
                                 "Failure reading from file {}",
                                 &maven_metadata_file.to_string_lossy()
                             )
-                        })?,
-                    )?;
+                        })?;
+                    let metadata = serde_json::from_str::<ForgeMavenMetadata>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
                     Ok(Some(metadata))
                 } else {
                     Ok(None)
@@ -103,19 +172,33 @@ impl ForgeDataStorage {
                 generated_directory: _,
             } => {
                 let maven_metadata_file = self.meta_dir()?.join("maven-metadata.json");
-                let maven_metadata_json = serde_json::to_string_pretty(&metadata)?;
-                std::fs::write(&maven_metadata_file, maven_metadata_json).with_context(|| {
-                    format!(
-                        "Failure writing to file {}",
-                        &maven_metadata_file.to_string_lossy()
-                    )
-                })?;
+                write_json_atomic(&maven_metadata_file, &metadata)?;
                 Ok(())
             }
             StorageFormat::Database => todo!(),
         }
     }
 
+    pub fn load_maven_metadata_validators(&self) -> Result<Option<UpstreamValidators>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => UpstreamValidators::load(&self.meta_dir()?.join("maven-metadata.json")),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn store_maven_metadata_validators(&self, validators: &UpstreamValidators) -> Result<()> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => validators.store(&self.meta_dir()?.join("maven-metadata.json")),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
     pub fn load_forge_promotions(&self) -> Result<Option<ForgeMavenPromotions>> {
         match *self.storage_format {
             StorageFormat::Json {
@@ -124,14 +207,15 @@ impl ForgeDataStorage {
             } => {
                 let promotions_metadata_file = self.meta_dir()?.join("promotions_slim.json");
                 if promotions_metadata_file.is_file() {
-                    let promotions = serde_json::from_str::<ForgeMavenPromotions>(
-                        &std::fs::read_to_string(&promotions_metadata_file).with_context(|| {
+                    let body =
+                        std::fs::read_to_string(&promotions_metadata_file).with_context(|| {
                             format!(
                                 "Failure reading from file {}",
                                 &promotions_metadata_file.to_string_lossy()
                             )
-                        })?,
-                    )?;
+                        })?;
+                    let promotions = serde_json::from_str::<ForgeMavenPromotions>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
                     Ok(Some(promotions))
                 } else {
                     Ok(None)
@@ -148,15 +232,7 @@ impl ForgeDataStorage {
                 generated_directory: _,
             } => {
                 let promotions_metadata_file = self.meta_dir()?.join("promotions_slim.json");
-                let promotions_metadata_json = serde_json::to_string_pretty(&promotions)?;
-                std::fs::write(&promotions_metadata_file, promotions_metadata_json).with_context(
-                    || {
-                        format!(
-                            "Failure writing to file {}",
-                            &promotions_metadata_file.to_string_lossy()
-                        )
-                    },
-                )?;
+                write_json_atomic(&promotions_metadata_file, &promotions)?;
 
                 Ok(())
             }
@@ -172,14 +248,14 @@ impl ForgeDataStorage {
             } => {
                 let derived_index_file = self.meta_dir()?.join("derived_index.json");
                 if derived_index_file.is_file() {
-                    let index = serde_json::from_str::<DerivedForgeIndex>(
-                        &std::fs::read_to_string(&derived_index_file).with_context(|| {
-                            format!(
-                                "Failure reading from file {}",
-                                &derived_index_file.to_string_lossy()
-                            )
-                        })?,
-                    )?;
+                    let body = std::fs::read_to_string(&derived_index_file).with_context(|| {
+                        format!(
+                            "Failure reading from file {}",
+                            &derived_index_file.to_string_lossy()
+                        )
+                    })?;
+                    let index = serde_json::from_str::<DerivedForgeIndex>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
                     Ok(Some(index))
                 } else {
                     Ok(None)
@@ -196,15 +272,7 @@ impl ForgeDataStorage {
                 generated_directory: _,
             } => {
                 let local_derived_index_file = self.meta_dir()?.join("derived_index.json");
-                let derived_index_json = serde_json::to_string_pretty(&index)?;
-                std::fs::write(&local_derived_index_file, derived_index_json).with_context(
-                    || {
-                        format!(
-                            "Failure writing to file {}",
-                            &local_derived_index_file.to_string_lossy()
-                        )
-                    },
-                )?;
+                write_json_atomic(&local_derived_index_file, &index)?;
                 Ok(())
             }
             StorageFormat::Database => todo!(),
@@ -244,11 +312,13 @@ impl ForgeDataStorage {
             } => {
                 let last_index_path = self.meta_dir()?.join("derived_index.last_index.json");
                 if last_index_path.is_file() {
-                    Ok(Some(serde_json::from_str::<MetaMcIndexEntry>(
-                        &std::fs::read_to_string(&last_index_path).with_context(|| {
-                            format!("Failure opening {}", &last_index_path.to_string_lossy())
-                        })?,
-                    )?))
+                    let body = std::fs::read_to_string(&last_index_path).with_context(|| {
+                        format!("Failure opening {}", &last_index_path.to_string_lossy())
+                    })?;
+                    Ok(Some(
+                        serde_json::from_str::<MetaMcIndexEntry>(&body)
+                            .map_err(|err| MetadataError::from_json_err(err, &body))?,
+                    ))
                 } else {
                     Ok(None)
                 }
@@ -267,19 +337,104 @@ impl ForgeDataStorage {
                 let derived_index_file = self.meta_dir()?.join("derived_index.json");
                 let last_index_path = self.meta_dir()?.join("derived_index.last_index.json");
                 entry.path = derived_index_file.to_string_lossy().to_string();
-                let last_index_json = serde_json::to_string_pretty(&entry)?;
-                std::fs::write(&last_index_path, last_index_json).with_context(|| {
-                    format!(
-                        "Failure writing to file {}",
-                        &last_index_path.to_string_lossy()
-                    )
-                })?
+                write_json_atomic(&last_index_path, &entry)?
+            }
+            StorageFormat::Database => todo!(),
+        }
+        Ok(())
+    }
+
+    pub fn installer_ledger_file(&self) -> Result<std::path::PathBuf> {
+        Ok(self.meta_dir()?.join("installer_ledger.json"))
+    }
+
+    pub fn load_installer_ledger(&self) -> Result<Option<InstallerLedger>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let ledger_file = self.installer_ledger_file()?;
+                if ledger_file.is_file() {
+                    let body = std::fs::read_to_string(&ledger_file).with_context(|| {
+                        format!("Failure opening {}", &ledger_file.to_string_lossy())
+                    })?;
+                    Ok(Some(
+                        serde_json::from_str::<InstallerLedger>(&body)
+                            .map_err(|err| MetadataError::from_json_err(err, &body))?,
+                    ))
+                } else {
+                    Ok(None)
+                }
             }
             StorageFormat::Database => todo!(),
         }
+    }
+
+    pub fn store_installer_ledger(&self, ledger: &InstallerLedger) -> Result<()> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => write_json_atomic(&self.installer_ledger_file()?, ledger)?,
+            StorageFormat::Database => todo!(),
+        }
         Ok(())
     }
 
+    pub fn mcp_index_file(&self) -> Result<std::path::PathBuf> {
+        Ok(self.meta_dir()?.join("mcp_index.json"))
+    }
+
+    pub fn load_mcp_index(&self) -> Result<Option<ForgeMcpIndex>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let mcp_index_file = self.mcp_index_file()?;
+                if mcp_index_file.is_file() {
+                    let body = std::fs::read_to_string(&mcp_index_file).with_context(|| {
+                        format!("Failure opening {}", &mcp_index_file.to_string_lossy())
+                    })?;
+                    Ok(Some(
+                        serde_json::from_str::<ForgeMcpIndex>(&body)
+                            .map_err(|err| MetadataError::from_json_err(err, &body))?,
+                    ))
+                } else {
+                    Ok(None)
+                }
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn store_mcp_index(&self, index: &ForgeMcpIndex) -> Result<()> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => write_json_atomic(&self.mcp_index_file()?, index)?,
+            StorageFormat::Database => todo!(),
+        }
+        Ok(())
+    }
+
+    /**
+     * Rebuild the MCP version index from the `MCP_VERSION` data of every stored installer manifest.
+     * Returns how many MCP versions were found.
+     */
+    pub fn reindex_mcp_versions(&self) -> Result<usize> {
+        let mut index = ForgeMcpIndex::default();
+        for long_version in json_file_stems(&self.installer_manifests_dir()?)? {
+            if let Some(profile) = self.load_installer_manifest(&long_version)? {
+                index.add(&long_version, &profile);
+            }
+        }
+        self.store_mcp_index(&index)?;
+        Ok(index.mcp_versions.len())
+    }
+
     pub fn load_files_manifest(&self, version_name: &str) -> Result<Option<ForgeVersionMeta>> {
         match *self.storage_format {
             StorageFormat::Json {
@@ -289,14 +444,15 @@ impl ForgeDataStorage {
                 let files_manifest_file =
                     self.manifests_dir()?.join(format!("{}.json", version_name));
                 if files_manifest_file.is_file() {
-                    let files_manifest = serde_json::from_str::<ForgeVersionMeta>(
-                        &std::fs::read_to_string(&files_manifest_file).with_context(|| {
+                    let body =
+                        std::fs::read_to_string(&files_manifest_file).with_context(|| {
                             format!(
                                 "Failure reading file {}",
                                 &files_manifest_file.to_string_lossy()
                             )
-                        })?,
-                    )?;
+                        })?;
+                    let files_manifest = serde_json::from_str::<ForgeVersionMeta>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
                     Ok(Some(files_manifest))
                 } else {
                     Ok(None)
@@ -319,13 +475,7 @@ impl ForgeDataStorage {
                 let files_manifest_file =
                     self.manifests_dir()?.join(format!("{}.json", version_name));
 
-                let files_metadata_json = serde_json::to_string_pretty(&manifest)?;
-                std::fs::write(&files_manifest_file, files_metadata_json).with_context(|| {
-                    format!(
-                        "Failure writing to file {}",
-                        &files_manifest_file.to_string_lossy()
-                    )
-                })?;
+                write_json_atomic(&files_manifest_file, &manifest)?;
             }
             StorageFormat::Database => todo!(),
         }
@@ -352,6 +502,25 @@ impl ForgeDataStorage {
         }
     }
 
+    /**
+     * The downloaded installer jar of a Forge build by its long version, `None` for builds without
+     * an installer or whose installer was not downloaded yet
+     */
+    pub fn installer_jar(&self, long_version: &str) -> Result<Option<std::path::PathBuf>> {
+        let Some(index) = self.load_index()? else {
+            return Ok(None);
+        };
+        let Some(entry) = index.versions.get(long_version) else {
+            return Ok(None);
+        };
+        let version = ForgeProcessedVersion::new(entry, &download::forge::maven_base_url()?);
+        let Some(filename) = version.filename().filter(|_| version.uses_installer()) else {
+            return Ok(None);
+        };
+        let jar_path = self.forge_jars_dir()?.join(filename);
+        Ok(jar_path.is_file().then_some(jar_path))
+    }
+
     pub fn installer_manifests_dir(&self) -> Result<std::path::PathBuf> {
         match *self.storage_format {
             StorageFormat::Json {
@@ -385,14 +554,16 @@ impl ForgeDataStorage {
                     .installer_manifests_dir()?
                     .join(format!("{}.json", version_name));
                 if installer_manifest_file.is_file() {
-                    let installer_manifest = serde_json::from_str::<ForgeInstallerProfile>(
-                        &std::fs::read_to_string(&installer_manifest_file).with_context(|| {
+                    let body =
+                        std::fs::read_to_string(&installer_manifest_file).with_context(|| {
                             format!(
                                 "Failure reading file {}",
                                 &installer_manifest_file.to_string_lossy()
                             )
-                        })?,
-                    )?;
+                        })?;
+                    let installer_manifest =
+                        serde_json::from_str::<ForgeInstallerProfile>(&body)
+                            .map_err(|err| MetadataError::from_json_err(err, &body))?;
                     Ok(Some(installer_manifest))
                 } else {
                     Ok(None)
@@ -416,15 +587,7 @@ impl ForgeDataStorage {
                     .installer_manifests_dir()?
                     .join(format!("{}.json", version_name));
 
-                let installer_manifest_json = serde_json::to_string_pretty(&manifest)?;
-                std::fs::write(&installer_manifest_file, installer_manifest_json).with_context(
-                    || {
-                        format!(
-                            "Failure writing to file {}",
-                            &installer_manifest_file.to_string_lossy()
-                        )
-                    },
-                )?;
+                write_json_atomic(&installer_manifest_file, &manifest)?;
             }
             StorageFormat::Database => todo!(),
         }
@@ -461,14 +624,15 @@ impl ForgeDataStorage {
                     .version_manifests_dir()?
                     .join(format!("{}.json", version_name));
                 if version_manifest_file.is_file() {
-                    let version_manifest = serde_json::from_str::<MojangVersion>(
-                        &std::fs::read_to_string(&version_manifest_file).with_context(|| {
+                    let body =
+                        std::fs::read_to_string(&version_manifest_file).with_context(|| {
                             format!(
                                 "Failure reading file {}",
                                 &version_manifest_file.to_string_lossy()
                             )
-                        })?,
-                    )?;
+                        })?;
+                    let version_manifest = serde_json::from_str::<MojangVersion>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
                     Ok(Some(version_manifest))
                 } else {
                     Ok(None)
@@ -485,18 +649,10 @@ impl ForgeDataStorage {
                 generated_directory: _,
             } => {
                 let version_manifest_file = self
-                    .installer_manifests_dir()?
+                    .version_manifests_dir()?
                     .join(format!("{}.json", version_name));
 
-                let version_manifest_json = serde_json::to_string_pretty(&version)?;
-                std::fs::write(&version_manifest_file, version_manifest_json).with_context(
-                    || {
-                        format!(
-                            "Failure writing to file {}",
-                            &version_manifest_file.to_string_lossy()
-                        )
-                    },
-                )?;
+                write_json_atomic(&version_manifest_file, &version)?;
             }
             StorageFormat::Database => todo!(),
         }
@@ -529,19 +685,20 @@ impl ForgeDataStorage {
                 meta_directory: _,
                 generated_directory: _,
             } => {
-                let version_manifest_file = self
-                    .version_manifests_dir()?
+                let installer_info_file = self
+                    .installer_info_dir()?
                     .join(format!("{}.json", version_name));
-                if version_manifest_file.is_file() {
-                    let version_manifest = serde_json::from_str::<InstallerInfo>(
-                        &std::fs::read_to_string(&version_manifest_file).with_context(|| {
+                if installer_info_file.is_file() {
+                    let body =
+                        std::fs::read_to_string(&installer_info_file).with_context(|| {
                             format!(
                                 "Failure reading file {}",
-                                &version_manifest_file.to_string_lossy()
+                                &installer_info_file.to_string_lossy()
                             )
-                        })?,
-                    )?;
-                    Ok(Some(version_manifest))
+                        })?;
+                    let installer_info = serde_json::from_str::<InstallerInfo>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
+                    Ok(Some(installer_info))
                 } else {
                     Ok(None)
                 }
@@ -550,6 +707,32 @@ impl ForgeDataStorage {
         }
     }
 
+    /**
+     * The legacy info of the ancient Forge builds, which is kept in the static directory
+     */
+    pub fn legacy_info_file(static_directory: &str) -> std::path::PathBuf {
+        std::path::Path::new(static_directory)
+            .join("forge")
+            .join("forge-legacyinfo.json")
+    }
+
+    pub fn load_legacy_info(&self, static_directory: &str) -> Result<Option<ForgeLegacyInfoList>> {
+        let legacy_info_file = Self::legacy_info_file(static_directory);
+        if legacy_info_file.is_file() {
+            let body = std::fs::read_to_string(&legacy_info_file).with_context(|| {
+                format!(
+                    "Failure reading file {}",
+                    legacy_info_file.to_string_lossy()
+                )
+            })?;
+            let legacy_info = serde_json::from_str::<ForgeLegacyInfoList>(&body)
+                .map_err(|err| MetadataError::from_json_err(err, &body))?;
+            Ok(Some(legacy_info))
+        } else {
+            Ok(None)
+        }
+    }
+
     pub fn store_installer_info(
         &self,
         version_name: &str,
@@ -561,91 +744,239 @@ impl ForgeDataStorage {
                 generated_directory: _,
             } => {
                 let installer_info_file = self
-                    .installer_manifests_dir()?
+                    .installer_info_dir()?
                     .join(format!("{}.json", version_name));
 
-                let installer_info_json = serde_json::to_string_pretty(&installer_info)?;
-                std::fs::write(&installer_info_file, installer_info_json).with_context(|| {
-                    format!(
-                        "Failure writing to file {}",
-                        &installer_info_file.to_string_lossy()
-                    )
-                })?;
+                write_json_atomic(&installer_info_file, &installer_info)?;
             }
             StorageFormat::Database => todo!(),
         }
         Ok(())
     }
+
+    /**
+     * Recompute the recommended and latest versions of the derived index from the stored promotions,
+     * without fetching anything upstream. Returns false if there is no stored index to reindex.
+     */
+    pub fn reindex(&self) -> Result<bool> {
+        let (Some(mut forge_index), Some(promotions)) =
+            (self.load_index()?, self.load_forge_promotions()?)
+        else {
+            return Ok(false);
+        };
+        let recommended_set = recommended_forge_versions(&promotions);
+
+        for info in forge_index.by_mc_version.values_mut() {
+            info.recommended = None;
+            info.latest = info.versions.last().cloned();
+        }
+        for (long_version, forge_version) in forge_index.versions.iter_mut() {
+            let is_recommended = recommended_set.contains(&forge_version.version);
+            forge_version.recommended = Some(is_recommended);
+            if is_recommended {
+                if let Some(info) = forge_index.by_mc_version.get_mut(&forge_version.mc_version) {
+                    info.recommended = Some(long_version.clone());
+                }
+            }
+        }
+
+        self.store_index(&forge_index)?;
+        Ok(true)
+    }
+
+    pub fn validate_stored(&self, report: &mut MetadataValidationReport) -> Result<()> {
+        let meta_dir = self.meta_dir()?;
+        report.check(
+            &meta_dir.join("maven-metadata.json"),
+            self.load_maven_metadata(),
+        );
+        report.check(
+            &meta_dir.join("promotions_slim.json"),
+            self.load_forge_promotions(),
+        );
+        report.check(&meta_dir.join("derived_index.json"), self.load_index());
+        report.check_parse(
+            &meta_dir.join("derived_index.last_index.json"),
+            self.load_index_entry(),
+        );
+        report.check_parse(&self.installer_ledger_file()?, self.load_installer_ledger());
+        report.check(&self.mcp_index_file()?, self.load_mcp_index());
+
+        let manifests_dir = self.manifests_dir()?;
+        for name in json_file_stems(&manifests_dir)? {
+            report.check(
+                &manifests_dir.join(format!("{}.json", name)),
+                self.load_files_manifest(&name),
+            );
+        }
+
+        let installer_manifests_dir = self.installer_manifests_dir()?;
+        for name in json_file_stems(&installer_manifests_dir)? {
+            report.check(
+                &installer_manifests_dir.join(format!("{}.json", name)),
+                self.load_installer_manifest(&name),
+            );
+        }
+
+        let version_manifests_dir = self.version_manifests_dir()?;
+        for name in json_file_stems(&version_manifests_dir)? {
+            report.check(
+                &version_manifests_dir.join(format!("{}.json", name)),
+                self.load_mojang_version(&name),
+            );
+        }
+
+        let installer_info_dir = self.installer_info_dir()?;
+        for name in json_file_stems(&installer_info_dir)? {
+            report.check(
+                &installer_info_dir.join(format!("{}.json", name)),
+                self.load_installer_info(&name),
+            );
+        }
+        Ok(())
+    }
+
+    /**
+     * Generate the Forge component from the derived index, the stored installer data and the
+     * legacy info in the static directory. Builds whose data was not fetched are skipped.
+     */
+    pub fn generate(&self, static_directory: &str, forge_wrapper: &ForgeWrapper) -> Result<bool> {
+        let Some(forge_index) = self.load_index()? else {
+            return Ok(false);
+        };
+        let legacy_info_list = self.load_legacy_info(static_directory)?.unwrap_or_default();
+        let quarantine = Quarantine::load(static_directory)?;
+        let maven_url = download::forge::maven_base_url()?;
+        let mojang_storage = MojangDataStorage::new(self.storage_format.clone());
+        let mut minecraft_libraries: HashMap<String, Vec<GradleSpecifier>> = HashMap::new();
+
+        let mut versions = vec![];
+        let mut skipped = 0;
+        for entry in forge_index.versions.values() {
+            let version = ForgeProcessedVersion::new(entry, &maven_url);
+            if version.url().is_none() || quarantine.forge.contains(&version.long_version) {
+                continue;
+            }
+
+            let meta_version =
+                if version.uses_installer() {
+                    let profile = self.load_installer_manifest(&version.long_version)?;
+                    let mojang_version = self.load_mojang_version(&version.long_version)?;
+                    let installer_info = self.load_installer_info(&version.long_version)?;
+                    match (profile, mojang_version, installer_info) {
+                        (
+                            Some(ForgeInstallerProfile::V2(profile)),
+                            Some(mojang_version),
+                            Some(installer_info),
+                        ) => version.to_installer_meta_version(
+                            &profile,
+                            &mojang_version,
+                            &installer_info,
+                            &maven_url,
+                            forge_wrapper,
+                        ),
+                        // installers without a version json describe the version in the legacy
+                        // install profile
+                        (Some(ForgeInstallerProfile::V1(profile)), _, _) => {
+                            let mc_version = &profile.install.minecraft;
+                            if !minecraft_libraries.contains_key(mc_version) {
+                                let libraries = mojang_storage.minecraft_libraries(mc_version)?;
+                                minecraft_libraries.insert(mc_version.clone(), libraries);
+                            }
+                            Some(version.to_profile_meta_version(
+                                &profile,
+                                &minecraft_libraries[mc_version],
+                            ))
+                        }
+                        _ => None,
+                    }
+                } else {
+                    legacy_info_list
+                        .number
+                        .get(&version.long_version)
+                        .and_then(|info| version.to_legacy_meta_version(info))
+                };
+
+            match meta_version {
+                Some(meta_version) => {
+                    versions.push((meta_version, entry.recommended.unwrap_or(false)))
+                }
+                None => {
+                    debug!("Not generating Forge {}", version.long_version);
+                    skipped += 1;
+                }
+            }
+        }
+        if skipped > 0 {
+            info!(
+                "Skipped {} Forge versions without generatable data",
+                skipped
+            );
+        }
+
+        GeneratedDataStorage::new(self.storage_format.clone()).store_package(
+            "net.minecraftforge",
+            "Forge",
+            versions,
+        )?;
+        Ok(true)
+    }
 }
 
 impl UpstreamMetadataUpdater {
-    pub async fn update_upstream_forge(&self) -> Result<()> {
+    pub async fn update_upstream_forge(&self, report: &mut UpdateReport) -> Result<()> {
         info!("Checking for Forge metadata");
-        self.update_forge_metadata()
+        self.update_forge_metadata(report)
             .await
             .with_context(|| "Failed to update Forge metadata.")?;
 
         self.update_forge_installer_metadata()
             .await
             .with_context(|| "Failed to update Forge legacy metadata.")?;
+
+        if self.metadata_cfg.forge_jars.is_pruning() {
+            let local_storage = ForgeDataStorage::new(self.storage_format.clone());
+            let metadata_cfg = self.metadata_cfg.clone();
+            tokio::task::spawn_blocking(move || {
+                local_storage.prune_jars(&metadata_cfg.forge_jars, &metadata_cfg.static_directory)
+            })
+            .await?
+            .with_context(|| "Failed to prune Forge jars.")?;
+        }
+
+        let local_storage = ForgeDataStorage::new(self.storage_format.clone());
+        let static_directory = self.metadata_cfg.static_directory.clone();
+        let forge_wrapper = self.metadata_cfg.forge_wrapper.forge_wrapper()?;
+        tokio::task::spawn_blocking(move || {
+            local_storage.generate(&static_directory, &forge_wrapper)
+        })
+        .await?
+        .with_context(|| "Failed to generate Forge metadata.")?;
         Ok(())
     }
 
-    pub async fn update_forge_metadata(&self) -> Result<()> {
+    pub async fn update_forge_metadata(&self, report: &mut UpdateReport) -> Result<()> {
         let local_storage = ForgeDataStorage {
             storage_format: self.storage_format.clone(),
         };
 
-        let maven_metadata = download::forge::load_maven_metadata().await?;
+        let local_validators = local_storage.load_maven_metadata_validators()?;
+        let (maven_metadata, remote_validators) =
+            match download::forge::load_maven_metadata_if_modified(local_validators.as_ref())
+                .await?
+            {
+                Conditional::Modified(metadata, validators) => (metadata, Some(validators)),
+                Conditional::NotModified => {
+                    info!("Forge maven metadata is unchanged upstream");
+                    let metadata = local_storage
+                        .load_maven_metadata()?
+                        .ok_or_else(|| anyhow!("Local Forge maven metadata disappeared"))?;
+                    (metadata, None)
+                }
+            };
         let promotions_metadata = download::forge::load_maven_promotions().await?;
 
-        let promoted_key_expression = regex::Regex::new(
-            "(?P<mc>[^-]+)-(?P<promotion>(latest)|(recommended))(-(?P<branch>[a-zA-Z0-9\\.]+))?",
-        )
-        .expect("Promotion regex must compile");
-
-        let mut recommended_set = HashSet::new();
-
-        // FIXME: does not fully validate that the file has not changed format
-        // NOTE: For some insane reason, the format of the versions here is special. It having a branch at the end means it
-        //           affects that particular branch.
-        //       We don't care about Forge having branches.
-        //       Therefore we only use the short version part for later identification and filter out the branch-specific
-        //           promotions (among other errors).
-        debug!("Processing Forge Promotions");
-
-        for (promo_key, shortversion) in &promotions_metadata.promos {
-            match promoted_key_expression.captures(promo_key) {
-                None => {
-                    warn!("Skipping promotion {}, the key did not parse:", promo_key);
-                }
-                Some(captures) => {
-                    if captures.name("mc").is_none() {
-                        debug!(
-                            "Skipping promotion {}, because it has no Minecraft version.",
-                            promo_key
-                        );
-                        continue;
-                    }
-                    if captures.name("branch").is_some() {
-                        debug!(
-                            "Skipping promotion {}, because it on a branch only.",
-                            promo_key
-                        );
-                        continue;
-                    } else if let Some(promotion) = captures.name("promotion") {
-                        if promotion.as_str() == "recommended" {
-                            recommended_set.insert(shortversion.clone());
-                            debug!("forge {} added to recommended set", &shortversion);
-                        } else if promotion.as_str() == "latest" {
-                            continue;
-                        }
-                    } else {
-                        warn!("Unknown capture state {:?}", captures);
-                    }
-                }
-            }
-        }
+        let recommended_set = recommended_forge_versions(&promotions_metadata);
 
         debug!("Processing Forge Versions");
         let remote_forge_version_pairs =
@@ -714,7 +1045,9 @@ impl UpstreamMetadataUpdater {
                 ).expect("Version regex must compile");
                 let ls = local_storage.clone();
                 let recommended = recommended_set.clone();
-                tokio::spawn(async move {
+                let id = long_version.clone();
+                let span = info_span!("version", uid = "net.minecraftforge", version = %id);
+                let task = tokio::spawn(async move {
                     match version_expression.captures(&long_version) {
                         None => Err(anyhow!(
                             "Forge long version {} does not parse!",
@@ -742,24 +1075,13 @@ impl UpstreamMetadataUpdater {
                             }
                         }
                     }
-                })
-            })
-            .buffer_unordered(self.metadata_cfg.max_parallel_fetch_connections);
-        let results = tasks
-            .map(|t| match t {
-                Ok(Ok(t)) => Ok(t),
-                Ok(Err(e)) => {
-                    debug!("Task had an error: {:?}", e);
-                    Err(e)
-                }
-                Err(e) => {
-                    debug!("Task had a Join error: {:?}", e);
-                    Err(e.into())
-                }
+                }.instrument(span));
+                async move { ("net.minecraftforge".to_string(), id, task.await) }
             })
-            .collect::<Vec<_>>()
-            .await;
-        let forge_versions = process_results(results)?;
+            .buffer_unordered(self.metadata_cfg.parallelism(UpdateSource::Forge));
+        let results = tasks.collect::<Vec<_>>().await;
+        let forge_versions = self.collect_version_results(results, report)?;
+        report.record_updated_versions("net.minecraftforge", forge_versions.len());
 
         for forge_version in forge_versions {
             let mc_version = forge_version.mc_version.clone();
@@ -807,6 +1129,9 @@ impl UpstreamMetadataUpdater {
 
         debug!("Dumping forge index files");
         local_storage.store_maven_metadata(&maven_metadata)?;
+        if let Some(validators) = remote_validators {
+            local_storage.store_maven_metadata_validators(&validators)?;
+        }
         local_storage.store_forge_promotions(&promotions_metadata)?;
         local_storage.store_index(&forge_index)?;
 
@@ -832,7 +1157,8 @@ impl UpstreamMetadataUpdater {
                 )
             })?;
         }
-        let legacy_info_path = forge_static_dir.join("forge-legacyinfo.json");
+        let legacy_info_path =
+            ForgeDataStorage::legacy_info_file(&self.metadata_cfg.static_directory);
         let aquire_legacy_info = !legacy_info_path.is_file();
 
         let mut legacy_info_list = ForgeLegacyInfoList::default();
@@ -847,71 +1173,113 @@ impl UpstreamMetadataUpdater {
             .index_hash()?
             .ok_or(anyhow!("local forge index missing"))?;
 
-        if let Some(last_index) = local_storage.load_index_entry()? {
-            // check if we even need to regenerate
-            if last_index.hash == derived_index_hash {
-                info!("Forge index up to date. Not regenerating.");
-                return Ok(());
-            } else {
-                info!("Forge index hash did not match, regenerating...")
+        let mut ledger = local_storage.load_installer_ledger()?.unwrap_or_default();
+        let quarantine = Quarantine::load(&self.metadata_cfg.static_directory)?;
+
+        // get the installer jars - if needed - and get the installer profiles out of them, for the
+        // builds that were not processed yet or failed last time
+        let maven_url = download::forge::maven_base_url()?;
+        let mut pending = vec![];
+        for (key, entry) in derived_index.versions {
+            let version = ForgeProcessedVersion::new(&entry, &maven_url);
+            // jar mods are gone through again while the legacy info is missing
+            let reacquire_legacy_info = aquire_legacy_info && !version.uses_installer();
+            if !ledger.is_pending(&key) && !reacquire_legacy_info {
+                continue;
             }
-        }
 
-        // get the installer jars - if needed - and get the installer profiles out of them
-        let tasks = stream::iter(derived_index.versions)
-            .filter_map(|(key, entry)| async move {
-                info!("Updating Forge {}", &key);
-                let version = ForgeProcessedVersion::new(&entry);
+            info!("Updating Forge {}", &key);
+            if version.is_ancient() {
+                debug!(
+                    "Forge {} is an ancient build, using {:?} as a jar mod",
+                    &key,
+                    version.filename()
+                );
+            }
 
-                if version.url().is_none() {
-                    debug!("Skipping forge build {} with no valid files", &entry.build);
-                    return None;
-                }
+            if version.url().is_none() {
+                debug!("Skipping forge build {} with no valid files", &entry.build);
+                ledger.record(&key, InstallerState::Unsupported, None);
+                continue;
+            }
 
-                if BAD_FORGE_VERSIONS.contains(&version.long_version.as_str()) {
-                    debug!("Skipping bad forge version {}", &version.long_version);
-                    return None;
-                }
+            if quarantine.forge.contains(&version.long_version) {
+                debug!(
+                    "Skipping quarantined forge version {}",
+                    &version.long_version
+                );
+                continue;
+            }
 
-                Some(version)
-            })
+            pending.push(version);
+        }
+        debug!("{} Forge installers to process", pending.len());
+
+        let tasks = stream::iter(pending)
             .map(|version| {
                 let ls = local_storage.clone();
-                tokio::spawn(async move {
-                    process_forge_installer(&ls, &version, aquire_legacy_info).await
-                })
+                let span = info_span!(
+                    "version",
+                    uid = "net.minecraftforge",
+                    version = %version.long_version
+                );
+                tokio::spawn(
+                    async move {
+                        let outcome =
+                            process_forge_installer(&ls, &version, aquire_legacy_info).await;
+                        (version.long_version, outcome)
+                    }
+                    .instrument(span),
+                )
             })
-            .buffer_unordered(self.metadata_cfg.max_parallel_fetch_connections);
-        let results = tasks
-            .map(|t| match t {
-                Ok(Ok(t)) => Ok(t),
-                Ok(Err(e)) => {
-                    debug!("Task had an error: {:?}", e);
-                    Err(e)
+            .buffer_unordered(self.metadata_cfg.parallelism(UpdateSource::Forge));
+        let results = tasks.collect::<Vec<_>>().await;
+
+        // a failing build is recorded and retried next time instead of holding up the others
+        let mut processed = 0;
+        for result in results {
+            let (long_version, outcome) = match result {
+                Ok(result) => result,
+                Err(err) => {
+                    warn!("Forge installer task had a Join error: {:?}", err);
+                    continue;
                 }
-                Err(e) => {
-                    debug!("Task had a Join error: {:?}", e);
-                    Err(e.into())
+            };
+            match outcome {
+                Ok(InstallerOutcome::Processed(legacy_info)) => {
+                    processed += 1;
+                    ledger.record(&long_version, InstallerState::Processed, None);
+                    if let Some(legacy_info) = legacy_info {
+                        legacy_info_list.number.insert(long_version, legacy_info);
+                    }
                 }
-            })
-            .collect::<Vec<_>>()
-            .await;
-
-        let legacy_version_infos = process_results_ok(results);
-
-        for (long_version, version_info) in legacy_version_infos.into_iter().flatten() {
-            legacy_info_list.number.insert(long_version, version_info);
+                Ok(InstallerOutcome::Unsupported) => {
+                    ledger.record(&long_version, InstallerState::Unsupported, None);
+                }
+                Err(err) => {
+                    warn!("Failed to process Forge {}: {:#}", long_version, err);
+                    ledger.record(
+                        &long_version,
+                        InstallerState::Failed,
+                        Some(format!("{:#}", err)),
+                    );
+                }
+            }
         }
+        local_storage.store_installer_ledger(&ledger)?;
+        info!(
+            "Processed {} Forge installers, {} failed and {} unsupported in total",
+            processed,
+            ledger.count(InstallerState::Failed),
+            ledger.count(InstallerState::Unsupported)
+        );
+
+        let mcp_versions = local_storage.reindex_mcp_versions()?;
+        debug!("Indexed {} MCP versions of Forge installers", mcp_versions);
 
         // only write legacy info if it's missing
         if !legacy_info_path.is_file() {
-            let legacy_info_json = serde_json::to_string_pretty(&legacy_info_list)?;
-            std::fs::write(&legacy_info_path, legacy_info_json).with_context(|| {
-                format!(
-                    "Failure writing to file {}",
-                    &legacy_info_path.to_string_lossy()
-                )
-            })?;
+            write_json_atomic(&legacy_info_path, &legacy_info_list)?;
         }
 
         // update our index
@@ -927,6 +1295,61 @@ impl UpstreamMetadataUpdater {
     }
 }
 
+/**
+ * Collect the short versions of every Forge build promoted as recommended, ignoring branch promotions
+ */
+fn recommended_forge_versions(promotions: &ForgeMavenPromotions) -> HashSet<String> {
+    let promoted_key_expression = regex::Regex::new(
+        "(?P<mc>[^-]+)-(?P<promotion>(latest)|(recommended))(-(?P<branch>[a-zA-Z0-9\\.]+))?",
+    )
+    .expect("Promotion regex must compile");
+
+    let mut recommended_set = HashSet::new();
+
+    // FIXME: does not fully validate that the file has not changed format
+    // NOTE: For some insane reason, the format of the versions here is special. It having a branch at the end means it
+    //           affects that particular branch.
+    //       We don't care about Forge having branches.
+    //       Therefore we only use the short version part for later identification and filter out the branch-specific
+    //           promotions (among other errors).
+    debug!("Processing Forge Promotions");
+
+    for (promo_key, shortversion) in &promotions.promos {
+        match promoted_key_expression.captures(promo_key) {
+            None => {
+                warn!("Skipping promotion {}, the key did not parse:", promo_key);
+            }
+            Some(captures) => {
+                if captures.name("mc").is_none() {
+                    debug!(
+                        "Skipping promotion {}, because it has no Minecraft version.",
+                        promo_key
+                    );
+                    continue;
+                }
+                if captures.name("branch").is_some() {
+                    debug!(
+                        "Skipping promotion {}, because it on a branch only.",
+                        promo_key
+                    );
+                    continue;
+                } else if let Some(promotion) = captures.name("promotion") {
+                    if promotion.as_str() == "recommended" {
+                        recommended_set.insert(shortversion.clone());
+                        debug!("forge {} added to recommended set", &shortversion);
+                    } else if promotion.as_str() == "latest" {
+                        continue;
+                    }
+                } else {
+                    warn!("Unknown capture state {:?}", captures);
+                }
+            }
+        }
+    }
+
+    recommended_set
+}
+
 async fn process_forge_version(
     local_storage: &ForgeDataStorage,
     recommended_set: &HashSet<String>,
@@ -965,10 +1388,7 @@ async fn get_single_forge_files_manifest(
     } else {
         info!("Getting Forge manifest for {long_version}");
 
-        let file_url = format!(
-            "https://files.minecraftforge.net/net/minecraftforge/forge/{}/meta.json",
-            &long_version
-        );
+        let file_url = download::forge::files_manifest_url(long_version)?;
         let remote_manifest = download::forge::load_single_forge_files_manifest(&file_url)
             .await
             .with_context(|| format!("Failure downloading {}", &file_url))?;
@@ -977,6 +1397,7 @@ async fn get_single_forge_files_manifest(
     };
 
     let mut ret_map: BTreeMap<String, ForgeFile> = BTreeMap::new();
+    let non_word_expression = regex::Regex::new("\\W").expect("Hash regex must compile");
 
     for (classifier, extension_obj) in &files_metadata.classifiers {
         let mut count = 0;
@@ -984,8 +1405,7 @@ async fn get_single_forge_files_manifest(
         if let Some(extension_obj) = extension_obj {
             for (extension, hash_type) in extension_obj {
                 if let Some(hash_type) = hash_type {
-                    let re = regex::Regex::new("\\W").unwrap();
-                    let processed_hash = re.replace_all(hash_type, "");
+                    let processed_hash = non_word_expression.replace_all(hash_type, "");
                     if processed_hash.len() == 32 {
                         let file_obj = ForgeFile {
                             classifier: classifier.as_str().to_owned(),
@@ -1024,14 +1444,21 @@ async fn get_single_forge_files_manifest(
     Ok(ret_map)
 }
 
+/// What processing the installer of a Forge build found
+enum InstallerOutcome {
+    /// its metadata was extracted, along with the legacy info of a jar mod when it was asked for
+    Processed(Option<ForgeLegacyInfo>),
+    Unsupported,
+}
+
 async fn process_forge_installer(
     local_storage: &ForgeDataStorage,
     version: &ForgeProcessedVersion,
     aquire_legacy_info: bool,
-) -> Result<Option<(String, ForgeLegacyInfo)>> {
+) -> Result<InstallerOutcome> {
     let jar_path = local_storage
         .forge_jars_dir()?
-        .join(&version.filename().expect("Missing forge filename"));
+        .join(version.filename().expect("Missing forge filename"));
 
     if version.uses_installer() {
         let installer_info = local_storage.load_installer_info(&version.long_version)?;
@@ -1050,6 +1477,7 @@ async fn process_forge_installer(
         }
 
         debug!("Processing forge jar from {}", &version.url().unwrap());
+        let mut supported = true;
         if profile.is_none() {
             use std::io::Read;
 
@@ -1124,7 +1552,8 @@ async fn process_forge_installer(
                     debug!(
                         "Forge Version {} is not supported and won't be generated later.",
                         &version.long_version
-                    )
+                    );
+                    supported = false;
                 }
             }
         }
@@ -1138,13 +1567,17 @@ async fn process_forge_installer(
 
             local_storage.store_installer_info(&version.long_version, &installer_info)?;
         }
-        Ok(None)
+        if supported {
+            Ok(InstallerOutcome::Processed(None))
+        } else {
+            Ok(InstallerOutcome::Unsupported)
+        }
     } else {
         // ignore the two versions without install manifests and jar mod class files
         // TODO: fix those versions?
 
         if version.mc_version_sane == "1.6.1" {
-            return Ok(None);
+            return Ok(InstallerOutcome::Unsupported);
         }
 
         // only gather legacy info if it's missing
@@ -1156,42 +1589,7 @@ async fn process_forge_installer(
                     .with_context(|| format!("Failure downloading {}", &version.url().unwrap()))?
             }
 
-            // find the latest timestamp in the zip file
-            let mut time_stamp = time::OffsetDateTime::UNIX_EPOCH;
-
-            {
-                // context drop to close file
-                let mut jar =
-                    zip::ZipArchive::new(std::fs::File::open(&jar_path).with_context(|| {
-                        format!("Failure opening {}", &jar_path.to_string_lossy())
-                    })?)
-                    .with_context(|| {
-                        format!(
-                            "Failure reading Jar archive {}",
-                            &jar_path.to_string_lossy()
-                        )
-                    })?;
-
-                for i in 0..jar.len() {
-                    let file = jar.by_index(i).with_context(|| {
-                        format!(
-                            "Failure reading Jar archive {} `index:{}`",
-                            &jar_path.to_string_lossy(),
-                            i
-                        )
-                    })?;
-                    let time_stamp_new = file.last_modified().to_time().with_context(|| {
-                        format!(
-                            "Failure reading Jar archive {} `index:{}` last modified time",
-                            &jar_path.to_string_lossy(),
-                            i
-                        )
-                    })?;
-                    if time_stamp_new > time_stamp {
-                        time_stamp = time_stamp_new;
-                    }
-                }
-            }
+            let time_stamp = latest_zip_timestamp(&jar_path)?;
 
             let legacy_info = ForgeLegacyInfo {
                 release_time: Some(time_stamp),
@@ -1200,9 +1598,8 @@ async fn process_forge_installer(
                 size: Some(jar_path.metadata()?.len()),
             };
 
-            return Ok(Some((version.long_version.clone(), legacy_info)));
-            // legacy_info_list.number.insert(key, legacy_info);
+            return Ok(InstallerOutcome::Processed(Some(legacy_info)));
         }
-        Ok(None)
+        Ok(InstallerOutcome::Processed(None))
     }
 }
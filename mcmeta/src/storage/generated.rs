@@ -0,0 +1,303 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Context, Result};
+use libmcmeta::models::{
+    MetaPackageIndex, MetaPackageIndexEntry, MetaVersion, MetaVersionIndex, MetaVersionIndexEntry,
+    META_FORMAT_VERSION,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::info;
+
+use crate::{
+    download::errors::MetadataError,
+    storage::StorageFormat,
+    utils::{hash, write_atomic, HashAlgo},
+};
+
+/// Held while the package index is read, changed and written back, so packages stored by updates
+/// of different sources at once never drop each other's entries
+static PACKAGE_INDEX: Mutex<()> = Mutex::new(());
+
+#[derive(Clone)]
+pub struct GeneratedDataStorage {
+    storage_format: Arc<StorageFormat>,
+}
+
+impl GeneratedDataStorage {
+    pub fn new(storage_format: Arc<StorageFormat>) -> Self {
+        Self { storage_format }
+    }
+
+    pub fn generated_dir(&self) -> Result<std::path::PathBuf> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                ref generated_directory,
+            } => {
+                let generated_dir = std::path::Path::new(&generated_directory).to_path_buf();
+
+                if !generated_dir.is_dir() {
+                    info!(
+                        "Generated metadata directory at {} does not exist, creating it",
+                        generated_dir.display()
+                    );
+                    std::fs::create_dir_all(&generated_dir)?;
+                }
+                Ok(generated_dir)
+            }
+            StorageFormat::Database => Err(anyhow!("Wrong storage format")),
+        }
+    }
+
+    fn load_json<T: DeserializeOwned>(&self, file: &std::path::Path) -> Result<Option<T>> {
+        if file.is_file() {
+            let body = std::fs::read_to_string(file)
+                .with_context(|| format!("Failure reading file {}", file.to_string_lossy()))?;
+            let value = serde_json::from_str::<T>(&body)
+                .map_err(|err| MetadataError::from_json_err(err, &body))
+                .with_context(|| format!("Failure parsing file {}", file.to_string_lossy()))?;
+            Ok(Some(value))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /**
+     * Write a generated file, returning the lowercase sha256 the indexes record for it
+     */
+    fn store_json<T: Serialize>(&self, file: &std::path::Path, value: &T) -> Result<String> {
+        if let Some(parent_dir) = file.parent() {
+            if !parent_dir.is_dir() {
+                std::fs::create_dir_all(parent_dir)?;
+            }
+        }
+        let body = serde_json::to_string_pretty(value)?;
+        write_atomic(file, &body)?;
+        Ok(hash(&body, HashAlgo::Sha256)?.to_lowercase())
+    }
+
+    pub fn load_package_index(&self) -> Result<Option<MetaPackageIndex>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => self.load_json(&self.generated_dir()?.join("index.json")),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn load_version_index(&self, uid: &str) -> Result<Option<MetaVersionIndex>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => self.load_json(&self.generated_dir()?.join(uid).join("index.json")),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    /**
+     * The number of generated versions of every package
+     */
+    pub fn count_versions(&self) -> Result<BTreeMap<String, usize>> {
+        let Some(package_index) = self.load_package_index()? else {
+            return Ok(BTreeMap::new());
+        };
+        let mut counts = BTreeMap::new();
+        for package in package_index.packages {
+            let count = self
+                .load_version_index(&package.uid)?
+                .map_or(0, |index| index.versions.len());
+            counts.insert(package.uid, count);
+        }
+        Ok(counts)
+    }
+
+    /**
+     * The sha256 of every generated version by uid and version, as recorded in the version indexes
+     */
+    pub fn version_hashes(&self) -> Result<BTreeMap<(String, String), String>> {
+        let Some(package_index) = self.load_package_index()? else {
+            return Ok(BTreeMap::new());
+        };
+        let mut hashes = BTreeMap::new();
+        for package in package_index.packages {
+            let Some(version_index) = self.load_version_index(&package.uid)? else {
+                continue;
+            };
+            for entry in version_index.versions {
+                hashes.insert((package.uid.clone(), entry.version), entry.sha256);
+            }
+        }
+        Ok(hashes)
+    }
+
+    pub fn load_meta_version(&self, uid: &str, version: &str) -> Result<Option<MetaVersion>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => self.load_json(
+                &self
+                    .generated_dir()?
+                    .join(uid)
+                    .join(format!("{}.json", version)),
+            ),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn store_package_index(&self, index: &MetaPackageIndex) -> Result<String> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => self.store_json(&self.generated_dir()?.join("index.json"), index),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn store_version_index(&self, index: &MetaVersionIndex) -> Result<String> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => self.store_json(
+                &self.generated_dir()?.join(&index.uid).join("index.json"),
+                index,
+            ),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn store_meta_version(&self, version: &MetaVersion) -> Result<String> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => self.store_json(
+                &self
+                    .generated_dir()?
+                    .join(&version.uid)
+                    .join(format!("{}.json", version.version)),
+                version,
+            ),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    /**
+     * Write every version of a package, the package index listing them newest first and its entry in the global index
+     */
+    pub fn store_package(
+        &self,
+        uid: &str,
+        name: &str,
+        versions: Vec<(MetaVersion, bool)>,
+    ) -> Result<()> {
+        let mut entries = vec![];
+        for (version, recommended) in versions {
+            let sha256 = self.store_meta_version(&version)?;
+            entries.push(MetaVersionIndexEntry {
+                version: version.version,
+                version_type: version.version_type,
+                release_time: version.release_time,
+                requires: version.requires,
+                conflicts: version.conflicts,
+                recommended,
+                volatile: version.volatile,
+                sha256,
+            });
+        }
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.release_time));
+
+        let version_index = MetaVersionIndex {
+            format_version: META_FORMAT_VERSION,
+            name: name.to_string(),
+            uid: uid.to_string(),
+            versions: entries,
+        };
+        let sha256 = self.store_version_index(&version_index)?;
+
+        let _package_index = PACKAGE_INDEX.lock().unwrap_or_else(|err| err.into_inner());
+        let mut package_index = self.load_package_index()?.unwrap_or(MetaPackageIndex {
+            format_version: META_FORMAT_VERSION,
+            packages: vec![],
+        });
+        package_index.packages.retain(|package| package.uid != uid);
+        package_index.packages.push(MetaPackageIndexEntry {
+            name: name.to_string(),
+            uid: uid.to_string(),
+            sha256,
+        });
+        package_index.packages.sort_by(|a, b| a.uid.cmp(&b.uid));
+        self.store_package_index(&package_index)?;
+
+        info!(
+            "Generated {} versions of {}",
+            version_index.versions.len(),
+            uid
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use libmcmeta::models::MetaVersion;
+    use tempdir::TempDir;
+
+    use super::GeneratedDataStorage;
+    use crate::app_config::StorageFormat;
+
+    #[test]
+    fn test_store_package_concurrently_keeps_every_package() {
+        let tmp_dir = TempDir::new("mcmeta_generated").unwrap();
+        let storage = GeneratedDataStorage::new(Arc::new(StorageFormat::Json {
+            meta_directory: tmp_dir.path().join("meta").to_string_lossy().into_owned(),
+            generated_directory: tmp_dir
+                .path()
+                .join("generated")
+                .to_string_lossy()
+                .into_owned(),
+        }));
+
+        std::thread::scope(|scope| {
+            for package in 0..8 {
+                let storage = &storage;
+                scope.spawn(move || {
+                    let uid = format!("org.example.package{}", package);
+                    for version in 0..5 {
+                        let version = MetaVersion {
+                            format_version: 1,
+                            name: uid.clone(),
+                            version: format!("1.{}", version),
+                            uid: uid.clone(),
+                            ..Default::default()
+                        };
+                        storage
+                            .store_package(&uid, &uid, vec![(version, false)])
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        let index = storage.load_package_index().unwrap().unwrap();
+        assert_eq!(
+            index
+                .packages
+                .iter()
+                .map(|package| package.uid.as_str())
+                .collect::<Vec<_>>(),
+            (0..8)
+                .map(|package| format!("org.example.package{}", package))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::storage::{
+    forge::ForgeDataStorage,
+    generated::GeneratedDataStorage,
+    mojang::MojangDataStorage,
+    validation::{json_file_stems, InvalidMetadataFile, MetadataValidationReport},
+    StorageFormat,
+};
+use crate::utils::{filehash, HashAlgo};
+
+/// How many versions of each component are checked per deep health check
+const SAMPLE_SIZE: usize = 5;
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct DeepHealthReport {
+    pub healthy: bool,
+    pub components: BTreeMap<String, MetadataValidationReport>,
+}
+
+/**
+ * Pick up to `count` evenly spread items, starting at an offset that changes between checks
+ * so that repeated checks cover different files
+ */
+fn sample<T: Clone>(items: &[T], count: usize) -> Vec<T> {
+    if items.len() <= count {
+        return items.to_vec();
+    }
+    let step = items.len() / count;
+    let offset = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as usize)
+        % step;
+    items
+        .iter()
+        .skip(offset)
+        .step_by(step)
+        .take(count)
+        .cloned()
+        .collect()
+}
+
+/**
+ * Check that a file exists and that its sha256 matches the one recorded in an index
+ */
+fn check_hash(report: &mut MetadataValidationReport, path: &std::path::Path, expected: &str) {
+    let problem = if !path.is_file() {
+        Some("file listed in the index is missing".to_string())
+    } else {
+        match filehash(&path.to_path_buf(), HashAlgo::Sha256) {
+            Ok(actual) if actual.eq_ignore_ascii_case(expected) => None,
+            Ok(actual) => Some(format!(
+                "sha256 {} does not match the index ({})",
+                actual.to_lowercase(),
+                expected
+            )),
+            Err(err) => Some(format!("{:#}", err)),
+        }
+    };
+    if let Some(error) = problem {
+        report.invalid_files.push(InvalidMetadataFile {
+            path: path.to_string_lossy().to_string(),
+            error,
+        });
+    }
+}
+
+impl MojangDataStorage {
+    pub fn check_health(&self) -> Result<MetadataValidationReport> {
+        let mut report = MetadataValidationReport::default();
+        report.check(
+            &self.meta_dir()?.join("version_manifest_v2.json"),
+            self.load_manifest(),
+        );
+
+        let versions_dir = self.versions_dir()?;
+        for id in sample(&json_file_stems(&versions_dir)?, SAMPLE_SIZE) {
+            report.check(
+                &versions_dir.join(format!("{}.json", id)),
+                self.load_minecraft_version(&id),
+            );
+        }
+        Ok(report)
+    }
+}
+
+impl ForgeDataStorage {
+    pub fn check_health(&self) -> Result<MetadataValidationReport> {
+        let mut report = MetadataValidationReport::default();
+        let meta_dir = self.meta_dir()?;
+        report.check(
+            &meta_dir.join("maven-metadata.json"),
+            self.load_maven_metadata(),
+        );
+        report.check(
+            &meta_dir.join("promotions_slim.json"),
+            self.load_forge_promotions(),
+        );
+        report.check(&meta_dir.join("derived_index.json"), self.load_index());
+
+        if let (Some(index_hash), Some(last_index)) = (self.index_hash()?, self.load_index_entry()?)
+        {
+            if !index_hash.eq_ignore_ascii_case(&last_index.hash) {
+                report.invalid_files.push(InvalidMetadataFile {
+                    path: meta_dir
+                        .join("derived_index.json")
+                        .to_string_lossy()
+                        .to_string(),
+                    error: "changed since the installers were last processed".to_string(),
+                });
+            }
+        }
+
+        let manifests_dir = self.manifests_dir()?;
+        for name in sample(&json_file_stems(&manifests_dir)?, SAMPLE_SIZE) {
+            report.check(
+                &manifests_dir.join(format!("{}.json", name)),
+                self.load_files_manifest(&name),
+            );
+        }
+
+        let installer_manifests_dir = self.installer_manifests_dir()?;
+        for name in sample(&json_file_stems(&installer_manifests_dir)?, SAMPLE_SIZE) {
+            report.check(
+                &installer_manifests_dir.join(format!("{}.json", name)),
+                self.load_installer_manifest(&name),
+            );
+        }
+        Ok(report)
+    }
+}
+
+impl GeneratedDataStorage {
+    /**
+     * Check the package index, and for every package its index and a sample of its versions
+     */
+    pub fn check_health(&self) -> Result<BTreeMap<String, MetadataValidationReport>> {
+        let mut components = BTreeMap::new();
+        let generated_dir = self.generated_dir()?;
+
+        let mut index_report = MetadataValidationReport::default();
+        let package_index = self.load_package_index();
+        let packages = match &package_index {
+            Ok(Some(index)) => index.packages.clone(),
+            _ => vec![],
+        };
+        index_report.check(&generated_dir.join("index.json"), package_index);
+        components.insert("index".to_string(), index_report);
+
+        for package in packages {
+            let mut report = MetadataValidationReport::default();
+            let package_dir = generated_dir.join(&package.uid);
+            check_hash(
+                &mut report,
+                &package_dir.join("index.json"),
+                &package.sha256,
+            );
+
+            let version_index = self.load_version_index(&package.uid);
+            let versions = match &version_index {
+                Ok(Some(index)) => index.versions.clone(),
+                _ => vec![],
+            };
+            report.check(&package_dir.join("index.json"), version_index);
+
+            for entry in sample(&versions, SAMPLE_SIZE) {
+                let path = package_dir.join(format!("{}.json", entry.version));
+                check_hash(&mut report, &path, &entry.sha256);
+                report.check_parse(&path, self.load_meta_version(&package.uid, &entry.version));
+            }
+            components.insert(package.uid, report);
+        }
+        Ok(components)
+    }
+}
+
+impl StorageFormat {
+    /**
+     * Check that the stored and generated metadata directories can be listed
+     */
+    pub fn check_reachable(&self) -> Result<()> {
+        match self {
+            StorageFormat::Json {
+                meta_directory,
+                generated_directory,
+            } => {
+                for directory in [meta_directory, generated_directory] {
+                    std::fs::read_dir(directory)
+                        .with_context(|| format!("Failure listing directory {}", directory))?;
+                }
+                Ok(())
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    /**
+     * Sample the stored metadata of every component, checking it parses and matches the index hashes
+     */
+    pub fn check_health(&self) -> Result<DeepHealthReport> {
+        let storage_format = Arc::new(self.clone());
+        let mut report = DeepHealthReport::default();
+
+        match self {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                report.components.insert(
+                    "raw/mojang".to_string(),
+                    MojangDataStorage::new(storage_format.clone()).check_health()?,
+                );
+                report.components.insert(
+                    "raw/forge".to_string(),
+                    ForgeDataStorage::new(storage_format.clone()).check_health()?,
+                );
+                report
+                    .components
+                    .extend(GeneratedDataStorage::new(storage_format).check_health()?);
+            }
+            StorageFormat::Database => todo!(),
+        }
+
+        report.healthy = report.components.values().all(|c| c.is_valid());
+        if report.healthy {
+            info!("Deep health check passed");
+        } else {
+            warn!(
+                "Deep health check failed for {:?}",
+                report
+                    .components
+                    .iter()
+                    .filter(|(_, c)| !c.is_valid())
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>()
+            );
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use libmcmeta::models::forge::ForgeProcessedVersion;
+use tracing::{debug, info};
+
+use crate::{
+    app_config::ForgeJarsConfig,
+    download,
+    storage::{forge::ForgeDataStorage, validation::json_file_stems},
+};
+
+/// What pruning the downloaded Forge jars deleted
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JarPruneReport {
+    pub deleted: usize,
+    pub freed_bytes: u64,
+}
+
+/// A downloaded jar along with what the retention policy decides on
+struct StoredJar {
+    path: PathBuf,
+    size: u64,
+    last_used: SystemTime,
+    processed: bool,
+}
+
+impl ForgeJarsConfig {
+    /**
+     * Whether the policy deletes anything at all, so jars are only listed when it does
+     */
+    pub fn is_pruning(&self) -> bool {
+        self.delete_processed || self.max_bytes > 0 || self.max_age > 0
+    }
+}
+
+impl ForgeDataStorage {
+    /**
+     * The file names of the jars whose metadata has been extracted, which are not needed to
+     * regenerate Forge: installers whose profile and info are stored, and jar mods listed in the
+     * legacy info
+     */
+    fn processed_jars(&self, static_directory: &str) -> Result<Option<HashSet<String>>> {
+        let Some(index) = self.load_index()? else {
+            return Ok(None);
+        };
+        let manifests: HashSet<String> = json_file_stems(&self.installer_manifests_dir()?)?
+            .into_iter()
+            .collect();
+        let infos: HashSet<String> = json_file_stems(&self.installer_info_dir()?)?
+            .into_iter()
+            .collect();
+        let legacy_info = self.load_legacy_info(static_directory)?.unwrap_or_default();
+
+        let maven_url = download::forge::maven_base_url()?;
+        let processed = index
+            .versions
+            .values()
+            .map(|entry| ForgeProcessedVersion::new(entry, &maven_url))
+            .filter(|version| {
+                if version.uses_installer() {
+                    manifests.contains(&version.long_version)
+                        && infos.contains(&version.long_version)
+                } else {
+                    legacy_info.number.contains_key(&version.long_version)
+                }
+            })
+            .filter_map(|version| version.filename())
+            .collect();
+        Ok(Some(processed))
+    }
+
+    /**
+     * Delete downloaded Forge jars by the retention policy: jars whose metadata has been extracted
+     * when `delete_processed` is set, then jars unused for longer than `max_age`, then the least
+     * recently used ones until the rest fit into `max_bytes`. A jar is used when it is downloaded
+     * or served, as far as the file system tracks access times.
+     */
+    pub fn prune_jars(
+        &self,
+        config: &ForgeJarsConfig,
+        static_directory: &str,
+    ) -> Result<JarPruneReport> {
+        let mut report = JarPruneReport::default();
+        if !config.is_pruning() {
+            return Ok(report);
+        }
+
+        let processed = if config.delete_processed {
+            self.processed_jars(static_directory)?
+        } else {
+            None
+        };
+        let jars_dir = self.forge_jars_dir()?;
+        let mut jars = vec![];
+        for entry in std::fs::read_dir(&jars_dir)
+            .with_context(|| format!("Failure reading directory {}", jars_dir.display()))?
+        {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            jars.push(StoredJar {
+                path: entry.path(),
+                size: metadata.len(),
+                last_used: metadata
+                    .accessed()
+                    .or_else(|_| metadata.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH),
+                processed: processed
+                    .as_ref()
+                    .is_some_and(|processed| processed.contains(&name)),
+            });
+        }
+        // processed jars go regardless, then the least recently used first
+        jars.sort_by_key(|jar| (!jar.processed, jar.last_used));
+
+        let cutoff = (config.max_age > 0)
+            .then(|| SystemTime::now().checked_sub(Duration::from_secs(config.max_age)))
+            .flatten();
+        let mut kept_bytes: u64 = jars.iter().map(|jar| jar.size).sum();
+        for jar in jars {
+            let reason = if jar.processed {
+                "metadata extracted"
+            } else if cutoff.is_some_and(|cutoff| jar.last_used < cutoff) {
+                "unused for longer than the maximum age"
+            } else if config.max_bytes > 0 && kept_bytes > config.max_bytes {
+                "over the size limit"
+            } else {
+                continue;
+            };
+            debug!("Deleting Forge jar {} ({})", jar.path.display(), reason);
+            std::fs::remove_file(&jar.path)
+                .with_context(|| format!("Failure deleting {}", jar.path.display()))?;
+            kept_bytes -= jar.size;
+            report.deleted += 1;
+            report.freed_bytes += jar.size;
+        }
+        if report.deleted > 0 {
+            info!(
+                "Deleted {} Forge jars, freeing {} bytes",
+                report.deleted, report.freed_bytes
+            );
+        }
+        Ok(report)
+    }
+}
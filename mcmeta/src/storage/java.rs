@@ -0,0 +1,218 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use futures::{stream, StreamExt};
+use libmcmeta::models::java::{runtime_os, JavaRuntimeIndex, JavaRuntimeMeta};
+use tracing::{debug, info};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{
+    download::{self, azul::AzulPlatform, errors::MetadataError},
+    storage::{
+        validation::MetadataValidationReport, StorageFormat, UpdateReport, UpdateSource,
+        UpstreamMetadataUpdater,
+    },
+    utils::{process_results, write_json_atomic},
+};
+
+/// A vendor whose Java runtime builds are indexed next to Mojang's own runtimes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JavaVendor {
+    Adoptium,
+    Azul,
+}
+
+impl JavaVendor {
+    pub const ALL: [JavaVendor; 2] = [JavaVendor::Adoptium, JavaVendor::Azul];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|vendor| vendor.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            JavaVendor::Adoptium => "adoptium",
+            JavaVendor::Azul => "azul",
+        }
+    }
+
+    pub fn uid(&self) -> &'static str {
+        match self {
+            JavaVendor::Adoptium => "net.adoptium.java",
+            JavaVendor::Azul => "com.azul.java",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct JavaDataStorage {
+    storage_format: Arc<StorageFormat>,
+}
+
+impl JavaDataStorage {
+    pub fn new(storage_format: Arc<StorageFormat>) -> Self {
+        Self { storage_format }
+    }
+
+    pub fn meta_dir(&self) -> Result<std::path::PathBuf> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                ref meta_directory,
+                generated_directory: _,
+            } => {
+                let metadata_dir = std::path::Path::new(&meta_directory);
+                let java_meta_dir = metadata_dir.join("java");
+
+                if !java_meta_dir.is_dir() {
+                    info!(
+                        "Java metadata directory at {} does not exist, creating it",
+                        java_meta_dir.display()
+                    );
+                    std::fs::create_dir_all(&java_meta_dir)?;
+                }
+                Ok(java_meta_dir)
+            }
+            StorageFormat::Database => Err(anyhow!("Wrong storage format")),
+        }
+    }
+
+    pub fn index_file(&self, vendor: JavaVendor) -> Result<std::path::PathBuf> {
+        Ok(self.meta_dir()?.join(format!("{}.json", vendor.name())))
+    }
+
+    pub fn load_index(&self, vendor: JavaVendor) -> Result<Option<JavaRuntimeIndex>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let file = self.index_file(vendor)?;
+                if file.is_file() {
+                    let body = std::fs::read_to_string(&file).with_context(|| {
+                        format!("Failure reading file {}", file.to_string_lossy())
+                    })?;
+                    let index = serde_json::from_str::<JavaRuntimeIndex>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
+                    Ok(Some(index))
+                } else {
+                    Ok(None)
+                }
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn store_index(&self, vendor: JavaVendor, index: &JavaRuntimeIndex) -> Result<()> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let file = self.index_file(vendor)?;
+                write_json_atomic(&file, index)?;
+                Ok(())
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn validate_stored(&self, report: &mut MetadataValidationReport) -> Result<()> {
+        for vendor in JavaVendor::ALL {
+            report.check(&self.index_file(vendor)?, self.load_index(vendor));
+        }
+        Ok(())
+    }
+}
+
+impl UpstreamMetadataUpdater {
+    pub async fn update_upstream_java(&self, report: &mut UpdateReport) -> Result<()> {
+        if self.metadata_cfg.java_runtime_majors.is_empty() {
+            debug!("No Java majors configured, skipping Java runtime indexing");
+            return Ok(());
+        }
+
+        info!("Checking for Java runtime metadata");
+        for vendor in JavaVendor::ALL {
+            self.update_java_runtimes(vendor, report)
+                .await
+                .with_context(|| format!("Failed to update {} Java runtimes.", vendor.name()))?;
+        }
+        Ok(())
+    }
+
+    pub async fn update_java_runtimes(
+        &self,
+        vendor: JavaVendor,
+        report: &mut UpdateReport,
+    ) -> Result<()> {
+        let local_storage = JavaDataStorage::new(self.storage_format.clone());
+
+        let mut majors = BTreeMap::new();
+        for &major in &self.metadata_cfg.java_runtime_majors {
+            let runtimes = match vendor {
+                JavaVendor::Adoptium => download::adoptium::load_latest_assets(major)
+                    .await?
+                    .iter()
+                    .filter_map(|asset| asset.to_runtime_meta())
+                    .collect(),
+                JavaVendor::Azul => self.load_azul_runtimes(major).await?,
+            };
+            majors.insert(major, runtimes);
+        }
+        let remote_index = JavaRuntimeIndex {
+            vendor: vendor.name().to_string(),
+            majors,
+        };
+
+        let local_index = local_storage.load_index(vendor)?;
+        let changed = remote_index
+            .majors
+            .iter()
+            .filter(|(major, runtimes)| {
+                let local_runtimes = local_index
+                    .as_ref()
+                    .and_then(|index| index.majors.get(*major));
+                match local_runtimes {
+                    Some(local_runtimes) => {
+                        serde_json::to_value(local_runtimes).ok()
+                            != serde_json::to_value(runtimes).ok()
+                    }
+                    None => true,
+                }
+            })
+            .count();
+        report.record_updated_versions(vendor.uid(), changed);
+
+        debug!("Dumping {} Java runtimes", vendor.name());
+        local_storage.store_index(vendor, &remote_index)?;
+        Ok(())
+    }
+
+    /**
+     * The newest Azul package of a Java major for every platform, which takes a request for the
+     * listing and another for the checksum of each package
+     */
+    async fn load_azul_runtimes(&self, major: i32) -> Result<Vec<JavaRuntimeMeta>> {
+        let load_platform = |platform: AzulPlatform| async move {
+            let packages = download::azul::load_latest_packages(major, &platform).await?;
+            let Some(package) = packages.first() else {
+                return Ok(None);
+            };
+            let detail = download::azul::load_package_detail(&package.package_uuid).await?;
+            Ok(runtime_os(platform.os, platform.arch_name())
+                .and_then(|runtime_os| detail.to_runtime_meta(&runtime_os)))
+        };
+
+        let results = stream::iter(download::azul::AZUL_PLATFORMS)
+            .map(load_platform)
+            .buffer_unordered(self.metadata_cfg.parallelism(UpdateSource::Java))
+            .collect::<Vec<Result<Option<JavaRuntimeMeta>>>>()
+            .await;
+        let mut runtimes = process_results(results)?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        runtimes.sort_by(|a, b| a.runtime_os.cmp(&b.runtime_os));
+        Ok(runtimes)
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use libmcmeta::models::liteloader::{LiteloaderEntry, LiteloaderIndex};
+use tracing::{debug, info};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{
+    download::{self, errors::MetadataError},
+    storage::{
+        validation::MetadataValidationReport, StorageFormat, UpdateReport, UpstreamMetadataUpdater,
+    },
+    utils::write_json_atomic,
+};
+
+pub const LITELOADER_UID: &str = "com.mumfrey.liteloader";
+
+#[derive(Clone)]
+pub struct LiteLoaderDataStorage {
+    storage_format: Arc<StorageFormat>,
+}
+
+impl LiteLoaderDataStorage {
+    pub fn new(storage_format: Arc<StorageFormat>) -> Self {
+        Self { storage_format }
+    }
+
+    pub fn meta_dir(&self) -> Result<std::path::PathBuf> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                ref meta_directory,
+                generated_directory: _,
+            } => {
+                let metadata_dir = std::path::Path::new(&meta_directory);
+                let liteloader_meta_dir = metadata_dir.join("liteloader");
+
+                if !liteloader_meta_dir.is_dir() {
+                    info!(
+                        "LiteLoader metadata directory at {} does not exist, creating it",
+                        liteloader_meta_dir.display()
+                    );
+                    std::fs::create_dir_all(&liteloader_meta_dir)?;
+                }
+                Ok(liteloader_meta_dir)
+            }
+            StorageFormat::Database => Err(anyhow!("Wrong storage format")),
+        }
+    }
+
+    pub fn versions_file(&self) -> Result<std::path::PathBuf> {
+        Ok(self.meta_dir()?.join("versions.json"))
+    }
+
+    pub fn load_index(&self) -> Result<Option<LiteloaderIndex>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let file = self.versions_file()?;
+                if file.is_file() {
+                    let body = std::fs::read_to_string(&file).with_context(|| {
+                        format!("Failure reading file {}", file.to_string_lossy())
+                    })?;
+                    let index = serde_json::from_str::<LiteloaderIndex>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
+                    Ok(Some(index))
+                } else {
+                    Ok(None)
+                }
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn store_index(&self, index: &LiteloaderIndex) -> Result<()> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let file = self.versions_file()?;
+                write_json_atomic(&file, &index)?;
+                Ok(())
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    /**
+     * The LiteLoader builds for a Minecraft version, `None` if there is no index or no builds for it
+     */
+    pub fn load_entry(&self, mc_version: &str) -> Result<Option<LiteloaderEntry>> {
+        Ok(self
+            .load_index()?
+            .and_then(|mut index| index.versions.remove(mc_version)))
+    }
+
+    pub fn validate_stored(&self, report: &mut MetadataValidationReport) -> Result<()> {
+        report.check(&self.versions_file()?, self.load_index());
+        Ok(())
+    }
+}
+
+impl UpstreamMetadataUpdater {
+    pub async fn update_upstream_liteloader(&self, report: &mut UpdateReport) -> Result<()> {
+        info!("Checking for LiteLoader metadata");
+        self.update_liteloader_metadata(report)
+            .await
+            .with_context(|| "Failed to update LiteLoader metadata.")?;
+        Ok(())
+    }
+
+    pub async fn update_liteloader_metadata(&self, report: &mut UpdateReport) -> Result<()> {
+        let local_storage = LiteLoaderDataStorage::new(self.storage_format.clone());
+
+        let remote_index = download::liteloader::load_versions().await?;
+        let local_index = local_storage.load_index()?;
+
+        // the whole index is a single file, count the Minecraft versions whose builds changed
+        let changed = remote_index
+            .versions
+            .iter()
+            .filter(|(mc_version, entry)| {
+                let local_entry = local_index
+                    .as_ref()
+                    .and_then(|index| index.versions.get(*mc_version));
+                match local_entry {
+                    Some(local_entry) => {
+                        serde_json::to_value(local_entry).ok() != serde_json::to_value(entry).ok()
+                    }
+                    None => true,
+                }
+            })
+            .count();
+        report.record_updated_versions(LITELOADER_UID, changed);
+
+        debug!("Dumping LiteLoader versions");
+        local_storage.store_index(&remote_index)?;
+
+        Ok(())
+    }
+}
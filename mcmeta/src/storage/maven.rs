@@ -0,0 +1,119 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use tracing::{debug, warn};
+
+use crate::{
+    app_config::MavenMirrorConfig,
+    download::{self, errors::NotFoundUpstream},
+};
+
+/// Files upstream publishes next to an artifact to check it with, kept as they are
+const CHECKSUM_EXTENSIONS: [&str; 5] = [".sha1", ".md5", ".sha256", ".sha512", ".asc"];
+
+/**
+ * Whether a path of a maven repository is safe to look up on disk and upstream, which rules out
+ * empty, `.` and `..` segments as well as the `.part` files of running downloads
+ */
+pub fn is_repository_path(path: &str) -> bool {
+    !path.ends_with(".part")
+        && path.split('/').all(|segment| {
+            !segment.is_empty() && segment != "." && segment != ".." && !segment.contains('\\')
+        })
+}
+
+fn is_checksum(path: &str) -> bool {
+    CHECKSUM_EXTENSIONS
+        .iter()
+        .any(|extension| path.ends_with(extension))
+}
+
+/// Files of the maven repositories launchers fetch libraries from, downloaded from the first
+/// upstream that has them when first asked for and kept on disk
+#[derive(Clone, Debug)]
+pub struct MavenMirror {
+    directory: PathBuf,
+    upstreams: Vec<String>,
+}
+
+impl MavenMirror {
+    pub fn new(config: &MavenMirrorConfig) -> Self {
+        Self {
+            directory: PathBuf::from(&config.directory),
+            upstreams: config
+                .upstreams
+                .iter()
+                .map(|upstream| upstream.trim().trim_end_matches('/').to_string())
+                .filter(|upstream| !upstream.is_empty())
+                .collect(),
+        }
+    }
+
+    /**
+     * The file at a path of the repository, e.g. `net/minecraftforge/forge/1.20.1-47.2.0/forge-1.20.1-47.2.0-installer.jar`.
+     * A file that is not cached yet is looked up on each upstream in order, failing with
+     * `NotFoundUpstream` when every upstream answered that it does not exist.
+     */
+    pub async fn fetch(&self, path: &str) -> Result<PathBuf> {
+        if !is_repository_path(path) {
+            return Err(anyhow!("Invalid repository path {}", path));
+        }
+        let file = self.directory.join(path);
+        if file.is_file() {
+            return Ok(file);
+        }
+
+        let mut failures = vec![];
+        let mut missing = 0;
+        for upstream in &self.upstreams {
+            let url = format!("{}/{}", upstream, path);
+            match fetch_from(&file, &url, is_checksum(path)).await {
+                Ok(()) => return Ok(file),
+                Err(err) => {
+                    if err.downcast_ref::<NotFoundUpstream>().is_some() {
+                        missing += 1;
+                    }
+                    failures.push(format!("{:#}", err));
+                }
+            }
+        }
+        // the artifact does not exist when every upstream says so, and is unavailable otherwise
+        if missing > 0 && missing == failures.len() {
+            return Err(NotFoundUpstream(path.to_string()).into());
+        }
+        Err(anyhow!(
+            "No upstream serves {}: {}",
+            path,
+            failures.join("; ")
+        ))
+    }
+}
+
+/**
+ * Download a file of the repository from one upstream, checked against the `.sha1` upstream
+ * publishes next to it unless it is a checksum itself
+ */
+async fn fetch_from(file: &std::path::Path, url: &str, checksum: bool) -> Result<()> {
+    let sha1 = if checksum {
+        None
+    } else {
+        match download::download_text(&format!("{}.sha1", url)).await {
+            Ok(body) => body
+                .split_whitespace()
+                .next()
+                .filter(|sha1| sha1.len() == 40 && sha1.bytes().all(|b| b.is_ascii_hexdigit()))
+                .map(str::to_lowercase),
+            Err(err) => {
+                debug!("No sha1 for {}: {:#}", url, err);
+                None
+            }
+        }
+    };
+
+    debug!("Downloading maven file from {}", url);
+    download::download_verified_file(file, url, sha1.as_deref()).await?;
+    if sha1.is_none() && !checksum {
+        warn!("Cached {} unverified, upstream has no sha1 for it", url);
+    }
+    Ok(())
+}
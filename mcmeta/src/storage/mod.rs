@@ -1,18 +1,132 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
-use crate::{app_config::MetadataConfig, app_config::StorageFormat};
+use crate::{
+    app_config::{ErrorPolicy, MetadataConfig, StorageFormat},
+    lint::Lint,
+    storage::coordinator::{VersionChanges, UPDATE_COORDINATOR},
+    utils::process_results,
+};
 use anyhow::Result;
-use tracing::info;
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+use tracing::{debug, info, info_span, warn, Instrument};
 
-mod forge;
-mod mojang;
+pub mod assets;
+pub mod bundle;
+pub mod checksums;
+pub mod closure;
+pub mod coordinator;
+pub mod fabric;
+pub mod forge;
+pub mod generated;
+pub mod health;
+pub mod jars;
+pub mod java;
+pub mod liteloader;
+pub mod maven;
+pub mod mojang;
+pub mod neoforge;
+pub mod optifine;
+pub mod quarantine;
+pub mod search;
+pub mod validation;
+
+/// Set once a full update of every enabled source succeeded since the process started
+pub static METADATA_SYNCED: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
+
+/// An upstream source of metadata which can be updated on its own
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateSource {
+    Mojang,
+    Forge,
+    NeoForge,
+    Fabric,
+    Babric,
+    LegacyFabric,
+    Ornithe,
+    LiteLoader,
+    OptiFine,
+    Java,
+}
+
+impl UpdateSource {
+    /// Every source, in the order a full update goes through them
+    pub const ALL: [UpdateSource; 10] = [
+        UpdateSource::Mojang,
+        UpdateSource::Forge,
+        UpdateSource::NeoForge,
+        UpdateSource::Fabric,
+        UpdateSource::Babric,
+        UpdateSource::LegacyFabric,
+        UpdateSource::Ornithe,
+        UpdateSource::LiteLoader,
+        UpdateSource::OptiFine,
+        UpdateSource::Java,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|source| source.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UpdateSource::Mojang => "mojang",
+            UpdateSource::Forge => "forge",
+            UpdateSource::NeoForge => "neoforge",
+            UpdateSource::Fabric => "fabric",
+            UpdateSource::Babric => "babric",
+            UpdateSource::LegacyFabric => "legacyfabric",
+            UpdateSource::Ornithe => "ornithe",
+            UpdateSource::LiteLoader => "liteloader",
+            UpdateSource::OptiFine => "optifine",
+            UpdateSource::Java => "java",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for UpdateSource {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Self::from_name(name.trim()).ok_or_else(|| {
+            D::Error::custom(format!(
+                "unknown source {}, expected one of {}",
+                name,
+                Self::ALL.map(|source| source.name()).join(", ")
+            ))
+        })
+    }
+}
 
 impl StorageFormat {
-    pub async fn update_upstream_metadata(&self, metadata_cfg: &MetadataConfig) -> Result<()> {
-        let updater = UpstreamMetadataUpdater {
-            storage_format: Arc::new(self.clone()),
-            metadata_cfg: Arc::new(metadata_cfg.clone()),
-        };
+    pub async fn update_upstream_metadata(
+        &self,
+        metadata_cfg: &MetadataConfig,
+    ) -> Result<UpdateReport> {
+        let report = self
+            .update_upstream_sources(&metadata_cfg.enabled_sources(), metadata_cfg)
+            .await?;
+        METADATA_SYNCED.store(true, std::sync::atomic::Ordering::Relaxed);
+        Ok(report)
+    }
+
+    /**
+     * Update the given sources only, waiting for any update of the same source already running to
+     * finish first
+     */
+    pub async fn update_upstream_sources(
+        &self,
+        sources: &[UpdateSource],
+        metadata_cfg: &MetadataConfig,
+    ) -> Result<UpdateReport> {
+        let updater =
+            UpstreamMetadataUpdater::new(Arc::new(self.clone()), Arc::new(metadata_cfg.clone()));
+        crate::download::apply_settings(metadata_cfg)?;
         match self {
             StorageFormat::Json {
                 meta_directory,
@@ -30,15 +144,321 @@ impl StorageFormat {
             StorageFormat::Database => todo!(),
         }
 
-        updater.update_mojang_metadata().await?;
-        updater.update_forge_metadata().await?;
+        UPDATE_COORDINATOR.restore_history(self);
+        let generated_storage = generated::GeneratedDataStorage::new(Arc::new(self.clone()));
+        let mut report = UpdateReport::default();
+        for source in sources {
+            let update = UPDATE_COORDINATOR.begin(*source).await;
+            let generating = UPDATE_COORDINATOR.lock_generating().await;
+            let hashes_before = generated_version_hashes(&generated_storage).await;
+            let result = async {
+                match source {
+                    UpdateSource::Mojang => updater.update_upstream_mojang(&mut report).await,
+                    UpdateSource::Forge => updater.update_upstream_forge(&mut report).await,
+                    UpdateSource::NeoForge => updater.update_upstream_neoforge(&mut report).await,
+                    UpdateSource::Fabric => updater.update_upstream_fabric(&mut report).await,
+                    UpdateSource::Babric => updater.update_upstream_babric(&mut report).await,
+                    UpdateSource::LegacyFabric => {
+                        updater.update_upstream_legacy_fabric(&mut report).await
+                    }
+                    UpdateSource::Ornithe => updater.update_upstream_ornithe(&mut report).await,
+                    UpdateSource::LiteLoader => {
+                        updater.update_upstream_liteloader(&mut report).await
+                    }
+                    UpdateSource::OptiFine => updater.update_upstream_optifine(&mut report).await,
+                    UpdateSource::Java => updater.update_upstream_java(&mut report).await,
+                }
+            }
+            .instrument(info_span!("update", source = source.name()))
+            .await;
+            // a failed update may still have written some files
+            let checksum_storage = self.clone();
+            let checksum_source = *source;
+            tokio::task::spawn_blocking(move || {
+                checksum_storage.refresh_checksums_of(&[checksum_source])
+            })
+            .await?;
+            let hashes_after = match result {
+                Ok(()) => generated_version_hashes(&generated_storage).await,
+                Err(_) => None,
+            };
+            drop(generating);
+            let hashes = hashes_before.zip(hashes_after);
+            update.finish(
+                &result,
+                hashes
+                    .as_ref()
+                    .map(|(before, after)| version_changes(before, after)),
+            );
+            if let Err(err) = UPDATE_COORDINATOR.store_history(self) {
+                warn!("Failure storing the update history: {:#}", err);
+            }
+            result?;
+            if let Some((hashes_before, hashes_after)) = hashes {
+                if crate::events::has_subscribers() {
+                    crate::events::publish_changes(*source, &hashes_before, &hashes_after);
+                }
+            }
+        }
+
+        crate::metrics::SERVER_METRICS.record_failed_versions(&report.failed_versions);
+        let _finishing = UPDATE_COORDINATOR.lock_finishing().await;
+
+        // responses are also checked against their file, but a request racing a rewrite could cache stale data
+        crate::routes::cache::RESPONSE_CACHE.clear();
+
+        let storage_format = Arc::new(self.clone());
+        match tokio::task::spawn_blocking(move || search::rebuild_index(storage_format)).await? {
+            Ok(index) => debug!("Indexed {} versions for search", index.len()),
+            // searches keep using the previous index
+            Err(err) => warn!("Failure rebuilding the search index: {:#}", err),
+        }
+
+        let lint_storage = generated_storage.clone();
+        match tokio::task::spawn_blocking(move || crate::lint::lint_generated(&lint_storage))
+            .await?
+        {
+            Ok(lints) => report.lints = lints,
+            // linting only points out suspicious output, it never fails the update
+            Err(err) => warn!("Failure linting generated metadata: {:#}", err),
+        }
+
+        if metadata_cfg.publish.enable {
+            let publish_cfg = metadata_cfg.publish.clone();
+            match tokio::task::spawn_blocking(move || {
+                crate::publish::publish_generated(&generated_storage.generated_dir()?, &publish_cfg)
+            })
+            .await?
+            {
+                Ok(_) => {}
+                // the metadata is updated either way, the next update publishes it again
+                Err(err) => warn!("Failure publishing generated metadata: {:#}", err),
+            }
+        }
+
+        report.log();
+        Ok(report)
+    }
+
+    /**
+     * Rebuild everything derived from the locally stored upstream metadata, without fetching anything
+     */
+    pub fn regenerate_metadata(&self, metadata_cfg: &MetadataConfig) -> Result<()> {
+        let storage_format = Arc::new(self.clone());
+        match self {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                // disabled sources keep what was generated from them while they were enabled
+                if metadata_cfg.is_enabled(UpdateSource::Mojang) {
+                    let mojang_storage = mojang::MojangDataStorage::new(storage_format.clone());
+                    if mojang_storage.generate(&metadata_cfg.static_directory)? {
+                        info!("Regenerated Mojang metadata");
+                    } else {
+                        info!("No local Mojang metadata to generate from");
+                    }
+                    if mojang_storage.generate_java_runtimes()? {
+                        info!("Regenerated Java runtime metadata");
+                    } else {
+                        info!("No local Java runtime metadata to generate from");
+                    }
+                }
+                if metadata_cfg.is_enabled(UpdateSource::Forge) {
+                    let forge_storage = forge::ForgeDataStorage::new(storage_format.clone());
+                    if forge_storage.reindex()? {
+                        info!("Reindexed local Forge metadata");
+                        info!(
+                            "Indexed {} MCP versions of local Forge installers",
+                            forge_storage.reindex_mcp_versions()?
+                        );
+                    } else {
+                        info!("No local Forge metadata to reindex");
+                    }
+                    if forge_storage.generate(
+                        &metadata_cfg.static_directory,
+                        &metadata_cfg.forge_wrapper.forge_wrapper()?,
+                    )? {
+                        info!("Regenerated Forge metadata");
+                    } else {
+                        info!("No local Forge metadata to generate from");
+                    }
+                }
+                for source in fabric::FabricSource::ALL {
+                    if !metadata_cfg.is_enabled(source.update_source()) {
+                        continue;
+                    }
+                    if fabric::FabricDataStorage::new(storage_format.clone(), source).generate()? {
+                        info!("Regenerated {} metadata", source.name());
+                    } else {
+                        info!("No local {} metadata to generate from", source.name());
+                    }
+                }
+            }
+            StorageFormat::Database => todo!(),
+        }
+        self.refresh_checksums_of(&metadata_cfg.enabled_sources());
 
         Ok(())
     }
 }
 
+/**
+ * The hashes of the generated versions, compared before and after each source is updated
+ */
+async fn generated_version_hashes(
+    storage: &generated::GeneratedDataStorage,
+) -> Option<BTreeMap<(String, String), String>> {
+    let storage = storage.clone();
+    match tokio::task::spawn_blocking(move || storage.version_hashes()).await {
+        Ok(Ok(hashes)) => Some(hashes),
+        Ok(Err(err)) => {
+            warn!("Failure reading the generated version hashes: {:#}", err);
+            None
+        }
+        Err(err) => {
+            warn!("Reading the generated version hashes failed: {:?}", err);
+            None
+        }
+    }
+}
+
+/**
+ * Count the generated versions an update added and changed
+ */
+fn version_changes(
+    before: &BTreeMap<(String, String), String>,
+    after: &BTreeMap<(String, String), String>,
+) -> VersionChanges {
+    let mut changes = VersionChanges::default();
+    for (_, change) in crate::events::version_changes(before, after) {
+        match change {
+            crate::events::VersionChange::Added => changes.added += 1,
+            crate::events::VersionChange::Changed => changes.updated += 1,
+            crate::events::VersionChange::Removed => {}
+        }
+    }
+    changes
+}
+
+/// Things worth an operator's attention noticed while updating the upstream metadata
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct UpdateReport {
+    /// number of versions downloaded or reprocessed per component uid
+    pub updated_versions: BTreeMap<String, usize>,
+    /// rule feature keys the models do not know yet, with the versions using them
+    pub unknown_rule_features: BTreeMap<String, BTreeSet<String>>,
+    /// suspicious generated output found by the lint pass
+    pub lints: Vec<Lint>,
+    /// versions that failed to update under the `continue` error policy, with their error, per
+    /// component uid
+    pub failed_versions: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl UpdateReport {
+    pub fn record_updated_versions(&mut self, uid: &str, count: usize) {
+        *self.updated_versions.entry(uid.to_string()).or_default() += count;
+    }
+
+    pub fn record_unknown_rule_features(&mut self, version: &str, features: BTreeSet<String>) {
+        for feature in features {
+            self.unknown_rule_features
+                .entry(feature)
+                .or_default()
+                .insert(version.to_string());
+        }
+    }
+
+    pub fn record_failed_version(&mut self, uid: &str, version: &str, err: &anyhow::Error) {
+        self.failed_versions
+            .entry(uid.to_string())
+            .or_default()
+            .insert(version.to_string(), format!("{:#}", err));
+    }
+
+    pub fn log(&self) {
+        for (uid, count) in &self.updated_versions {
+            info!("Updated {} versions of {}", count, uid);
+        }
+        for (feature, versions) in &self.unknown_rule_features {
+            warn!(
+                "Unknown rule feature {} is used by {} versions: {:?}",
+                feature,
+                versions.len(),
+                versions
+            );
+        }
+        for lint in &self.lints {
+            warn!("Lint: {}", lint);
+        }
+        for (uid, versions) in &self.failed_versions {
+            warn!(
+                "{} versions of {} failed to update and are retried on the next update",
+                versions.len(),
+                uid
+            );
+            for (version, error) in versions {
+                warn!("Failed to update {} {}: {}", uid, version, error);
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct UpstreamMetadataUpdater {
     storage_format: Arc<StorageFormat>,
     metadata_cfg: Arc<MetadataConfig>,
 }
+
+impl UpstreamMetadataUpdater {
+    pub fn new(storage_format: Arc<StorageFormat>, metadata_cfg: Arc<MetadataConfig>) -> Self {
+        Self {
+            storage_format,
+            metadata_cfg,
+        }
+    }
+
+    /**
+     * The results of the tasks updating single versions, each with its uid and version, by the
+     * error policy: `fail_fast` fails on any error, concatenating them like `process_results`, and
+     * `continue` records the failed versions in the report and goes on with the others
+     */
+    pub(crate) fn collect_version_results<T>(
+        &self,
+        results: Vec<(String, String, VersionTaskResult<T>)>,
+        report: &mut UpdateReport,
+    ) -> Result<Vec<T>> {
+        let results = results.into_iter().map(|(uid, version, task)| {
+            let result = match task {
+                Ok(Ok(t)) => Ok(t),
+                Ok(Err(e)) => {
+                    debug!("Task had an error: {:?}", e);
+                    Err(e)
+                }
+                Err(e) => {
+                    debug!("Task had a Join error: {:?}", e);
+                    Err(e.into())
+                }
+            };
+            (uid, version, result)
+        });
+        match self.metadata_cfg.error_policy {
+            ErrorPolicy::FailFast => {
+                process_results(results.map(|(_, _, result)| result).collect())
+            }
+            ErrorPolicy::Continue => {
+                let mut ok_results = vec![];
+                for (uid, version, result) in results {
+                    match result {
+                        Ok(ok_result) => ok_results.push(ok_result),
+                        Err(err) => report.record_failed_version(&uid, &version, &err),
+                    }
+                }
+                Ok(ok_results)
+            }
+        }
+    }
+}
+
+/// The outcome of a spawned task updating a single version
+pub(crate) type VersionTaskResult<T> = std::result::Result<Result<T>, tokio::task::JoinError>;
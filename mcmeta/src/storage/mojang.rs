@@ -1,27 +1,57 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    sync::Arc,
+};
 
 use futures::{stream, StreamExt};
+use libmcmeta::models::java::{mojang_runtime_os, MojangJavaRuntimeIndex, JAVA_UID};
 use libmcmeta::models::mojang::{
-    ExperimentEntry, ExperimentIndex, MinecraftVersion, MojangVersionManifest,
-    MojangVersionManifestVersion, OldSnapshotEntry, OldSnapshotIndex, VersionDownload,
-    VersionDownloads,
+    extract_lwjgl, ExperimentEntry, ExperimentIndex, LegacyOverrideIndex, LibraryPatches,
+    MinecraftVersion, MojangVersion, MojangVersionManifest, MojangVersionManifestVersion,
+    OldSnapshotEntry, OldSnapshotIndex, VersionDownload, VersionDownloads, LWJGL2_UID, LWJGL3_UID,
 };
-use tracing::{debug, info, warn};
+use libmcmeta::models::{GradleSpecifier, MetaVersion, META_FORMAT_VERSION};
+use serde::de::DeserializeOwned;
+use tempdir::TempDir;
+use tracing::{debug, info, info_span, warn, Instrument};
 
 use anyhow::{anyhow, Context, Result};
 
 use crate::{
-    download,
-    storage::{StorageFormat, UpstreamMetadataUpdater},
-    utils::process_results,
+    download::{
+        self,
+        conditional::{Conditional, UpstreamValidators},
+        errors::MetadataError,
+    },
+    storage::{
+        generated::GeneratedDataStorage,
+        quarantine::Quarantine,
+        validation::{json_file_stems, MetadataValidationReport},
+        StorageFormat, UpdateReport, UpdateSource, UpstreamMetadataUpdater,
+    },
+    utils::{filehash, write_json_atomic, HashAlgo},
 };
 
+/// Files of static Mojang data, in the `mojang` directory of the static directory
+pub const EXPERIMENTS_FILE: &str = "minecraft-experiments.json";
+pub const OLD_SNAPSHOTS_FILE: &str = "minecraft-old-snapshots.json";
+pub const LEGACY_OVERRIDES_FILE: &str = "minecraft-legacy-override.json";
+pub const LIBRARY_PATCHES_FILE: &str = "library-patches.json";
+
+/// Types of the generated versions of experiments and old snapshots
+pub const EXPERIMENT_TYPE: &str = "experiment";
+pub const OLD_SNAPSHOT_TYPE: &str = "old_snapshot";
+
 #[derive(Clone)]
 pub struct MojangDataStorage {
-    storage_format: Arc<StorageFormat>,
+    pub(super) storage_format: Arc<StorageFormat>,
 }
 
 impl MojangDataStorage {
+    pub fn new(storage_format: Arc<StorageFormat>) -> Self {
+        Self { storage_format }
+    }
+
     pub fn meta_dir(&self) -> Result<std::path::PathBuf> {
         match *self.storage_format {
             StorageFormat::Json {
@@ -74,14 +104,15 @@ impl MojangDataStorage {
             } => {
                 let local_manifest_path = self.meta_dir()?.join("version_manifest_v2.json");
                 if local_manifest_path.is_file() {
-                    let local_manifest = serde_json::from_str::<MojangVersionManifest>(
-                        &std::fs::read_to_string(&local_manifest_path).with_context(|| {
+                    let body =
+                        std::fs::read_to_string(&local_manifest_path).with_context(|| {
                             format!(
                                 "Failure reading file {}",
                                 &local_manifest_path.to_string_lossy()
                             )
-                        })?,
-                    )?;
+                        })?;
+                    let local_manifest = serde_json::from_str::<MojangVersionManifest>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
                     Ok(Some(local_manifest))
                 } else {
                     Ok(None)
@@ -98,19 +129,91 @@ impl MojangDataStorage {
                 generated_directory: _,
             } => {
                 let local_manifest_path = self.meta_dir()?.join("version_manifest_v2.json");
-                let manifest_json = serde_json::to_string_pretty(&manifest)?;
-                std::fs::write(&local_manifest_path, manifest_json).with_context(|| {
-                    format!(
-                        "Failure writing file {}",
-                        local_manifest_path.to_string_lossy()
-                    )
-                })?;
+                write_json_atomic(&local_manifest_path, &manifest)?;
                 Ok(())
             }
             StorageFormat::Database => todo!(),
         }
     }
 
+    /**
+     * The path of a file of static Mojang data
+     */
+    pub fn static_file(static_directory: &str, name: &str) -> std::path::PathBuf {
+        std::path::Path::new(static_directory)
+            .join("mojang")
+            .join(name)
+    }
+
+    fn load_static<T: DeserializeOwned>(static_directory: &str, name: &str) -> Result<Option<T>> {
+        let static_file = Self::static_file(static_directory, name);
+        if static_file.is_file() {
+            let body = std::fs::read_to_string(&static_file).with_context(|| {
+                format!("Failure reading file {}", static_file.to_string_lossy())
+            })?;
+            let data = serde_json::from_str::<T>(&body)
+                .map_err(|err| MetadataError::from_json_err(err, &body))?;
+            Ok(Some(data))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn load_legacy_overrides(
+        &self,
+        static_directory: &str,
+    ) -> Result<Option<LegacyOverrideIndex>> {
+        Self::load_static(static_directory, LEGACY_OVERRIDES_FILE)
+    }
+
+    pub fn load_library_patches(&self, static_directory: &str) -> Result<Option<LibraryPatches>> {
+        Self::load_static(static_directory, LIBRARY_PATCHES_FILE)
+    }
+
+    pub fn load_experiments(&self, static_directory: &str) -> Result<Option<ExperimentIndex>> {
+        Self::load_static(static_directory, EXPERIMENTS_FILE)
+    }
+
+    pub fn load_old_snapshots(&self, static_directory: &str) -> Result<Option<OldSnapshotIndex>> {
+        Self::load_static(static_directory, OLD_SNAPSHOTS_FILE)
+    }
+
+    pub fn load_manifest_validators(&self) -> Result<Option<UpstreamValidators>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => UpstreamValidators::load(&self.meta_dir()?.join("version_manifest_v2.json")),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn store_manifest_validators(&self, validators: &UpstreamValidators) -> Result<()> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => validators.store(&self.meta_dir()?.join("version_manifest_v2.json")),
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    /**
+     * The libraries of a stored Minecraft version, none if it is not stored
+     */
+    pub fn minecraft_libraries(&self, id: &str) -> Result<Vec<GradleSpecifier>> {
+        Ok(self
+            .load_minecraft_version(id)?
+            .map(|version| {
+                version
+                    .libraries
+                    .iter()
+                    .filter_map(|library| library.name.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
     pub fn load_minecraft_version(&self, id: &str) -> Result<Option<MinecraftVersion>> {
         match *self.storage_format {
             StorageFormat::Json {
@@ -119,11 +222,11 @@ impl MojangDataStorage {
             } => {
                 let version_file = self.versions_dir()?.join(format!("{}.json", id));
                 if version_file.is_file() {
-                    let version = serde_json::from_str::<MinecraftVersion>(
-                        &std::fs::read_to_string(&version_file).with_context(|| {
-                            format!("Failure reading file {}", version_file.to_string_lossy())
-                        })?,
-                    )?;
+                    let body = std::fs::read_to_string(&version_file).with_context(|| {
+                        format!("Failure reading file {}", version_file.to_string_lossy())
+                    })?;
+                    let version = serde_json::from_str::<MinecraftVersion>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
                     Ok(Some(version))
                 } else {
                     Ok(None)
@@ -140,38 +243,337 @@ impl MojangDataStorage {
                 generated_directory: _,
             } => {
                 let version_file = self.versions_dir()?.join(format!("{}.json", version.id));
-                let version_manifest_json = serde_json::to_string_pretty(&version)?;
-                std::fs::write(&version_file, version_manifest_json).with_context(|| {
-                    format!("Failure writing file {}", version_file.to_string_lossy())
-                })?;
+                write_json_atomic(&version_file, &version)?;
             }
             StorageFormat::Database => todo!(),
         }
         Ok(())
     }
+
+    pub fn java_runtimes_file(&self) -> Result<std::path::PathBuf> {
+        Ok(self.meta_dir()?.join("java_all.json"))
+    }
+
+    pub fn load_java_runtimes(&self) -> Result<Option<MojangJavaRuntimeIndex>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let file = self.java_runtimes_file()?;
+                if file.is_file() {
+                    let body = std::fs::read_to_string(&file).with_context(|| {
+                        format!("Failure reading file {}", file.to_string_lossy())
+                    })?;
+                    let index = serde_json::from_str::<MojangJavaRuntimeIndex>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
+                    Ok(Some(index))
+                } else {
+                    Ok(None)
+                }
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn store_java_runtimes(&self, index: &MojangJavaRuntimeIndex) -> Result<()> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let file = self.java_runtimes_file()?;
+                write_json_atomic(&file, index)?;
+                Ok(())
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn validate_stored(&self, report: &mut MetadataValidationReport) -> Result<()> {
+        report.check(
+            &self.meta_dir()?.join("version_manifest_v2.json"),
+            self.load_manifest(),
+        );
+        report.check_parse(&self.java_runtimes_file()?, self.load_java_runtimes());
+
+        let versions_dir = self.versions_dir()?;
+        for id in json_file_stems(&versions_dir)? {
+            report.check(
+                &versions_dir.join(format!("{}.json", id)),
+                self.load_minecraft_version(&id),
+            );
+        }
+        Ok(())
+    }
+
+    /**
+     * Generate the Minecraft component from every stored version, recommending the latest release.
+     * Their LWJGL libraries are split off into the LWJGL 2 and 3 components, where a LWJGL version
+     * shipped with different libraries by several Minecraft versions takes those of the newest one.
+     */
+    pub fn generate(&self, static_directory: &str) -> Result<bool> {
+        let Some(manifest) = self.load_manifest()? else {
+            return Ok(false);
+        };
+        let mut legacy_overrides = self
+            .load_legacy_overrides(static_directory)?
+            .map(|index| index.versions)
+            .unwrap_or_default();
+        let library_patches = self.load_library_patches(static_directory)?;
+        // versions from our static data are told apart from Mojang's by their type
+        let mut static_types: HashMap<String, &str> = HashMap::new();
+        if let Some(experiments) = self.load_experiments(static_directory)? {
+            static_types.extend(
+                experiments
+                    .experiments
+                    .into_iter()
+                    .map(|experiment| (experiment.id, EXPERIMENT_TYPE)),
+            );
+        }
+        if let Some(old_snapshots) = self.load_old_snapshots(static_directory)? {
+            static_types.extend(
+                old_snapshots
+                    .old_snapshots
+                    .into_iter()
+                    .map(|snapshot| (snapshot.id, OLD_SNAPSHOT_TYPE)),
+            );
+        }
+
+        let mut versions = vec![];
+        let mut lwjgl_versions: BTreeMap<(String, String), MetaVersion> = BTreeMap::new();
+        let mut recommended_lwjgl = None;
+        let quarantine = Quarantine::load(static_directory)?;
+        for id in json_file_stems(&self.versions_dir()?)? {
+            if quarantine.mojang.contains(&id) {
+                debug!("Not generating quarantined Minecraft {}", id);
+                continue;
+            }
+            let Some(version) = self.load_minecraft_version(&id)? else {
+                continue;
+            };
+            let mut meta_version = match minecraft_meta_version(&version) {
+                Ok(meta_version) => meta_version,
+                Err(err) => {
+                    warn!("Skipping Minecraft {}: {:#}", version.id, err);
+                    continue;
+                }
+            };
+            let recommended = version.id == manifest.latest.release;
+            if let Some(version_type) = static_types.get(&version.id) {
+                meta_version.version_type = Some(version_type.to_string());
+            }
+
+            // patched before LWJGL is split off, the libraries added to it move along
+            if let Some(library_patches) = &library_patches {
+                library_patches.apply_onto_meta_version(&mut meta_version);
+            }
+            if let Some(lwjgl) = extract_lwjgl(&mut meta_version) {
+                let key = (lwjgl.uid.clone(), lwjgl.version.clone());
+                if recommended {
+                    recommended_lwjgl = Some(key.clone());
+                }
+                match lwjgl_versions.get(&key) {
+                    Some(known) if known.release_time >= lwjgl.release_time => {
+                        if serde_json::to_value(&known.libraries)?
+                            != serde_json::to_value(&lwjgl.libraries)?
+                        {
+                            debug!(
+                                "Minecraft {} ships different libraries for {} {}",
+                                version.id, key.0, key.1
+                            );
+                        }
+                    }
+                    _ => {
+                        lwjgl_versions.insert(key, lwjgl);
+                    }
+                }
+            }
+            // versions before 1.6 launch through the legacy launcher, which brings its own
+            // libraries and arguments, so the corrected entry replaces what Mojang lists
+            if let Some(legacy_override) = legacy_overrides.remove(&version.id) {
+                legacy_override.apply_onto_meta_version(&mut meta_version, true);
+            }
+            versions.push((meta_version, recommended));
+        }
+
+        let generated_storage = GeneratedDataStorage::new(self.storage_format.clone());
+        generated_storage.store_package("net.minecraft", "Minecraft", versions)?;
+        for (uid, name) in [(LWJGL2_UID, "LWJGL 2"), (LWJGL3_UID, "LWJGL 3")] {
+            let lwjgl = lwjgl_versions
+                .iter()
+                .filter(|((lwjgl_uid, _), _)| lwjgl_uid == uid)
+                .map(|(key, lwjgl)| (lwjgl.clone(), Some(key) == recommended_lwjgl.as_ref()))
+                .collect::<Vec<_>>();
+            if !lwjgl.is_empty() {
+                generated_storage.store_package(uid, name, lwjgl)?;
+            }
+        }
+        Ok(true)
+    }
+
+    /**
+     * Generate the Java component from the stored runtime index, with a version per Java major
+     * listing its runtimes for every platform a launcher runs on.
+     */
+    pub fn generate_java_runtimes(&self) -> Result<bool> {
+        let Some(index) = self.load_java_runtimes()? else {
+            return Ok(false);
+        };
+
+        let mut majors: BTreeMap<i32, MetaVersion> = BTreeMap::new();
+        for (platform, components) in &index {
+            let Some(runtime_os) = mojang_runtime_os(platform) else {
+                continue;
+            };
+            for (component, runtimes) in components {
+                for runtime in runtimes {
+                    let Some(runtime) = runtime.to_runtime_meta(component, runtime_os) else {
+                        debug!(
+                            "Skipping {} runtime {} with version {}",
+                            platform, component, runtime.version.name
+                        );
+                        continue;
+                    };
+                    let major = runtime.version.major;
+                    let version = majors.entry(major).or_insert_with(|| MetaVersion {
+                        format_version: META_FORMAT_VERSION,
+                        name: format!("Java {}", major),
+                        uid: JAVA_UID.to_string(),
+                        version: major.to_string(),
+                        runtimes: Some(vec![]),
+                        ..Default::default()
+                    });
+                    if version.release_time < Some(runtime.release_time) {
+                        version.release_time = Some(runtime.release_time);
+                    }
+                    version.runtimes.get_or_insert_with(Vec::new).push(runtime);
+                }
+            }
+        }
+
+        let versions = majors
+            .into_values()
+            .map(|version| (version, false))
+            .collect();
+        GeneratedDataStorage::new(self.storage_format.clone()).store_package(
+            JAVA_UID,
+            "Java Runtimes",
+            versions,
+        )?;
+        Ok(true)
+    }
+}
+
+/**
+ * Convert a stored Minecraft version into its component, rejecting what the conversion cannot represent
+ */
+fn minecraft_meta_version(version: &MinecraftVersion) -> Result<MetaVersion> {
+    if version.downloads.is_none() {
+        return Err(anyhow!("it has no client download"));
+    }
+    if version.compliance_level.is_some_and(|level| level > 1) {
+        return Err(anyhow!(
+            "compliance level {:?} is not supported",
+            version.compliance_level
+        ));
+    }
+
+    // both models describe the same json, the launcher one is what the conversion is defined on
+    let mojang_version: MojangVersion = serde_json::from_value(serde_json::to_value(version)?)
+        .with_context(|| "Failure reading the version as a launcher version json")?;
+    let mut meta_version =
+        mojang_version.to_meta_version("Minecraft", "net.minecraft", &version.id);
+    meta_version.order = Some(-2);
+    Ok(meta_version)
 }
 
 impl UpstreamMetadataUpdater {
-    pub async fn update_upstream_mojang(&self) -> Result<()> {
+    pub async fn update_upstream_mojang(&self, report: &mut UpdateReport) -> Result<()> {
         info!("Checking for Mojang metadata");
 
-        self.update_mojang_metadata()
+        self.update_mojang_metadata(report)
             .await
             .with_context(|| "Failed to update Mojang metadata.")?;
-        self.update_mojang_static_metadata()
+        self.update_mojang_static_metadata(report)
             .await
             .with_context(|| "Failed to update Mojang static metadata.")?;
+
+        self.update_mojang_java_runtimes(report)
+            .await
+            .with_context(|| "Failed to update Mojang Java runtimes.")?;
+
+        let local_storage = MojangDataStorage::new(self.storage_format.clone());
+        let static_directory = self.metadata_cfg.static_directory.clone();
+        tokio::task::spawn_blocking(move || {
+            local_storage.generate(&static_directory)?;
+            local_storage.generate_java_runtimes()
+        })
+        .await?
+        .with_context(|| "Failed to generate Mojang metadata.")?;
+
+        if self.metadata_cfg.assets.mirror_indexes {
+            self.update_mojang_asset_indexes()
+                .await
+                .with_context(|| "Failed to update Mojang asset indexes.")?;
+        }
+        Ok(())
+    }
+
+    pub async fn update_mojang_java_runtimes(&self, report: &mut UpdateReport) -> Result<()> {
+        let local_storage = MojangDataStorage::new(self.storage_format.clone());
+
+        let remote_index = download::mojang::load_java_runtimes().await?;
+        let local_index = local_storage.load_java_runtimes()?;
+
+        // the whole index is a single file, count the platform runtimes that changed
+        let changed = remote_index
+            .iter()
+            .flat_map(|(platform, components)| {
+                components
+                    .iter()
+                    .map(move |(component, runtimes)| (platform, component, runtimes))
+            })
+            .filter(|(platform, component, runtimes)| {
+                let local_runtimes = local_index
+                    .as_ref()
+                    .and_then(|index| index.get(*platform))
+                    .and_then(|components| components.get(*component));
+                match local_runtimes {
+                    Some(local_runtimes) => {
+                        serde_json::to_value(local_runtimes).ok()
+                            != serde_json::to_value(runtimes).ok()
+                    }
+                    None => true,
+                }
+            })
+            .count();
+        report.record_updated_versions(JAVA_UID, changed);
+
+        debug!("Dumping Mojang Java runtimes");
+        local_storage.store_java_runtimes(&remote_index)?;
         Ok(())
     }
 
-    pub async fn update_mojang_metadata(&self) -> Result<()> {
-        use std::collections::{HashMap, HashSet};
+    pub async fn update_mojang_metadata(&self, report: &mut UpdateReport) -> Result<()> {
+        use std::collections::HashMap;
 
         let local_storage = MojangDataStorage {
             storage_format: self.storage_format.clone(),
         };
         info!("Acquiring remote Mojang metadata");
-        let remote_manifest = download::mojang::load_manifest().await?;
+        let local_validators = local_storage.load_manifest_validators()?;
+        let (remote_manifest, remote_validators) =
+            match download::mojang::load_manifest_if_modified(local_validators.as_ref()).await? {
+                Conditional::Modified(manifest, validators) => (manifest, Some(validators)),
+                Conditional::NotModified => {
+                    info!("Mojang version manifest is unchanged upstream");
+                    let manifest = local_storage
+                        .load_manifest()?
+                        .ok_or_else(|| anyhow!("Local Mojang version manifest disappeared"))?;
+                    (manifest, None)
+                }
+            };
         let remote_versions: HashMap<String, MojangVersionManifestVersion> = HashMap::from_iter(
             remote_manifest
                 .versions
@@ -182,7 +584,7 @@ impl UpstreamMetadataUpdater {
             HashSet::<String>::from_iter(remote_manifest.versions.iter().map(|v| v.id.clone()));
 
         let local_manifest = local_storage.load_manifest()?;
-        let pending_ids: Vec<(String, bool)> = if let Some(local_manifest) = local_manifest {
+        let pending_ids: Vec<(String, bool)> = if let Some(local_manifest) = &local_manifest {
             let local_versions: HashMap<String, MojangVersionManifestVersion> = HashMap::from_iter(
                 local_manifest
                     .versions
@@ -224,7 +626,15 @@ impl UpstreamMetadataUpdater {
 
             remote_ids.into_iter().map(|id| (id, true)).collect()
         };
+        let quarantine = Quarantine::load(&self.metadata_cfg.static_directory)?;
+        let (quarantined_ids, pending_ids): (Vec<_>, Vec<_>) = pending_ids
+            .into_iter()
+            .partition(|(id, _)| quarantine.mojang.contains(id));
+        for (id, _) in &quarantined_ids {
+            debug!("Skipping quarantined Mojang version {}", id);
+        }
 
+        let strict = self.metadata_cfg.strict_rule_features;
         let tasks = stream::iter(pending_ids)
             .map(|(version, force_update)| {
                 let ls = local_storage.clone();
@@ -232,122 +642,217 @@ impl UpstreamMetadataUpdater {
                     .get(&version)
                     .expect("version to exist remotely")
                     .clone();
-                tokio::spawn(async move {
-                    update_mojang_version_manifest(&ls, &v, force_update)
-                        .await
-                        .with_context(|| format!("Failed to initialize Mojang version {}", v.id))
-                })
-            })
-            .buffer_unordered(self.metadata_cfg.max_parallel_fetch_connections);
-        let results = tasks
-            .map(|t| match t {
-                Ok(Ok(t)) => Ok(t),
-                Ok(Err(e)) => {
-                    debug!("Task had an error: {:?}", e);
-                    Err(e)
-                }
-                Err(e) => {
-                    debug!("Task had a Join error: {:?}", e);
-                    Err(e.into())
-                }
+                let span = info_span!("version", uid = "net.minecraft", version = %version);
+                let task = tokio::spawn(
+                    async move {
+                        update_mojang_version_manifest(&ls, &v, force_update, strict)
+                            .await
+                            .with_context(|| {
+                                format!("Failed to initialize Mojang version {}", v.id)
+                            })
+                    }
+                    .instrument(span),
+                );
+                async move { ("net.minecraft".to_string(), version, task.await) }
             })
-            .collect::<Vec<_>>()
-            .await;
-        process_results(results)?;
+            .buffer_unordered(self.metadata_cfg.parallelism(UpdateSource::Mojang));
+        let results = tasks.collect::<Vec<_>>().await;
+        let withheld_ids = results
+            .iter()
+            .filter(|(_, _, task)| !matches!(task, Ok(Ok(_))))
+            .map(|(_, version, _)| version.clone())
+            .chain(quarantined_ids.into_iter().map(|(id, _)| id))
+            .collect::<HashSet<_>>();
+        for (version, features) in self
+            .collect_version_results(results, report)?
+            .into_iter()
+            .flatten()
+        {
+            report.record_updated_versions("net.minecraft", 1);
+            report.record_unknown_rule_features(&version, features);
+        }
+
+        // failed and quarantined versions keep their previous entry, or none, so the next update
+        // retries them
+        let mut remote_manifest = remote_manifest;
+        if !withheld_ids.is_empty() {
+            let previous_versions = local_manifest
+                .iter()
+                .flat_map(|manifest| manifest.versions.iter())
+                .map(|version| (version.id.as_str(), version))
+                .collect::<HashMap<_, _>>();
+            remote_manifest.versions = remote_manifest
+                .versions
+                .into_iter()
+                .filter_map(|version| {
+                    if withheld_ids.contains(&version.id) {
+                        previous_versions.get(version.id.as_str()).cloned().cloned()
+                    } else {
+                        Some(version)
+                    }
+                })
+                .collect();
+        }
 
         // update the locally stored manifest
         local_storage.store_manifest(&remote_manifest)?;
+        // an unchanged manifest upstream would skip the withheld versions next time
+        if let Some(validators) = remote_validators.filter(|_| withheld_ids.is_empty()) {
+            local_storage.store_manifest_validators(&validators)?;
+        }
         Ok(())
     }
 
-    pub async fn update_mojang_static_metadata(&self) -> Result<()> {
+    pub async fn update_mojang_static_metadata(&self, report: &mut UpdateReport) -> Result<()> {
         let local_storage = MojangDataStorage {
             storage_format: self.storage_format.clone(),
         };
 
-        let static_dir = std::path::Path::new(&self.metadata_cfg.static_directory);
-
-        let static_experiments_path = static_dir.join("mojang").join("minecraft-experiments.json");
-        if static_experiments_path.is_file() {
-            let experiments = serde_json::from_str::<ExperimentIndex>(&std::fs::read_to_string(
-                &static_experiments_path,
-            )?)?;
+        let static_directory = &self.metadata_cfg.static_directory;
+        let strict = self.metadata_cfg.strict_rule_features;
 
+        if let Some(experiments) = local_storage.load_experiments(static_directory)? {
             let tasks = stream::iter(experiments.experiments)
                 .map(|experiment| {
                     let ls = local_storage.clone();
                     let e = experiment;
 
-                    tokio::spawn(async move {
-                        update_mojang_experiment(&ls, &e).await.with_context(|| {
-                            format!("Failed to initialize Mojang experiment {}", e.id)
-                        })
-                    })
-                })
-                .buffer_unordered(self.metadata_cfg.max_parallel_fetch_connections);
-            let results = tasks
-                .map(|t| match t {
-                    Ok(Ok(t)) => Ok(t),
-                    Ok(Err(e)) => {
-                        debug!("Task had an error: {:?}", e);
-                        Err(e)
-                    }
-                    Err(e) => {
-                        debug!("Task had a Join error: {:?}", e);
-                        Err(e.into())
-                    }
+                    let id = e.id.clone();
+                    let span = info_span!("version", uid = "net.minecraft", version = %id);
+                    let task = tokio::spawn(
+                        async move {
+                            update_mojang_experiment(&ls, &e, strict)
+                                .await
+                                .with_context(|| {
+                                    format!("Failed to initialize Mojang experiment {}", e.id)
+                                })
+                        }
+                        .instrument(span),
+                    );
+                    async move { ("net.minecraft".to_string(), id, task.await) }
                 })
-                .collect::<Vec<_>>()
-                .await;
-            process_results(results)?;
+                .buffer_unordered(self.metadata_cfg.parallelism(UpdateSource::Mojang));
+            let results = tasks.collect::<Vec<_>>().await;
+            for (version, features) in self
+                .collect_version_results(results, report)?
+                .into_iter()
+                .flatten()
+            {
+                report.record_updated_versions("net.minecraft", 1);
+                report.record_unknown_rule_features(&version, features);
+            }
         }
 
-        let static_old_snapshots_path = static_dir
-            .join("mojang")
-            .join("minecraft-old-snapshots.json");
-        if static_old_snapshots_path.is_file() {
-            let old_snapshots = serde_json::from_str::<OldSnapshotIndex>(
-                &std::fs::read_to_string(&static_old_snapshots_path)?,
-            )?;
-
-            let tasks = stream::iter(old_snapshots.old_snapshots)
-                .map(|snapshot| {
-                    let ls = local_storage.clone();
-                    let s = snapshot;
+        let mut known_ids: HashSet<String> = local_storage
+            .load_manifest()?
+            .map(|manifest| manifest.versions.into_iter().map(|v| v.id).collect())
+            .unwrap_or_default();
 
-                    tokio::spawn(async move {
-                        update_mojang_old_snapshot(&ls, &s).await.with_context(|| {
-                            format!("Failed to initialize Mojang experiment {}", s.id)
-                        })
-                    })
-                })
-                .buffer_unordered(self.metadata_cfg.max_parallel_fetch_connections);
-            let results = tasks
-                .map(|t| match t {
-                    Ok(Ok(t)) => Ok(t),
-                    Ok(Err(e)) => {
-                        debug!("Task had an error: {:?}", e);
-                        Err(e)
-                    }
-                    Err(e) => {
-                        debug!("Task had a Join error: {:?}", e);
-                        Err(e.into())
+        if let Some(old_snapshots) = local_storage.load_old_snapshots(static_directory)? {
+            known_ids.extend(old_snapshots.old_snapshots.iter().map(|s| s.id.clone()));
+
+            self.update_mojang_old_snapshots(old_snapshots.old_snapshots, report)
+                .await?;
+        }
+
+        // external archives only fill in versions neither Mojang nor our static data know
+        for archive_url in &self.metadata_cfg.old_version_archives {
+            let archive = download::mojang::load_old_snapshot_index(archive_url)
+                .await
+                .with_context(|| format!("Failed to fetch old version archive {}", archive_url))?;
+            let missing_snapshots = archive
+                .old_snapshots
+                .into_iter()
+                .filter(|snapshot| known_ids.insert(snapshot.id.clone()))
+                .collect::<Vec<_>>();
+            info!(
+                "Old version archive {} lists {} versions missing from Mojang's manifest",
+                archive_url,
+                missing_snapshots.len()
+            );
+
+            self.update_mojang_old_snapshots(missing_snapshots, report)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn update_mojang_old_snapshots(
+        &self,
+        old_snapshots: Vec<OldSnapshotEntry>,
+        report: &mut UpdateReport,
+    ) -> Result<()> {
+        let local_storage = MojangDataStorage {
+            storage_format: self.storage_format.clone(),
+        };
+        let strict = self.metadata_cfg.strict_rule_features;
+
+        let tasks = stream::iter(old_snapshots)
+            .map(|snapshot| {
+                let ls = local_storage.clone();
+                let s = snapshot;
+
+                let id = s.id.clone();
+                let span = info_span!("version", uid = "net.minecraft", version = %id);
+                let task = tokio::spawn(
+                    async move {
+                        update_mojang_old_snapshot(&ls, &s, strict)
+                            .await
+                            .with_context(|| {
+                                format!("Failed to initialize Mojang old version {}", s.id)
+                            })
                     }
-                })
-                .collect::<Vec<_>>()
-                .await;
-            process_results(results)?;
+                    .instrument(span),
+                );
+                async move { ("net.minecraft".to_string(), id, task.await) }
+            })
+            .buffer_unordered(self.metadata_cfg.parallelism(UpdateSource::Mojang));
+        let results = tasks.collect::<Vec<_>>().await;
+        for (version, features) in self
+            .collect_version_results(results, report)?
+            .into_iter()
+            .flatten()
+        {
+            report.record_updated_versions("net.minecraft", 1);
+            report.record_unknown_rule_features(&version, features);
         }
 
         Ok(())
     }
 }
 
+/**
+ * Check a downloaded version for rule feature keys the models do not know,
+ * failing on them in strict mode and returning them otherwise
+ */
+fn check_rule_features(version: &MinecraftVersion, strict: bool) -> Result<BTreeSet<String>> {
+    let unknown_features = version.unknown_rule_features();
+    if !unknown_features.is_empty() {
+        if strict {
+            return Err(anyhow!(
+                "Version {} uses unknown rule features {:?}",
+                version.id,
+                unknown_features
+            ));
+        }
+        warn!(
+            "Version {} uses unknown rule features {:?}",
+            version.id, unknown_features
+        );
+    }
+    Ok(unknown_features)
+}
+
+/**
+ * Download a version if needed, returning the unknown rule features of a downloaded version
+ */
 async fn update_mojang_version_manifest(
     local_storage: &MojangDataStorage,
     version: &MojangVersionManifestVersion,
     force_update: bool,
-) -> Result<()> {
+    strict: bool,
+) -> Result<Option<(String, BTreeSet<String>)>> {
     let local_manifest = local_storage.load_minecraft_version(&version.id)?;
     if local_manifest.is_none() || force_update {
         info!(
@@ -356,23 +861,25 @@ async fn update_mojang_version_manifest(
         );
         let version_manifest = download::mojang::load_version_manifest(&version.url)
             .await
-            .map_err(|err| {
+            .inspect_err(|err| {
                 warn!(
                     "Error parsing manifest for version {}: {}",
                     &version.id,
                     err.to_string()
                 );
-                err
             })?;
+        let unknown_features = check_rule_features(&version_manifest, strict)?;
         local_storage.store_minecraft_version(&version_manifest)?;
+        return Ok(Some((version_manifest.id, unknown_features)));
     }
-    Ok(())
+    Ok(None)
 }
 
 async fn update_mojang_experiment(
     local_storage: &MojangDataStorage,
     version: &ExperimentEntry,
-) -> Result<()> {
+    strict: bool,
+) -> Result<Option<(String, BTreeSet<String>)>> {
     let local_version = local_storage.load_minecraft_version(&version.id)?;
     if local_version.is_none() {
         info!(
@@ -381,23 +888,25 @@ async fn update_mojang_experiment(
         );
         let version_manifest = download::mojang::load_zipped_version(&version.url)
             .await
-            .map_err(|err| {
+            .inspect_err(|err| {
                 warn!(
                     "Error parsing manifest for version {}: {}",
                     &version.id,
                     err.to_string()
                 );
-                err
             })?;
+        let unknown_features = check_rule_features(&version_manifest, strict)?;
         local_storage.store_minecraft_version(&version_manifest)?;
+        return Ok(Some((version_manifest.id, unknown_features)));
     }
-    Ok(())
+    Ok(None)
 }
 
 async fn update_mojang_old_snapshot(
     local_storage: &MojangDataStorage,
     snapshot: &OldSnapshotEntry,
-) -> Result<()> {
+    strict: bool,
+) -> Result<Option<(String, BTreeSet<String>)>> {
     let local_version = local_storage.load_minecraft_version(&snapshot.id)?;
     if local_version.is_none() {
         info!(
@@ -407,23 +916,31 @@ async fn update_mojang_old_snapshot(
 
         let mut version_manifest = download::mojang::load_version_manifest(&snapshot.url)
             .await
-            .map_err(|err| {
+            .inspect_err(|err| {
                 warn!(
                     "Error parsing manifest for version {}: {}",
                     &snapshot.id,
                     err.to_string()
                 );
-                err
             })?;
 
-        version_manifest.release_time = version_manifest.release_time.clone() + "T00:00:00+02:00";
+        // the archived manifests only carry a date
+        if !version_manifest.release_time.contains('T') {
+            version_manifest.release_time =
+                version_manifest.release_time.clone() + "T00:00:00+02:00";
+        }
         version_manifest.time = version_manifest.release_time.clone();
 
+        let (sha1, size) = match (&snapshot.sha1, snapshot.size) {
+            (Some(sha1), Some(size)) => (sha1.clone(), size),
+            _ => hash_old_snapshot_jar(snapshot).await?,
+        };
+
         version_manifest.downloads = Some(VersionDownloads {
             client: VersionDownload {
                 url: snapshot.jar.clone(),
-                sha1: snapshot.sha1.clone(),
-                size: snapshot.size,
+                sha1,
+                size,
             },
             server: None,
             windows_server: None,
@@ -433,7 +950,26 @@ async fn update_mojang_old_snapshot(
 
         version_manifest.release_type = "old_snapshot".to_string();
 
+        let unknown_features = check_rule_features(&version_manifest, strict)?;
         local_storage.store_minecraft_version(&version_manifest)?;
+        return Ok(Some((version_manifest.id, unknown_features)));
     }
-    Ok(())
+    Ok(None)
+}
+
+/**
+ * Download the jar of an old version to compute the hash and size its archive did not list
+ */
+async fn hash_old_snapshot_jar(snapshot: &OldSnapshotEntry) -> Result<(String, i32)> {
+    let tmp_dir = TempDir::new("mcmeta_old_snapshot_jar")?;
+    let jar_path = tmp_dir.path().join(format!("{}.jar", snapshot.id));
+
+    debug!("Downloading old version jar from {}", &snapshot.jar);
+    download::download_binary_file(&jar_path, &snapshot.jar)
+        .await
+        .with_context(|| format!("Failure downloading {}", &snapshot.jar))?;
+
+    let sha1 = filehash(&jar_path, HashAlgo::Sha1)?.to_lowercase();
+    let size = i32::try_from(jar_path.metadata()?.len())?;
+    Ok((sha1, size))
 }
@@ -0,0 +1,434 @@
+use std::{collections::BTreeMap, io::Read, sync::Arc};
+
+use futures::{stream, StreamExt};
+use libmcmeta::models::{
+    forge::{ForgeInstallerProfile, InstallerInfo},
+    mojang::MojangVersion,
+    neoforge::{DerivedNeoForgeIndex, NeoForgeEntry, NeoForgeMavenVersions},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tempdir::TempDir;
+use tracing::{debug, info, info_span, warn, Instrument};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{
+    download::{self, errors::MetadataError},
+    storage::{
+        quarantine::Quarantine,
+        validation::{json_file_stems, MetadataValidationReport},
+        StorageFormat, UpdateReport, UpdateSource, UpstreamMetadataUpdater,
+    },
+    utils::{filehash, process_results_ok, write_json_atomic, HashAlgo},
+};
+
+/// The artifacts NeoForge is published as, the legacy `forge` one only for Minecraft 1.20.1
+pub const NEOFORGE_ARTIFACTS: [&str; 2] = ["forge", "neoforge"];
+
+#[derive(Clone)]
+pub struct NeoForgeDataStorage {
+    storage_format: Arc<StorageFormat>,
+}
+
+impl NeoForgeDataStorage {
+    pub fn new(storage_format: Arc<StorageFormat>) -> Self {
+        Self { storage_format }
+    }
+
+    pub fn meta_dir(&self) -> Result<std::path::PathBuf> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                ref meta_directory,
+                generated_directory: _,
+            } => {
+                let metadata_dir = std::path::Path::new(&meta_directory);
+                let neoforge_meta_dir = metadata_dir.join("neoforge");
+
+                if !neoforge_meta_dir.is_dir() {
+                    info!(
+                        "NeoForge metadata directory at {} does not exist, creating it",
+                        neoforge_meta_dir.display()
+                    );
+                    std::fs::create_dir_all(&neoforge_meta_dir)?;
+                }
+                Ok(neoforge_meta_dir)
+            }
+            StorageFormat::Database => Err(anyhow!("Wrong storage format")),
+        }
+    }
+
+    fn sub_dir(&self, name: &str) -> Result<std::path::PathBuf> {
+        let dir = self.meta_dir()?.join(name);
+        if !dir.is_dir() {
+            info!(
+                "NeoForge {} directory at {} does not exist, creating it",
+                name,
+                dir.display()
+            );
+            std::fs::create_dir_all(&dir)?;
+        }
+        Ok(dir)
+    }
+
+    pub fn installer_manifests_dir(&self) -> Result<std::path::PathBuf> {
+        self.sub_dir("installer_manifests")
+    }
+
+    pub fn version_manifests_dir(&self) -> Result<std::path::PathBuf> {
+        self.sub_dir("version_manifests")
+    }
+
+    pub fn installer_info_dir(&self) -> Result<std::path::PathBuf> {
+        self.sub_dir("installer_info")
+    }
+
+    fn load_json<T: DeserializeOwned>(&self, file: &std::path::Path) -> Result<Option<T>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                if file.is_file() {
+                    let body = std::fs::read_to_string(file).with_context(|| {
+                        format!("Failure reading file {}", file.to_string_lossy())
+                    })?;
+                    let value = serde_json::from_str::<T>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
+                    Ok(Some(value))
+                } else {
+                    Ok(None)
+                }
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    fn store_json<T: Serialize>(&self, file: &std::path::Path, value: &T) -> Result<()> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                write_json_atomic(file, value)?;
+                Ok(())
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    /**
+     * The versions of every NeoForge artifact, keyed by artifact
+     */
+    pub fn load_maven_metadata(&self) -> Result<Option<BTreeMap<String, NeoForgeMavenVersions>>> {
+        self.load_json(&self.meta_dir()?.join("maven-metadata.json"))
+    }
+
+    pub fn store_maven_metadata(
+        &self,
+        metadata: &BTreeMap<String, NeoForgeMavenVersions>,
+    ) -> Result<()> {
+        self.store_json(&self.meta_dir()?.join("maven-metadata.json"), metadata)
+    }
+
+    pub fn load_index(&self) -> Result<Option<DerivedNeoForgeIndex>> {
+        self.load_json(&self.meta_dir()?.join("derived_index.json"))
+    }
+
+    pub fn store_index(&self, index: &DerivedNeoForgeIndex) -> Result<()> {
+        self.store_json(&self.meta_dir()?.join("derived_index.json"), index)
+    }
+
+    pub fn load_installer_manifest(
+        &self,
+        long_version: &str,
+    ) -> Result<Option<ForgeInstallerProfile>> {
+        self.load_json(
+            &self
+                .installer_manifests_dir()?
+                .join(format!("{}.json", long_version)),
+        )
+    }
+
+    pub fn store_installer_manifest(
+        &self,
+        long_version: &str,
+        manifest: &ForgeInstallerProfile,
+    ) -> Result<()> {
+        self.store_json(
+            &self
+                .installer_manifests_dir()?
+                .join(format!("{}.json", long_version)),
+            manifest,
+        )
+    }
+
+    pub fn load_mojang_version(&self, long_version: &str) -> Result<Option<MojangVersion>> {
+        self.load_json(
+            &self
+                .version_manifests_dir()?
+                .join(format!("{}.json", long_version)),
+        )
+    }
+
+    pub fn store_mojang_version(&self, long_version: &str, version: &MojangVersion) -> Result<()> {
+        self.store_json(
+            &self
+                .version_manifests_dir()?
+                .join(format!("{}.json", long_version)),
+            version,
+        )
+    }
+
+    pub fn load_installer_info(&self, long_version: &str) -> Result<Option<InstallerInfo>> {
+        self.load_json(
+            &self
+                .installer_info_dir()?
+                .join(format!("{}.json", long_version)),
+        )
+    }
+
+    pub fn store_installer_info(
+        &self,
+        long_version: &str,
+        installer_info: &InstallerInfo,
+    ) -> Result<()> {
+        self.store_json(
+            &self
+                .installer_info_dir()?
+                .join(format!("{}.json", long_version)),
+            installer_info,
+        )
+    }
+
+    pub fn validate_stored(&self, report: &mut MetadataValidationReport) -> Result<()> {
+        let meta_dir = self.meta_dir()?;
+        report.check_parse(
+            &meta_dir.join("maven-metadata.json"),
+            self.load_maven_metadata(),
+        );
+        report.check(&meta_dir.join("derived_index.json"), self.load_index());
+
+        let installer_manifests_dir = self.installer_manifests_dir()?;
+        for name in json_file_stems(&installer_manifests_dir)? {
+            report.check(
+                &installer_manifests_dir.join(format!("{}.json", name)),
+                self.load_installer_manifest(&name),
+            );
+        }
+
+        let version_manifests_dir = self.version_manifests_dir()?;
+        // installer version jsons only patch a Mojang version, they lack the fields its validation requires
+        for name in json_file_stems(&version_manifests_dir)? {
+            report.check_parse(
+                &version_manifests_dir.join(format!("{}.json", name)),
+                self.load_mojang_version(&name),
+            );
+        }
+
+        let installer_info_dir = self.installer_info_dir()?;
+        for name in json_file_stems(&installer_info_dir)? {
+            report.check(
+                &installer_info_dir.join(format!("{}.json", name)),
+                self.load_installer_info(&name),
+            );
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Build the index of every NeoForge build the maven lists, skipping versions of neither scheme
+ */
+fn derive_neoforge_index(
+    maven_metadata: &BTreeMap<String, NeoForgeMavenVersions>,
+) -> DerivedNeoForgeIndex {
+    let mut index = DerivedNeoForgeIndex::default();
+
+    for (artifact, versions) in maven_metadata {
+        for long_version in &versions.versions {
+            let Some(entry) = NeoForgeEntry::parse(artifact, long_version) else {
+                warn!(
+                    "Skipping NeoForge {} version {}, it does not parse",
+                    artifact, long_version
+                );
+                continue;
+            };
+            index
+                .by_mc_version
+                .entry(entry.mc_version.clone())
+                .or_default()
+                .versions
+                .push(entry.long_version.clone());
+            index.versions.insert(entry.long_version.clone(), entry);
+        }
+    }
+
+    // the maven lists versions oldest first
+    for info in index.by_mc_version.values_mut() {
+        info.latest = info.versions.last().cloned();
+        if let Some(latest) = info
+            .latest
+            .as_ref()
+            .and_then(|latest| index.versions.get_mut(latest))
+        {
+            latest.latest = Some(true);
+        }
+    }
+    index
+}
+
+impl UpstreamMetadataUpdater {
+    pub async fn update_upstream_neoforge(&self, report: &mut UpdateReport) -> Result<()> {
+        info!("Checking for NeoForge metadata");
+        self.update_neoforge_metadata(report)
+            .await
+            .with_context(|| "Failed to update NeoForge metadata.")?;
+        Ok(())
+    }
+
+    pub async fn update_neoforge_metadata(&self, report: &mut UpdateReport) -> Result<()> {
+        let local_storage = NeoForgeDataStorage::new(self.storage_format.clone());
+
+        let mut maven_metadata = BTreeMap::new();
+        for artifact in NEOFORGE_ARTIFACTS {
+            maven_metadata.insert(
+                artifact.to_string(),
+                download::neoforge::load_maven_versions(artifact).await?,
+            );
+        }
+
+        debug!("Processing NeoForge versions");
+        let neoforge_index = derive_neoforge_index(&maven_metadata);
+
+        let quarantine = Quarantine::load(&self.metadata_cfg.static_directory)?;
+        let mut pending = vec![];
+        for entry in neoforge_index.versions.values() {
+            if quarantine.neoforge.contains(&entry.long_version) {
+                debug!(
+                    "Skipping quarantined NeoForge version {}",
+                    entry.long_version
+                );
+                continue;
+            }
+            if local_storage
+                .load_installer_manifest(&entry.long_version)?
+                .is_none()
+                || local_storage
+                    .load_installer_info(&entry.long_version)?
+                    .is_none()
+            {
+                pending.push(entry.clone());
+            }
+        }
+        if !pending.is_empty() {
+            info!("Missing local NeoForge versions: {}", pending.len());
+        }
+
+        let maven_url = download::neoforge::maven_base_url()?;
+        let tasks = stream::iter(pending)
+            .map(|entry| {
+                let ls = local_storage.clone();
+                let maven_url = maven_url.clone();
+                let span = info_span!(
+                    "version",
+                    uid = "net.neoforged",
+                    version = %entry.long_version
+                );
+                tokio::spawn(
+                    async move {
+                        process_neoforge_installer(&ls, &entry, &maven_url)
+                            .await
+                            .with_context(|| {
+                                format!("Failed to update NeoForge {}", entry.long_version)
+                            })
+                    }
+                    .instrument(span),
+                )
+            })
+            .buffer_unordered(self.metadata_cfg.parallelism(UpdateSource::NeoForge));
+        let results = tasks
+            .map(|t| match t {
+                Ok(Ok(t)) => Ok(t),
+                Ok(Err(e)) => {
+                    debug!("Task had an error: {:?}", e);
+                    Err(e)
+                }
+                Err(e) => {
+                    debug!("Task had a Join error: {:?}", e);
+                    Err(e.into())
+                }
+            })
+            .collect::<Vec<_>>()
+            .await;
+        let processed = process_results_ok(results);
+        report.record_updated_versions("net.neoforged", processed.len());
+
+        debug!("Dumping NeoForge index files");
+        local_storage.store_maven_metadata(&maven_metadata)?;
+        local_storage.store_index(&neoforge_index)?;
+
+        Ok(())
+    }
+}
+
+fn read_jar_entry(jar: &mut zip::ZipArchive<std::fs::File>, name: &str) -> Result<Option<String>> {
+    let Ok(mut zip_entry) = jar.by_name(name) else {
+        return Ok(None);
+    };
+    let mut data = String::new();
+    zip_entry
+        .read_to_string(&mut data)
+        .with_context(|| format!("Failure reading '{}'", name))?;
+    Ok(Some(data))
+}
+
+/**
+ * Download the installer of a NeoForge build and store the install profile and version json it contains
+ */
+async fn process_neoforge_installer(
+    local_storage: &NeoForgeDataStorage,
+    entry: &NeoForgeEntry,
+    maven_url: &str,
+) -> Result<()> {
+    let url = entry.installer_url(maven_url);
+    let tmp_dir = TempDir::new("mcmeta_neoforge_installer")?;
+    let jar_path = tmp_dir.path().join(entry.installer_filename());
+
+    debug!("Downloading neoforge installer from {}", &url);
+    download::download_binary_file(&jar_path, &url)
+        .await
+        .with_context(|| format!("Failure downloading {}", &url))?;
+
+    let mut jar = zip::ZipArchive::new(
+        std::fs::File::open(&jar_path)
+            .with_context(|| format!("Failure opening {}", &jar_path.to_string_lossy()))?,
+    )
+    .with_context(|| {
+        format!(
+            "Failure reading Jar archive {}",
+            &jar_path.to_string_lossy()
+        )
+    })?;
+
+    if let Some(version_data) = read_jar_entry(&mut jar, "version.json")? {
+        let mojang_version: MojangVersion = serde_json::from_str(&version_data)
+            .map_err(|err| MetadataError::from_json_err(err, &version_data))
+            .with_context(|| format!("Failure reading 'version.json' in {}", &url))?;
+        local_storage.store_mojang_version(&entry.long_version, &mojang_version)?;
+    }
+
+    let install_profile_data = read_jar_entry(&mut jar, "install_profile.json")?
+        .ok_or_else(|| anyhow!("{} is missing install_profile.json", &url))?;
+    let profile: ForgeInstallerProfile = serde_json::from_str(&install_profile_data)
+        .map_err(|err| MetadataError::from_json_err(err, &install_profile_data))
+        .with_context(|| format!("Failure reading 'install_profile.json' in {}", &url))?;
+    local_storage.store_installer_manifest(&entry.long_version, &profile)?;
+
+    let installer_info = InstallerInfo {
+        sha1hash: Some(filehash(&jar_path, HashAlgo::Sha1)?),
+        sha256hash: Some(filehash(&jar_path, HashAlgo::Sha256)?),
+        size: Some(jar_path.metadata()?.len()),
+    };
+    local_storage.store_installer_info(&entry.long_version, &installer_info)?;
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use futures::{stream, StreamExt};
+use libmcmeta::models::optifine::{OptiFineBuild, OptiFineIndex};
+use tempdir::TempDir;
+use tracing::{debug, info, warn};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{
+    download::{self, errors::MetadataError},
+    storage::{
+        validation::MetadataValidationReport, StorageFormat, UpdateReport, UpdateSource,
+        UpstreamMetadataUpdater,
+    },
+    utils::{filehash, write_json_atomic, HashAlgo},
+};
+
+pub const OPTIFINE_UID: &str = "optifine.OptiFine";
+
+#[derive(Clone)]
+pub struct OptiFineDataStorage {
+    storage_format: Arc<StorageFormat>,
+}
+
+impl OptiFineDataStorage {
+    pub fn new(storage_format: Arc<StorageFormat>) -> Self {
+        Self { storage_format }
+    }
+
+    pub fn meta_dir(&self) -> Result<std::path::PathBuf> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                ref meta_directory,
+                generated_directory: _,
+            } => {
+                let metadata_dir = std::path::Path::new(&meta_directory);
+                let optifine_meta_dir = metadata_dir.join("optifine");
+
+                if !optifine_meta_dir.is_dir() {
+                    info!(
+                        "OptiFine metadata directory at {} does not exist, creating it",
+                        optifine_meta_dir.display()
+                    );
+                    std::fs::create_dir_all(&optifine_meta_dir)?;
+                }
+                Ok(optifine_meta_dir)
+            }
+            StorageFormat::Database => Err(anyhow!("Wrong storage format")),
+        }
+    }
+
+    pub fn versions_file(&self) -> Result<std::path::PathBuf> {
+        Ok(self.meta_dir()?.join("versions.json"))
+    }
+
+    pub fn load_index(&self) -> Result<Option<OptiFineIndex>> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let file = self.versions_file()?;
+                if file.is_file() {
+                    let body = std::fs::read_to_string(&file).with_context(|| {
+                        format!("Failure reading file {}", file.to_string_lossy())
+                    })?;
+                    let index = serde_json::from_str::<OptiFineIndex>(&body)
+                        .map_err(|err| MetadataError::from_json_err(err, &body))?;
+                    Ok(Some(index))
+                } else {
+                    Ok(None)
+                }
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    pub fn store_index(&self, index: &OptiFineIndex) -> Result<()> {
+        match *self.storage_format {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                let file = self.versions_file()?;
+                write_json_atomic(&file, &index)?;
+                Ok(())
+            }
+            StorageFormat::Database => todo!(),
+        }
+    }
+
+    /**
+     * The OptiFine builds for a Minecraft version, `None` if there is no index or no builds for it
+     */
+    pub fn load_builds(&self, mc_version: &str) -> Result<Option<Vec<OptiFineBuild>>> {
+        Ok(self
+            .load_index()?
+            .and_then(|mut index| index.versions.remove(mc_version)))
+    }
+
+    pub fn validate_stored(&self, report: &mut MetadataValidationReport) -> Result<()> {
+        report.check(&self.versions_file()?, self.load_index());
+        Ok(())
+    }
+}
+
+impl UpstreamMetadataUpdater {
+    pub async fn update_upstream_optifine(&self, report: &mut UpdateReport) -> Result<()> {
+        info!("Checking for OptiFine metadata");
+        self.update_optifine_metadata(report)
+            .await
+            .with_context(|| "Failed to update OptiFine metadata.")?;
+        Ok(())
+    }
+
+    /**
+     * Scrape the builds and hash the jars of those not hashed yet. Hashing is best effort, a jar
+     * failing to download is hashed on the next update instead of failing this one.
+     */
+    pub async fn update_optifine_metadata(&self, report: &mut UpdateReport) -> Result<()> {
+        let local_storage = OptiFineDataStorage::new(self.storage_format.clone());
+
+        let remote_builds = download::optifine::load_builds().await?;
+        let local_index = local_storage.load_index()?.unwrap_or_default();
+
+        let mut new_builds = 0;
+        let mut builds = vec![];
+        let mut pending = vec![];
+        for mut build in remote_builds {
+            match local_index.build(&build.filename) {
+                Some(known) if known.is_hashed() => {
+                    build.sha1 = known.sha1.clone();
+                    build.sha256 = known.sha256.clone();
+                    build.size = known.size;
+                }
+                Some(_) => pending.push(builds.len()),
+                None => {
+                    new_builds += 1;
+                    pending.push(builds.len());
+                }
+            }
+            builds.push(build);
+        }
+        report.record_updated_versions(OPTIFINE_UID, new_builds);
+
+        info!("Hashing {} OptiFine jars", pending.len());
+        let hashed = stream::iter(pending)
+            .map(|position| {
+                let build = builds[position].clone();
+                async move { (position, hash_optifine_jar(&build).await) }
+            })
+            .buffer_unordered(self.metadata_cfg.parallelism(UpdateSource::OptiFine))
+            .collect::<Vec<_>>()
+            .await;
+        for (position, result) in hashed {
+            let build = &mut builds[position];
+            match result {
+                Ok((sha1, sha256, size)) => {
+                    build.sha1 = Some(sha1);
+                    build.sha256 = Some(sha256);
+                    build.size = Some(size);
+                }
+                Err(err) => warn!("Failure hashing OptiFine {}: {:#}", build.filename, err),
+            }
+        }
+
+        let mut index = OptiFineIndex {
+            versions: BTreeMap::new(),
+        };
+        for build in builds {
+            index
+                .versions
+                .entry(build.mc_version.clone())
+                .or_default()
+                .push(build);
+        }
+
+        debug!("Dumping OptiFine versions");
+        local_storage.store_index(&index)?;
+
+        Ok(())
+    }
+}
+
+/**
+ * Download the jar of a build to find its sha1, sha256 and size
+ */
+async fn hash_optifine_jar(build: &OptiFineBuild) -> Result<(String, String, u64)> {
+    let url = download::optifine::resolve_jar_url(build).await?;
+    let tmp_dir = TempDir::new("mcmeta_optifine_jar")?;
+    let jar_path = tmp_dir.path().join(&build.filename);
+
+    debug!("Downloading jar from {}", &url);
+    download::download_binary_file(&jar_path, &url)
+        .await
+        .with_context(|| format!("Failure downloading {}", &url))?;
+
+    Ok((
+        filehash(&jar_path, HashAlgo::Sha1)?.to_lowercase(),
+        filehash(&jar_path, HashAlgo::Sha256)?.to_lowercase(),
+        jar_path.metadata()?.len(),
+    ))
+}
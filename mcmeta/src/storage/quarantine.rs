@@ -0,0 +1,44 @@
+use std::collections::BTreeSet;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::download::errors::MetadataError;
+
+pub const QUARANTINE_FILE: &str = "quarantine.json";
+
+/// Upstream versions known to be broken, which updates skip, by source
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Quarantine {
+    /// Minecraft version ids, neither fetched nor generated
+    #[serde(default)]
+    pub mojang: BTreeSet<String>,
+    /// long versions of Forge builds, whose installers are neither processed nor generated
+    #[serde(default)]
+    pub forge: BTreeSet<String>,
+    /// long versions of NeoForge builds, whose installers are not processed
+    #[serde(default)]
+    pub neoforge: BTreeSet<String>,
+}
+
+impl Quarantine {
+    pub fn file(static_directory: &str) -> std::path::PathBuf {
+        std::path::Path::new(static_directory).join(QUARANTINE_FILE)
+    }
+
+    /**
+     * Read the quarantine list from the static directory, which is done at the start of every
+     * update so edits take effect without a restart. Nothing is quarantined without the file.
+     */
+    pub fn load(static_directory: &str) -> Result<Self> {
+        let quarantine_file = Self::file(static_directory);
+        if !quarantine_file.is_file() {
+            return Ok(Self::default());
+        }
+        let body = std::fs::read_to_string(&quarantine_file).with_context(|| {
+            format!("Failure reading file {}", quarantine_file.to_string_lossy())
+        })?;
+        serde_json::from_str::<Self>(&body)
+            .map_err(|err| MetadataError::from_json_err(err, &body).into())
+    }
+}
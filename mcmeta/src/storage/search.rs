@@ -0,0 +1,228 @@
+use std::sync::{Arc, RwLock};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::routes::{forge::FORGE_UID, mojang::MINECRAFT_UID, neoforge::NEOFORGE_UID};
+use crate::storage::{
+    fabric::{FabricDataStorage, FabricSource},
+    forge::ForgeDataStorage,
+    java::{JavaDataStorage, JavaVendor},
+    liteloader::{LiteLoaderDataStorage, LITELOADER_UID},
+    mojang::MojangDataStorage,
+    neoforge::NeoForgeDataStorage,
+    optifine::{OptiFineDataStorage, OPTIFINE_UID},
+    StorageFormat, UpdateSource,
+};
+
+lazy_static! {
+    /// Rebuilt after every update, none until the first search or update built it
+    pub static ref SEARCH_INDEX: RwLock<Option<Arc<SearchIndex>>> = RwLock::new(None);
+}
+
+/// A stored version, as found by a search
+#[derive(Serialize, Debug, Clone)]
+pub struct SearchHit {
+    pub source: UpdateSource,
+    pub uid: String,
+    pub version: String,
+}
+
+/// How closely a version matches a query, best first
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum Match {
+    Exact,
+    Prefix,
+    Contains,
+}
+
+/// The version ids of every source, in the order the sources update
+#[derive(Default)]
+pub struct SearchIndex {
+    /// hits with the lowercase version they are matched by
+    entries: Vec<(String, SearchHit)>,
+}
+
+impl SearchIndex {
+    fn add(&mut self, source: UpdateSource, uid: &str, versions: impl IntoIterator<Item = String>) {
+        self.entries.extend(versions.into_iter().map(|version| {
+            (
+                version.to_lowercase(),
+                SearchHit {
+                    source,
+                    uid: uid.to_string(),
+                    version,
+                },
+            )
+        }));
+    }
+
+    /**
+     * Index the stored upstream versions of every source, skipping those not stored yet
+     */
+    pub fn build(storage_format: Arc<StorageFormat>) -> Result<Self> {
+        let mut index = Self::default();
+        for source in UpdateSource::ALL {
+            match source {
+                UpdateSource::Mojang => {
+                    if let Some(manifest) =
+                        MojangDataStorage::new(storage_format.clone()).load_manifest()?
+                    {
+                        index.add(
+                            source,
+                            MINECRAFT_UID,
+                            manifest.versions.into_iter().map(|version| version.id),
+                        );
+                    }
+                }
+                UpdateSource::Forge => {
+                    if let Some(forge_index) =
+                        ForgeDataStorage::new(storage_format.clone()).load_index()?
+                    {
+                        index.add(source, FORGE_UID, forge_index.versions.into_keys());
+                    }
+                }
+                UpdateSource::NeoForge => {
+                    if let Some(neoforge_index) =
+                        NeoForgeDataStorage::new(storage_format.clone()).load_index()?
+                    {
+                        index.add(source, NEOFORGE_UID, neoforge_index.versions.into_keys());
+                    }
+                }
+                UpdateSource::Fabric
+                | UpdateSource::Babric
+                | UpdateSource::LegacyFabric
+                | UpdateSource::Ornithe => {
+                    let Some(fabric_source) = FabricSource::ALL
+                        .into_iter()
+                        .find(|fabric_source| fabric_source.update_source() == source)
+                    else {
+                        continue;
+                    };
+                    let storage = FabricDataStorage::new(storage_format.clone(), fabric_source);
+                    let components = fabric_source.server()?.components;
+                    if let Some(loaders) = storage.load_loader_versions()? {
+                        index.add(
+                            source,
+                            &components.loader_uid,
+                            loaders.into_iter().map(|loader| loader.version),
+                        );
+                    }
+                    if let Some(intermediaries) = storage.load_intermediary_versions()? {
+                        index.add(
+                            source,
+                            &components.intermediary_uid,
+                            intermediaries
+                                .into_iter()
+                                .map(|intermediary| intermediary.version),
+                        );
+                    }
+                }
+                UpdateSource::LiteLoader => {
+                    if let Some(liteloader_index) =
+                        LiteLoaderDataStorage::new(storage_format.clone()).load_index()?
+                    {
+                        index.add(
+                            source,
+                            LITELOADER_UID,
+                            liteloader_index.versions.into_keys(),
+                        );
+                    }
+                }
+                UpdateSource::OptiFine => {
+                    if let Some(optifine_index) =
+                        OptiFineDataStorage::new(storage_format.clone()).load_index()?
+                    {
+                        // builds are only told apart by their file name
+                        index.add(
+                            source,
+                            OPTIFINE_UID,
+                            optifine_index
+                                .versions
+                                .into_values()
+                                .flatten()
+                                .map(|build| {
+                                    build
+                                        .filename
+                                        .strip_suffix(".jar")
+                                        .map_or_else(|| build.filename.clone(), str::to_string)
+                                }),
+                        );
+                    }
+                }
+                UpdateSource::Java => {
+                    let storage = JavaDataStorage::new(storage_format.clone());
+                    for vendor in JavaVendor::ALL {
+                        if let Some(runtimes) = storage.load_index(vendor)? {
+                            index.add(
+                                source,
+                                vendor.uid(),
+                                runtimes.majors.into_keys().map(|major| major.to_string()),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /**
+     * The versions containing the query, ignoring case, of the given sources or all of them.
+     * Exact matches come first, then versions starting with the query, each in index order.
+     */
+    pub fn search(&self, query: &str, sources: Option<&[UpdateSource]>) -> Vec<SearchHit> {
+        let query = query.to_lowercase();
+        let mut hits: Vec<(Match, &SearchHit)> = self
+            .entries
+            .iter()
+            .filter(|(_, hit)| sources.is_none_or(|sources| sources.contains(&hit.source)))
+            .filter_map(|(version, hit)| {
+                let matched = if *version == query {
+                    Match::Exact
+                } else if version.starts_with(&query) {
+                    Match::Prefix
+                } else if version.contains(&query) {
+                    Match::Contains
+                } else {
+                    return None;
+                };
+                Some((matched, hit))
+            })
+            .collect();
+        // stable, so versions keep their index order within a kind of match
+        hits.sort_by_key(|(matched, _)| *matched);
+        hits.into_iter().map(|(_, hit)| hit.clone()).collect()
+    }
+}
+
+/**
+ * Rebuild the search index from the stored metadata, replacing the previous one once built
+ */
+pub fn rebuild_index(storage_format: Arc<StorageFormat>) -> Result<Arc<SearchIndex>> {
+    let index = Arc::new(SearchIndex::build(storage_format)?);
+    *SEARCH_INDEX.write().unwrap_or_else(|err| err.into_inner()) = Some(index.clone());
+    Ok(index)
+}
+
+/**
+ * The current search index, building it first if no update built it yet
+ */
+pub fn current_index(storage_format: Arc<StorageFormat>) -> Result<Arc<SearchIndex>> {
+    if let Some(index) = SEARCH_INDEX
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .as_ref()
+    {
+        return Ok(index.clone());
+    }
+    rebuild_index(storage_format)
+}
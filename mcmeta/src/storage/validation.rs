@@ -0,0 +1,139 @@
+use std::{fmt::Display, sync::Arc};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_valid::Validate;
+use tracing::{info, warn};
+
+use crate::storage::{
+    fabric::{FabricDataStorage, FabricSource},
+    forge::ForgeDataStorage,
+    java::JavaDataStorage,
+    liteloader::LiteLoaderDataStorage,
+    mojang::MojangDataStorage,
+    neoforge::NeoForgeDataStorage,
+    optifine::OptiFineDataStorage,
+    StorageFormat,
+};
+
+#[derive(Serialize, Debug, Clone)]
+pub struct InvalidMetadataFile {
+    pub path: String,
+    pub error: String,
+}
+
+impl Display for InvalidMetadataFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.error)
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct MetadataValidationReport {
+    pub checked_files: usize,
+    pub invalid_files: Vec<InvalidMetadataFile>,
+}
+
+impl MetadataValidationReport {
+    /**
+     * Record the result of loading a stored file, only checking that it parsed
+     */
+    pub fn check_parse<T>(&mut self, path: &std::path::Path, loaded: Result<Option<T>>) {
+        match loaded {
+            Ok(Some(_)) => self.checked_files += 1,
+            Ok(None) => {}
+            Err(err) => {
+                self.checked_files += 1;
+                self.invalid_files.push(InvalidMetadataFile {
+                    path: path.to_string_lossy().to_string(),
+                    error: format!("{:#}", err),
+                });
+            }
+        }
+    }
+
+    /**
+     * Record the result of loading a stored file, checking that it parsed and validates against its model
+     */
+    pub fn check<T: Validate>(&mut self, path: &std::path::Path, loaded: Result<Option<T>>) {
+        match loaded {
+            Ok(Some(value)) => {
+                self.checked_files += 1;
+                if let Err(errors) = value.validate() {
+                    self.invalid_files.push(InvalidMetadataFile {
+                        path: path.to_string_lossy().to_string(),
+                        error: serde_json::to_string(&errors).unwrap_or(errors.to_string()),
+                    });
+                }
+            }
+            other => self.check_parse(path, other),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.invalid_files.is_empty()
+    }
+}
+
+/**
+ * List the names (without the `.json` extension) of every json file in a directory
+ */
+pub fn json_file_stems(dir: &std::path::Path) -> Result<Vec<String>> {
+    let mut stems = vec![];
+    for entry in std::fs::read_dir(dir)
+        .with_context(|| format!("Failure reading directory {}", dir.to_string_lossy()))?
+    {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+            if let Some(stem) = path.file_stem() {
+                stems.push(stem.to_string_lossy().to_string());
+            }
+        }
+    }
+    stems.sort();
+    Ok(stems)
+}
+
+impl StorageFormat {
+    /**
+     * Re-parse and re-validate every stored metadata file against the libmcmeta models
+     */
+    pub fn validate_metadata(&self) -> Result<MetadataValidationReport> {
+        let storage_format = Arc::new(self.clone());
+        let mut report = MetadataValidationReport::default();
+
+        match self {
+            StorageFormat::Json {
+                meta_directory: _,
+                generated_directory: _,
+            } => {
+                MojangDataStorage::new(storage_format.clone()).validate_stored(&mut report)?;
+                ForgeDataStorage::new(storage_format.clone()).validate_stored(&mut report)?;
+                NeoForgeDataStorage::new(storage_format.clone()).validate_stored(&mut report)?;
+                for source in FabricSource::ALL {
+                    FabricDataStorage::new(storage_format.clone(), source)
+                        .validate_stored(&mut report)?;
+                }
+                LiteLoaderDataStorage::new(storage_format.clone()).validate_stored(&mut report)?;
+                OptiFineDataStorage::new(storage_format.clone()).validate_stored(&mut report)?;
+                JavaDataStorage::new(storage_format.clone()).validate_stored(&mut report)?;
+            }
+            StorageFormat::Database => todo!(),
+        }
+
+        if report.is_valid() {
+            info!(
+                "Validated {} stored metadata files, all valid",
+                report.checked_files
+            );
+        } else {
+            warn!(
+                "Validated {} stored metadata files, {} invalid",
+                report.checked_files,
+                report.invalid_files.len()
+            );
+        }
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,312 @@
+use std::{future::Future, panic::AssertUnwindSafe, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use futures::FutureExt;
+use tokio::{sync::watch, task::JoinHandle};
+use tracing::{error, info, warn};
+
+use crate::app_config::{reload_metadata, ServerConfig};
+use crate::storage::coordinator::{UpdatesLocked, UPDATE_COORDINATOR};
+
+const INITIAL_RESTART_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(600);
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+/**
+ * Run a background task, restarting it with an exponential backoff whenever it fails or panics.
+ * The supervisor only stops once the task finishes successfully or it is aborted, which cancels
+ * the task too.
+ */
+pub fn supervise<F, Fut>(name: &'static str, task: F) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut backoff = INITIAL_RESTART_BACKOFF;
+        loop {
+            let started = tokio::time::Instant::now();
+            // awaited in place, so aborting the supervisor cancels the task along with it
+            match AssertUnwindSafe(task()).catch_unwind().await {
+                Ok(Ok(())) => {
+                    info!("Background task {} finished", name);
+                    return;
+                }
+                Ok(Err(err)) => {
+                    error!("Background task {} failed: {:?}", name, err);
+                }
+                Err(panic) => {
+                    error!(
+                        "Background task {} panicked: {}",
+                        name,
+                        panic_message(panic.as_ref())
+                    );
+                }
+            }
+
+            // a task that ran for a while before failing is not crash looping
+            if started.elapsed() > MAX_RESTART_BACKOFF {
+                backoff = INITIAL_RESTART_BACKOFF;
+            }
+            warn!(
+                "Restarting background task {} in {} seconds",
+                name,
+                backoff.as_secs()
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_RESTART_BACKOFF);
+        }
+    })
+}
+
+/**
+ * Run the first metadata update in the background while the server already answers, retrying
+ * until it succeeds. `/ready` only reports ready once it did.
+ */
+pub fn spawn_initial_update(config: Arc<ServerConfig>) -> JoinHandle<()> {
+    supervise("initial metadata update", move || {
+        let config = config.clone();
+        async move {
+            info!("Running initial metadata update");
+            config
+                .storage_format
+                .update_upstream_metadata(&config.current_metadata())
+                .await?;
+            info!("Initial metadata update finished, ready to serve");
+            Ok(())
+        }
+    })
+}
+
+/**
+ * Periodically update the upstream metadata in the background, every `metadata.update_interval`
+ * seconds as last reloaded, until a reload sets it to 0
+ */
+pub fn spawn_update_scheduler(config: Arc<ServerConfig>) -> Option<JoinHandle<()>> {
+    if config.metadata.update_interval == 0 {
+        info!("Periodic metadata updates are disabled");
+        return None;
+    }
+
+    Some(supervise("metadata update scheduler", move || {
+        let config = config.clone();
+        async move {
+            loop {
+                let interval = config.current_metadata().update_interval;
+                if interval == 0 {
+                    info!("Periodic metadata updates were disabled by a reload");
+                    return Ok(());
+                }
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+                info!("Running scheduled metadata update");
+                config
+                    .storage_format
+                    .update_upstream_metadata(&config.current_metadata())
+                    .await?;
+            }
+        }
+    }))
+}
+
+/**
+ * Re-read the config on SIGHUP, run later updates with its metadata settings and regenerate with them
+ */
+#[cfg(unix)]
+pub fn spawn_reload_handler(config: Arc<ServerConfig>, config_path: String) -> JoinHandle<()> {
+    supervise("SIGHUP reload handler", move || {
+        let initial_config = config.clone();
+        let config_path = config_path.clone();
+        async move {
+            let mut hangups =
+                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+            while hangups.recv().await.is_some() {
+                info!("Received SIGHUP, reloading config and regenerating metadata");
+                let config = match ServerConfig::from_config(&config_path) {
+                    Ok(config) => config,
+                    Err(err) => {
+                        error!(
+                            "Failure reloading config, keeping the current one: {:?}",
+                            err
+                        );
+                        continue;
+                    }
+                };
+                if config.bind_address != initial_config.bind_address
+                    || config.private_bind_address != initial_config.private_bind_address
+                {
+                    warn!("The bind addresses changed, restart the server to apply them");
+                }
+                if initial_config.metadata.update_interval == 0
+                    && config.metadata.update_interval != 0
+                {
+                    warn!("Periodic metadata updates were disabled at start, restart the server to enable them");
+                }
+                if let Err(err) = crate::download::apply_settings(&config.metadata) {
+                    error!(
+                        "Failure applying the reloaded download settings, keeping the current ones: {:?}",
+                        err
+                    );
+                    continue;
+                }
+                let changed = config
+                    .metadata
+                    .changed_settings(&initial_config.current_metadata());
+                reload_metadata(config.metadata.clone());
+                if changed.is_empty() {
+                    info!("Reloaded the config, no metadata setting changed");
+                } else {
+                    info!("Reloaded the metadata settings {}", changed.join(", "));
+                }
+
+                // regenerating rewrites the same files as an update
+                let _updates = UPDATE_COORDINATOR.lock_all().await;
+                let storage_format = initial_config.storage_format.clone();
+                let metadata_cfg = config.metadata;
+                match tokio::task::spawn_blocking(move || {
+                    storage_format.regenerate_metadata(&metadata_cfg)
+                })
+                .await
+                {
+                    Ok(Ok(())) => info!("Finished regenerating metadata"),
+                    Ok(Err(err)) => error!("Failure regenerating metadata: {:?}", err),
+                    Err(err) => error!("Regeneration task failed: {:?}", err),
+                }
+            }
+            Ok(())
+        }
+    })
+}
+
+lazy_static! {
+    /// Set once the server starts shutting down, so responses that never end on their own do
+    static ref SHUTTING_DOWN: watch::Sender<bool> = watch::channel(false).0;
+}
+
+/**
+ * Tell every response waiting in `shutdown_started` that the server is shutting down
+ */
+pub fn start_shutdown() {
+    SHUTTING_DOWN.send_replace(true);
+}
+
+/**
+ * Resolve once the server started shutting down, right away when it already has, e.g. to end
+ * event streams that would keep the graceful shutdown waiting forever
+ */
+pub async fn shutdown_started() {
+    let mut shutting_down = SHUTTING_DOWN.subscribe();
+    while !*shutting_down.borrow_and_update() {
+        if shutting_down.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+/**
+ * Resolve once the process is asked to stop, by SIGINT or, on unix, SIGTERM
+ */
+pub async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            error!("Failure listening for SIGINT: {:?}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminations) => {
+                terminations.recv().await;
+            }
+            Err(err) => {
+                error!("Failure listening for SIGTERM: {:?}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => info!("Received SIGINT, shutting down"),
+        _ = terminate => info!("Received SIGTERM, shutting down"),
+    }
+}
+
+/**
+ * Stop the update tasks and wait up to `timeout` for a running update to finish writing.
+ * The returned guard keeps any further update from starting until the process exits.
+ */
+pub async fn stop_updates(
+    update_tasks: impl IntoIterator<Item = JoinHandle<()>>,
+    timeout: Duration,
+) -> Option<UpdatesLocked<'static>> {
+    for task in update_tasks {
+        task.abort();
+    }
+
+    match tokio::time::timeout(timeout, UPDATE_COORDINATOR.lock_all()).await {
+        Ok(updates) => Some(updates),
+        Err(_) => {
+            warn!(
+                "A metadata update is still running after {} seconds, stopping anyway",
+                timeout.as_secs()
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use tokio::sync::Notify;
+
+    use super::supervise;
+
+    /// Records being dropped, which is how a cancelled task ends
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_supervise_abort_cancels_task() {
+        let started = Arc::new(Notify::new());
+        let dropped = Arc::new(AtomicBool::new(false));
+        let supervisor = supervise("test", {
+            let started = started.clone();
+            let dropped = dropped.clone();
+            move || {
+                let started = started.clone();
+                let flag = DropFlag(dropped.clone());
+                async move {
+                    let _flag = flag;
+                    started.notify_one();
+                    std::future::pending::<anyhow::Result<()>>().await
+                }
+            }
+        });
+
+        started.notified().await;
+        supervisor.abort();
+        assert!(supervisor.await.unwrap_err().is_cancelled());
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+}
@@ -1,4 +1,6 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use tracing::debug;
 
 fn json_matching_brace(c: char) -> char {
     match c {
@@ -24,7 +26,10 @@ pub fn get_json_context(err: &serde_json::Error, body: &str, max_len: usize) ->
         .unwrap_or_default()
         .0;
     let mut ctx = body.split_at(line_offset).1.to_owned();
-    let offset = ctx.char_indices().nth(err.column()).unwrap().0;
+    let offset = ctx
+        .char_indices()
+        .nth(err.column())
+        .map_or(ctx.len(), |(i, _)| i);
     ctx = ctx.split_at(offset).1.to_owned();
 
     let mut token_contexts: Vec<char> = vec![];
@@ -100,7 +105,10 @@ pub fn get_json_context_back(err: &serde_json::Error, body: &str, max_len: usize
         .0;
     let (pre_line, ctx_line) = body.split_at(line_offset);
     let mut ctx = ctx_line.to_owned();
-    let offset = ctx.char_indices().nth(err.column()).unwrap().0;
+    let offset = ctx
+        .char_indices()
+        .nth(err.column())
+        .map_or(ctx.len(), |(i, _)| i);
     ctx = ctx.split_at(offset).0.to_owned();
     ctx = pre_line.to_owned() + &ctx;
 
@@ -110,7 +118,7 @@ pub fn get_json_context_back(err: &serde_json::Error, body: &str, max_len: usize
     let mut found_open = false;
     let mut found_close = false;
     let mut ctx_end = 0;
-    let mut last_char: char = ctx.chars().rev().next().unwrap_or_default();
+    let mut last_char: char = ctx.chars().next_back().unwrap_or_default();
     for (i, c) in ctx.char_indices().rev() {
         if c == '\\' && !in_str && string_open_pre {
             token_contexts.push(last_char);
@@ -220,6 +228,82 @@ pub fn hash(data: impl AsRef<[u8]>, algo: HashAlgo) -> Result<String> {
     }
 }
 
+/**
+ * Find the newest last modified time of the entries of a zip or jar file
+ */
+pub fn latest_zip_timestamp(path: &std::path::Path) -> Result<time::OffsetDateTime> {
+    let mut time_stamp = time::OffsetDateTime::UNIX_EPOCH;
+
+    let mut archive = zip::ZipArchive::new(
+        std::fs::File::open(path)
+            .with_context(|| format!("Failure opening {}", path.to_string_lossy()))?,
+    )
+    .with_context(|| format!("Failure reading Jar archive {}", path.to_string_lossy()))?;
+
+    for i in 0..archive.len() {
+        let file = archive.by_index(i).with_context(|| {
+            format!(
+                "Failure reading Jar archive {} `index:{}`",
+                path.to_string_lossy(),
+                i
+            )
+        })?;
+        // the zips of ancient builds contain entries without a valid timestamp
+        let Ok(time_stamp_new) = file.last_modified().to_time() else {
+            debug!(
+                "Skipping invalid last modified time of {} in {}",
+                file.name(),
+                path.to_string_lossy()
+            );
+            continue;
+        };
+        if time_stamp_new > time_stamp {
+            time_stamp = time_stamp_new;
+        }
+    }
+
+    Ok(time_stamp)
+}
+
+/// Numbers the temporary files of `write_atomic`, so concurrent writes of a file never share one
+static TEMP_FILES: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/**
+ * Write a file through a temporary file next to it which is then renamed over it,
+ * so neither readers nor a crash mid-write ever leave a partially written file behind
+ */
+pub fn write_atomic(path: &std::path::Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    use std::io::Write;
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Not a file path {}", path.to_string_lossy()))?;
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        TEMP_FILES.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    ));
+
+    let written = std::fs::File::create_new(&temp_path).and_then(|mut file| {
+        file.write_all(contents.as_ref())?;
+        file.sync_all()
+    });
+    if let Err(err) = written.and_then(|_| std::fs::rename(&temp_path, path)) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(err)
+            .with_context(|| format!("Failure writing file {}", path.to_string_lossy()));
+    }
+    Ok(())
+}
+
+/**
+ * Write a value as pretty printed json, atomically as `write_atomic` does
+ */
+pub fn write_json_atomic<T: Serialize + ?Sized>(path: &std::path::Path, value: &T) -> Result<()> {
+    write_atomic(path, serde_json::to_string_pretty(value)?)
+}
+
 /**
 * Process a `Vec<Result<T>>` int a `Result<Vec<T>>` concatenating any error messages encountered
 */
@@ -249,3 +333,35 @@ pub fn process_results_ok<T>(results: Vec<Result<T>>) -> Vec<T> {
         .filter_map(|res: Result<T>| res.ok())
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::write_atomic;
+
+    #[test]
+    fn test_write_atomic_concurrently() {
+        let tmp_dir = TempDir::new("mcmeta_write_atomic").unwrap();
+        let path = tmp_dir.path().join("index.json");
+        let len = 1 << 20;
+
+        std::thread::scope(|scope| {
+            for writer in 0..8u8 {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..5 {
+                        write_atomic(path, vec![b'a' + writer; len]).unwrap();
+                    }
+                });
+            }
+        });
+
+        // whichever write came last, the file holds all of it and nothing of the others
+        let contents = std::fs::read(&path).unwrap();
+        assert_eq!(contents.len(), len);
+        assert!(contents.iter().all(|byte| *byte == contents[0]));
+        let leftovers = std::fs::read_dir(tmp_dir.path()).unwrap().count();
+        assert_eq!(leftovers, 1);
+    }
+}